@@ -1,12 +1,41 @@
-use std::num::NonZeroU16;
+use std::num::{NonZeroU16, NonZeroU32};
+use std::path::PathBuf;
+
+use filmorator_core::attention::AttentionPolicy;
+use filmorator_core::exposure::ExposurePolicy;
+use filmorator_core::groups::GroupMode;
+use filmorator_core::progress::DEFAULT_COMPLETION_TARGET;
+use filmorator_core::scheduler::MatchupMode;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub port: NonZeroU16,
     pub database_url: String,
-    pub bucket: String,
-    pub s3_endpoint: Option<String>,
-    pub s3_public_url: Option<String>,
+    pub images: ImageSource,
+    /// How matchups treat photo groups; `None` ignores groups entirely.
+    pub matchup_grouping: Option<GroupMode>,
+    /// How unevenly photos may be shown.
+    pub exposure_policy: ExposurePolicy,
+    /// Whether matchups adapt to each session or follow a campaign-wide Swiss tournament.
+    pub matchup_mode: MatchupMode,
+    /// How often repeat and calibration checks are interleaved.
+    pub attention_policy: AttentionPolicy,
+    /// Answers wanted per seed matchup, for campaigns whose manifest doesn't say.
+    pub completion_target: u32,
+    /// Secret that owns the default campaign, which has no owner until one is set.
+    pub owner_secret: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImageSource {
+    /// A directory laid out like the bucket, served by the app itself.
+    Local(PathBuf),
+    S3 {
+        bucket: String,
+        endpoint: Option<String>,
+        /// Endpoint the browser reaches S3 on, for presigning, when it differs from `endpoint`.
+        public_url: Option<String>,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -15,8 +44,20 @@ pub enum ConfigError {
     InvalidPort(String),
     #[error("DATABASE_URL required")]
     MissingDatabaseUrl,
-    #[error("FILMORATOR_BUCKET required")]
+    #[error("FILMORATOR_BUCKET or FILMORATOR_IMAGE_DIR required")]
     MissingBucket,
+    #[error("FILMORATOR_MATCHUP_GROUPING invalid: {0}")]
+    InvalidGrouping(String),
+    #[error("FILMORATOR_MATCHUP_MODE invalid, want adaptive or swiss: {0}")]
+    InvalidMatchupMode(String),
+    #[error("FILMORATOR_SWISS_ROUND_MATCHUPS invalid, want a positive whole number: {0}")]
+    InvalidSwissRound(String),
+    #[error("FILMORATOR_MAX_EXPOSURE_RATIO invalid, want a number of at least 1: {0}")]
+    InvalidExposureRatio(String),
+    #[error("{0} invalid, want a whole number, 0 to turn the check off: {1}")]
+    InvalidCheckInterval(&'static str, String),
+    #[error("FILMORATOR_COMPLETION_TARGET invalid, want a positive whole number: {0}")]
+    InvalidCompletionTarget(String),
 }
 
 impl Config {
@@ -28,13 +69,87 @@ impl Config {
             .and_then(NonZeroU16::new)
             .ok_or(ConfigError::InvalidPort(port_str))?;
 
+        let matchup_grouping = std::env::var("FILMORATOR_MATCHUP_GROUPING")
+            .ok()
+            .map(|mode| {
+                mode.parse::<GroupMode>()
+                    .map_err(ConfigError::InvalidGrouping)
+            })
+            .transpose()?;
+
+        let exposure_policy = match std::env::var("FILMORATOR_MAX_EXPOSURE_RATIO") {
+            Ok(ratio) => ExposurePolicy {
+                max_ratio: ratio
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| r.is_finite() && *r >= 1.0)
+                    .ok_or(ConfigError::InvalidExposureRatio(ratio))?,
+            },
+            Err(_) => ExposurePolicy::default(),
+        };
+
+        let matchup_mode = match std::env::var("FILMORATOR_MATCHUP_MODE").as_deref() {
+            Err(_) | Ok("adaptive") => MatchupMode::Adaptive,
+            Ok("swiss") => MatchupMode::Swiss {
+                matchups_per_round: std::env::var("FILMORATOR_SWISS_ROUND_MATCHUPS")
+                    .ok()
+                    .map(|n| {
+                        n.parse::<NonZeroU32>()
+                            .map_err(|_| ConfigError::InvalidSwissRound(n))
+                    })
+                    .transpose()?,
+            },
+            Ok(other) => return Err(ConfigError::InvalidMatchupMode(other.to_string())),
+        };
+
+        let check_every = |var: &'static str, default: u32| match std::env::var(var) {
+            Ok(every) => every
+                .parse::<u32>()
+                .map_err(|_| ConfigError::InvalidCheckInterval(var, every)),
+            Err(_) => Ok(default),
+        };
+        let defaults = AttentionPolicy::default();
+        let attention_policy = AttentionPolicy {
+            repeat_every: check_every("FILMORATOR_REPEAT_CHECK_EVERY", defaults.repeat_every)?,
+            calibration_every: check_every(
+                "FILMORATOR_CALIBRATION_CHECK_EVERY",
+                defaults.calibration_every,
+            )?,
+            ..defaults
+        };
+
+        let completion_target = match std::env::var("FILMORATOR_COMPLETION_TARGET") {
+            Ok(target) => target
+                .parse::<u32>()
+                .ok()
+                .filter(|&t| t > 0)
+                .ok_or(ConfigError::InvalidCompletionTarget(target))?,
+            Err(_) => DEFAULT_COMPLETION_TARGET,
+        };
+
+        let images = match std::env::var_os("FILMORATOR_IMAGE_DIR") {
+            Some(dir) => ImageSource::Local(dir.into()),
+            None => ImageSource::S3 {
+                bucket: std::env::var("FILMORATOR_BUCKET")
+                    .map_err(|_| ConfigError::MissingBucket)?,
+                endpoint: std::env::var("AWS_ENDPOINT_URL").ok(),
+                public_url: std::env::var("S3_PUBLIC_URL").ok(),
+            },
+        };
+
         Ok(Self {
             port,
             database_url: std::env::var("DATABASE_URL")
                 .map_err(|_| ConfigError::MissingDatabaseUrl)?,
-            bucket: std::env::var("FILMORATOR_BUCKET").map_err(|_| ConfigError::MissingBucket)?,
-            s3_endpoint: std::env::var("AWS_ENDPOINT_URL").ok(),
-            s3_public_url: std::env::var("S3_PUBLIC_URL").ok(),
+            images,
+            matchup_grouping,
+            exposure_policy,
+            matchup_mode,
+            attention_policy,
+            completion_target,
+            owner_secret: std::env::var("FILMORATOR_OWNER_SECRET")
+                .ok()
+                .filter(|secret| !secret.trim().is_empty()),
         })
     }
 }
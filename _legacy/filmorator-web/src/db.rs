@@ -1,57 +1,329 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::{PgPool, Row};
 use uuid::Uuid;
 
-use filmorator_core::models::{ComparisonResult, Matchup, PhotoRating, Session};
+use filmorator_core::events::{Event, LoggedEvent};
+use filmorator_core::groups::PhotoGroups;
+use filmorator_core::identity::PhotoSync;
+use filmorator_core::matchup::SwissTournament;
+use filmorator_core::models::{
+    Campaign, CampaignRating, CampaignStatus, ComparisonResult, Matchup, MatchupKind, Photo,
+    PhotoRating, Session,
+};
+use filmorator_core::pool::MatchupPool;
+use filmorator_core::provenance::{DeviceClass, Provenance, Viewport};
+use filmorator_core::ranking::{Anchor, AnchorMode, PositionBias};
+use filmorator_core::refit::RatingFit;
+use filmorator_core::types::Triple;
 
-fn i32_vec_to_u32_vec(v: Vec<i32>) -> sqlx::Result<Vec<u32>> {
-    v.into_iter()
+fn i32_vec_to_triple(v: Vec<i32>) -> sqlx::Result<Triple> {
+    let indices = v
+        .into_iter()
         .map(|i| u32::try_from(i).map_err(|_| sqlx::Error::Protocol("Negative index".into())))
-        .collect()
+        .collect::<sqlx::Result<Vec<u32>>>()?;
+    Triple::try_from(indices.as_slice()).map_err(|e| sqlx::Error::Decode(e.into()))
 }
 
-fn u32_vec_to_i32_vec(v: &[u32]) -> sqlx::Result<Vec<i32>> {
-    v.iter()
+fn triple_to_i32_vec(triple: Triple) -> sqlx::Result<Vec<i32>> {
+    triple
+        .indices()
+        .iter()
         .map(|&i| i32::try_from(i).map_err(|_| sqlx::Error::Protocol("Index overflow".into())))
         .collect()
 }
 
 fn matchup_from_row(row: sqlx::postgres::PgRow) -> sqlx::Result<Matchup> {
     use sqlx::Row;
+    let kind = MatchupKind::from_parts(row.get("kind"), row.get("repeat_of"))
+        .ok_or_else(|| sqlx::Error::Protocol("Invalid matchup kind".into()))?;
     Ok(Matchup {
         id: row.get("id"),
         session_id: row.get("session_id"),
-        photo_indices: i32_vec_to_u32_vec(row.get("photo_indices"))?,
+        campaign_id: row.get("campaign_id"),
+        photo_indices: i32_vec_to_triple(row.get("photo_indices"))?,
         is_seed: row.get("is_seed"),
+        kind,
         created_at: row.get("created_at"),
+        served_at: row.get("served_at"),
     })
 }
 
-pub async fn get_or_create_session(pool: &PgPool, session_id: Uuid) -> sqlx::Result<Session> {
+fn comparison_from_row(row: &sqlx::postgres::PgRow) -> sqlx::Result<ComparisonResult> {
+    Ok(ComparisonResult {
+        id: row.get("id"),
+        matchup_id: row.get("matchup_id"),
+        session_id: row.get("session_id"),
+        ranked_photo_indices: i32_vec_to_triple(row.get("ranked_photo_indices"))?,
+        displayed_order: row
+            .get::<Option<Vec<i32>>, _>("displayed_order")
+            .map(i32_vec_to_triple)
+            .transpose()?,
+        idempotency_key: row.get("idempotency_key"),
+        provenance: provenance_from_row(row)?,
+        latency_ms: row
+            .get::<Option<i32>, _>("latency_ms")
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|_| sqlx::Error::Protocol("Negative latency".into()))?,
+        created_at: row.get("created_at"),
+        superseded_at: row.get("superseded_at"),
+        superseded_by: row.get("superseded_by"),
+    })
+}
+
+fn provenance_from_row(row: &sqlx::postgres::PgRow) -> sqlx::Result<Provenance> {
+    let non_negative = |v: Option<i32>| {
+        v.map(u32::try_from)
+            .transpose()
+            .map_err(|_| sqlx::Error::Protocol("Negative provenance value".into()))
+    };
+    let viewport = match (
+        non_negative(row.get("viewport_width"))?,
+        non_negative(row.get("viewport_height"))?,
+    ) {
+        (Some(width), Some(height)) => Some(Viewport { width, height }),
+        _ => None,
+    };
+    Ok(Provenance {
+        decision_ms: non_negative(row.get("decision_ms"))?,
+        viewport,
+        device_class: row
+            .get::<Option<&str>, _>("device_class")
+            .map(str::parse)
+            .transpose()
+            .map_err(sqlx::Error::Protocol)?,
+        previews_loaded: row.get("previews_loaded"),
+        zoomed: row.get("zoomed"),
+    })
+}
+
+fn session_from_row(row: &sqlx::postgres::PgRow) -> Session {
+    Session {
+        id: row.get("id"),
+        campaign_id: row.get("campaign_id"),
+        excluded: row.get("excluded"),
+        created_at: row.get("created_at"),
+        last_active_at: row.get("last_active_at"),
+    }
+}
+
+fn campaign_from_row(row: &sqlx::postgres::PgRow) -> sqlx::Result<Campaign> {
+    let status = row
+        .get::<&str, _>("status")
+        .parse::<CampaignStatus>()
+        .map_err(sqlx::Error::Protocol)?;
+    Ok(Campaign {
+        id: row.get("id"),
+        name: row.get("name"),
+        owner_secret_hash: row.get("owner_secret_hash"),
+        status,
+        threshold_reached_at: row.get("threshold_reached_at"),
+        closed_at: row.get("closed_at"),
+        reopened_at: row.get("reopened_at"),
+        created_at: row.get("created_at"),
+    })
+}
+
+/// The campaign a session belongs to; `None` for an unknown session.
+pub async fn get_session_campaign(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> sqlx::Result<Option<Campaign>> {
+    let row = sqlx::query(
+        r"
+        SELECT c.id, c.name, c.owner_secret_hash, c.status, c.threshold_reached_at,
+               c.closed_at, c.reopened_at, c.created_at
+        FROM campaigns c
+        JOIN sessions s ON s.campaign_id = c.id
+        WHERE s.id = $1
+        ",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.as_ref().map(campaign_from_row).transpose()
+}
+
+pub async fn get_campaign(pool: &PgPool, campaign_id: &str) -> sqlx::Result<Option<Campaign>> {
+    let row = sqlx::query(
+        r"
+        SELECT id, name, owner_secret_hash, status, threshold_reached_at,
+               closed_at, reopened_at, created_at
+        FROM campaigns
+        WHERE id = $1
+        ",
+    )
+    .bind(campaign_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.as_ref().map(campaign_from_row).transpose()
+}
+
+/// Inserts a campaign, or updates an existing one's name, status and
+/// transition timestamps. The owner secret hash is never changed.
+pub async fn save_campaign(pool: &PgPool, campaign: &Campaign) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let before = sqlx::query("SELECT status FROM campaigns WHERE id = $1 FOR UPDATE")
+        .bind(&campaign.id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|row| row.get::<&str, _>("status").parse::<CampaignStatus>())
+        .transpose()
+        .map_err(sqlx::Error::Protocol)?;
+
+    sqlx::query(
+        r"
+        INSERT INTO campaigns
+            (id, name, owner_secret_hash, status, threshold_reached_at,
+             closed_at, reopened_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (id) DO UPDATE
+        SET name = $2, status = $4,
+            threshold_reached_at = COALESCE(campaigns.threshold_reached_at, $5),
+            closed_at = $6, reopened_at = $7
+        ",
+    )
+    .bind(&campaign.id)
+    .bind(&campaign.name)
+    .bind(&campaign.owner_secret_hash)
+    .bind(campaign.status.as_str())
+    .bind(campaign.threshold_reached_at)
+    .bind(campaign.closed_at)
+    .bind(campaign.reopened_at)
+    .bind(campaign.created_at)
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some((at, event)) = Event::campaign_transition(before, campaign) {
+        append_event(&mut *tx, at, &event).await?;
+    }
+    tx.commit().await
+}
+
+/// Returns `false` for an unknown, closed or already marked campaign.
+pub async fn set_threshold_reached(
+    pool: &PgPool,
+    campaign_id: &str,
+    at: DateTime<Utc>,
+) -> sqlx::Result<bool> {
+    let updated = sqlx::query(
+        r"UPDATE campaigns SET threshold_reached_at = $2
+          WHERE id = $1 AND threshold_reached_at IS NULL AND status <> 'closed'",
+    )
+    .bind(campaign_id)
+    .bind(at)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+/// Returns `false` for an unknown session.
+pub async fn set_session_excluded(
+    pool: &PgPool,
+    session_id: Uuid,
+    excluded: bool,
+) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let updated = sqlx::query("UPDATE sessions SET excluded = $2 WHERE id = $1")
+        .bind(session_id)
+        .bind(excluded)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    let event = Event::SessionExcluded {
+        session_id,
+        excluded,
+    };
+    append_event(&mut *tx, Utc::now(), &event).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Stores a session from another server, included in the ranking. Returns
+/// `false` if one with its id exists.
+pub async fn import_session(pool: &PgPool, session: &Session) -> sqlx::Result<bool> {
+    let inserted = sqlx::query(
+        r"
+        INSERT INTO sessions (id, campaign_id, excluded, created_at, last_active_at)
+        VALUES ($1, $2, FALSE, $3, $4)
+        ON CONFLICT (id) DO NOTHING
+        ",
+    )
+    .bind(session.id)
+    .bind(&session.campaign_id)
+    .bind(session.created_at)
+    .bind(session.last_active_at)
+    .execute(pool)
+    .await?;
+
+    Ok(inserted.rows_affected() > 0)
+}
+
+pub async fn get_campaign_sessions(pool: &PgPool, campaign_id: &str) -> sqlx::Result<Vec<Session>> {
+    let rows = sqlx::query(
+        r"
+        SELECT id, campaign_id, excluded, created_at, last_active_at
+        FROM sessions
+        WHERE campaign_id = $1
+        ORDER BY created_at
+        ",
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(session_from_row).collect())
+}
+
+pub async fn get_or_create_session(
+    pool: &PgPool,
+    session_id: Uuid,
+    campaign_id: &str,
+) -> sqlx::Result<Session> {
     let now = Utc::now();
 
     let row = sqlx::query(
         r"
-        INSERT INTO sessions (id, created_at, last_active_at)
-        VALUES ($1, $2, $2)
-        ON CONFLICT (id) DO UPDATE SET last_active_at = $2
-        RETURNING id, created_at, last_active_at
+        INSERT INTO sessions (id, campaign_id, created_at, last_active_at)
+        VALUES ($1, $2, $3, $3)
+        ON CONFLICT (id) DO UPDATE SET last_active_at = $3
+        RETURNING id, campaign_id, excluded, created_at, last_active_at
         ",
     )
     .bind(session_id)
+    .bind(campaign_id)
     .bind(now)
     .fetch_one(pool)
     .await?;
 
-    Ok(Session {
-        id: row.get("id"),
-        created_at: row.get("created_at"),
-        last_active_at: row.get("last_active_at"),
-    })
+    Ok(session_from_row(&row))
+}
+
+pub async fn set_owner_secret_hash(
+    pool: &PgPool,
+    campaign_id: &str,
+    owner_secret_hash: &str,
+) -> sqlx::Result<bool> {
+    let updated = sqlx::query("UPDATE campaigns SET owner_secret_hash = $2 WHERE id = $1")
+        .bind(campaign_id)
+        .bind(owner_secret_hash)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(updated > 0)
 }
 
-pub async fn count_photos(pool: &PgPool) -> sqlx::Result<u32> {
-    let row = sqlx::query("SELECT COUNT(*) as count FROM photos")
+pub async fn count_photos(pool: &PgPool, campaign_id: &str) -> sqlx::Result<u32> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM photos WHERE campaign_id = $1")
+        .bind(campaign_id)
         .fetch_one(pool)
         .await?;
 
@@ -60,29 +332,50 @@ pub async fn count_photos(pool: &PgPool) -> sqlx::Result<u32> {
 }
 
 pub async fn create_matchup(pool: &PgPool, matchup: &Matchup) -> sqlx::Result<()> {
-    let indices = u32_vec_to_i32_vec(&matchup.photo_indices)?;
+    let indices = triple_to_i32_vec(matchup.photo_indices)?;
 
     sqlx::query(
         r"
-        INSERT INTO matchups (id, session_id, photo_indices, is_seed, created_at)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO matchups
+            (id, session_id, campaign_id, photo_indices, is_seed, kind, repeat_of,
+             created_at, served_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         ",
     )
     .bind(matchup.id)
     .bind(matchup.session_id)
+    .bind(&matchup.campaign_id)
     .bind(&indices)
     .bind(matchup.is_seed)
+    .bind(matchup.kind.as_str())
+    .bind(matchup.kind.repeat_of())
     .bind(matchup.created_at)
+    .bind(matchup.served_at)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
+/// Records when a matchup was last sent to its session. Returns `false` if it doesn't exist.
+pub async fn mark_matchup_served(
+    pool: &PgPool,
+    matchup_id: Uuid,
+    at: DateTime<Utc>,
+) -> sqlx::Result<bool> {
+    let updated = sqlx::query("UPDATE matchups SET served_at = $2 WHERE id = $1")
+        .bind(matchup_id)
+        .bind(at)
+        .execute(pool)
+        .await?;
+    Ok(updated.rows_affected() > 0)
+}
+
 pub async fn get_matchup(pool: &PgPool, matchup_id: Uuid) -> sqlx::Result<Option<Matchup>> {
     let row = sqlx::query(
         r"
-        SELECT id, session_id, photo_indices, is_seed, created_at
+        SELECT id, session_id, campaign_id, photo_indices, is_seed, kind, repeat_of, created_at,
+               served_at
         FROM matchups
         WHERE id = $1
         ",
@@ -94,33 +387,282 @@ pub async fn get_matchup(pool: &PgPool, matchup_id: Uuid) -> sqlx::Result<Option
     row.map(matchup_from_row).transpose()
 }
 
-pub async fn save_comparison(pool: &PgPool, result: &ComparisonResult) -> sqlx::Result<()> {
-    let ranked = u32_vec_to_i32_vec(&result.ranked_photo_indices)?;
+pub async fn get_session_matchups(pool: &PgPool, session_id: Uuid) -> sqlx::Result<Vec<Matchup>> {
+    let rows = sqlx::query(
+        r"
+        SELECT id, session_id, campaign_id, photo_indices, is_seed, kind, repeat_of, created_at,
+               served_at
+        FROM matchups
+        WHERE session_id = $1
+        ORDER BY created_at
+        ",
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
 
-    sqlx::query(
+    rows.into_iter().map(matchup_from_row).collect()
+}
+
+pub async fn get_campaign_matchups(pool: &PgPool, campaign_id: &str) -> sqlx::Result<Vec<Matchup>> {
+    let rows = sqlx::query(
         r"
-        INSERT INTO comparison_results (id, matchup_id, session_id, ranked_photo_indices, created_at)
-        VALUES ($1, $2, $3, $4, $5)
+        SELECT id, session_id, campaign_id, photo_indices, is_seed, kind, repeat_of, created_at,
+               served_at
+        FROM matchups
+        WHERE campaign_id = $1
+        ORDER BY created_at
+        ",
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(matchup_from_row).collect()
+}
+
+/// Stores a comparison unless its matchup is already answered or its idempotency
+/// key already used. Returns whether it was stored.
+pub async fn save_comparison(pool: &PgPool, result: &ComparisonResult) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+    if !insert_comparison(&mut *tx, result).await? {
+        return Ok(false);
+    }
+
+    let event = Event::ComparisonSubmitted {
+        comparison: result.clone(),
+    };
+    append_event(&mut *tx, result.created_at, &event).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn insert_comparison(
+    executor: impl sqlx::PgExecutor<'_>,
+    result: &ComparisonResult,
+) -> sqlx::Result<bool> {
+    let ranked = triple_to_i32_vec(result.ranked_photo_indices)?;
+    let displayed = result.displayed_order.map(triple_to_i32_vec).transpose()?;
+
+    let provenance = &result.provenance;
+    let to_i32 = |v: Option<u32>| v.map(|v| i32::try_from(v).unwrap_or(i32::MAX));
+
+    // The comparison is filed under its session's campaign
+    let inserted = sqlx::query(
+        r"
+        INSERT INTO comparison_results
+            (id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+             idempotency_key, created_at, campaign_id, decision_ms, viewport_width,
+             viewport_height, device_class, previews_loaded, zoomed, latency_ms)
+        SELECT $1, $2, $3, $4, $5, $6, $7, s.campaign_id, $8, $9, $10, $11, $12, $13, $14
+        FROM sessions s
+        WHERE s.id = $3
+        ON CONFLICT DO NOTHING
         ",
     )
     .bind(result.id)
     .bind(result.matchup_id)
     .bind(result.session_id)
     .bind(&ranked)
+    .bind(&displayed)
+    .bind(result.idempotency_key)
     .bind(result.created_at)
-    .execute(pool)
+    .bind(to_i32(provenance.decision_ms))
+    .bind(to_i32(provenance.viewport.map(|v| v.width)))
+    .bind(to_i32(provenance.viewport.map(|v| v.height)))
+    .bind(provenance.device_class.map(DeviceClass::as_str))
+    .bind(provenance.previews_loaded)
+    .bind(provenance.zoomed)
+    .bind(to_i32(result.latency_ms))
+    .execute(executor)
+    .await?;
+
+    Ok(inserted.rows_affected() > 0)
+}
+
+/// Marks an active comparison superseded at `at` with no replacement,
+/// re-opening its matchup. Returns `false` if it was already superseded.
+pub async fn retract_comparison(
+    pool: &PgPool,
+    comparison_id: Uuid,
+    at: DateTime<Utc>,
+) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        r"UPDATE comparison_results SET superseded_at = $2
+          WHERE id = $1 AND superseded_at IS NULL",
+    )
+    .bind(comparison_id)
+    .bind(at)
+    .execute(&mut *tx)
     .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    append_event(&mut *tx, at, &Event::ComparisonRetracted { comparison_id }).await?;
+    tx.commit().await?;
+    Ok(true)
+}
 
+/// Atomically supersedes an active comparison with `revision`. Returns `false`,
+/// changing nothing, if the old comparison was already superseded.
+pub async fn revise_comparison(
+    pool: &PgPool,
+    comparison_id: Uuid,
+    revision: &ComparisonResult,
+) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let superseded = sqlx::query(
+        r"UPDATE comparison_results SET superseded_at = $2, superseded_by = $3
+          WHERE id = $1 AND superseded_at IS NULL",
+    )
+    .bind(comparison_id)
+    .bind(revision.created_at)
+    .bind(revision.id)
+    .execute(&mut *tx)
+    .await?;
+
+    if superseded.rows_affected() == 0 || !insert_comparison(&mut *tx, revision).await? {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+
+    let event = Event::ComparisonRevised {
+        comparison_id,
+        revision: revision.clone(),
+    };
+    append_event(&mut *tx, revision.created_at, &event).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn append_event(
+    executor: impl sqlx::PgExecutor<'_>,
+    at: DateTime<Utc>,
+    event: &Event,
+) -> sqlx::Result<()> {
+    let payload = serde_json::to_string(event).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    sqlx::query("INSERT INTO events (occurred_at, payload) VALUES ($1, $2::jsonb)")
+        .bind(at)
+        .bind(payload)
+        .execute(executor)
+        .await?;
     Ok(())
 }
 
+/// The event log after `after_seq`, in append order.
+pub async fn get_events(pool: &PgPool, after_seq: u64) -> sqlx::Result<Vec<LoggedEvent>> {
+    let after = i64::try_from(after_seq).unwrap_or(i64::MAX);
+    let rows = sqlx::query(
+        "SELECT seq, occurred_at, payload::text AS payload FROM events WHERE seq > $1 ORDER BY seq",
+    )
+    .bind(after)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            let seq: i64 = row.get("seq");
+            Ok(LoggedEvent {
+                seq: u64::try_from(seq)
+                    .map_err(|_| sqlx::Error::Protocol("Negative event seq".into()))?,
+                at: row.get("occurred_at"),
+                event: serde_json::from_str(row.get("payload"))
+                    .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            })
+        })
+        .collect()
+}
+
+/// The stored answer a submission collides with: the one for its matchup, or
+/// the one the session stored under the same idempotency key.
+pub async fn find_prior_submission(
+    pool: &PgPool,
+    session_id: Uuid,
+    matchup_id: Uuid,
+    idempotency_key: Option<Uuid>,
+) -> sqlx::Result<Option<ComparisonResult>> {
+    let row = sqlx::query(
+        r"
+        SELECT id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+               idempotency_key, created_at, superseded_at, superseded_by,
+               decision_ms, viewport_width, viewport_height, device_class,
+               previews_loaded, zoomed, latency_ms
+        FROM comparison_results
+        WHERE (matchup_id = $2 AND superseded_at IS NULL)
+           OR (session_id = $1 AND idempotency_key = $3)
+        ORDER BY superseded_at IS NULL DESC
+        LIMIT 1
+        ",
+    )
+    .bind(session_id)
+    .bind(matchup_id)
+    .bind(idempotency_key)
+    .fetch_optional(pool)
+    .await?;
+
+    row.as_ref().map(comparison_from_row).transpose()
+}
+
+/// Active (not superseded) comparisons of a session, oldest first.
 pub async fn get_session_comparisons(
     pool: &PgPool,
     session_id: Uuid,
 ) -> sqlx::Result<Vec<ComparisonResult>> {
     let rows = sqlx::query(
         r"
-        SELECT id, matchup_id, session_id, ranked_photo_indices, created_at
+        SELECT id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+               idempotency_key, created_at, superseded_at, superseded_by,
+               decision_ms, viewport_width, viewport_height, device_class,
+               previews_loaded, zoomed, latency_ms
+        FROM comparison_results
+        WHERE session_id = $1 AND superseded_at IS NULL
+        ORDER BY created_at
+        ",
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(comparison_from_row).collect()
+}
+
+pub async fn get_campaign_comparisons(
+    pool: &PgPool,
+    campaign_id: &str,
+) -> sqlx::Result<Vec<ComparisonResult>> {
+    let rows = sqlx::query(
+        r"
+        SELECT id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+               idempotency_key, created_at, superseded_at, superseded_by,
+               decision_ms, viewport_width, viewport_height, device_class,
+               previews_loaded, zoomed, latency_ms
+        FROM comparison_results
+        WHERE campaign_id = $1 AND superseded_at IS NULL
+        ORDER BY created_at
+        ",
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(comparison_from_row).collect()
+}
+
+/// Every comparison of a session, superseded ones included, oldest first.
+pub async fn get_comparison_history(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> sqlx::Result<Vec<ComparisonResult>> {
+    let rows = sqlx::query(
+        r"
+        SELECT id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+               idempotency_key, created_at, superseded_at, superseded_by,
+               decision_ms, viewport_width, viewport_height, device_class,
+               previews_loaded, zoomed, latency_ms
         FROM comparison_results
         WHERE session_id = $1
         ORDER BY created_at
@@ -130,17 +672,7 @@ pub async fn get_session_comparisons(
     .fetch_all(pool)
     .await?;
 
-    rows.into_iter()
-        .map(|r| {
-            Ok(ComparisonResult {
-                id: r.get("id"),
-                matchup_id: r.get("matchup_id"),
-                session_id: r.get("session_id"),
-                ranked_photo_indices: i32_vec_to_u32_vec(r.get("ranked_photo_indices"))?,
-                created_at: r.get("created_at"),
-            })
-        })
-        .collect()
+    rows.iter().map(comparison_from_row).collect()
 }
 
 pub async fn get_session_ratings(
@@ -171,11 +703,115 @@ pub async fn get_session_ratings(
         .collect()
 }
 
-pub async fn save_ratings(
+pub async fn get_campaign_ratings(
+    pool: &PgPool,
+    campaign_id: &str,
+) -> sqlx::Result<Vec<CampaignRating>> {
+    let rows = sqlx::query(
+        r"
+        SELECT photo_idx, strength, uncertainty, comparison_count, updated_at
+        FROM campaign_ratings
+        WHERE campaign_id = $1
+        ORDER BY strength DESC
+        ",
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|r| {
+            let non_negative = |_| sqlx::Error::Protocol("Negative campaign rating field".into());
+            Ok(CampaignRating {
+                photo_idx: u32::try_from(r.get::<i32, _>("photo_idx")).map_err(non_negative)?,
+                strength: r.get("strength"),
+                uncertainty: r.get("uncertainty"),
+                comparison_count: u32::try_from(r.get::<i32, _>("comparison_count"))
+                    .map_err(non_negative)?,
+                updated_at: r.get("updated_at"),
+            })
+        })
+        .collect()
+}
+
+pub async fn get_campaign_position_bias(
+    pool: &PgPool,
+    campaign_id: &str,
+) -> sqlx::Result<Option<PositionBias>> {
+    let stored: Option<String> =
+        sqlx::query_scalar("SELECT bias::text FROM campaign_position_bias WHERE campaign_id = $1")
+            .bind(campaign_id)
+            .fetch_optional(pool)
+            .await?;
+    stored
+        .map(|json| serde_json::from_str(&json).map_err(|e| sqlx::Error::Decode(e.into())))
+        .transpose()
+}
+
+/// Replaces a campaign's ratings and position bias in one transaction.
+pub async fn save_campaign_ratings(
+    pool: &PgPool,
+    campaign_id: &str,
+    ratings: &[CampaignRating],
+    position_bias: &PositionBias,
+) -> sqlx::Result<()> {
+    let bias = serde_json::to_string(position_bias).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r"
+        INSERT INTO campaign_position_bias (campaign_id, bias) VALUES ($1, $2::jsonb)
+        ON CONFLICT (campaign_id) DO UPDATE SET bias = EXCLUDED.bias
+        ",
+    )
+    .bind(campaign_id)
+    .bind(bias)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM campaign_ratings WHERE campaign_id = $1")
+        .bind(campaign_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for rating in ratings {
+        let overflow = |_| sqlx::Error::Protocol("Index overflow".into());
+        sqlx::query(
+            r"
+            INSERT INTO campaign_ratings
+                (campaign_id, photo_idx, strength, uncertainty, comparison_count, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+        )
+        .bind(campaign_id)
+        .bind(i32::try_from(rating.photo_idx).map_err(overflow)?)
+        .bind(rating.strength)
+        .bind(rating.uncertainty)
+        .bind(i32::try_from(rating.comparison_count).map_err(overflow)?)
+        .bind(rating.updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Replaces a session's ratings and fit metadata in one transaction, so readers
+/// see either the previous fit or this one. `fit.version` is ignored; returns
+/// the stored version, one past the previous fit's.
+pub async fn save_rating_fit(
     pool: &PgPool,
     session_id: Uuid,
     ratings: &[PhotoRating],
-) -> sqlx::Result<()> {
+    fit: &RatingFit,
+) -> sqlx::Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM photo_ratings WHERE session_id = $1")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+
     for rating in ratings {
         let photo_idx = i32::try_from(rating.photo_idx)
             .map_err(|_| sqlx::Error::Protocol("Index overflow".into()))?;
@@ -183,28 +819,67 @@ pub async fn save_ratings(
             r"
             INSERT INTO photo_ratings (session_id, photo_idx, strength, uncertainty)
             VALUES ($1, $2, $3, $4)
-            ON CONFLICT (session_id, photo_idx) DO UPDATE
-            SET strength = $3, uncertainty = $4
             ",
         )
         .bind(session_id)
         .bind(photo_idx)
         .bind(rating.strength)
         .bind(rating.uncertainty)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
     }
-    Ok(())
+
+    let version: i64 = sqlx::query_scalar(
+        r"
+        INSERT INTO rating_fits
+            (session_id, version, model_version, comparisons, iterations,
+             first_position_advantage, fitted_at)
+        VALUES ($1, 1, $2, $3, $4, $5, $6)
+        ON CONFLICT (session_id) DO UPDATE
+        SET version = rating_fits.version + 1, model_version = $2, comparisons = $3,
+            iterations = $4, first_position_advantage = $5, fitted_at = $6
+        RETURNING version
+        ",
+    )
+    .bind(session_id)
+    .bind(i32::try_from(fit.model_version).unwrap_or(i32::MAX))
+    .bind(i32::try_from(fit.comparisons).unwrap_or(i32::MAX))
+    .bind(i32::try_from(fit.iterations).unwrap_or(i32::MAX))
+    .bind(fit.first_position_advantage)
+    .bind(fit.fitted_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    u64::try_from(version).map_err(|_| sqlx::Error::Protocol("Negative fit version".into()))
 }
 
-pub async fn count_compared_pairs(pool: &PgPool, session_id: Uuid) -> sqlx::Result<u64> {
-    let comparisons = get_session_comparisons(pool, session_id).await?;
-    let pairs: std::collections::HashSet<(u32, u32)> = comparisons
-        .iter()
-        .flat_map(ComparisonResult::to_pairwise)
-        .map(|(a, b)| filmorator_core::matchup::normalize_pair(a, b))
-        .collect();
-    Ok(pairs.len() as u64)
+/// Metadata of the fit behind a session's current ratings; `None` before the first fit.
+pub async fn get_rating_fit(pool: &PgPool, session_id: Uuid) -> sqlx::Result<Option<RatingFit>> {
+    let row = sqlx::query(
+        r"
+        SELECT version, model_version, comparisons, iterations,
+               first_position_advantage, fitted_at
+        FROM rating_fits
+        WHERE session_id = $1
+        ",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|r| {
+        let non_negative = |_| sqlx::Error::Protocol("Negative fit field".into());
+        Ok(RatingFit {
+            version: u64::try_from(r.get::<i64, _>("version")).map_err(non_negative)?,
+            model_version: u32::try_from(r.get::<i32, _>("model_version")).map_err(non_negative)?,
+            comparisons: u32::try_from(r.get::<i32, _>("comparisons")).map_err(non_negative)?,
+            iterations: u32::try_from(r.get::<i32, _>("iterations")).map_err(non_negative)?,
+            first_position_advantage: r.get("first_position_advantage"),
+            fitted_at: r.get("fitted_at"),
+        })
+    })
+    .transpose()
 }
 
 pub async fn get_pending_seed_matchup(
@@ -213,9 +888,10 @@ pub async fn get_pending_seed_matchup(
 ) -> sqlx::Result<Option<Matchup>> {
     let row = sqlx::query(
         r"
-        SELECT m.id, m.session_id, m.photo_indices, m.is_seed, m.created_at
+        SELECT m.id, m.session_id, m.campaign_id, m.photo_indices, m.is_seed, m.kind, m.repeat_of, m.created_at,
+               m.served_at
         FROM matchups m
-        LEFT JOIN comparison_results cr ON m.id = cr.matchup_id
+        LEFT JOIN comparison_results cr ON m.id = cr.matchup_id AND cr.superseded_at IS NULL
         WHERE m.session_id = $1 AND m.is_seed = true AND cr.id IS NULL
         ORDER BY m.created_at
         LIMIT 1
@@ -228,6 +904,54 @@ pub async fn get_pending_seed_matchup(
     row.map(matchup_from_row).transpose()
 }
 
+pub async fn get_pending_check_matchup(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> sqlx::Result<Option<Matchup>> {
+    let row = sqlx::query(
+        r"
+        SELECT m.id, m.session_id, m.campaign_id, m.photo_indices, m.is_seed, m.kind, m.repeat_of, m.created_at,
+               m.served_at
+        FROM matchups m
+        LEFT JOIN comparison_results cr ON m.id = cr.matchup_id AND cr.superseded_at IS NULL
+        WHERE m.session_id = $1 AND m.kind <> 'regular' AND cr.id IS NULL
+        ORDER BY m.created_at
+        LIMIT 1
+        ",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(matchup_from_row).transpose()
+}
+
+/// A matchup whose answer was retracted and not yet given again.
+pub async fn get_requeued_matchup(
+    pool: &PgPool,
+    session_id: Uuid,
+) -> sqlx::Result<Option<Matchup>> {
+    let row = sqlx::query(
+        r"
+        SELECT m.id, m.session_id, m.campaign_id, m.photo_indices, m.is_seed, m.kind, m.repeat_of, m.created_at,
+               m.served_at
+        FROM matchups m
+        WHERE m.session_id = $1
+          AND EXISTS (SELECT 1 FROM comparison_results cr
+                      WHERE cr.matchup_id = m.id AND cr.superseded_at IS NOT NULL)
+          AND NOT EXISTS (SELECT 1 FROM comparison_results cr
+                          WHERE cr.matchup_id = m.id AND cr.superseded_at IS NULL)
+        ORDER BY m.created_at
+        LIMIT 1
+        ",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(matchup_from_row).transpose()
+}
+
 pub async fn has_seed_matchups(pool: &PgPool, session_id: Uuid) -> sqlx::Result<bool> {
     let row = sqlx::query(
         "SELECT EXISTS(SELECT 1 FROM matchups WHERE session_id = $1 AND is_seed = true) as exists",
@@ -239,33 +963,224 @@ pub async fn has_seed_matchups(pool: &PgPool, session_id: Uuid) -> sqlx::Result<
     Ok(row.get::<bool, _>("exists"))
 }
 
-pub async fn upsert_photo(pool: &PgPool, filename: &str, position: u32) -> sqlx::Result<()> {
-    let id = Uuid::new_v4();
+pub async fn get_seed_pool(pool: &PgPool, session_id: Uuid) -> sqlx::Result<Option<MatchupPool>> {
+    let stored: Option<String> =
+        sqlx::query_scalar("SELECT pool::text FROM seed_pools WHERE session_id = $1")
+            .bind(session_id)
+            .fetch_optional(pool)
+            .await?;
+    stored
+        .map(|json| serde_json::from_str(&json).map_err(|e| sqlx::Error::Decode(e.into())))
+        .transpose()
+}
+
+pub async fn save_seed_pool(
+    pool: &PgPool,
+    session_id: Uuid,
+    seed_pool: &MatchupPool,
+) -> sqlx::Result<()> {
+    let json = serde_json::to_string(seed_pool).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    sqlx::query(
+        r"
+        INSERT INTO seed_pools (session_id, pool) VALUES ($1, $2::jsonb)
+        ON CONFLICT (session_id) DO UPDATE SET pool = EXCLUDED.pool
+        ",
+    )
+    .bind(session_id)
+    .bind(json)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_swiss_tournament(
+    pool: &PgPool,
+    campaign_id: &str,
+) -> sqlx::Result<Option<SwissTournament>> {
+    let stored: Option<String> =
+        sqlx::query_scalar("SELECT state::text FROM swiss_tournaments WHERE campaign_id = $1")
+            .bind(campaign_id)
+            .fetch_optional(pool)
+            .await?;
+    stored
+        .map(|json| serde_json::from_str(&json).map_err(|e| sqlx::Error::Decode(e.into())))
+        .transpose()
+}
+
+pub async fn save_swiss_tournament(
+    pool: &PgPool,
+    campaign_id: &str,
+    tournament: &SwissTournament,
+) -> sqlx::Result<()> {
+    let json = serde_json::to_string(tournament).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    sqlx::query(
+        r"
+        INSERT INTO swiss_tournaments (campaign_id, state) VALUES ($1, $2::jsonb)
+        ON CONFLICT (campaign_id) DO UPDATE SET state = EXCLUDED.state
+        ",
+    )
+    .bind(campaign_id)
+    .bind(json)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// A campaign's photos in index order.
+pub async fn get_photos(pool: &PgPool, campaign_id: &str) -> sqlx::Result<Vec<Photo>> {
+    let rows = sqlx::query(
+        r"SELECT id, campaign_id, filename, file_hash, position, group_label
+          FROM photos WHERE campaign_id = $1 ORDER BY position",
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|r| {
+            Ok(Photo {
+                id: r.get("id"),
+                campaign_id: r.get("campaign_id"),
+                filename: r.get("filename"),
+                file_hash: r.get("file_hash"),
+                position: u32::try_from(r.get::<i32, _>("position"))
+                    .map_err(|_| sqlx::Error::Protocol("Negative position".into()))?,
+                group: r.get("group_label"),
+            })
+        })
+        .collect()
+}
+
+/// Stores a sync's added, renamed and rehashed photos in one transaction.
+/// Positions of existing photos are never written.
+pub async fn apply_photo_sync(pool: &PgPool, sync: &PhotoSync) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for photo in &sync.photos {
+        if sync.renamed.contains(&photo.position) || sync.rehashed.contains(&photo.position) {
+            sqlx::query(
+                r"UPDATE photos SET filename = $2, file_hash = $3, group_label = $4
+                  WHERE id = $1",
+            )
+            .bind(photo.id)
+            .bind(&photo.filename)
+            .bind(&photo.file_hash)
+            .bind(&photo.group)
+            .execute(&mut *tx)
+            .await?;
+        } else if sync.added.contains(&photo.position) {
+            let position = i32::try_from(photo.position)
+                .map_err(|_| sqlx::Error::Protocol("Position overflow".into()))?;
+            sqlx::query(
+                r"INSERT INTO photos (id, campaign_id, filename, file_hash, position, group_label)
+                  VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(photo.id)
+            .bind(&photo.campaign_id)
+            .bind(&photo.filename)
+            .bind(&photo.file_hash)
+            .bind(position)
+            .bind(&photo.group)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await
+}
+
+pub async fn get_anchors(pool: &PgPool, campaign_id: &str) -> sqlx::Result<Vec<Anchor>> {
+    let rows = sqlx::query(
+        r"SELECT p.position, a.strength, a.prior_weight
+          FROM anchor_photos a
+          JOIN photos p ON p.id = a.photo_id
+          WHERE p.campaign_id = $1
+          ORDER BY p.position",
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            let position: i32 = row.get("position");
+            let prior_weight: Option<f64> = row.get("prior_weight");
+            Ok(Anchor {
+                photo_idx: u32::try_from(position)
+                    .map_err(|_| sqlx::Error::Protocol("Negative position".into()))?,
+                strength: row.get("strength"),
+                mode: prior_weight.map_or(AnchorMode::Fixed, |weight| AnchorMode::Prior { weight }),
+            })
+        })
+        .collect()
+}
+
+/// Designates the photo at `anchor.photo_idx` as an anchor. Returns `false` if no such photo.
+pub async fn upsert_anchor(
+    pool: &PgPool,
+    campaign_id: &str,
+    anchor: &Anchor,
+) -> sqlx::Result<bool> {
+    let position_i32 = i32::try_from(anchor.photo_idx)
+        .map_err(|_| sqlx::Error::Protocol("Position overflow".into()))?;
+    let prior_weight = match anchor.mode {
+        AnchorMode::Fixed => None,
+        AnchorMode::Prior { weight } => Some(weight),
+    };
+
+    let result = sqlx::query(
+        r"INSERT INTO anchor_photos (photo_id, strength, prior_weight)
+          SELECT id, $2, $3 FROM photos WHERE campaign_id = $4 AND position = $1
+          ON CONFLICT (photo_id) DO UPDATE SET strength = $2, prior_weight = $3",
+    )
+    .bind(position_i32)
+    .bind(anchor.strength)
+    .bind(prior_weight)
+    .bind(campaign_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_anchor(pool: &PgPool, campaign_id: &str, position: u32) -> sqlx::Result<bool> {
     let position_i32 =
         i32::try_from(position).map_err(|_| sqlx::Error::Protocol("Position overflow".into()))?;
 
-    sqlx::query(
-        r"INSERT INTO photos (id, filename, file_hash, position)
-          VALUES ($1, $2, $2, $3)
-          ON CONFLICT (file_hash) DO UPDATE SET position = $3",
+    let result = sqlx::query(
+        r"DELETE FROM anchor_photos
+          WHERE photo_id IN (SELECT id FROM photos WHERE campaign_id = $1 AND position = $2)",
     )
-    .bind(id)
-    .bind(filename)
+    .bind(campaign_id)
     .bind(position_i32)
     .execute(pool)
     .await?;
 
-    Ok(())
+    Ok(result.rows_affected() > 0)
+}
+
+/// Group labels of a campaign's photos, indexed by position.
+pub async fn get_photo_groups(pool: &PgPool, campaign_id: &str) -> sqlx::Result<PhotoGroups> {
+    let rows =
+        sqlx::query("SELECT group_label FROM photos WHERE campaign_id = $1 ORDER BY position")
+            .bind(campaign_id)
+            .fetch_all(pool)
+            .await?;
+
+    let labels: Vec<Option<String>> = rows.iter().map(|r| r.get("group_label")).collect();
+    Ok(PhotoGroups::from_labels(&labels))
 }
 
 pub async fn get_photo_filename_by_position(
     pool: &PgPool,
+    campaign_id: &str,
     position: u32,
 ) -> sqlx::Result<Option<String>> {
     let position_i32 =
         i32::try_from(position).map_err(|_| sqlx::Error::Protocol("Position overflow".into()))?;
 
-    let row = sqlx::query("SELECT filename FROM photos WHERE position = $1")
+    let row = sqlx::query("SELECT filename FROM photos WHERE campaign_id = $1 AND position = $2")
+        .bind(campaign_id)
         .bind(position_i32)
         .fetch_optional(pool)
         .await?;
@@ -2,37 +2,170 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
+use filmorator_core::bundle::{BundleError, ImportError};
+use filmorator_core::events::ReplayError;
+use filmorator_core::models::CampaignError;
+use filmorator_core::pool::PoolError;
+use filmorator_core::scheduler::ScheduleError;
+use filmorator_core::submission::SubmissionError;
+use filmorator_core::undo::UndoError;
+
+use crate::sync::PhotoSyncError;
 
 #[derive(Debug)]
 pub enum AppError {
     Database(sqlx::Error),
-    S3(anyhow::Error),
+    Images(anyhow::Error),
+    Pool(PoolError),
+    Campaign(CampaignError),
+    PhotoSync(PhotoSyncError),
+    Bundle(BundleError),
+    Import(ImportError<sqlx::Error>),
+    Replay(ReplayError),
     NotFound(&'static str),
     BadRequest(&'static str),
     Forbidden(&'static str),
+    Conflict(&'static str),
     Internal(&'static str),
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, msg) = match &self {
-            Self::Database(e) => {
+            // The owner needs to know which file broke the sync
+            Self::PhotoSync(PhotoSyncError::Refused(e)) => {
+                return (StatusCode::CONFLICT, format!("Photo sync refused: {e}")).into_response();
+            }
+            Self::PhotoSync(e) => {
+                tracing::error!("Photo sync: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Photo sync failed")
+            }
+            // Like a refused sync, the owner needs the reason to fix the bundle
+            Self::Bundle(e) => {
+                return (StatusCode::BAD_REQUEST, format!("Invalid bundle: {e}")).into_response();
+            }
+            Self::Import(ImportError::Repository(e)) | Self::Database(e) => {
                 tracing::error!("DB: {e}");
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error")
             }
-            Self::S3(e) => {
-                tracing::error!("S3: {e}");
-                (StatusCode::INTERNAL_SERVER_ERROR, "S3 error")
+            Self::Import(e) => {
+                return (StatusCode::CONFLICT, format!("Import refused: {e}")).into_response();
+            }
+            // Only an owner rebuilding ratings gets here, and the log needs fixing by hand
+            Self::Replay(e) => {
+                tracing::error!("Event log: {e}");
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Event log does not replay: {e}"),
+                )
+                    .into_response();
+            }
+            Self::Images(e) => {
+                tracing::error!("Image store: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Image store error")
+            }
+            Self::Pool(e) => {
+                tracing::error!("Matchup pool: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Invalid matchup pool")
+            }
+            Self::Campaign(CampaignError::Closed) => (StatusCode::CONFLICT, "Campaign is closed"),
+            Self::Campaign(CampaignError::NotClosed) => {
+                (StatusCode::CONFLICT, "Campaign is not closed")
+            }
+            Self::Campaign(CampaignError::ThresholdAlreadyReached) => {
+                (StatusCode::CONFLICT, "Campaign threshold already reached")
             }
             Self::NotFound(m) => (StatusCode::NOT_FOUND, *m),
             Self::BadRequest(m) => (StatusCode::BAD_REQUEST, *m),
             Self::Forbidden(m) => (StatusCode::FORBIDDEN, *m),
+            Self::Conflict(m) => (StatusCode::CONFLICT, *m),
             Self::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, *m),
         };
         (status, msg).into_response()
     }
 }
 
+impl From<PoolError> for AppError {
+    fn from(e: PoolError) -> Self {
+        Self::Pool(e)
+    }
+}
+
+impl From<ScheduleError<sqlx::Error>> for AppError {
+    fn from(e: ScheduleError<sqlx::Error>) -> Self {
+        match e {
+            ScheduleError::NotEnoughPhotos => Self::BadRequest("Not enough photos"),
+            ScheduleError::Pool(e) => Self::Pool(e),
+            ScheduleError::Matchup(e) => {
+                tracing::error!("Scheduled matchup: {e}");
+                Self::Internal("Invalid matchup")
+            }
+            ScheduleError::Repository(e) => Self::Database(e),
+        }
+    }
+}
+
+impl From<SubmissionError<sqlx::Error>> for AppError {
+    fn from(e: SubmissionError<sqlx::Error>) -> Self {
+        match e {
+            SubmissionError::Campaign(e) => Self::Campaign(e),
+            SubmissionError::MatchupNotFound => Self::NotFound("Matchup not found"),
+            SubmissionError::ForeignMatchup => {
+                Self::Forbidden("Matchup belongs to different session")
+            }
+            SubmissionError::InvalidRanking(_) => Self::BadRequest("Invalid ranking"),
+            SubmissionError::InvalidDisplayedOrder(_) => {
+                Self::BadRequest("Invalid displayed order")
+            }
+            SubmissionError::Conflict => {
+                Self::Conflict("Matchup already answered with a different ranking")
+            }
+            SubmissionError::Undo(UndoError::NothingToUndo) => {
+                Self::NotFound("No comparison to undo")
+            }
+            SubmissionError::Undo(UndoError::WindowExpired) => {
+                Self::Conflict("Undo window has passed")
+            }
+            SubmissionError::AlreadyUndone => Self::Conflict("Comparison already undone"),
+            SubmissionError::Inconsistent(what) => {
+                tracing::error!("Submission: {what}");
+                Self::Internal("Inconsistent comparisons")
+            }
+            SubmissionError::Repository(e) => Self::Database(e),
+        }
+    }
+}
+
+impl From<CampaignError> for AppError {
+    fn from(e: CampaignError) -> Self {
+        Self::Campaign(e)
+    }
+}
+
+impl From<PhotoSyncError> for AppError {
+    fn from(e: PhotoSyncError) -> Self {
+        Self::PhotoSync(e)
+    }
+}
+
+impl From<BundleError> for AppError {
+    fn from(e: BundleError) -> Self {
+        Self::Bundle(e)
+    }
+}
+
+impl From<ImportError<sqlx::Error>> for AppError {
+    fn from(e: ImportError<sqlx::Error>) -> Self {
+        Self::Import(e)
+    }
+}
+
+impl From<ReplayError> for AppError {
+    fn from(e: ReplayError) -> Self {
+        Self::Replay(e)
+    }
+}
+
 impl From<sqlx::Error> for AppError {
     fn from(e: sqlx::Error) -> Self {
         Self::Database(e)
@@ -41,6 +174,6 @@ impl From<sqlx::Error> for AppError {
 
 impl From<anyhow::Error> for AppError {
     fn from(e: anyhow::Error) -> Self {
-        Self::S3(e)
+        Self::Images(e)
     }
 }
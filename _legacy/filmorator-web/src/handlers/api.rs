@@ -1,37 +1,99 @@
 use axum::{
-    extract::{Path, State},
-    http::{header, StatusCode},
-    response::IntoResponse,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
 use uuid::Uuid;
 
-use crate::db;
 use crate::error::AppError;
+use crate::images::{image_key, load_manifest, save_manifest, ImageStore, ImageTier, Images};
+use crate::local::LocalImageStore;
 use crate::state::AppState;
+use crate::sync;
 
+use super::owner::OwnerSecret;
 use super::session::{session_cookie_header, SessionId};
-use filmorator_core::matchup::{
-    extract_compared_pairs, generate_seed_matchups, select_dynamic_matchup,
+use filmorator_core::attention::self_consistency;
+use filmorator_core::bundle::{self, Bundle};
+use filmorator_core::events::replay;
+use filmorator_core::export::{comparison_rows, result_rows, to_csv, Table, RESULT_ITERATIONS};
+use filmorator_core::exposure::ExposureTracker;
+use filmorator_core::groups::{group_rankings, GroupRanking};
+use filmorator_core::matchup::{extract_compared_pairs, shuffle_display_order};
+use filmorator_core::models::{
+    owner_secret_hash, CampaignRating, ComparisonResult, Matchup, DEFAULT_CAMPAIGN_ID,
 };
-use filmorator_core::models::{ComparisonResult, Matchup};
-use filmorator_core::ranking::BradleyTerry;
-
-const MATCHUP_SIZE: u32 = 3;
-const RATING_ITERATIONS: u32 = 50;
+use filmorator_core::progress::{
+    graph_progress, seed_pool_size, target_comparisons, GraphProgress,
+};
+use filmorator_core::provenance::{
+    provenance_stats, session_provenance_stats, Provenance, ProvenanceStats, SessionProvenance,
+};
+use filmorator_core::ranking::{Anchor, AnchorMode};
+use filmorator_core::refit::RatingFit;
+use filmorator_core::repository::Repository;
+use filmorator_core::scheduler::{next_matchup, Scheduled, MATCHUP_SIZE};
+use filmorator_core::submission::{self, Submission, Submitted};
+use filmorator_core::types::{PhotoIdx, Triple};
+
+/// Request header carrying a client-chosen UUID that makes submission retries safe.
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Largest bundle accepted by [`import_bundle`]; a bundle with images holds every original.
+pub const BUNDLE_LIMIT: usize = 1024 * 1024 * 1024;
+/// Locally served images are revalidated by `ETag` once this expires.
+const FILE_CACHE_CONTROL: &str = "public, max-age=3600";
 
 #[derive(Serialize)]
 pub struct MatchupResponse {
     pub matchup_id: Uuid,
-    pub photo_indices: Vec<u32>,
+    /// Matchup photos in the order they should be displayed, left to right.
+    pub photo_indices: Triple,
 }
 
+/// An answer to a matchup. Both orders must list exactly its photos.
 #[derive(Deserialize)]
 pub struct CompareRequest {
     pub matchup_id: Uuid,
-    pub ranked_photo_indices: Vec<u32>,
+    pub ranked_photo_indices: Vec<PhotoIdx>,
+    #[serde(default)]
+    pub displayed_order: Option<Vec<PhotoIdx>>,
+    #[serde(default)]
+    pub provenance: Provenance,
+}
+
+/// A replacement answer for the session's most recent comparison.
+#[derive(Deserialize)]
+pub struct ReviseRequest {
+    pub ranked_photo_indices: Vec<PhotoIdx>,
+    #[serde(default)]
+    pub displayed_order: Option<Vec<PhotoIdx>>,
+    #[serde(default)]
+    pub provenance: Provenance,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Include the original images, so the bundle restores a server from scratch.
+    #[serde(default)]
+    pub images: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    Csv,
+    #[default]
+    Json,
+}
+
+#[derive(Deserialize)]
+pub struct TableQuery {
+    #[serde(default)]
+    pub format: TableFormat,
 }
 
 #[derive(Serialize)]
@@ -39,11 +101,26 @@ pub struct ProgressResponse {
     pub compared_pairs: u64,
     pub total_pairs: u64,
     pub percent: u8,
+    pub graph: GraphProgress,
 }
 
 #[derive(Serialize)]
 pub struct RankingResponse {
     pub rankings: Vec<RankingEntry>,
+    /// The fit behind `rankings`; `None` until the first fit has been stored.
+    pub fit: Option<RatingFit>,
+}
+
+#[derive(Serialize)]
+pub struct GroupRankingResponse {
+    pub groups: Vec<GroupRanking>,
+}
+
+#[derive(Serialize)]
+pub struct CampaignProvenanceResponse {
+    /// Over every session, excluded ones included.
+    pub campaign: ProvenanceStats,
+    pub sessions: Vec<SessionProvenance>,
 }
 
 #[derive(Serialize)]
@@ -59,81 +136,51 @@ pub async fn create_matchup(
 ) -> Result<impl IntoResponse, AppError> {
     let session_id = session.0;
 
-    db::get_or_create_session(&state.db, session_id).await?;
-
-    let num_photos = db::count_photos(&state.db).await?;
-
-    if num_photos < MATCHUP_SIZE {
-        return Err(AppError::BadRequest("Not enough photos"));
-    }
+    // New sessions start in the default campaign; `/c/:campaign_id` starts them elsewhere
+    let session = state
+        .repo
+        .touch_session(session_id, DEFAULT_CAMPAIGN_ID)
+        .await?;
+    ensure_campaign_accepting(&state, session_id).await?;
 
-    // Check for pending seed matchup first
-    if let Some(pending) = db::get_pending_seed_matchup(&state.db, session_id).await? {
-        let response = MatchupResponse {
-            matchup_id: pending.id,
-            photo_indices: pending.photo_indices,
-        };
-        return Ok((
-            [(header::SET_COOKIE, session_cookie_header(session_id)?)],
-            Json(response),
-        )
-            .into_response());
+    match next_matchup(&state.repo, &session, &state.matchup_policy).await? {
+        Scheduled::Matchup(matchup) => serve_matchup(&state, session_id, &matchup).await,
+        Scheduled::Exhausted => Ok((StatusCode::OK, "All pairs compared").into_response()),
     }
+}
 
-    // Generate seed matchups if none exist
-    let has_seeds = db::has_seed_matchups(&state.db, session_id).await?;
-    if !has_seeds {
-        let seeds = generate_seed_matchups(num_photos, MATCHUP_SIZE as usize);
-        for indices in seeds {
-            let matchup = Matchup::new(session_id, indices, true);
-            db::create_matchup(&state.db, &matchup).await?;
-        }
-
-        // Return first seed matchup
-        if let Some(first) = db::get_pending_seed_matchup(&state.db, session_id).await? {
-            let response = MatchupResponse {
-                matchup_id: first.id,
-                photo_indices: first.photo_indices,
-            };
-            return Ok((
-                [(header::SET_COOKIE, session_cookie_header(session_id)?)],
-                Json(response),
-            )
-                .into_response());
-        }
+/// Refuses matchups and comparisons once the session's campaign is closed.
+async fn ensure_campaign_accepting(state: &AppState, session_id: Uuid) -> Result<(), AppError> {
+    if let Some(campaign) = state.repo.session_campaign(session_id).await? {
+        campaign.ensure_accepting()?;
     }
+    Ok(())
+}
 
-    // Seeds exhausted: generate dynamic matchup
-    let ratings = db::get_session_ratings(&state.db, session_id).await?;
-
-    let comparisons = db::get_session_comparisons(&state.db, session_id).await?;
-
-    let pairs: Vec<(u32, u32)> = comparisons
-        .iter()
-        .flat_map(ComparisonResult::to_pairwise)
-        .collect();
-    let compared = extract_compared_pairs(&pairs);
-
-    let photo_indices = if ratings.is_empty() {
-        // No ratings yet, pick first few photos
-        (0..MATCHUP_SIZE).collect()
-    } else {
-        match select_dynamic_matchup(&ratings, &compared, MATCHUP_SIZE as usize) {
-            Some(indices) => indices,
-            None => {
-                return Ok((StatusCode::OK, "All pairs compared").into_response());
-            }
-        }
-    };
-
-    let matchup = Matchup::new(session_id, photo_indices.clone(), false);
-    db::create_matchup(&state.db, &matchup).await?;
+/// The campaign a session takes part in; the default one for a session not seen yet.
+async fn session_campaign_id(state: &AppState, session_id: Uuid) -> Result<String, AppError> {
+    Ok(state
+        .repo
+        .session_campaign(session_id)
+        .await?
+        .map_or_else(|| DEFAULT_CAMPAIGN_ID.to_string(), |c| c.id))
+}
 
+/// Responds with a matchup in a fresh random display order, noting when it
+/// was served so the answer's latency can be measured.
+async fn serve_matchup(
+    state: &AppState,
+    session_id: Uuid,
+    matchup: &Matchup,
+) -> Result<Response, AppError> {
+    state
+        .repo
+        .mark_matchup_served(matchup.id, Utc::now())
+        .await?;
     let response = MatchupResponse {
         matchup_id: matchup.id,
-        photo_indices,
+        photo_indices: shuffle_display_order(matchup.photo_indices),
     };
-
     Ok((
         [(header::SET_COOKIE, session_cookie_header(session_id)?)],
         Json(response),
@@ -144,49 +191,73 @@ pub async fn create_matchup(
 pub async fn submit_comparison(
     State(state): State<AppState>,
     session: SessionId,
+    headers: HeaderMap,
     Json(request): Json<CompareRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     let session_id = session.0;
-
-    // Validate matchup exists and belongs to session
-    let matchup = match db::get_matchup(&state.db, request.matchup_id).await? {
-        Some(m) if m.session_id == session_id => m,
-        Some(_) => {
-            return Err(AppError::Forbidden("Matchup belongs to different session"));
-        }
-        None => return Err(AppError::NotFound("Matchup not found")),
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|s| Uuid::parse_str(s.trim()).ok())
+                .ok_or(AppError::BadRequest("Invalid idempotency key"))
+        })
+        .transpose()?;
+    let submission = Submission {
+        ranked: request.ranked_photo_indices,
+        displayed_order: request.displayed_order,
+        provenance: request.provenance,
+        idempotency_key,
     };
 
-    // Validate ranked indices match matchup
-    let matchup_set: HashSet<u32> = matchup.photo_indices.iter().copied().collect();
-    let ranked_set: HashSet<u32> = request.ranked_photo_indices.iter().copied().collect();
-    if matchup_set != ranked_set {
-        return Err(AppError::BadRequest("Invalid ranking"));
+    // Retries get the stored answer back; a different answer for the same matchup is refused
+    match submission::submit(&state.repo, session_id, request.matchup_id, submission).await? {
+        Submitted::Created(answered) => {
+            state
+                .ratings
+                .request_refit(session_id, &answered.matchup.campaign_id);
+            Ok((StatusCode::CREATED, Json(answered.comparison)))
+        }
+        Submitted::Replayed(prior) => Ok((StatusCode::OK, Json(prior))),
     }
+}
 
-    // Save comparison result
-    let result =
-        ComparisonResult::new(request.matchup_id, session_id, request.ranked_photo_indices);
-    db::save_comparison(&state.db, &result).await?;
-
-    // Recompute ratings
-    let num_photos = db::count_photos(&state.db).await?;
-
-    let comparisons = db::get_session_comparisons(&state.db, session_id).await?;
+/// Retracts the session's latest comparison if it is within the undo window,
+/// and serves its matchup again.
+pub async fn undo_comparison(
+    State(state): State<AppState>,
+    session: SessionId,
+) -> Result<impl IntoResponse, AppError> {
+    let session_id = session.0;
+    let matchup = submission::undo(&state.repo, session_id, Utc::now()).await?;
+    state
+        .ratings
+        .request_refit(session_id, &matchup.campaign_id);
+    serve_matchup(&state, session_id, &matchup).await
+}
 
-    let Some(mut bt) = BradleyTerry::new(num_photos as usize) else {
-        return Err(AppError::Internal("Too many photos"));
+/// Replaces the session's latest comparison with a new ranking of the same
+/// matchup, if it is within the undo window.
+pub async fn revise_comparison(
+    State(state): State<AppState>,
+    session: SessionId,
+    Json(request): Json<ReviseRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let session_id = session.0;
+    let submission = Submission {
+        ranked: request.ranked_photo_indices,
+        displayed_order: request.displayed_order,
+        provenance: request.provenance,
+        idempotency_key: None,
     };
 
-    let pairs: Vec<(u32, u32)> = comparisons
-        .iter()
-        .flat_map(ComparisonResult::to_pairwise)
-        .collect();
-    bt.record_comparisons(&pairs);
-
-    let ratings = bt.compute_ratings(RATING_ITERATIONS);
-    db::save_ratings(&state.db, session_id, &ratings).await?;
-    Ok((StatusCode::OK, "Comparison recorded").into_response())
+    let answered = submission::revise(&state.repo, session_id, submission, Utc::now()).await?;
+    state
+        .ratings
+        .request_refit(session_id, &answered.matchup.campaign_id);
+    Ok((StatusCode::CREATED, Json(answered.comparison)))
 }
 
 pub async fn get_progress(
@@ -195,28 +266,149 @@ pub async fn get_progress(
 ) -> Result<impl IntoResponse, AppError> {
     let session_id = session.0;
 
-    let num_photos = db::count_photos(&state.db).await?;
+    let campaign_id = session_campaign_id(&state, session_id).await?;
+    let num_photos = state.repo.count_photos(&campaign_id).await?;
 
-    let compared = db::count_compared_pairs(&state.db, session_id).await?;
+    let comparisons = state.repo.session_comparisons(session_id).await?;
+    let seed_pool = state.repo.seed_pool(session_id).await?;
 
+    let pairs: Vec<(u32, u32)> = comparisons
+        .iter()
+        .flat_map(ComparisonResult::to_pairwise)
+        .collect();
+    let compared = extract_compared_pairs(&pairs).len() as u64;
     let total = filmorator_core::matchup::total_pairs_needed(num_photos);
     let percent = filmorator_core::matchup::completion_percent(compared, num_photos);
 
+    // Before the session's pool is generated, estimate the size it will have
+    let pool_size = seed_pool.map_or_else(
+        || seed_pool_size(num_photos, MATCHUP_SIZE as usize),
+        |pool| pool.len(),
+    );
+    // The campaign's manifest sets its own target; the configured one covers the rest
+    let completion_target = load_manifest(&state.images, &campaign_id)
+        .await?
+        .map_or(state.completion_target, |manifest| {
+            manifest.completion_target()
+        });
+    let target = target_comparisons(pool_size, completion_target);
+    note_threshold(&state, &campaign_id, num_photos, target).await?;
+
     Ok(Json(ProgressResponse {
         compared_pairs: compared,
         total_pairs: total,
         percent,
+        graph: graph_progress(num_photos, &comparisons, target),
     })
     .into_response())
 }
 
+/// Records when the campaign's answers, over every session, first meet the
+/// statistical threshold for `target` comparisons.
+async fn note_threshold(
+    state: &AppState,
+    campaign_id: &str,
+    num_photos: u32,
+    target: u32,
+) -> Result<(), AppError> {
+    let Some(mut campaign) = state.repo.campaign(campaign_id).await? else {
+        return Ok(());
+    };
+    if campaign.threshold_reached_at.is_some() || campaign.ensure_accepting().is_err() {
+        return Ok(());
+    }
+    let comparisons = state.repo.campaign_comparisons(campaign_id).await?;
+    if graph_progress(num_photos, &comparisons, target).threshold_reached
+        && campaign.mark_threshold_reached(Utc::now()).is_ok()
+    {
+        if let Some(at) = campaign.threshold_reached_at {
+            state.repo.set_threshold_reached(campaign_id, at).await?;
+        }
+    }
+    Ok(())
+}
+
+/// How evenly the campaign's photos have been shown, over every session.
+pub async fn get_exposure(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let num_photos = state.repo.count_photos(&campaign_id).await?;
+    let served = state.repo.campaign_matchups(&campaign_id).await?;
+    let comparisons = state.repo.campaign_comparisons(&campaign_id).await?;
+
+    let exposure = ExposureTracker::from_history(num_photos, &served, &comparisons);
+
+    Ok(Json(exposure.stats()).into_response())
+}
+
+/// Self-consistency of the session's answers to quality-check matchups.
+pub async fn get_consistency(
+    State(state): State<AppState>,
+    session: SessionId,
+) -> Result<impl IntoResponse, AppError> {
+    let session_id = session.0;
+
+    let served = state.repo.session_matchups(session_id).await?;
+    let comparisons = state.repo.session_comparisons(session_id).await?;
+    let ratings = state.repo.session_ratings(session_id).await?;
+
+    Ok(Json(self_consistency(&served, &comparisons, &ratings)).into_response())
+}
+
+/// How the session's answers were given: decision times, devices, zooming.
+pub async fn get_provenance(
+    State(state): State<AppState>,
+    session: SessionId,
+) -> Result<impl IntoResponse, AppError> {
+    let comparisons = state.repo.session_comparisons(session.0).await?;
+    Ok(Json(provenance_stats(&comparisons)).into_response())
+}
+
+/// How the campaign's answers were given, overall and session by session.
+pub async fn get_campaign_provenance(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let sessions = state.repo.campaign_sessions(&campaign_id).await?;
+    let comparisons = state.repo.campaign_comparisons(&campaign_id).await?;
+
+    Ok(Json(CampaignProvenanceResponse {
+        campaign: provenance_stats(&comparisons),
+        sessions: session_provenance_stats(&sessions, &comparisons),
+    })
+    .into_response())
+}
+
+/// Display-position bias measured by the campaign's latest rating fit, over
+/// the comparisons of every session that isn't excluded.
+pub async fn get_position_bias(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let Some(bias) = state.repo.campaign_position_bias(&campaign_id).await? else {
+        // Fits follow submissions; a campaign without one yet gets one now
+        state.ratings.request_campaign_refit(&campaign_id);
+        return Err(AppError::NotFound("Position bias not measured yet"));
+    };
+    Ok(Json(bias).into_response())
+}
+
 pub async fn get_ranking(
     State(state): State<AppState>,
     session: SessionId,
 ) -> Result<impl IntoResponse, AppError> {
     let session_id = session.0;
 
-    let ratings = db::get_session_ratings(&state.db, session_id).await?;
+    // Fits land atomically in the background; this may trail the latest submission
+    let ratings = state.repo.session_ratings(session_id).await?;
+    let fit = state.repo.rating_fit(session_id).await?;
 
     let rankings: Vec<RankingEntry> = ratings
         .into_iter()
@@ -227,16 +419,176 @@ pub async fn get_ranking(
         })
         .collect();
 
-    Ok(Json(RankingResponse { rankings }).into_response())
+    Ok(Json(RankingResponse { rankings, fit }).into_response())
+}
+
+#[derive(Serialize)]
+pub struct CampaignRankingResponse {
+    pub rankings: Vec<CampaignRating>,
+}
+
+/// The ranking over every included session of a campaign, strongest first.
+pub async fn get_campaign_ranking(
+    State(state): State<AppState>,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    if state.repo.campaign(&campaign_id).await?.is_none() {
+        return Err(AppError::NotFound("Campaign not found"));
+    }
+    let rankings = state.repo.campaign_ratings(&campaign_id).await?;
+    Ok(Json(CampaignRankingResponse { rankings }).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ExclusionRequest {
+    pub excluded: bool,
 }
 
+/// Leaves a session out of (or back in) the campaign ranking, say for a
+/// participant who answered carelessly. Their answers are kept.
+pub async fn set_session_excluded(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path((campaign_id, session_id)): Path<(String, Uuid)>,
+    Json(request): Json<ExclusionRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let in_campaign = state
+        .repo
+        .session_campaign(session_id)
+        .await?
+        .is_some_and(|c| c.id == campaign_id);
+    if !in_campaign
+        || !state
+            .repo
+            .set_session_excluded(session_id, request.excluded)
+            .await?
+    {
+        return Err(AppError::NotFound("Session not found"));
+    }
+    state.ratings.request_campaign_refit(&campaign_id);
+    Ok((StatusCode::OK, "Session updated").into_response())
+}
+
+/// Stops the campaign taking matchups and answers.
+pub async fn close_campaign(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut campaign = owner.authorize(&state, &campaign_id).await?;
+    campaign.close(Utc::now())?;
+    state.repo.save_campaign(&campaign).await?;
+    Ok(Json(campaign).into_response())
+}
+
+/// Lets a closed campaign take matchups and answers again.
+pub async fn reopen_campaign(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut campaign = owner.authorize(&state, &campaign_id).await?;
+    campaign.reopen(Utc::now())?;
+    state.repo.save_campaign(&campaign).await?;
+    Ok(Json(campaign).into_response())
+}
+
+#[derive(Serialize)]
+pub struct RebuildResponse {
+    pub events: usize,
+    pub sessions_refitted: usize,
+}
+
+/// Recomputes the campaign's session and campaign ratings from the event log,
+/// for when the derived tables can't be trusted.
+pub async fn rebuild_ratings(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let events = state.repo.events(0).await?;
+    let replayed = replay(&events)?;
+    let sessions_refitted = state
+        .ratings
+        .rebuild(&campaign_id, replayed)
+        .await
+        .map_err(|e| {
+            tracing::error!("Rebuilding ratings of {campaign_id}: {e}");
+            AppError::Internal("Rebuilding ratings failed")
+        })?;
+    Ok(Json(RebuildResponse {
+        events: events.len(),
+        sessions_refitted,
+    })
+    .into_response())
+}
+
+/// Session ratings split by photo group, on the same scale as `/api/ranking`.
+pub async fn get_group_ranking(
+    State(state): State<AppState>,
+    session: SessionId,
+) -> Result<impl IntoResponse, AppError> {
+    let session_id = session.0;
+
+    let campaign_id = session_campaign_id(&state, session_id).await?;
+    let ratings = state.repo.session_ratings(session_id).await?;
+    let groups = state.repo.photo_groups(&campaign_id).await?;
+
+    Ok(Json(GroupRankingResponse {
+        groups: group_rankings(&ratings, &groups),
+    })
+    .into_response())
+}
+
+pub async fn get_anchors(
+    State(state): State<AppState>,
+    Path(campaign_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let anchors = state.repo.anchors(&campaign_id).await?;
+    Ok(Json(anchors).into_response())
+}
+
+/// Designates an anchor photo with a strength from an earlier campaign's ranking.
+pub async fn put_anchor(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+    Json(anchor): Json<Anchor>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let valid_weight = match anchor.mode {
+        AnchorMode::Fixed => true,
+        AnchorMode::Prior { weight } => weight.is_finite() && weight > 0.0,
+    };
+    if !anchor.strength.is_finite() || !valid_weight {
+        return Err(AppError::BadRequest("Invalid anchor"));
+    }
+    if !state.repo.upsert_anchor(&campaign_id, &anchor).await? {
+        return Err(AppError::NotFound("Photo not found"));
+    }
+    Ok((StatusCode::OK, "Anchor saved").into_response())
+}
+
+pub async fn delete_anchor(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path((campaign_id, photo_idx)): Path<(String, u32)>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    if !state.repo.delete_anchor(&campaign_id, photo_idx).await? {
+        return Err(AppError::NotFound("Anchor not found"));
+    }
+    Ok((StatusCode::OK, "Anchor removed").into_response())
+}
+
+/// Redirects to an image of a photo in the session's campaign.
 pub async fn get_image(
     State(state): State<AppState>,
+    session: SessionId,
     Path((tier, id)): Path<(String, String)>,
 ) -> Result<impl IntoResponse, AppError> {
-    use crate::s3::ImageTier;
-    use std::time::Duration;
-
     let Some(tier) = ImageTier::from_str(&tier) else {
         return Err(AppError::BadRequest("Invalid tier"));
     };
@@ -247,29 +599,325 @@ pub async fn get_image(
     };
 
     // Get filename from database
-    let Some(filename) = db::get_photo_filename_by_position(&state.db, position).await? else {
+    let campaign_id = session_campaign_id(&state, session.0).await?;
+    let Some(filename) = state.repo.photo_filename(&campaign_id, position).await? else {
         return Err(AppError::NotFound("Photo not found"));
     };
 
-    // Generate presigned URL (15 minute expiry)
     let url = state
-        .s3
-        .presign_url(tier, &filename, Duration::from_secs(900))
+        .images
+        .public_url(tier, &image_key(&campaign_id, &filename))
         .await?;
-
-    // Redirect to presigned URL
     Ok((StatusCode::TEMPORARY_REDIRECT, [(header::LOCATION, url)]).into_response())
 }
 
+/// Serves an image from the local image directory; 404 when images are in S3.
+pub async fn get_file(
+    State(state): State<AppState>,
+    Path((tier, filename)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let Images::Local(store) = &state.images else {
+        return Err(AppError::NotFound("Not found"));
+    };
+    let Some(tier) = ImageTier::from_str(&tier) else {
+        return Err(AppError::BadRequest("Invalid tier"));
+    };
+    serve_local_file(store, tier, &filename, &headers).await
+}
+
+/// `filename` from `store`, or `304 Not Modified` when `headers` carry its `ETag`.
+async fn serve_local_file(
+    store: &LocalImageStore,
+    tier: ImageTier,
+    filename: &str,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    let Some(path) = store.path(tier, filename) else {
+        return Err(AppError::BadRequest("Invalid filename"));
+    };
+
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Err(AppError::NotFound("File not found")),
+    };
+    // Size and modification time change whenever the file is replaced
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    let etag = format!("\"{:x}-{modified:x}\"", metadata.len());
+
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, FILE_CACHE_CONTROL.to_string()),
+    ];
+    let unchanged = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|t| t.trim() == etag || t.trim() == "*"));
+    if unchanged {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| AppError::Images(e.into()))?;
+    Ok((
+        cache_headers,
+        [(header::CONTENT_TYPE, content_type(filename))],
+        bytes,
+    )
+        .into_response())
+}
+
+fn content_type(filename: &str) -> &'static str {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("gif") => "image/gif",
+        Some("tif" | "tiff") => "image/tiff",
+        _ => "application/octet-stream",
+    }
+}
+
 pub async fn sync_photos(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
-    let filenames = state.s3.list_photos().await?;
+    let synced = sync::sync_from_store(&state.repo, &state.images).await?;
 
-    let count = filenames.len();
+    Ok((
+        StatusCode::OK,
+        format!(
+            "Synced {} photos ({} added, {} renamed)",
+            synced.photos.len(),
+            synced.added.len(),
+            synced.renamed.len()
+        ),
+    )
+        .into_response())
+}
 
-    for (position, filename) in filenames.into_iter().enumerate() {
-        let pos = u32::try_from(position).map_err(|_| AppError::Internal("Position overflow"))?;
-        db::upsert_photo(&state.db, &filename, pos).await?;
+/// Downloads a campaign as a bundle archive, with its manifest if the store
+/// has one; see [`Bundle`]. Owner only.
+pub async fn export_bundle(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let Some(mut bundle) = bundle::export(&state.repo, &campaign_id).await? else {
+        return Err(AppError::NotFound("Campaign not found"));
+    };
+    bundle.manifest = load_manifest(&state.images, &campaign_id).await?;
+    if query.images {
+        for photo in &bundle.photos {
+            let key = image_key(&campaign_id, &photo.filename);
+            let bytes = state.images.get(ImageTier::Original, &key).await?;
+            bundle.images.insert(photo.filename.clone(), bytes);
+        }
     }
 
-    Ok((StatusCode::OK, format!("Synced {count} photos")).into_response())
+    let mut archive = Vec::new();
+    bundle
+        .write(&mut archive)
+        .map_err(|_| AppError::Internal("Failed to write bundle"))?;
+    let disposition = format!("attachment; filename=\"{}.tar\"", bundle.campaign.id);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+/// Every comparison in the campaign, undone and revised ones included. Owner only.
+pub async fn export_comparisons(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+    Query(query): Query<TableQuery>,
+) -> Result<Response, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let Some(bundle) = bundle::export(&state.repo, &campaign_id).await? else {
+        return Err(AppError::NotFound("Campaign not found"));
+    };
+    let filename = format!("{}-comparisons", bundle.campaign.id);
+    Ok(table_response(
+        &comparison_rows(&bundle),
+        query.format,
+        &filename,
+    ))
+}
+
+/// The campaign ranking, fitted over every included session on request.
+pub async fn export_results(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    Path(campaign_id): Path<String>,
+    Query(query): Query<TableQuery>,
+) -> Result<Response, AppError> {
+    owner.authorize(&state, &campaign_id).await?;
+    let Some(bundle) = bundle::export(&state.repo, &campaign_id).await? else {
+        return Err(AppError::NotFound("Campaign not found"));
+    };
+    let anchors = state.repo.anchors(&campaign_id).await?;
+    let filename = format!("{}-results", bundle.campaign.id);
+
+    // The fit is CPU-bound; keep it off the async worker threads
+    let rows =
+        tokio::task::spawn_blocking(move || result_rows(&bundle, anchors, RESULT_ITERATIONS))
+            .await
+            .map_err(|e| {
+                tracing::error!("Results fit: {e}");
+                AppError::Internal("Results fit failed")
+            })?
+            .ok_or(AppError::Internal("Too many photos"))?;
+    Ok(table_response(&rows, query.format, &filename))
+}
+
+fn table_response<T: Table>(rows: &[T], format: TableFormat, filename: &str) -> Response {
+    match format {
+        TableFormat::Json => Json(rows).into_response(),
+        TableFormat::Csv => (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{filename}.csv\""),
+                ),
+            ],
+            to_csv(rows),
+        )
+            .into_response(),
+    }
+}
+
+/// Imports a bundle archive, storing whatever this server doesn't have yet.
+/// A new campaign is owned by the request's secret; an existing one must be.
+///
+/// There is no image processing, so images in the bundle are stored as every tier,
+/// under the filename this server knows the photo by. The manifest is stored
+/// if the campaign has none here, and must match if it has.
+pub async fn import_bundle(
+    State(state): State<AppState>,
+    owner: OwnerSecret,
+    body: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    let bundle = Bundle::read(body.as_ref())?;
+    let campaign_id = &bundle.campaign.id;
+    let stored_manifest = load_manifest(&state.images, campaign_id).await?;
+    if let (Some(stored), Some(imported)) = (&stored_manifest, &bundle.manifest) {
+        if stored != imported {
+            return Err(AppError::Conflict("Campaign has a different manifest here"));
+        }
+    }
+    let report = bundle::import(&state.repo, &bundle, &owner_secret_hash(&owner.0)).await?;
+
+    if let (None, Some(manifest)) = (stored_manifest, &bundle.manifest) {
+        save_manifest(&state.images, manifest).await?;
+    }
+    // Photos may have been renamed here; their content was checked on import
+    let photos = state.repo.photos(campaign_id).await?;
+    for imported in &bundle.photos {
+        let (Some(bytes), Some(photo)) = (
+            bundle.images.get(&imported.filename),
+            photos.get(imported.position as usize),
+        ) else {
+            continue;
+        };
+        for tier in ImageTier::ALL {
+            state
+                .images
+                .put(
+                    tier,
+                    &image_key(campaign_id, &photo.filename),
+                    bytes.clone(),
+                )
+                .await?;
+        }
+    }
+
+    if report.comparisons_added > 0 {
+        for session in &bundle.sessions {
+            state.ratings.request_refit(session.id, campaign_id);
+        }
+    }
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    use crate::local::tests::TempDir;
+
+    #[tokio::test]
+    async fn local_files_answer_matching_etags_with_not_modified() {
+        let dir = TempDir::new();
+        let store = dir.store();
+        store
+            .put(ImageTier::Preview, "a.jpg", b"jpeg".to_vec())
+            .await
+            .unwrap();
+        let serve = |headers: HeaderMap| {
+            let store = store.clone();
+            async move {
+                serve_local_file(&store, ImageTier::Preview, "a.jpg", &headers)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let first = serve(HeaderMap::new()).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[header::ETAG].clone();
+        assert_eq!(first.headers()[header::CONTENT_TYPE], "image/jpeg");
+
+        let if_none_match =
+            |value: HeaderValue| HeaderMap::from_iter([(header::IF_NONE_MATCH, value)]);
+        let cached = serve(if_none_match(etag.clone())).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[header::ETAG], etag);
+        let listed = format!("\"other\", {}", etag.to_str().unwrap());
+        let cached = serve(if_none_match(listed.parse().unwrap())).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        let cached = serve(if_none_match(HeaderValue::from_static("*"))).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        let stale = serve(if_none_match(HeaderValue::from_static("\"other\""))).await;
+        assert_eq!(stale.status(), StatusCode::OK);
+
+        // Replacing the file changes its tag
+        store
+            .put(ImageTier::Preview, "a.jpg", b"new jpeg".to_vec())
+            .await
+            .unwrap();
+        let replaced = serve(if_none_match(etag.clone())).await;
+        assert_eq!(replaced.status(), StatusCode::OK);
+        assert_ne!(replaced.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn local_files_refuse_missing_and_escaping_paths() {
+        let dir = TempDir::new();
+        let store = dir.store();
+        let headers = HeaderMap::new();
+
+        assert!(matches!(
+            serve_local_file(&store, ImageTier::Preview, "a.jpg", &headers).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            serve_local_file(&store, ImageTier::Preview, "../a.jpg", &headers).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
 }
@@ -1,6 +1,14 @@
-use axum::response::Html;
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{Html, IntoResponse};
+use filmorator_core::models::DEFAULT_CAMPAIGN_ID;
+use filmorator_core::repository::Repository;
+use uuid::Uuid;
 
+use super::session::{session_cookie_header, SessionId};
 use super::style;
+use crate::error::AppError;
+use crate::state::AppState;
 
 const COMPARE_CSS: &str = r"
 body { min-height: 100vh; padding: 20px; }
@@ -31,18 +39,51 @@ h1 { font-size: 1.5rem; font-weight: 300; }
 ";
 
 const COMPARE_JS: &str = r#"
-let matchupId = null, photoIndices = [], ranking = [];
+let matchupId = null, submissionKey = null, photoIndices = [], ranking = [], canUndo = false;
+let shownAt = 0, previewsLoaded = new Set(), zoomed = false;
+
+if (window.visualViewport) {
+    visualViewport.addEventListener('resize', () => { if (visualViewport.scale > 1) zoomed = true; });
+}
 
 async function loadMatchup() {
     try {
         const res = await fetch('/api/matchup', { method: 'POST' });
         if (!res.ok) { showStatus(await res.text() || 'Failed to load matchup', true); return; }
-        const data = await res.json();
-        matchupId = data.matchup_id;
-        photoIndices = data.photo_indices;
-        ranking = [];
-        renderPhotos();
-        loadProgress();
+        showMatchup(await res.json());
+    } catch (e) { showStatus('Network error', true); }
+}
+
+function showMatchup(data) {
+    matchupId = data.matchup_id;
+    submissionKey = crypto.randomUUID();
+    photoIndices = data.photo_indices;
+    ranking = [];
+    previewsLoaded = new Set();
+    zoomed = false;
+    renderPhotos();
+    shownAt = performance.now();
+    loadProgress();
+}
+
+function provenance() {
+    const width = window.innerWidth, height = window.innerHeight;
+    const coarse = window.matchMedia('(pointer: coarse)').matches;
+    return {
+        decision_ms: Math.round(performance.now() - shownAt),
+        viewport: { width, height },
+        device_class: !coarse ? 'desktop' : Math.min(width, height) < 600 ? 'phone' : 'tablet',
+        previews_loaded: previewsLoaded.size === photoIndices.length,
+        zoomed
+    };
+}
+
+async function undoLast() {
+    try {
+        const res = await fetch('/api/compare/undo', { method: 'POST' });
+        canUndo = false;
+        if (!res.ok) { document.getElementById('undoBtn').disabled = true; return; }
+        showMatchup(await res.json());
     } catch (e) { showStatus('Network error', true); }
 }
 
@@ -52,7 +93,7 @@ async function loadProgress() {
         if (res.ok) {
             const data = await res.json();
             document.getElementById('progress').textContent =
-                `${data.percent}% (${data.compared_pairs}/${data.total_pairs} pairs)`;
+                `${data.graph.target_percent}% (${data.graph.comparisons_per_photo.toFixed(1)}/${data.graph.target_per_photo.toFixed(1)} per photo)`;
         }
     } catch (e) { }
 }
@@ -68,13 +109,14 @@ function renderPhotos() {
         <div class="ranking" id="ranking"><span style="color: var(--muted);">Click photos in order: best → worst</span></div>
         <div class="actions">
             <button class="btn btn-secondary" onclick="clearRanking()">Clear</button>
+            <button class="btn btn-secondary" id="undoBtn" ${canUndo ? '' : 'disabled'} onclick="undoLast()">Undo last</button>
             <button class="btn btn-primary" id="submitBtn" disabled onclick="submitRanking()">Submit</button>
         </div>`;
 }
 
 function loadPreview(img, idx) {
     const preview = new Image();
-    preview.onload = () => { img.src = preview.src; };
+    preview.onload = () => { img.src = preview.src; previewsLoaded.add(idx); };
     preview.src = `/img/preview/${idx}`;
 }
 
@@ -106,10 +148,17 @@ function updateUI() {
 async function submitRanking() {
     try {
         const res = await fetch('/api/compare', {
-            method: 'POST', headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({ matchup_id: matchupId, ranked_photo_indices: ranking })
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json', 'Idempotency-Key': submissionKey },
+            body: JSON.stringify({
+                matchup_id: matchupId,
+                ranked_photo_indices: ranking,
+                displayed_order: photoIndices,
+                provenance: provenance()
+            })
         });
         if (!res.ok) { showStatus('Failed to submit', true); return; }
+        canUndo = true;
         loadMatchup();
     } catch (e) { showStatus('Network error', true); }
 }
@@ -121,7 +170,38 @@ function showStatus(msg, isError) {
 loadMatchup();
 "#;
 
-pub async fn page() -> Html<String> {
+/// The compare page for the default campaign.
+pub async fn page(
+    State(state): State<AppState>,
+    session: SessionId,
+) -> Result<impl IntoResponse, AppError> {
+    campaign_page(State(state), Path(DEFAULT_CAMPAIGN_ID.to_string()), session).await
+}
+
+/// The compare page for a campaign. A session belongs to one campaign, so a
+/// visitor whose session is in another one is given a new session here.
+pub async fn campaign_page(
+    State(state): State<AppState>,
+    Path(campaign_id): Path<String>,
+    session: SessionId,
+) -> Result<impl IntoResponse, AppError> {
+    if state.repo.campaign(&campaign_id).await?.is_none() {
+        return Err(AppError::NotFound("Campaign not found"));
+    }
+    let mut session = state.repo.touch_session(session.0, &campaign_id).await?;
+    if session.campaign_id != campaign_id {
+        session = state
+            .repo
+            .touch_session(Uuid::new_v4(), &campaign_id)
+            .await?;
+    }
+    Ok((
+        [(header::SET_COOKIE, session_cookie_header(session.id)?)],
+        render(),
+    ))
+}
+
+fn render() -> Html<String> {
     Html(format!(
         r#"<!DOCTYPE html>
 <html lang="en">
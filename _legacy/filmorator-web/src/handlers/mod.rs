@@ -1,5 +1,6 @@
 pub mod api;
 pub mod compare;
+pub mod owner;
 pub mod session;
 mod style;
 
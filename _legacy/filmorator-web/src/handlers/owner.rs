@@ -0,0 +1,52 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+};
+use filmorator_core::models::Campaign;
+use filmorator_core::repository::Repository;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// The campaign owner's secret, sent as `Authorization: Bearer <secret>`.
+pub struct OwnerSecret(pub String);
+
+#[async_trait]
+impl FromRequestParts<AppState> for OwnerSecret {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| Self(secret.to_string()))
+            .ok_or((StatusCode::UNAUTHORIZED, "Owner secret required"))
+    }
+}
+
+impl OwnerSecret {
+    /// The campaign, if this is its owner's secret.
+    pub async fn authorize(
+        &self,
+        state: &AppState,
+        campaign_id: &str,
+    ) -> Result<Campaign, AppError> {
+        let campaign = state
+            .repo
+            .campaign(campaign_id)
+            .await?
+            .ok_or(AppError::NotFound("Campaign not found"))?;
+        if !campaign.is_owned_by(&self.0) {
+            return Err(AppError::Forbidden("Not the campaign owner"));
+        }
+        Ok(campaign)
+    }
+}
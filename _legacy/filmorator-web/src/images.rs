@@ -0,0 +1,203 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::path::{Component, Path};
+
+use anyhow::Context;
+use filmorator_core::manifest::Manifest;
+use filmorator_core::models::DEFAULT_CAMPAIGN_ID;
+
+use crate::local::LocalImageStore;
+use crate::s3::S3Client;
+
+/// Directory of the store that holds campaigns other than the default one.
+pub const CAMPAIGNS_DIR: &str = "campaigns/";
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImageTier {
+    Thumb,
+    Preview,
+    Original,
+}
+
+impl ImageTier {
+    pub const ALL: [Self; 3] = [Self::Thumb, Self::Preview, Self::Original];
+
+    #[must_use]
+    pub fn as_prefix(self) -> &'static str {
+        match self {
+            Self::Thumb => "thumb",
+            Self::Preview => "preview",
+            Self::Original => "original",
+        }
+    }
+
+    #[must_use]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "thumb" => Some(Self::Thumb),
+            "preview" => Some(Self::Preview),
+            "original" => Some(Self::Original),
+            _ => None,
+        }
+    }
+}
+
+/// Where photos live, one copy per tier under the same filename.
+pub trait ImageStore: Send + Sync {
+    /// Filenames of every original, sorted; group directories are kept as `/`-separated prefixes.
+    fn list(&self) -> impl Future<Output = anyhow::Result<Vec<String>>> + Send;
+
+    fn get(
+        &self,
+        tier: ImageTier,
+        filename: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<u8>>> + Send;
+
+    fn put(
+        &self,
+        tier: ImageTier,
+        filename: &str,
+        bytes: Vec<u8>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// The bytes at [`manifest_key`], outside the tiers; `None` if the campaign has no manifest.
+    fn manifest(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<Vec<u8>>>> + Send;
+
+    fn put_manifest(
+        &self,
+        campaign_id: &str,
+        bytes: Vec<u8>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// A URL the browser can load the image from for at least the next few minutes.
+    fn public_url(
+        &self,
+        tier: ImageTier,
+        filename: &str,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send;
+}
+
+/// A relative path without `..`, `.` or root components, so it can't escape a tier.
+#[must_use]
+pub fn is_valid_filename(filename: &str) -> bool {
+    !filename.is_empty()
+        && Path::new(filename)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Where a campaign's photo is kept in the store.
+///
+/// The default campaign's photos are synced from the store and keep their
+/// filenames; other campaigns only get photos from bundle imports and keep
+/// them under `campaigns/<campaign_id>/`, which syncs skip.
+#[must_use]
+pub fn image_key<'a>(campaign_id: &str, filename: &'a str) -> Cow<'a, str> {
+    if campaign_id == DEFAULT_CAMPAIGN_ID {
+        Cow::Borrowed(filename)
+    } else {
+        Cow::Owned(format!("{CAMPAIGNS_DIR}{campaign_id}/{filename}"))
+    }
+}
+
+/// Where a campaign's [`Manifest`] is kept in the store, next to the tiers.
+#[must_use]
+pub fn manifest_key(campaign_id: &str) -> String {
+    format!("{CAMPAIGNS_DIR}{campaign_id}/manifest.json")
+}
+
+/// The campaign's manifest, validated; `None` if it has none.
+pub async fn load_manifest(
+    images: &impl ImageStore,
+    campaign_id: &str,
+) -> anyhow::Result<Option<Manifest>> {
+    let Some(bytes) = images.manifest(campaign_id).await? else {
+        return Ok(None);
+    };
+    let manifest: Manifest = serde_json::from_slice(&bytes)
+        .with_context(|| format!("reading {}", manifest_key(campaign_id)))?;
+    anyhow::ensure!(
+        manifest.id() == campaign_id,
+        "{} is for campaign {}",
+        manifest_key(campaign_id),
+        manifest.id()
+    );
+    Ok(Some(manifest))
+}
+
+pub async fn save_manifest(images: &impl ImageStore, manifest: &Manifest) -> anyhow::Result<()> {
+    let bytes = serde_json::to_vec_pretty(manifest)?;
+    images.put_manifest(manifest.id(), bytes).await
+}
+
+/// The image store chosen at startup.
+#[derive(Clone)]
+pub enum Images {
+    S3(S3Client),
+    Local(LocalImageStore),
+}
+
+impl ImageStore for Images {
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        match self {
+            Self::S3(s3) => s3.list().await,
+            Self::Local(local) => local.list().await,
+        }
+    }
+
+    async fn get(&self, tier: ImageTier, filename: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::S3(s3) => s3.get(tier, filename).await,
+            Self::Local(local) => local.get(tier, filename).await,
+        }
+    }
+
+    async fn put(&self, tier: ImageTier, filename: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::S3(s3) => s3.put(tier, filename, bytes).await,
+            Self::Local(local) => local.put(tier, filename, bytes).await,
+        }
+    }
+
+    async fn manifest(&self, campaign_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            Self::S3(s3) => s3.manifest(campaign_id).await,
+            Self::Local(local) => local.manifest(campaign_id).await,
+        }
+    }
+
+    async fn put_manifest(&self, campaign_id: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::S3(s3) => s3.put_manifest(campaign_id, bytes).await,
+            Self::Local(local) => local.put_manifest(campaign_id, bytes).await,
+        }
+    }
+
+    async fn public_url(&self, tier: ImageTier, filename: &str) -> anyhow::Result<String> {
+        match self {
+            Self::S3(s3) => s3.public_url(tier, filename).await,
+            Self::Local(local) => local.public_url(tier, filename).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames_stay_inside_their_tier() {
+        assert!(is_valid_filename("a.jpg"));
+        assert!(is_valid_filename("roll-03/12.jpg"));
+
+        assert!(!is_valid_filename(""));
+        assert!(!is_valid_filename(".."));
+        assert!(!is_valid_filename("../a.jpg"));
+        assert!(!is_valid_filename("roll-03/../../a.jpg"));
+        assert!(!is_valid_filename("./a.jpg"));
+        assert!(!is_valid_filename("/etc/passwd"));
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::images::{is_valid_filename, manifest_key, ImageStore, ImageTier};
+
+/// [`ImageStore`] over a directory laid out like the bucket (`original/`, `preview/`,
+/// `thumb/`), with images served by the app itself under `/files/`.
+#[derive(Debug, Clone)]
+pub struct LocalImageStore {
+    root: PathBuf,
+}
+
+impl LocalImageStore {
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Where `filename` lives on disk, or `None` if it would escape the tier directory.
+    #[must_use]
+    pub fn path(&self, tier: ImageTier, filename: &str) -> Option<PathBuf> {
+        is_valid_filename(filename).then(|| self.root.join(tier.as_prefix()).join(filename))
+    }
+
+    fn checked_path(&self, tier: ImageTier, filename: &str) -> anyhow::Result<PathBuf> {
+        self.path(tier, filename)
+            .with_context(|| format!("invalid image filename: {filename:?}"))
+    }
+
+    fn manifest_path(&self, campaign_id: &str) -> anyhow::Result<PathBuf> {
+        let key = manifest_key(campaign_id);
+        anyhow::ensure!(
+            is_valid_filename(&key),
+            "invalid campaign id: {campaign_id:?}"
+        );
+        Ok(self.root.join(key))
+    }
+}
+
+impl ImageStore for LocalImageStore {
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        let originals = self.root.join(ImageTier::Original.as_prefix());
+        let mut filenames = Vec::new();
+        // Like an empty bucket, a fresh directory has no originals yet
+        if !tokio::fs::try_exists(&originals).await? {
+            return Ok(filenames);
+        }
+        // Subdirectories are photo groups, so walk the whole tree
+        let mut pending = vec![originals.clone()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir)
+                .await
+                .with_context(|| format!("reading {}", dir.display()))?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let relative = path.strip_prefix(&originals)?;
+                let Some(parts) = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_str())
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    tracing::warn!("Skipping non-UTF-8 image path {}", path.display());
+                    continue;
+                };
+                filenames.push(parts.join("/"));
+            }
+        }
+
+        filenames.sort();
+        Ok(filenames)
+    }
+
+    async fn get(&self, tier: ImageTier, filename: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.checked_path(tier, filename)?;
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("reading {}", path.display()))
+    }
+
+    async fn put(&self, tier: ImageTier, filename: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let path = self.checked_path(tier, filename)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    async fn manifest(&self, campaign_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.manifest_path(campaign_id)?;
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    async fn put_manifest(&self, campaign_id: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let path = self.manifest_path(campaign_id)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    async fn public_url(&self, tier: ImageTier, filename: &str) -> anyhow::Result<String> {
+        self.checked_path(tier, filename)?;
+        let mut url = format!("/files/{}", tier.as_prefix());
+        for segment in filename.split('/') {
+            url.push('/');
+            url.push_str(&encode_segment(segment));
+        }
+        Ok(url)
+    }
+}
+
+/// Percent-encodes everything but unreserved characters in one URL path segment.
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    /// A fresh directory under the system temp dir, removed on drop.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("filmorator-{}", Uuid::new_v4()));
+            std::fs::create_dir(&dir).unwrap();
+            Self(dir)
+        }
+
+        pub(crate) fn store(&self) -> LocalImageStore {
+            LocalImageStore::new(&self.0)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn put_then_get_and_list() {
+        let dir = TempDir::new();
+        let store = dir.store();
+        assert!(store.list().await.unwrap().is_empty());
+
+        store
+            .put(ImageTier::Original, "roll-03/12.jpg", b"twelve".to_vec())
+            .await
+            .unwrap();
+        store
+            .put(ImageTier::Original, "b.jpg", b"bee".to_vec())
+            .await
+            .unwrap();
+        store
+            .put(ImageTier::Thumb, "c.jpg", b"sea".to_vec())
+            .await
+            .unwrap();
+
+        // Only originals are listed, with group directories kept
+        assert_eq!(store.list().await.unwrap(), ["b.jpg", "roll-03/12.jpg"]);
+        assert_eq!(
+            store
+                .get(ImageTier::Original, "roll-03/12.jpg")
+                .await
+                .unwrap(),
+            b"twelve"
+        );
+        assert_eq!(store.get(ImageTier::Thumb, "c.jpg").await.unwrap(), b"sea");
+        assert!(store.get(ImageTier::Preview, "b.jpg").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_paths_outside_the_tiers() {
+        let dir = TempDir::new();
+        let store = dir.store();
+
+        for filename in ["", "../escaped.jpg", "/tmp/escaped.jpg"] {
+            assert!(store.path(ImageTier::Original, filename).is_none());
+            assert!(store
+                .put(ImageTier::Original, filename, b"x".to_vec())
+                .await
+                .is_err());
+            assert!(store.get(ImageTier::Original, filename).await.is_err());
+            assert!(store
+                .public_url(ImageTier::Original, filename)
+                .await
+                .is_err());
+        }
+        assert!(!dir.0.join("escaped.jpg").exists());
+        assert!(store.manifest("../..").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn manifests_round_trip() {
+        let dir = TempDir::new();
+        let store = dir.store();
+
+        assert_eq!(store.manifest("trip").await.unwrap(), None);
+        store.put_manifest("trip", b"{}".to_vec()).await.unwrap();
+        assert_eq!(
+            store.manifest("trip").await.unwrap().as_deref(),
+            Some(&b"{}"[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn public_urls_encode_each_segment() {
+        let store = LocalImageStore::new("/unused");
+        assert_eq!(
+            store
+                .public_url(ImageTier::Preview, "roll 3/a&b.jpg")
+                .await
+                .unwrap(),
+            "/files/preview/roll%203/a%26b.jpg"
+        );
+    }
+}
@@ -1,19 +1,33 @@
 mod config;
+#[cfg(not(feature = "sqlite"))]
 mod db;
 mod error;
 mod handlers;
+mod images;
+mod local;
+#[cfg(not(feature = "sqlite"))]
+mod postgres;
 mod s3;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod state;
+mod sync;
+mod worker;
 
-use axum::{routing::get, routing::post, Router};
-use sqlx::postgres::PgPoolOptions;
+use axum::extract::DefaultBodyLimit;
+use axum::{routing::delete, routing::get, routing::post, routing::put, Router};
+use filmorator_core::models::{owner_secret_hash, DEFAULT_CAMPAIGN_ID};
+use filmorator_core::repository::Repository;
+use filmorator_core::scheduler::MatchupPolicy;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use config::Config;
+use config::{Config, ImageSource};
+use images::Images;
+use local::LocalImageStore;
 use s3::S3Client;
-use state::AppState;
+use state::{AppState, Store};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -26,55 +40,149 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let config = Config::from_env()?;
-    let db = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&config.database_url)
-        .await?;
-    sqlx::migrate!("../migrations").run(&db).await?;
+    let repo = Store::connect(&config.database_url).await?;
+    if let Some(secret) = &config.owner_secret {
+        repo.set_owner_secret_hash(DEFAULT_CAMPAIGN_ID, &owner_secret_hash(secret))
+            .await?;
+        tracing::info!("Default campaign owned by FILMORATOR_OWNER_SECRET");
+    }
 
-    let s3 = match &config.s3_endpoint {
-        Some(endpoint) => {
-            tracing::info!("Using custom S3 endpoint: {endpoint}");
-            if let Some(public) = &config.s3_public_url {
-                tracing::info!("Public S3 URL for presigning: {public}");
-            }
-            S3Client::with_endpoint(
-                config.bucket.clone(),
-                endpoint,
-                config.s3_public_url.as_deref(),
-            )
-            .await
-        }
-        None => S3Client::from_env(config.bucket.clone()).await,
-    };
+    let images = connect_images(config.images).await;
 
-    sync_photos_from_s3(&db, &s3).await?;
+    match sync::sync_from_store(&repo, &images).await {
+        Ok(synced) => tracing::info!(
+            "Synced {} photos ({} added, {} renamed)",
+            synced.photos.len(),
+            synced.added.len(),
+            synced.renamed.len()
+        ),
+        // Keep serving the photos as indexed rather than reshuffle them
+        Err(sync::PhotoSyncError::Refused(e)) => tracing::error!("Photo sync refused: {e}"),
+        Err(e) => return Err(e.into()),
+    }
+
+    let app = routes()
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+        .with_state(AppState::new(
+            repo,
+            images,
+            MatchupPolicy {
+                grouping: config.matchup_grouping,
+                exposure: config.exposure_policy,
+                mode: config.matchup_mode,
+                attention: config.attention_policy,
+            },
+            config.completion_target,
+        ));
+
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.port)).await?;
+    tracing::info!("Listening on port {}", config.port);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
 
-    let app = Router::new()
+fn routes() -> Router<AppState> {
+    Router::new()
         .route("/", get(handlers::index))
         .route("/compare", get(handlers::compare::page))
+        .route("/c/:campaign_id", get(handlers::compare::campaign_page))
         .route("/api/matchup", post(handlers::api::create_matchup))
         .route("/api/compare", post(handlers::api::submit_comparison))
+        .route("/api/compare/undo", post(handlers::api::undo_comparison))
+        .route(
+            "/api/compare/revise",
+            post(handlers::api::revise_comparison),
+        )
         .route("/api/ranking", get(handlers::api::get_ranking))
+        .route("/api/ranking/groups", get(handlers::api::get_group_ranking))
+        .route(
+            "/api/campaigns/:campaign_id/ranking",
+            get(handlers::api::get_campaign_ranking),
+        )
         .route("/api/progress", get(handlers::api::get_progress))
+        .route("/api/consistency", get(handlers::api::get_consistency))
+        .route("/api/provenance", get(handlers::api::get_provenance))
+        .route(
+            "/api/campaigns/:campaign_id/sessions/:session_id/excluded",
+            put(handlers::api::set_session_excluded),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/close",
+            post(handlers::api::close_campaign),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/reopen",
+            post(handlers::api::reopen_campaign),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/ratings/rebuild",
+            post(handlers::api::rebuild_ratings),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/exposure",
+            get(handlers::api::get_exposure),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/provenance",
+            get(handlers::api::get_campaign_provenance),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/position-bias",
+            get(handlers::api::get_position_bias),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/anchors",
+            get(handlers::api::get_anchors).put(handlers::api::put_anchor),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/anchors/:photo_idx",
+            delete(handlers::api::delete_anchor),
+        )
         .route("/api/sync", post(handlers::api::sync_photos))
+        .route(
+            "/api/campaigns/:campaign_id/bundle",
+            get(handlers::api::export_bundle),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/comparisons",
+            get(handlers::api::export_comparisons),
+        )
+        .route(
+            "/api/campaigns/:campaign_id/results",
+            get(handlers::api::export_results),
+        )
+        .route(
+            "/api/bundles",
+            post(handlers::api::import_bundle)
+                .layer(DefaultBodyLimit::max(handlers::api::BUNDLE_LIMIT)),
+        )
         .route("/img/:tier/:id", get(handlers::api::get_image))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(AppState::new(db, s3));
-
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.port)).await?;
-    tracing::info!("Listening on port {}", config.port);
-    axum::serve(listener, app).await?;
-    Ok(())
+        .route("/files/:tier/*filename", get(handlers::api::get_file))
 }
 
-async fn sync_photos_from_s3(db: &sqlx::PgPool, s3: &S3Client) -> anyhow::Result<()> {
-    let filenames = s3.list_photos().await?;
-    tracing::info!("Found {} photos in S3", filenames.len());
-    for (pos, filename) in filenames.iter().enumerate() {
-        let pos = u32::try_from(pos).map_err(|_| anyhow::anyhow!("Position overflow"))?;
-        db::upsert_photo(db, filename, pos).await?;
+/// The image store `source` points at.
+async fn connect_images(source: ImageSource) -> Images {
+    match source {
+        ImageSource::Local(dir) => {
+            tracing::info!("Serving images from {}", dir.display());
+            Images::Local(LocalImageStore::new(dir))
+        }
+        ImageSource::S3 {
+            bucket,
+            endpoint: Some(endpoint),
+            public_url,
+        } => {
+            tracing::info!("Using custom S3 endpoint: {endpoint}");
+            if let Some(public) = &public_url {
+                tracing::info!("Public S3 URL for presigning: {public}");
+            }
+            Images::S3(S3Client::with_endpoint(bucket, &endpoint, public_url.as_deref()).await)
+        }
+        ImageSource::S3 {
+            bucket,
+            endpoint: None,
+            ..
+        } => Images::S3(S3Client::from_env(bucket).await),
     }
-    Ok(())
 }
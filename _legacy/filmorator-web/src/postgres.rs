@@ -0,0 +1,258 @@
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use filmorator_core::events::LoggedEvent;
+use filmorator_core::groups::PhotoGroups;
+use filmorator_core::identity::PhotoSync;
+use filmorator_core::matchup::SwissTournament;
+use filmorator_core::models::{
+    Campaign, CampaignRating, ComparisonResult, Matchup, Photo, PhotoRating, Session,
+};
+use filmorator_core::pool::MatchupPool;
+use filmorator_core::ranking::{Anchor, PositionBias};
+use filmorator_core::refit::RatingFit;
+use filmorator_core::repository::Repository;
+
+use crate::db;
+
+/// [`Repository`] backed by the Postgres schema in `migrations/`.
+#[derive(Clone)]
+pub struct PgRepository {
+    pool: PgPool,
+}
+
+impl PgRepository {
+    /// Connects and brings the schema up to date.
+    pub async fn connect(database_url: &str) -> anyhow::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await?;
+        sqlx::migrate!("../migrations").run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+impl Repository for PgRepository {
+    type Error = sqlx::Error;
+
+    async fn touch_session(&self, session_id: Uuid, campaign_id: &str) -> sqlx::Result<Session> {
+        db::get_or_create_session(&self.pool, session_id, campaign_id).await
+    }
+
+    async fn import_session(&self, session: &Session) -> sqlx::Result<bool> {
+        db::import_session(&self.pool, session).await
+    }
+
+    async fn set_session_excluded(&self, session_id: Uuid, excluded: bool) -> sqlx::Result<bool> {
+        db::set_session_excluded(&self.pool, session_id, excluded).await
+    }
+
+    async fn session_campaign(&self, session_id: Uuid) -> sqlx::Result<Option<Campaign>> {
+        db::get_session_campaign(&self.pool, session_id).await
+    }
+
+    async fn campaign(&self, campaign_id: &str) -> sqlx::Result<Option<Campaign>> {
+        db::get_campaign(&self.pool, campaign_id).await
+    }
+
+    async fn campaign_sessions(&self, campaign_id: &str) -> sqlx::Result<Vec<Session>> {
+        db::get_campaign_sessions(&self.pool, campaign_id).await
+    }
+
+    async fn save_campaign(&self, campaign: &Campaign) -> sqlx::Result<()> {
+        db::save_campaign(&self.pool, campaign).await
+    }
+
+    async fn set_threshold_reached(
+        &self,
+        campaign_id: &str,
+        at: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        db::set_threshold_reached(&self.pool, campaign_id, at).await
+    }
+
+    async fn set_owner_secret_hash(
+        &self,
+        campaign_id: &str,
+        owner_secret_hash: &str,
+    ) -> sqlx::Result<bool> {
+        db::set_owner_secret_hash(&self.pool, campaign_id, owner_secret_hash).await
+    }
+
+    async fn count_photos(&self, campaign_id: &str) -> sqlx::Result<u32> {
+        db::count_photos(&self.pool, campaign_id).await
+    }
+
+    async fn photos(&self, campaign_id: &str) -> sqlx::Result<Vec<Photo>> {
+        db::get_photos(&self.pool, campaign_id).await
+    }
+
+    async fn apply_photo_sync(&self, sync: &PhotoSync) -> sqlx::Result<()> {
+        db::apply_photo_sync(&self.pool, sync).await
+    }
+
+    async fn photo_filename(
+        &self,
+        campaign_id: &str,
+        position: u32,
+    ) -> sqlx::Result<Option<String>> {
+        db::get_photo_filename_by_position(&self.pool, campaign_id, position).await
+    }
+
+    async fn photo_groups(&self, campaign_id: &str) -> sqlx::Result<PhotoGroups> {
+        db::get_photo_groups(&self.pool, campaign_id).await
+    }
+
+    async fn anchors(&self, campaign_id: &str) -> sqlx::Result<Vec<Anchor>> {
+        db::get_anchors(&self.pool, campaign_id).await
+    }
+
+    async fn upsert_anchor(&self, campaign_id: &str, anchor: &Anchor) -> sqlx::Result<bool> {
+        db::upsert_anchor(&self.pool, campaign_id, anchor).await
+    }
+
+    async fn delete_anchor(&self, campaign_id: &str, photo_idx: u32) -> sqlx::Result<bool> {
+        db::delete_anchor(&self.pool, campaign_id, photo_idx).await
+    }
+
+    async fn create_matchup(&self, matchup: &Matchup) -> sqlx::Result<()> {
+        db::create_matchup(&self.pool, matchup).await
+    }
+
+    async fn matchup(&self, matchup_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        db::get_matchup(&self.pool, matchup_id).await
+    }
+
+    async fn mark_matchup_served(&self, matchup_id: Uuid, at: DateTime<Utc>) -> sqlx::Result<bool> {
+        db::mark_matchup_served(&self.pool, matchup_id, at).await
+    }
+
+    async fn session_matchups(&self, session_id: Uuid) -> sqlx::Result<Vec<Matchup>> {
+        db::get_session_matchups(&self.pool, session_id).await
+    }
+
+    async fn campaign_matchups(&self, campaign_id: &str) -> sqlx::Result<Vec<Matchup>> {
+        db::get_campaign_matchups(&self.pool, campaign_id).await
+    }
+
+    async fn pending_seed_matchup(&self, session_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        db::get_pending_seed_matchup(&self.pool, session_id).await
+    }
+
+    async fn pending_check_matchup(&self, session_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        db::get_pending_check_matchup(&self.pool, session_id).await
+    }
+
+    async fn requeued_matchup(&self, session_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        db::get_requeued_matchup(&self.pool, session_id).await
+    }
+
+    async fn has_seed_matchups(&self, session_id: Uuid) -> sqlx::Result<bool> {
+        db::has_seed_matchups(&self.pool, session_id).await
+    }
+
+    async fn seed_pool(&self, session_id: Uuid) -> sqlx::Result<Option<MatchupPool>> {
+        db::get_seed_pool(&self.pool, session_id).await
+    }
+
+    async fn save_seed_pool(&self, session_id: Uuid, pool: &MatchupPool) -> sqlx::Result<()> {
+        db::save_seed_pool(&self.pool, session_id, pool).await
+    }
+
+    async fn swiss_tournament(&self, campaign_id: &str) -> sqlx::Result<Option<SwissTournament>> {
+        db::get_swiss_tournament(&self.pool, campaign_id).await
+    }
+
+    async fn save_swiss_tournament(
+        &self,
+        campaign_id: &str,
+        tournament: &SwissTournament,
+    ) -> sqlx::Result<()> {
+        db::save_swiss_tournament(&self.pool, campaign_id, tournament).await
+    }
+
+    async fn save_comparison(&self, result: &ComparisonResult) -> sqlx::Result<bool> {
+        db::save_comparison(&self.pool, result).await
+    }
+
+    async fn retract_comparison(
+        &self,
+        comparison_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        db::retract_comparison(&self.pool, comparison_id, at).await
+    }
+
+    async fn revise_comparison(
+        &self,
+        comparison_id: Uuid,
+        revision: &ComparisonResult,
+    ) -> sqlx::Result<bool> {
+        db::revise_comparison(&self.pool, comparison_id, revision).await
+    }
+
+    async fn find_prior_submission(
+        &self,
+        session_id: Uuid,
+        matchup_id: Uuid,
+        idempotency_key: Option<Uuid>,
+    ) -> sqlx::Result<Option<ComparisonResult>> {
+        db::find_prior_submission(&self.pool, session_id, matchup_id, idempotency_key).await
+    }
+
+    async fn session_comparisons(&self, session_id: Uuid) -> sqlx::Result<Vec<ComparisonResult>> {
+        db::get_session_comparisons(&self.pool, session_id).await
+    }
+
+    async fn campaign_comparisons(&self, campaign_id: &str) -> sqlx::Result<Vec<ComparisonResult>> {
+        db::get_campaign_comparisons(&self.pool, campaign_id).await
+    }
+
+    async fn comparison_history(&self, session_id: Uuid) -> sqlx::Result<Vec<ComparisonResult>> {
+        db::get_comparison_history(&self.pool, session_id).await
+    }
+
+    async fn session_ratings(&self, session_id: Uuid) -> sqlx::Result<Vec<PhotoRating>> {
+        db::get_session_ratings(&self.pool, session_id).await
+    }
+
+    async fn save_rating_fit(
+        &self,
+        session_id: Uuid,
+        ratings: &[PhotoRating],
+        fit: &RatingFit,
+    ) -> sqlx::Result<u64> {
+        db::save_rating_fit(&self.pool, session_id, ratings, fit).await
+    }
+
+    async fn rating_fit(&self, session_id: Uuid) -> sqlx::Result<Option<RatingFit>> {
+        db::get_rating_fit(&self.pool, session_id).await
+    }
+
+    async fn campaign_ratings(&self, campaign_id: &str) -> sqlx::Result<Vec<CampaignRating>> {
+        db::get_campaign_ratings(&self.pool, campaign_id).await
+    }
+
+    async fn campaign_position_bias(
+        &self,
+        campaign_id: &str,
+    ) -> sqlx::Result<Option<PositionBias>> {
+        db::get_campaign_position_bias(&self.pool, campaign_id).await
+    }
+
+    async fn save_campaign_ratings(
+        &self,
+        campaign_id: &str,
+        ratings: &[CampaignRating],
+        position_bias: &PositionBias,
+    ) -> sqlx::Result<()> {
+        db::save_campaign_ratings(&self.pool, campaign_id, ratings, position_bias).await
+    }
+
+    async fn events(&self, after_seq: u64) -> sqlx::Result<Vec<LoggedEvent>> {
+        db::get_events(&self.pool, after_seq).await
+    }
+}
@@ -1,33 +1,12 @@
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::{presigning::PresigningConfig, Client};
 use std::time::Duration;
 
-#[derive(Debug, Clone, Copy)]
-pub enum ImageTier {
-    Thumb,
-    Preview,
-    Original,
-}
-
-impl ImageTier {
-    #[must_use]
-    pub fn as_prefix(self) -> &'static str {
-        match self {
-            Self::Thumb => "thumb",
-            Self::Preview => "preview",
-            Self::Original => "original",
-        }
-    }
+use crate::images::{manifest_key, ImageStore, ImageTier};
 
-    #[must_use]
-    pub fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "thumb" => Some(Self::Thumb),
-            "preview" => Some(Self::Preview),
-            "original" => Some(Self::Original),
-            _ => None,
-        }
-    }
-}
+/// How long presigned image URLs stay valid.
+const PRESIGN_EXPIRY: Duration = Duration::from_mins(15);
 
 #[derive(Clone)]
 pub struct S3Client {
@@ -69,26 +48,14 @@ impl S3Client {
             bucket,
         }
     }
+}
 
-    pub async fn presign_url(
-        &self,
-        tier: ImageTier,
-        filename: &str,
-        expires_in: Duration,
-    ) -> anyhow::Result<String> {
-        let key = format!("{}/{filename}", tier.as_prefix());
-        let presigning_config = PresigningConfig::builder().expires_in(expires_in).build()?;
-        let presigned = self
-            .presign_client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(&key)
-            .presigned(presigning_config)
-            .await?;
-        Ok(presigned.uri().to_string())
-    }
+fn key(tier: ImageTier, filename: &str) -> String {
+    format!("{}/{filename}", tier.as_prefix())
+}
 
-    pub async fn list_photos(&self) -> Result<Vec<String>, anyhow::Error> {
+impl ImageStore for S3Client {
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
         let mut filenames = Vec::new();
         let mut continuation_token: Option<String> = None;
 
@@ -127,4 +94,72 @@ impl S3Client {
         filenames.sort();
         Ok(filenames)
     }
+
+    async fn get(&self, tier: ImageTier, filename: &str) -> anyhow::Result<Vec<u8>> {
+        let response = self
+            .internal_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key(tier, filename))
+            .send()
+            .await?;
+        Ok(response.body.collect().await?.into_bytes().to_vec())
+    }
+
+    async fn put(&self, tier: ImageTier, filename: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        self.internal_client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key(tier, filename))
+            .body(ByteStream::from(bytes))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn manifest(&self, campaign_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let response = self
+            .internal_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(manifest_key(campaign_id))
+            .send()
+            .await;
+        match response {
+            Ok(response) => Ok(Some(response.body.collect().await?.into_bytes().to_vec())),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(GetObjectError::is_no_such_key) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put_manifest(&self, campaign_id: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        self.internal_client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(manifest_key(campaign_id))
+            .content_type("application/json")
+            .body(ByteStream::from(bytes))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn public_url(&self, tier: ImageTier, filename: &str) -> anyhow::Result<String> {
+        let presigning_config = PresigningConfig::builder()
+            .expires_in(PRESIGN_EXPIRY)
+            .build()?;
+        let presigned = self
+            .presign_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key(tier, filename))
+            .presigned(presigning_config)
+            .await?;
+        Ok(presigned.uri().to_string())
+    }
 }
@@ -0,0 +1,1126 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+use uuid::Uuid;
+
+use filmorator_core::events::{Event, LoggedEvent};
+use filmorator_core::groups::PhotoGroups;
+use filmorator_core::identity::PhotoSync;
+use filmorator_core::matchup::SwissTournament;
+use filmorator_core::models::{
+    Campaign, CampaignRating, CampaignStatus, ComparisonResult, Matchup, MatchupKind, Photo,
+    PhotoRating, Session,
+};
+use filmorator_core::pool::MatchupPool;
+use filmorator_core::provenance::{DeviceClass, Provenance, Viewport};
+use filmorator_core::ranking::{Anchor, AnchorMode, PositionBias};
+use filmorator_core::refit::RatingFit;
+use filmorator_core::repository::Repository;
+use filmorator_core::types::Triple;
+
+const MATCHUP_COLUMNS: &str = "m.id, m.session_id, m.campaign_id, m.photo_indices, m.is_seed, \
+    m.kind, m.repeat_of, m.created_at, m.served_at";
+
+const COMPARISON_COLUMNS: &str = r"
+    id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+    idempotency_key, created_at, superseded_at, superseded_by,
+    decision_ms, viewport_width, viewport_height, device_class,
+    previews_loaded, zoomed, latency_ms";
+
+const CAMPAIGN_COLUMNS: &str = r"
+    c.id, c.name, c.owner_secret_hash, c.status, c.threshold_reached_at,
+    c.closed_at, c.reopened_at, c.created_at";
+
+/// [`Repository`] in a single local database file, for running without Postgres.
+///
+/// Uses the schema in `migrations-sqlite/`. Photo index arrays are stored as JSON.
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    /// Opens the database, creating the file if needed, and brings the schema
+    /// up to date. `database_url` is like `sqlite://filmorator.db`.
+    pub async fn connect(database_url: &str) -> anyhow::Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!("../migrations-sqlite").run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+fn indices_to_json(indices: Triple) -> sqlx::Result<String> {
+    serde_json::to_string(&indices).map_err(|e| sqlx::Error::Encode(e.into()))
+}
+
+fn indices_from_json(json: &str) -> sqlx::Result<Triple> {
+    serde_json::from_str(json).map_err(|e| sqlx::Error::Decode(e.into()))
+}
+
+fn matchup_from_row(row: &SqliteRow) -> sqlx::Result<Matchup> {
+    let kind = MatchupKind::from_parts(row.get("kind"), row.get("repeat_of"))
+        .ok_or_else(|| sqlx::Error::Protocol("Invalid matchup kind".into()))?;
+    Ok(Matchup {
+        id: row.get("id"),
+        session_id: row.get("session_id"),
+        campaign_id: row.get("campaign_id"),
+        photo_indices: indices_from_json(row.get("photo_indices"))?,
+        is_seed: row.get("is_seed"),
+        kind,
+        created_at: row.get("created_at"),
+        served_at: row.get("served_at"),
+    })
+}
+
+fn comparison_from_row(row: &SqliteRow) -> sqlx::Result<ComparisonResult> {
+    let viewport = match (
+        row.get::<Option<u32>, _>("viewport_width"),
+        row.get::<Option<u32>, _>("viewport_height"),
+    ) {
+        (Some(width), Some(height)) => Some(Viewport { width, height }),
+        _ => None,
+    };
+    let provenance = Provenance {
+        decision_ms: row.get("decision_ms"),
+        viewport,
+        device_class: row
+            .get::<Option<&str>, _>("device_class")
+            .map(str::parse)
+            .transpose()
+            .map_err(sqlx::Error::Protocol)?,
+        previews_loaded: row.get("previews_loaded"),
+        zoomed: row.get("zoomed"),
+    };
+    Ok(ComparisonResult {
+        id: row.get("id"),
+        matchup_id: row.get("matchup_id"),
+        session_id: row.get("session_id"),
+        ranked_photo_indices: indices_from_json(row.get("ranked_photo_indices"))?,
+        displayed_order: row
+            .get::<Option<&str>, _>("displayed_order")
+            .map(indices_from_json)
+            .transpose()?,
+        idempotency_key: row.get("idempotency_key"),
+        provenance,
+        latency_ms: row.get("latency_ms"),
+        created_at: row.get("created_at"),
+        superseded_at: row.get("superseded_at"),
+        superseded_by: row.get("superseded_by"),
+    })
+}
+
+fn session_from_row(row: &SqliteRow) -> Session {
+    Session {
+        id: row.get("id"),
+        campaign_id: row.get("campaign_id"),
+        excluded: row.get("excluded"),
+        created_at: row.get("created_at"),
+        last_active_at: row.get("last_active_at"),
+    }
+}
+
+fn campaign_from_row(row: &SqliteRow) -> sqlx::Result<Campaign> {
+    let status = row
+        .get::<&str, _>("status")
+        .parse::<CampaignStatus>()
+        .map_err(sqlx::Error::Protocol)?;
+    Ok(Campaign {
+        id: row.get("id"),
+        name: row.get("name"),
+        owner_secret_hash: row.get("owner_secret_hash"),
+        status,
+        threshold_reached_at: row.get("threshold_reached_at"),
+        closed_at: row.get("closed_at"),
+        reopened_at: row.get("reopened_at"),
+        created_at: row.get("created_at"),
+    })
+}
+
+/// Stores a comparison under its session's campaign; `false` if it collides
+/// with an active answer or idempotency key, or the session is unknown.
+async fn insert_comparison(
+    executor: impl sqlx::SqliteExecutor<'_>,
+    result: &ComparisonResult,
+) -> sqlx::Result<bool> {
+    let ranked = indices_to_json(result.ranked_photo_indices)?;
+    let displayed = result.displayed_order.map(indices_to_json).transpose()?;
+    let provenance = &result.provenance;
+
+    let inserted = sqlx::query(
+        r"
+        INSERT INTO comparison_results
+            (id, matchup_id, session_id, ranked_photo_indices, displayed_order,
+             idempotency_key, created_at, campaign_id, decision_ms, viewport_width,
+             viewport_height, device_class, previews_loaded, zoomed, latency_ms)
+        SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, s.campaign_id, ?8, ?9, ?10, ?11, ?12, ?13, ?14
+        FROM sessions s
+        WHERE s.id = ?3
+        ON CONFLICT DO NOTHING
+        ",
+    )
+    .bind(result.id)
+    .bind(result.matchup_id)
+    .bind(result.session_id)
+    .bind(ranked)
+    .bind(displayed)
+    .bind(result.idempotency_key)
+    .bind(result.created_at)
+    .bind(provenance.decision_ms)
+    .bind(provenance.viewport.map(|v| v.width))
+    .bind(provenance.viewport.map(|v| v.height))
+    .bind(provenance.device_class.map(DeviceClass::as_str))
+    .bind(provenance.previews_loaded)
+    .bind(provenance.zoomed)
+    .bind(result.latency_ms)
+    .execute(executor)
+    .await?;
+
+    Ok(inserted.rows_affected() > 0)
+}
+
+async fn append_event(
+    executor: impl sqlx::SqliteExecutor<'_>,
+    at: DateTime<Utc>,
+    event: &Event,
+) -> sqlx::Result<()> {
+    let payload = serde_json::to_string(event).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    sqlx::query("INSERT INTO events (occurred_at, payload) VALUES (?1, ?2)")
+        .bind(at)
+        .bind(payload)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+impl SqliteRepository {
+    async fn first_matchup(&self, session_id: Uuid, filter: &str) -> sqlx::Result<Option<Matchup>> {
+        let row = sqlx::query(&format!(
+            r"
+            SELECT {MATCHUP_COLUMNS}
+            FROM matchups m
+            WHERE m.session_id = ?1 AND {filter}
+            ORDER BY m.created_at, m.rowid
+            LIMIT 1
+            "
+        ))
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(matchup_from_row).transpose()
+    }
+
+    async fn comparisons(
+        &self,
+        session_id: Option<Uuid>,
+        campaign_id: Option<&str>,
+    ) -> sqlx::Result<Vec<ComparisonResult>> {
+        let rows = sqlx::query(&format!(
+            r"
+            SELECT {COMPARISON_COLUMNS}
+            FROM comparison_results
+            WHERE superseded_at IS NULL
+              AND (?1 IS NULL OR session_id = ?1)
+              AND (?2 IS NULL OR campaign_id = ?2)
+            ORDER BY created_at, rowid
+            "
+        ))
+        .bind(session_id)
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(comparison_from_row).collect()
+    }
+}
+
+impl Repository for SqliteRepository {
+    type Error = sqlx::Error;
+
+    async fn touch_session(&self, session_id: Uuid, campaign_id: &str) -> sqlx::Result<Session> {
+        let row = sqlx::query(
+            r"
+            INSERT INTO sessions (id, campaign_id, created_at, last_active_at)
+            VALUES (?1, ?2, ?3, ?3)
+            ON CONFLICT (id) DO UPDATE SET last_active_at = ?3
+            RETURNING id, campaign_id, excluded, created_at, last_active_at
+            ",
+        )
+        .bind(session_id)
+        .bind(campaign_id)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn import_session(&self, session: &Session) -> sqlx::Result<bool> {
+        let inserted = sqlx::query(
+            r"
+            INSERT INTO sessions (id, campaign_id, excluded, created_at, last_active_at)
+            VALUES (?1, ?2, FALSE, ?3, ?4)
+            ON CONFLICT (id) DO NOTHING
+            ",
+        )
+        .bind(session.id)
+        .bind(&session.campaign_id)
+        .bind(session.created_at)
+        .bind(session.last_active_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(inserted.rows_affected() > 0)
+    }
+
+    async fn set_session_excluded(&self, session_id: Uuid, excluded: bool) -> sqlx::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query("UPDATE sessions SET excluded = ?2 WHERE id = ?1")
+            .bind(session_id)
+            .bind(excluded)
+            .execute(&mut *tx)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        let event = Event::SessionExcluded {
+            session_id,
+            excluded,
+        };
+        append_event(&mut *tx, Utc::now(), &event).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn session_campaign(&self, session_id: Uuid) -> sqlx::Result<Option<Campaign>> {
+        let row = sqlx::query(&format!(
+            r"
+            SELECT {CAMPAIGN_COLUMNS}
+            FROM campaigns c
+            JOIN sessions s ON s.campaign_id = c.id
+            WHERE s.id = ?1
+            "
+        ))
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(campaign_from_row).transpose()
+    }
+
+    async fn campaign(&self, campaign_id: &str) -> sqlx::Result<Option<Campaign>> {
+        let row = sqlx::query(&format!(
+            "SELECT {CAMPAIGN_COLUMNS} FROM campaigns c WHERE c.id = ?1"
+        ))
+        .bind(campaign_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(campaign_from_row).transpose()
+    }
+
+    async fn campaign_sessions(&self, campaign_id: &str) -> sqlx::Result<Vec<Session>> {
+        let rows = sqlx::query(
+            r"
+            SELECT id, campaign_id, excluded, created_at, last_active_at
+            FROM sessions
+            WHERE campaign_id = ?1
+            ORDER BY created_at, rowid
+            ",
+        )
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(session_from_row).collect())
+    }
+
+    async fn save_campaign(&self, campaign: &Campaign) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = sqlx::query_scalar::<_, String>("SELECT status FROM campaigns WHERE id = ?1")
+            .bind(&campaign.id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|status| status.parse::<CampaignStatus>())
+            .transpose()
+            .map_err(sqlx::Error::Protocol)?;
+
+        sqlx::query(
+            r"
+            INSERT INTO campaigns
+                (id, name, owner_secret_hash, status, threshold_reached_at,
+                 closed_at, reopened_at, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT (id) DO UPDATE
+            SET name = ?2, status = ?4,
+                threshold_reached_at = COALESCE(campaigns.threshold_reached_at, ?5),
+                closed_at = ?6, reopened_at = ?7
+            ",
+        )
+        .bind(&campaign.id)
+        .bind(&campaign.name)
+        .bind(&campaign.owner_secret_hash)
+        .bind(campaign.status.as_str())
+        .bind(campaign.threshold_reached_at)
+        .bind(campaign.closed_at)
+        .bind(campaign.reopened_at)
+        .bind(campaign.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some((at, event)) = Event::campaign_transition(before, campaign) {
+            append_event(&mut *tx, at, &event).await?;
+        }
+        tx.commit().await
+    }
+
+    async fn set_threshold_reached(
+        &self,
+        campaign_id: &str,
+        at: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        let updated = sqlx::query(
+            r"UPDATE campaigns SET threshold_reached_at = ?2
+              WHERE id = ?1 AND threshold_reached_at IS NULL AND status <> 'closed'",
+        )
+        .bind(campaign_id)
+        .bind(at)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(updated > 0)
+    }
+
+    async fn set_owner_secret_hash(
+        &self,
+        campaign_id: &str,
+        owner_secret_hash: &str,
+    ) -> sqlx::Result<bool> {
+        let updated = sqlx::query("UPDATE campaigns SET owner_secret_hash = ?2 WHERE id = ?1")
+            .bind(campaign_id)
+            .bind(owner_secret_hash)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(updated > 0)
+    }
+
+    async fn count_photos(&self, campaign_id: &str) -> sqlx::Result<u32> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM photos WHERE campaign_id = ?1")
+            .bind(campaign_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn photos(&self, campaign_id: &str) -> sqlx::Result<Vec<Photo>> {
+        let rows = sqlx::query(
+            r"SELECT id, campaign_id, filename, file_hash, position, group_label
+              FROM photos WHERE campaign_id = ?1 ORDER BY position",
+        )
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| Photo {
+                id: r.get("id"),
+                campaign_id: r.get("campaign_id"),
+                filename: r.get("filename"),
+                file_hash: r.get("file_hash"),
+                position: r.get("position"),
+                group: r.get("group_label"),
+            })
+            .collect())
+    }
+
+    async fn apply_photo_sync(&self, sync: &PhotoSync) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for photo in &sync.photos {
+            if sync.renamed.contains(&photo.position) || sync.rehashed.contains(&photo.position) {
+                sqlx::query(
+                    r"UPDATE photos SET filename = ?2, file_hash = ?3, group_label = ?4
+                      WHERE id = ?1",
+                )
+                .bind(photo.id)
+                .bind(&photo.filename)
+                .bind(&photo.file_hash)
+                .bind(&photo.group)
+                .execute(&mut *tx)
+                .await?;
+            } else if sync.added.contains(&photo.position) {
+                sqlx::query(
+                    r"INSERT INTO photos (id, campaign_id, filename, file_hash, position, group_label)
+                      VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )
+                .bind(photo.id)
+                .bind(&photo.campaign_id)
+                .bind(&photo.filename)
+                .bind(&photo.file_hash)
+                .bind(photo.position)
+                .bind(&photo.group)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await
+    }
+
+    async fn photo_filename(
+        &self,
+        campaign_id: &str,
+        position: u32,
+    ) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar("SELECT filename FROM photos WHERE campaign_id = ?1 AND position = ?2")
+            .bind(campaign_id)
+            .bind(position)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn photo_groups(&self, campaign_id: &str) -> sqlx::Result<PhotoGroups> {
+        let labels: Vec<Option<String>> = sqlx::query_scalar(
+            "SELECT group_label FROM photos WHERE campaign_id = ?1 ORDER BY position",
+        )
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(PhotoGroups::from_labels(&labels))
+    }
+
+    async fn anchors(&self, campaign_id: &str) -> sqlx::Result<Vec<Anchor>> {
+        let rows = sqlx::query(
+            r"SELECT p.position, a.strength, a.prior_weight
+              FROM anchor_photos a
+              JOIN photos p ON p.id = a.photo_id
+              WHERE p.campaign_id = ?1
+              ORDER BY p.position",
+        )
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let prior_weight: Option<f64> = row.get("prior_weight");
+                Anchor {
+                    photo_idx: row.get("position"),
+                    strength: row.get("strength"),
+                    mode: prior_weight
+                        .map_or(AnchorMode::Fixed, |weight| AnchorMode::Prior { weight }),
+                }
+            })
+            .collect())
+    }
+
+    async fn upsert_anchor(&self, campaign_id: &str, anchor: &Anchor) -> sqlx::Result<bool> {
+        let prior_weight = match anchor.mode {
+            AnchorMode::Fixed => None,
+            AnchorMode::Prior { weight } => Some(weight),
+        };
+
+        let result = sqlx::query(
+            r"INSERT INTO anchor_photos (photo_id, strength, prior_weight)
+              SELECT id, ?2, ?3 FROM photos WHERE campaign_id = ?4 AND position = ?1
+              ON CONFLICT (photo_id) DO UPDATE SET strength = ?2, prior_weight = ?3",
+        )
+        .bind(anchor.photo_idx)
+        .bind(anchor.strength)
+        .bind(prior_weight)
+        .bind(campaign_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_anchor(&self, campaign_id: &str, photo_idx: u32) -> sqlx::Result<bool> {
+        let result = sqlx::query(
+            r"DELETE FROM anchor_photos
+              WHERE photo_id IN (SELECT id FROM photos WHERE campaign_id = ?1 AND position = ?2)",
+        )
+        .bind(campaign_id)
+        .bind(photo_idx)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn create_matchup(&self, matchup: &Matchup) -> sqlx::Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO matchups
+                (id, session_id, campaign_id, photo_indices, is_seed, kind, repeat_of,
+                 created_at, served_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ",
+        )
+        .bind(matchup.id)
+        .bind(matchup.session_id)
+        .bind(&matchup.campaign_id)
+        .bind(indices_to_json(matchup.photo_indices)?)
+        .bind(matchup.is_seed)
+        .bind(matchup.kind.as_str())
+        .bind(matchup.kind.repeat_of())
+        .bind(matchup.created_at)
+        .bind(matchup.served_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn matchup(&self, matchup_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        let row = sqlx::query(&format!(
+            "SELECT {MATCHUP_COLUMNS} FROM matchups m WHERE m.id = ?1"
+        ))
+        .bind(matchup_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(matchup_from_row).transpose()
+    }
+
+    async fn mark_matchup_served(&self, matchup_id: Uuid, at: DateTime<Utc>) -> sqlx::Result<bool> {
+        let updated = sqlx::query("UPDATE matchups SET served_at = ?2 WHERE id = ?1")
+            .bind(matchup_id)
+            .bind(at)
+            .execute(&self.pool)
+            .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    async fn session_matchups(&self, session_id: Uuid) -> sqlx::Result<Vec<Matchup>> {
+        let rows = sqlx::query(&format!(
+            r"
+            SELECT {MATCHUP_COLUMNS}
+            FROM matchups m
+            WHERE m.session_id = ?1
+            ORDER BY m.created_at, m.rowid
+            "
+        ))
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(matchup_from_row).collect()
+    }
+
+    async fn campaign_matchups(&self, campaign_id: &str) -> sqlx::Result<Vec<Matchup>> {
+        let rows = sqlx::query(&format!(
+            r"
+            SELECT {MATCHUP_COLUMNS}
+            FROM matchups m
+            WHERE m.campaign_id = ?1
+            ORDER BY m.created_at, m.rowid
+            "
+        ))
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(matchup_from_row).collect()
+    }
+
+    async fn pending_seed_matchup(&self, session_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        self.first_matchup(
+            session_id,
+            r"m.is_seed AND NOT EXISTS (SELECT 1 FROM comparison_results cr
+                                       WHERE cr.matchup_id = m.id AND cr.superseded_at IS NULL)",
+        )
+        .await
+    }
+
+    async fn pending_check_matchup(&self, session_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        self.first_matchup(
+            session_id,
+            r"m.kind <> 'regular'
+              AND NOT EXISTS (SELECT 1 FROM comparison_results cr
+                              WHERE cr.matchup_id = m.id AND cr.superseded_at IS NULL)",
+        )
+        .await
+    }
+
+    async fn requeued_matchup(&self, session_id: Uuid) -> sqlx::Result<Option<Matchup>> {
+        self.first_matchup(
+            session_id,
+            r"EXISTS (SELECT 1 FROM comparison_results cr
+                      WHERE cr.matchup_id = m.id AND cr.superseded_at IS NOT NULL)
+              AND NOT EXISTS (SELECT 1 FROM comparison_results cr
+                              WHERE cr.matchup_id = m.id AND cr.superseded_at IS NULL)",
+        )
+        .await
+    }
+
+    async fn has_seed_matchups(&self, session_id: Uuid) -> sqlx::Result<bool> {
+        sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM matchups WHERE session_id = ?1 AND is_seed)",
+        )
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn seed_pool(&self, session_id: Uuid) -> sqlx::Result<Option<MatchupPool>> {
+        let stored: Option<String> =
+            sqlx::query_scalar("SELECT pool FROM seed_pools WHERE session_id = ?1")
+                .bind(session_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| sqlx::Error::Decode(e.into())))
+            .transpose()
+    }
+
+    async fn save_seed_pool(&self, session_id: Uuid, pool: &MatchupPool) -> sqlx::Result<()> {
+        let json = serde_json::to_string(pool).map_err(|e| sqlx::Error::Encode(e.into()))?;
+        sqlx::query(
+            r"
+            INSERT INTO seed_pools (session_id, pool) VALUES (?1, ?2)
+            ON CONFLICT (session_id) DO UPDATE SET pool = excluded.pool
+            ",
+        )
+        .bind(session_id)
+        .bind(json)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn swiss_tournament(&self, campaign_id: &str) -> sqlx::Result<Option<SwissTournament>> {
+        let stored: Option<String> =
+            sqlx::query_scalar("SELECT state FROM swiss_tournaments WHERE campaign_id = ?1")
+                .bind(campaign_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| sqlx::Error::Decode(e.into())))
+            .transpose()
+    }
+
+    async fn save_swiss_tournament(
+        &self,
+        campaign_id: &str,
+        tournament: &SwissTournament,
+    ) -> sqlx::Result<()> {
+        let json = serde_json::to_string(tournament).map_err(|e| sqlx::Error::Encode(e.into()))?;
+        sqlx::query(
+            r"
+            INSERT INTO swiss_tournaments (campaign_id, state) VALUES (?1, ?2)
+            ON CONFLICT (campaign_id) DO UPDATE SET state = excluded.state
+            ",
+        )
+        .bind(campaign_id)
+        .bind(json)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn save_comparison(&self, result: &ComparisonResult) -> sqlx::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        if !insert_comparison(&mut *tx, result).await? {
+            return Ok(false);
+        }
+
+        let event = Event::ComparisonSubmitted {
+            comparison: result.clone(),
+        };
+        append_event(&mut *tx, result.created_at, &event).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn retract_comparison(
+        &self,
+        comparison_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r"UPDATE comparison_results SET superseded_at = ?2
+              WHERE id = ?1 AND superseded_at IS NULL",
+        )
+        .bind(comparison_id)
+        .bind(at)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        append_event(&mut *tx, at, &Event::ComparisonRetracted { comparison_id }).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn revise_comparison(
+        &self,
+        comparison_id: Uuid,
+        revision: &ComparisonResult,
+    ) -> sqlx::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let superseded = sqlx::query(
+            r"UPDATE comparison_results SET superseded_at = ?2, superseded_by = ?3
+              WHERE id = ?1 AND superseded_at IS NULL",
+        )
+        .bind(comparison_id)
+        .bind(revision.created_at)
+        .bind(revision.id)
+        .execute(&mut *tx)
+        .await?;
+
+        if superseded.rows_affected() == 0 || !insert_comparison(&mut *tx, revision).await? {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        let event = Event::ComparisonRevised {
+            comparison_id,
+            revision: revision.clone(),
+        };
+        append_event(&mut *tx, revision.created_at, &event).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn find_prior_submission(
+        &self,
+        session_id: Uuid,
+        matchup_id: Uuid,
+        idempotency_key: Option<Uuid>,
+    ) -> sqlx::Result<Option<ComparisonResult>> {
+        let row = sqlx::query(&format!(
+            r"
+            SELECT {COMPARISON_COLUMNS}
+            FROM comparison_results
+            WHERE (matchup_id = ?2 AND superseded_at IS NULL)
+               OR (session_id = ?1 AND idempotency_key = ?3)
+            ORDER BY superseded_at IS NULL DESC
+            LIMIT 1
+            "
+        ))
+        .bind(session_id)
+        .bind(matchup_id)
+        .bind(idempotency_key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(comparison_from_row).transpose()
+    }
+
+    async fn session_comparisons(&self, session_id: Uuid) -> sqlx::Result<Vec<ComparisonResult>> {
+        self.comparisons(Some(session_id), None).await
+    }
+
+    async fn campaign_comparisons(&self, campaign_id: &str) -> sqlx::Result<Vec<ComparisonResult>> {
+        self.comparisons(None, Some(campaign_id)).await
+    }
+
+    async fn comparison_history(&self, session_id: Uuid) -> sqlx::Result<Vec<ComparisonResult>> {
+        let rows = sqlx::query(&format!(
+            r"
+            SELECT {COMPARISON_COLUMNS}
+            FROM comparison_results
+            WHERE session_id = ?1
+            ORDER BY created_at, rowid
+            "
+        ))
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(comparison_from_row).collect()
+    }
+
+    async fn session_ratings(&self, session_id: Uuid) -> sqlx::Result<Vec<PhotoRating>> {
+        let rows = sqlx::query(
+            r"
+            SELECT photo_idx, strength, uncertainty
+            FROM photo_ratings
+            WHERE session_id = ?1
+            ORDER BY strength DESC
+            ",
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| PhotoRating {
+                photo_idx: r.get("photo_idx"),
+                strength: r.get("strength"),
+                uncertainty: r.get("uncertainty"),
+            })
+            .collect())
+    }
+
+    async fn save_rating_fit(
+        &self,
+        session_id: Uuid,
+        ratings: &[PhotoRating],
+        fit: &RatingFit,
+    ) -> sqlx::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM photo_ratings WHERE session_id = ?1")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for rating in ratings {
+            sqlx::query(
+                r"
+                INSERT INTO photo_ratings (session_id, photo_idx, strength, uncertainty)
+                VALUES (?1, ?2, ?3, ?4)
+                ",
+            )
+            .bind(session_id)
+            .bind(rating.photo_idx)
+            .bind(rating.strength)
+            .bind(rating.uncertainty)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let version: i64 = sqlx::query_scalar(
+            r"
+            INSERT INTO rating_fits
+                (session_id, version, model_version, comparisons, iterations,
+                 first_position_advantage, fitted_at)
+            VALUES (?1, 1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (session_id) DO UPDATE
+            SET version = rating_fits.version + 1, model_version = ?2, comparisons = ?3,
+                iterations = ?4, first_position_advantage = ?5, fitted_at = ?6
+            RETURNING version
+            ",
+        )
+        .bind(session_id)
+        .bind(fit.model_version)
+        .bind(fit.comparisons)
+        .bind(fit.iterations)
+        .bind(fit.first_position_advantage)
+        .bind(fit.fitted_at)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        u64::try_from(version).map_err(|_| sqlx::Error::Protocol("Negative fit version".into()))
+    }
+
+    async fn rating_fit(&self, session_id: Uuid) -> sqlx::Result<Option<RatingFit>> {
+        let row = sqlx::query(
+            r"
+            SELECT version, model_version, comparisons, iterations,
+                   first_position_advantage, fitted_at
+            FROM rating_fits
+            WHERE session_id = ?1
+            ",
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|r| {
+            Ok(RatingFit {
+                version: u64::try_from(r.get::<i64, _>("version"))
+                    .map_err(|_| sqlx::Error::Protocol("Negative fit version".into()))?,
+                model_version: r.get("model_version"),
+                comparisons: r.get("comparisons"),
+                iterations: r.get("iterations"),
+                first_position_advantage: r.get("first_position_advantage"),
+                fitted_at: r.get("fitted_at"),
+            })
+        })
+        .transpose()
+    }
+
+    async fn campaign_ratings(&self, campaign_id: &str) -> sqlx::Result<Vec<CampaignRating>> {
+        let rows = sqlx::query(
+            r"
+            SELECT photo_idx, strength, uncertainty, comparison_count, updated_at
+            FROM campaign_ratings
+            WHERE campaign_id = ?1
+            ORDER BY strength DESC
+            ",
+        )
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| CampaignRating {
+                photo_idx: r.get("photo_idx"),
+                strength: r.get("strength"),
+                uncertainty: r.get("uncertainty"),
+                comparison_count: r.get("comparison_count"),
+                updated_at: r.get("updated_at"),
+            })
+            .collect())
+    }
+
+    async fn campaign_position_bias(
+        &self,
+        campaign_id: &str,
+    ) -> sqlx::Result<Option<PositionBias>> {
+        let stored: Option<String> =
+            sqlx::query_scalar("SELECT bias FROM campaign_position_bias WHERE campaign_id = ?1")
+                .bind(campaign_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| sqlx::Error::Decode(e.into())))
+            .transpose()
+    }
+
+    async fn save_campaign_ratings(
+        &self,
+        campaign_id: &str,
+        ratings: &[CampaignRating],
+        position_bias: &PositionBias,
+    ) -> sqlx::Result<()> {
+        let bias =
+            serde_json::to_string(position_bias).map_err(|e| sqlx::Error::Encode(e.into()))?;
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO campaign_position_bias (campaign_id, bias) VALUES (?1, ?2)
+            ON CONFLICT (campaign_id) DO UPDATE SET bias = excluded.bias
+            ",
+        )
+        .bind(campaign_id)
+        .bind(bias)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM campaign_ratings WHERE campaign_id = ?1")
+            .bind(campaign_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for rating in ratings {
+            sqlx::query(
+                r"
+                INSERT INTO campaign_ratings
+                    (campaign_id, photo_idx, strength, uncertainty, comparison_count, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ",
+            )
+            .bind(campaign_id)
+            .bind(rating.photo_idx)
+            .bind(rating.strength)
+            .bind(rating.uncertainty)
+            .bind(rating.comparison_count)
+            .bind(rating.updated_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn events(&self, after_seq: u64) -> sqlx::Result<Vec<LoggedEvent>> {
+        let after = i64::try_from(after_seq).unwrap_or(i64::MAX);
+        let rows =
+            sqlx::query("SELECT seq, occurred_at, payload FROM events WHERE seq > ?1 ORDER BY seq")
+                .bind(after)
+                .fetch_all(&self.pool)
+                .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(LoggedEvent {
+                    seq: u64::try_from(row.get::<i64, _>("seq"))
+                        .map_err(|_| sqlx::Error::Protocol("Negative event seq".into()))?,
+                    at: row.get("occurred_at"),
+                    event: serde_json::from_str(row.get("payload"))
+                        .map_err(|e| sqlx::Error::Decode(e.into()))?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filmorator_core::contract;
+
+    async fn repository() -> SqliteRepository {
+        SqliteRepository::connect("sqlite::memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn comparisons_are_unique_per_matchup_and_key() {
+        contract::comparisons_are_unique_per_matchup_and_key(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn retracted_matchups_are_requeued_until_answered_again() {
+        contract::retracted_matchups_are_requeued_until_answered_again(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn failed_revision_changes_nothing() {
+        contract::failed_revision_changes_nothing(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn event_log_replays_to_the_stored_comparisons() {
+        contract::event_log_replays_to_the_stored_comparisons(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn photos_and_anchors_are_per_campaign() {
+        contract::photos_and_anchors_are_per_campaign(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn campaign_status_changes_are_logged() {
+        contract::campaign_status_changes_are_logged(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn threshold_is_recorded_once_and_never_while_closed() {
+        contract::threshold_is_recorded_once_and_never_while_closed(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn sessions_keep_the_campaign_they_started_in() {
+        contract::sessions_keep_the_campaign_they_started_in(&repository().await).await;
+    }
+
+    #[tokio::test]
+    async fn events_refuse_updates_and_deletes() {
+        let repo = repository().await;
+        contract::campaign_status_changes_are_logged(&repo).await;
+
+        let update = sqlx::query("UPDATE events SET payload = '{}'")
+            .execute(&repo.pool)
+            .await;
+        assert!(update.is_err());
+        let delete = sqlx::query("DELETE FROM events").execute(&repo.pool).await;
+        assert!(delete.is_err());
+        assert_eq!(repo.events(0).await.unwrap().len(), 1);
+    }
+}
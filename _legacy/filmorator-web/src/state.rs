@@ -1,17 +1,39 @@
-use sqlx::PgPool;
+use filmorator_core::scheduler::MatchupPolicy;
 
-use crate::s3::S3Client;
+use crate::images::Images;
+use crate::worker::RatingWorker;
+
+/// The storage backend this build uses: Postgres, or a local file with the `sqlite` feature.
+#[cfg(not(feature = "sqlite"))]
+pub type Store = crate::postgres::PgRepository;
+#[cfg(feature = "sqlite")]
+pub type Store = crate::sqlite::SqliteRepository;
 
 /// Application state shared across handlers.
 #[derive(Clone)]
 pub struct AppState {
-    pub db: PgPool,
-    pub s3: S3Client,
+    pub repo: Store,
+    pub images: Images,
+    pub matchup_policy: MatchupPolicy,
+    /// Answers wanted per seed matchup where the campaign's manifest doesn't say.
+    pub completion_target: u32,
+    pub ratings: RatingWorker,
 }
 
 impl AppState {
     #[must_use]
-    pub fn new(db: PgPool, s3: S3Client) -> Self {
-        Self { db, s3 }
+    pub fn new(
+        repo: Store,
+        images: Images,
+        matchup_policy: MatchupPolicy,
+        completion_target: u32,
+    ) -> Self {
+        Self {
+            ratings: RatingWorker::spawn(repo.clone()),
+            repo,
+            images,
+            matchup_policy,
+            completion_target,
+        }
     }
 }
@@ -0,0 +1,59 @@
+use filmorator_core::identity::{
+    content_hash, is_content_hash, sync_photos, ListedPhoto, PhotoSync, SyncError,
+};
+use filmorator_core::models::DEFAULT_CAMPAIGN_ID;
+use filmorator_core::repository::Repository;
+
+use crate::images::{ImageStore, ImageTier, Images, CAMPAIGNS_DIR};
+use crate::state::Store;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PhotoSyncError {
+    /// Storage no longer matches the indexed photos; nothing was changed.
+    #[error(transparent)]
+    Refused(#[from] SyncError),
+    #[error("database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("image store: {0}")]
+    Images(anyhow::Error),
+}
+
+/// Reconciles the default campaign's photos with the originals in the image store,
+/// identifying photos by content so that no existing index moves. Other campaigns'
+/// images, under [`CAMPAIGNS_DIR`], are left out.
+///
+/// Originals are only downloaded and hashed when their filename is new or the
+/// stored hash predates content hashing; known keys are assumed unchanged.
+pub async fn sync_from_store(repo: &Store, images: &Images) -> Result<PhotoSync, PhotoSyncError> {
+    let existing = repo.photos(DEFAULT_CAMPAIGN_ID).await?;
+    let filenames = images.list().await.map_err(PhotoSyncError::Images)?;
+
+    let mut listed = Vec::with_capacity(filenames.len());
+    for filename in filenames {
+        if filename.starts_with(CAMPAIGNS_DIR) {
+            continue;
+        }
+        let known = existing
+            .iter()
+            .find(|p| p.filename == filename && is_content_hash(&p.file_hash));
+        let file_hash = if let Some(photo) = known {
+            photo.file_hash.clone()
+        } else {
+            let bytes = images
+                .get(ImageTier::Original, &filename)
+                .await
+                .map_err(PhotoSyncError::Images)?;
+            content_hash(&bytes)
+        };
+        listed.push(ListedPhoto {
+            filename,
+            file_hash,
+        });
+    }
+
+    let sync = sync_photos(DEFAULT_CAMPAIGN_ID, &existing, &listed)?;
+    if !sync.is_unchanged() {
+        repo.apply_photo_sync(&sync).await?;
+    }
+    Ok(sync)
+}
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{self, JoinSet};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use filmorator_core::events::Replay;
+use filmorator_core::refit::{fit_campaign_ratings, fit_ratings, RefitSchedule};
+use filmorator_core::repository::Repository;
+
+const RATING_ITERATIONS: u32 = 50;
+
+/// Ratings the worker keeps fresh.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Refit {
+    Session(Uuid),
+    Campaign(String),
+}
+
+/// What handlers ask of the worker.
+enum Job {
+    /// Debounced with other requests for the same ratings.
+    Refit(Refit),
+    /// Run as soon as no fit of the campaign's ratings is running.
+    Rebuild(Rebuild),
+}
+
+/// A refit of a campaign's ratings from its replayed event log.
+struct Rebuild {
+    campaign_id: String,
+    replay: Replay,
+    done: oneshot::Sender<anyhow::Result<usize>>,
+}
+
+/// Handle to the background task that refits session and campaign ratings.
+///
+/// Handlers only signal that a session's ratings are stale; the worker debounces
+/// the signals and stores each fit atomically, so reads see the previous
+/// complete fit until the next one lands. It is the only writer of ratings.
+#[derive(Clone)]
+pub struct RatingWorker {
+    requests: mpsc::UnboundedSender<Job>,
+}
+
+impl RatingWorker {
+    /// Starts the worker on the current Tokio runtime.
+    #[must_use]
+    pub fn spawn<R: Repository + Clone + 'static>(repo: R) -> Self {
+        let (requests, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(repo, rx));
+        Self { requests }
+    }
+
+    /// Schedules a refit of the session's ratings and its campaign's.
+    pub fn request_refit(&self, session_id: Uuid, campaign_id: &str) {
+        let sent = self.send(Refit::Session(session_id))
+            && self.send(Refit::Campaign(campaign_id.to_string()));
+        if !sent {
+            tracing::error!("Rating worker stopped; session {session_id} not refitted");
+        }
+    }
+
+    /// Schedules a refit of the campaign's ratings alone, as after a change of
+    /// which sessions it includes.
+    pub fn request_campaign_refit(&self, campaign_id: &str) {
+        if !self.send(Refit::Campaign(campaign_id.to_string())) {
+            tracing::error!("Rating worker stopped; campaign {campaign_id} not refitted");
+        }
+    }
+
+    /// Refits the campaign's session and campaign ratings from `replay`, once
+    /// no other fit of the campaign's ratings is running; see [`rebuild_from_log`].
+    /// Returns how many sessions were refitted.
+    pub async fn rebuild(&self, campaign_id: &str, replay: Replay) -> anyhow::Result<usize> {
+        let (done, finished) = oneshot::channel();
+        let rebuild = Rebuild {
+            campaign_id: campaign_id.to_string(),
+            replay,
+            done,
+        };
+        if self.requests.send(Job::Rebuild(rebuild)).is_err() {
+            anyhow::bail!("rating worker stopped");
+        }
+        finished.await?
+    }
+
+    fn send(&self, refit: Refit) -> bool {
+        self.requests.send(Job::Refit(refit)).is_ok()
+    }
+}
+
+/// Fits run as their own tasks, so a slow campaign fit doesn't hold up the
+/// others. A fit due while the same one is still running starts after it, so
+/// an older fit never lands over a newer one. A rebuild counts as a fit of its
+/// campaign's ratings.
+async fn run<R: Repository + Clone + 'static>(repo: R, mut rx: mpsc::UnboundedReceiver<Job>) {
+    let mut schedule = RefitSchedule::default();
+    let mut running: HashMap<task::Id, Refit> = HashMap::new();
+    let mut rerun: HashSet<Refit> = HashSet::new();
+    let mut rebuilds: Vec<Rebuild> = Vec::new();
+    let mut fits = JoinSet::new();
+
+    loop {
+        let next_due = schedule.next_due().map(Instant::from_std);
+        tokio::select! {
+            request = rx.recv() => match request {
+                Some(Job::Refit(refit)) => schedule.request(refit, Instant::now().into_std()),
+                Some(Job::Rebuild(rebuild)) => {
+                    let refit = Refit::Campaign(rebuild.campaign_id.clone());
+                    if running.values().any(|r| *r == refit) {
+                        rebuilds.push(rebuild);
+                    } else {
+                        let id = fits.spawn(rebuild_and_reply(repo.clone(), rebuild)).id();
+                        running.insert(id, refit);
+                    }
+                }
+                None => break,
+            },
+            () = sleep_until(next_due), if next_due.is_some() => {
+                for refit in schedule.take_due(Instant::now().into_std()) {
+                    if running.values().any(|r| *r == refit) {
+                        rerun.insert(refit);
+                    } else {
+                        let id = fits.spawn(fit_and_log(repo.clone(), refit.clone())).id();
+                        running.insert(id, refit);
+                    }
+                }
+            }
+            Some(done) = fits.join_next_with_id(), if !fits.is_empty() => {
+                let id = done.map_or_else(|e| e.id(), |(id, ())| id);
+                let Some(refit) = running.remove(&id) else { continue };
+                // Waiting rebuilds go first; a refit due meanwhile reruns after them
+                let waiting = rebuilds
+                    .iter()
+                    .position(|r| matches!(&refit, Refit::Campaign(id) if *id == r.campaign_id));
+                if let Some(rebuild) = waiting.map(|i| rebuilds.remove(i)) {
+                    let id = fits.spawn(rebuild_and_reply(repo.clone(), rebuild)).id();
+                    running.insert(id, refit);
+                } else if rerun.remove(&refit) {
+                    let id = fits.spawn(fit_and_log(repo.clone(), refit.clone())).id();
+                    running.insert(id, refit);
+                }
+            }
+        }
+    }
+
+    // Every handle is gone; don't drop fits that were still waiting out the debounce
+    while fits.join_next().await.is_some() {}
+    for rebuild in rebuilds {
+        rebuild_and_reply(repo.clone(), rebuild).await;
+    }
+    for refit in schedule.drain().into_iter().chain(rerun) {
+        fit_and_log(repo.clone(), refit).await;
+    }
+}
+
+async fn rebuild_and_reply<R: Repository>(repo: R, rebuild: Rebuild) {
+    let refitted = rebuild_from_log(&repo, &rebuild.campaign_id, &rebuild.replay).await;
+    // The owner may have given up waiting; the ratings are rebuilt regardless
+    let _ = rebuild.done.send(refitted);
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    if let Some(deadline) = deadline {
+        tokio::time::sleep_until(deadline).await;
+    }
+}
+
+async fn fit_and_log<R: Repository>(repo: R, refit: Refit) {
+    match refit {
+        Refit::Session(session_id) => match fit_session(&repo, session_id).await {
+            Ok(Some(version)) => {
+                tracing::debug!("Session {session_id} ratings at version {version}");
+            }
+            Ok(None) => tracing::warn!("Too many photos to fit session {session_id}"),
+            Err(e) => tracing::error!("Refitting session {session_id}: {e}"),
+        },
+        Refit::Campaign(campaign_id) => match fit_campaign(&repo, &campaign_id).await {
+            Ok(true) => tracing::debug!("Campaign {campaign_id} ratings refitted"),
+            Ok(false) => tracing::warn!("Too many photos to fit campaign {campaign_id}"),
+            Err(e) => tracing::error!("Refitting campaign {campaign_id}: {e}"),
+        },
+    }
+}
+
+/// Recomputes a session's ratings from all its comparisons, correcting for
+/// display-position bias, and stores them with their fit metadata.
+///
+/// Returns the stored fit version, or `None` if there are too many photos to fit.
+async fn fit_session<R: Repository>(repo: &R, session_id: Uuid) -> anyhow::Result<Option<u64>> {
+    let campaign = repo
+        .session_campaign(session_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("unknown session"))?;
+    let num_photos = repo.count_photos(&campaign.id).await?;
+    let comparisons = repo.session_comparisons(session_id).await?;
+    let served = repo.session_matchups(session_id).await?;
+    let anchors = repo.anchors(&campaign.id).await?;
+
+    // The fit is CPU-bound; keep it off the async worker threads
+    let fitted = tokio::task::spawn_blocking(move || {
+        fit_ratings(
+            num_photos,
+            &served,
+            &comparisons,
+            anchors,
+            RATING_ITERATIONS,
+        )
+    })
+    .await?;
+    let Some((ratings, fit)) = fitted else {
+        return Ok(None);
+    };
+
+    let version = repo.save_rating_fit(session_id, &ratings, &fit).await?;
+    Ok(Some(version))
+}
+
+/// Recomputes a campaign's ratings over every session that isn't excluded and
+/// stores them with the position bias measured in the fit. Returns `false` if there are too many photos to fit.
+async fn fit_campaign<R: Repository>(repo: &R, campaign_id: &str) -> anyhow::Result<bool> {
+    let num_photos = repo.count_photos(campaign_id).await?;
+    let sessions = repo.campaign_sessions(campaign_id).await?;
+    let served = repo.campaign_matchups(campaign_id).await?;
+    let comparisons = repo.campaign_comparisons(campaign_id).await?;
+    let anchors = repo.anchors(campaign_id).await?;
+
+    let fitted = tokio::task::spawn_blocking(move || {
+        fit_campaign_ratings(
+            num_photos,
+            &sessions,
+            &served,
+            &comparisons,
+            anchors,
+            RATING_ITERATIONS,
+        )
+    })
+    .await?;
+    let Some((ratings, position_bias)) = fitted else {
+        return Ok(false);
+    };
+
+    repo.save_campaign_ratings(campaign_id, &ratings, &position_bias)
+        .await?;
+    Ok(true)
+}
+
+/// Refits every session of the campaign, and the campaign itself, from the
+/// comparisons and exclusions in `replay` rather than the tables derived from
+/// the event log, and stores the fits. Returns how many sessions were refitted.
+async fn rebuild_from_log<R: Repository>(
+    repo: &R,
+    campaign_id: &str,
+    replay: &Replay,
+) -> anyhow::Result<usize> {
+    let num_photos = repo.count_photos(campaign_id).await?;
+    let anchors = repo.anchors(campaign_id).await?;
+    let mut sessions = repo.campaign_sessions(campaign_id).await?;
+    for session in &mut sessions {
+        session.excluded = replay.is_excluded(session.id);
+    }
+
+    for session in &sessions {
+        let served = repo.session_matchups(session.id).await?;
+        let comparisons = replay.session_comparisons(session.id);
+        let anchors = anchors.clone();
+        let fitted = tokio::task::spawn_blocking(move || {
+            fit_ratings(
+                num_photos,
+                &served,
+                &comparisons,
+                anchors,
+                RATING_ITERATIONS,
+            )
+        })
+        .await?;
+        let (ratings, fit) = fitted.ok_or_else(|| anyhow::anyhow!("too many photos to fit"))?;
+        repo.save_rating_fit(session.id, &ratings, &fit).await?;
+    }
+
+    let refitted = sessions.len();
+    let served = repo.campaign_matchups(campaign_id).await?;
+    let comparisons: Vec<_> = replay
+        .comparisons()
+        .iter()
+        .filter(|c| sessions.iter().any(|s| s.id == c.session_id))
+        .cloned()
+        .collect();
+    let fitted = tokio::task::spawn_blocking(move || {
+        fit_campaign_ratings(
+            num_photos,
+            &sessions,
+            &served,
+            &comparisons,
+            anchors,
+            RATING_ITERATIONS,
+        )
+    })
+    .await?;
+    let (ratings, position_bias) =
+        fitted.ok_or_else(|| anyhow::anyhow!("too many photos to fit"))?;
+    repo.save_campaign_ratings(campaign_id, &ratings, &position_bias)
+        .await?;
+    Ok(refitted)
+}
@@ -0,0 +1,190 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use clap::{Args, Subcommand};
+use ureq::http::Response;
+use ureq::Body;
+
+use filmorator_core::bundle::{Bundle, ImportReport};
+
+/// Where the owner secret is read from when `--secret` isn't given.
+pub(super) const OWNER_SECRET_ENV: &str = "FILMORATOR_OWNER_SECRET";
+
+#[derive(Args)]
+pub struct BundleArgs {
+    #[command(subcommand)]
+    command: BundleCommand,
+}
+
+#[derive(Subcommand)]
+enum BundleCommand {
+    /// Download a campaign from a server and verify it
+    Export {
+        /// Server base URL, such as `http://localhost:3000`
+        #[arg(long)]
+        server: String,
+        campaign: String,
+        /// Where to write the bundle
+        #[arg(long, short)]
+        output: PathBuf,
+        /// Include the original images
+        #[arg(long)]
+        images: bool,
+        /// The campaign's owner secret
+        #[arg(long, env = OWNER_SECRET_ENV, hide_env_values = true)]
+        secret: String,
+    },
+    /// Verify a bundle and upload it to a server; importing it again changes nothing
+    Import {
+        /// Server base URL, such as `http://localhost:3000`
+        #[arg(long)]
+        server: String,
+        bundle: PathBuf,
+        /// Owner secret for the campaign; an imported campaign is new and claimed by it
+        #[arg(long, env = OWNER_SECRET_ENV, hide_env_values = true)]
+        secret: String,
+    },
+    /// Check a bundle's hashes and references, and summarize it
+    Verify { bundle: PathBuf },
+}
+
+pub fn run(args: &BundleArgs) -> anyhow::Result<()> {
+    match &args.command {
+        BundleCommand::Export {
+            server,
+            campaign,
+            output,
+            images,
+            secret,
+        } => export(server, campaign, output, *images, secret),
+        BundleCommand::Import {
+            server,
+            bundle,
+            secret,
+        } => import(server, bundle, secret),
+        BundleCommand::Verify { bundle } => {
+            println!("{}", summary(&read(bundle)?));
+            Ok(())
+        }
+    }
+}
+
+fn export(
+    server: &str,
+    campaign: &str,
+    output: &Path,
+    images: bool,
+    secret: &str,
+) -> anyhow::Result<()> {
+    let url = format!(
+        "{}/api/campaigns/{campaign}/bundle",
+        server.trim_end_matches('/')
+    );
+    let response = ureq::get(&url)
+        .query("images", images.to_string())
+        .header("authorization", bearer(secret))
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .call()
+        .with_context(|| format!("requesting {url}"))?;
+
+    let mut body = success(response)?.into_body().into_reader();
+    let mut file = BufWriter::new(
+        File::create(output).with_context(|| format!("creating {}", output.display()))?,
+    );
+    std::io::copy(&mut body, &mut file).context("downloading bundle")?;
+    drop(file);
+
+    // Catch a truncated download here rather than at import
+    println!("{}", summary(&read(output)?));
+    Ok(())
+}
+
+fn import(server: &str, path: &Path, secret: &str) -> anyhow::Result<()> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .with_context(|| format!("reading {}", path.display()))?;
+    let bundle =
+        Bundle::read(bytes.as_slice()).with_context(|| format!("verifying {}", path.display()))?;
+
+    let url = format!("{}/api/bundles", server.trim_end_matches('/'));
+    let response = ureq::post(&url)
+        .header("content-type", "application/x-tar")
+        .header("authorization", bearer(secret))
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .send(&bytes[..])
+        .with_context(|| format!("uploading to {url}"))?;
+    let report: ImportReport = serde_json::from_str(
+        &success(response)?
+            .into_body()
+            .read_to_string()
+            .context("reading import report")?,
+    )
+    .context("parsing import report")?;
+
+    if report == ImportReport::default() {
+        println!("Campaign {} was already imported", bundle.campaign.id);
+        return Ok(());
+    }
+    println!(
+        "Imported campaign {}{}: {} photos, {} sessions, {} matchups, {} comparisons, \
+         {} rating snapshots",
+        bundle.campaign.id,
+        if report.campaign_created {
+            " (new)"
+        } else {
+            ""
+        },
+        report.photos_added,
+        report.sessions_added,
+        report.matchups_added,
+        report.comparisons_added,
+        report.ratings_added,
+    );
+    Ok(())
+}
+
+/// The response if it succeeded; otherwise the server's explanation as an error.
+pub(super) fn success(response: Response<Body>) -> anyhow::Result<Response<Body>> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response.into_body().read_to_string().unwrap_or_default();
+    bail!("server answered {status}: {message}")
+}
+
+/// The `Authorization` header value proving campaign ownership.
+pub(super) fn bearer(secret: &str) -> String {
+    format!("Bearer {secret}")
+}
+
+pub(super) fn read(path: &Path) -> anyhow::Result<Bundle> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    Bundle::read(BufReader::new(file)).with_context(|| format!("verifying {}", path.display()))
+}
+
+fn summary(bundle: &Bundle) -> String {
+    let excluded = bundle.sessions.iter().filter(|s| s.excluded).count();
+    let superseded = bundle.comparisons.iter().filter(|c| !c.is_active()).count();
+    format!(
+        "Campaign {} ({}), {}, exported {}: {} photos, {} sessions ({excluded} excluded), \
+         {} matchups, {} comparisons ({superseded} superseded), {} rating snapshots, {} images",
+        bundle.campaign.id,
+        bundle.campaign.name,
+        bundle.campaign.status.as_str(),
+        bundle.exported_at.format("%Y-%m-%d %H:%M UTC"),
+        bundle.photos.len(),
+        bundle.sessions.len(),
+        bundle.matchups.len(),
+        bundle.comparisons.len(),
+        bundle.ratings.len(),
+        bundle.images.len(),
+    )
+}
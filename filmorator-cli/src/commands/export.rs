@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::{ArgGroup, Args, ValueEnum};
+
+use filmorator_core::bundle::Bundle;
+use filmorator_core::export::{comparison_rows, result_rows, to_csv, Table, RESULT_ITERATIONS};
+
+use super::bundle::{bearer, read, success, OWNER_SECRET_ENV};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TableArg {
+    /// Every answer, undone and revised ones included, with provenance
+    Comparisons,
+    /// The campaign ranking with win/loss counts
+    Results,
+}
+
+impl TableArg {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Comparisons => "comparisons",
+            Self::Results => "results",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Csv,
+    Json,
+}
+
+impl FormatArg {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("source").required(true).args(["server", "bundle"])))]
+pub struct ExportArgs {
+    #[arg(value_enum)]
+    table: TableArg,
+    /// Server base URL, such as `http://localhost:3000`
+    #[arg(long, requires = "campaign")]
+    server: Option<String>,
+    /// Campaign to export from the server
+    #[arg(requires = "server")]
+    campaign: Option<String>,
+    /// The campaign's owner secret, needed with `--server`
+    #[arg(long, env = OWNER_SECRET_ENV, hide_env_values = true)]
+    secret: Option<String>,
+    /// Compute from a bundle instead of asking a server; results ignore anchors
+    #[arg(long)]
+    bundle: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = FormatArg::Csv)]
+    format: FormatArg,
+    /// Where to write the table; standard output by default
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &ExportArgs) -> anyhow::Result<()> {
+    let table = match (&args.server, &args.campaign, &args.bundle) {
+        (Some(server), Some(campaign), _) => {
+            let secret = args
+                .secret
+                .as_deref()
+                .with_context(|| format!("--server needs --secret or {OWNER_SECRET_ENV}"))?;
+            download(server, campaign, secret, args.table, args.format)?
+        }
+        (_, _, Some(path)) => local(&read(path)?, args.table, args.format)?,
+        _ => unreachable!("clap requires a server and campaign, or a bundle"),
+    };
+
+    match &args.output {
+        Some(path) => write(path, &table),
+        None => std::io::stdout()
+            .write_all(table.as_bytes())
+            .context("writing table"),
+    }
+}
+
+fn download(
+    server: &str,
+    campaign: &str,
+    secret: &str,
+    table: TableArg,
+    format: FormatArg,
+) -> anyhow::Result<String> {
+    let url = format!(
+        "{}/api/campaigns/{campaign}/{}",
+        server.trim_end_matches('/'),
+        table.as_str()
+    );
+    let response = ureq::get(&url)
+        .query("format", format.as_str())
+        .header("authorization", bearer(secret))
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .call()
+        .with_context(|| format!("requesting {url}"))?;
+    success(response)?
+        .into_body()
+        .read_to_string()
+        .context("downloading table")
+}
+
+fn local(bundle: &Bundle, table: TableArg, format: FormatArg) -> anyhow::Result<String> {
+    match table {
+        TableArg::Comparisons => render(&comparison_rows(bundle), format),
+        TableArg::Results => {
+            let rows = result_rows(bundle, Vec::new(), RESULT_ITERATIONS)
+                .context("too many photos to fit")?;
+            render(&rows, format)
+        }
+    }
+}
+
+fn render<T: Table>(rows: &[T], format: FormatArg) -> anyhow::Result<String> {
+    Ok(match format {
+        FormatArg::Csv => to_csv(rows),
+        FormatArg::Json => serde_json::to_string_pretty(rows)? + "\n",
+    })
+}
+
+fn write(path: &Path, table: &str) -> anyhow::Result<()> {
+    let mut file =
+        BufWriter::new(File::create(path).with_context(|| format!("creating {}", path.display()))?);
+    file.write_all(table.as_bytes())
+        .and_then(|()| file.flush())
+        .with_context(|| format!("writing {}", path.display()))
+}
@@ -0,0 +1,27 @@
+pub mod bundle;
+pub mod export;
+pub mod plan;
+pub mod simulate;
+
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Export, import and verify portable campaign bundles
+    Bundle(bundle::BundleArgs),
+    /// Export comparisons or results as CSV or JSON for spreadsheets
+    Export(export::ExportArgs),
+    /// Estimate comparisons and participants a campaign needs before sharing it
+    Plan(plan::PlanArgs),
+    /// Evaluate matchup and ranking strategies against synthetic participants
+    Simulate(simulate::SimulateArgs),
+}
+
+pub fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Bundle(args) => bundle::run(&args),
+        Command::Export(args) => export::run(&args),
+        Command::Plan(args) => plan::run(&args),
+        Command::Simulate(args) => simulate::run(&args),
+    }
+}
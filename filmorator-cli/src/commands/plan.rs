@@ -0,0 +1,104 @@
+use anyhow::Context;
+use clap::Args;
+
+use filmorator_core::planner::{self, PlanRequest};
+
+#[derive(Args)]
+pub struct PlanArgs {
+    /// Photos in the campaign
+    #[arg(long)]
+    photos: u32,
+    #[arg(long, default_value_t = 3)]
+    matchup_size: usize,
+    /// Size of the top set that should be identified
+    #[arg(long, default_value_t = 10)]
+    top_k: usize,
+    /// Top-k recall to reach: share of the true top k found in the estimated top k
+    #[arg(long, default_value_t = 0.9, value_parser = share)]
+    recall: f64,
+    /// Share of simulated campaigns that must reach --recall
+    #[arg(long, default_value_t = 0.8, value_parser = share)]
+    confidence: f64,
+    /// Matchups a typical participant answers
+    #[arg(long, default_value_t = 20)]
+    per_participant: u32,
+    /// Per-judgment noise (standard deviation) of participants
+    #[arg(long, default_value_t = 1.0)]
+    noise: f64,
+    /// Standard deviation of the true log-strengths
+    #[arg(long, default_value_t = 1.0)]
+    spread: f64,
+    /// Simulated campaigns to average over
+    #[arg(long, default_value_t = 8)]
+    runs: u32,
+    /// Stop searching beyond this many comparisons
+    #[arg(long)]
+    max_comparisons: Option<u32>,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Print the full plan as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Parses a share in `(0, 1]`, such as `0.9`.
+fn share(value: &str) -> Result<f64, String> {
+    let share: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    if share > 0.0 && share <= 1.0 {
+        Ok(share)
+    } else {
+        Err(format!("{value} is not above 0 and at most 1"))
+    }
+}
+
+pub fn run(args: &PlanArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        (1..=args.photos as usize).contains(&args.top_k),
+        "--top-k must be between 1 and the {} photos, got {}",
+        args.photos,
+        args.top_k
+    );
+    let request = PlanRequest {
+        num_photos: args.photos,
+        matchup_size: args.matchup_size,
+        top_k: args.top_k,
+        recall: args.recall,
+        confidence: args.confidence,
+        comparisons_per_participant: args.per_participant,
+        noise: args.noise,
+        strength_spread: args.spread,
+        runs: args.runs,
+        max_comparisons: args.max_comparisons,
+        seed: args.seed,
+        ..PlanRequest::default()
+    };
+    let plan = planner::plan(&request).context("invalid plan")?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+
+    println!(
+        "Noiseless lower bounds: {} comparisons to reach the target, {} to sort every photo",
+        plan.target_lower_bound_comparisons, plan.lower_bound_comparisons
+    );
+    let target = format!(
+        "Top-{} recall of {:.0}% in {:.0}% of simulated campaigns",
+        args.top_k,
+        args.recall * 100.0,
+        args.confidence * 100.0,
+    );
+    match (plan.comparisons, plan.participants) {
+        (Some(comparisons), Some(participants)) => println!(
+            "{target}: ~{comparisons} comparisons, ~{participants} participants answering {} each",
+            args.per_participant,
+        ),
+        _ => println!(
+            "{target} not reached within {} comparisons; \
+             raise --max-comparisons or lower the target",
+            plan.searched_up_to,
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use serde_json::json;
+
+use filmorator_core::simulation::{
+    self, Participant, RatingModel, SimulationConfig, SimulationReport, Strategy,
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyArg {
+    Random,
+    SeedThenUncertainty,
+    Balanced,
+    Swiss,
+    All,
+}
+
+impl StrategyArg {
+    fn expand(self) -> Vec<(&'static str, Strategy)> {
+        match self {
+            Self::Random => vec![("random", Strategy::Random)],
+            Self::SeedThenUncertainty => {
+                vec![("seed-then-uncertainty", Strategy::SeedThenUncertainty)]
+            }
+            Self::Balanced => vec![("balanced", Strategy::Balanced)],
+            Self::Swiss => vec![("swiss", Strategy::Swiss)],
+            Self::All => [
+                Self::Random,
+                Self::SeedThenUncertainty,
+                Self::Balanced,
+                Self::Swiss,
+            ]
+            .into_iter()
+            .flat_map(Self::expand)
+            .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ModelArg {
+    BradleyTerry,
+    PositionBiased,
+}
+
+impl From<ModelArg> for RatingModel {
+    fn from(model: ModelArg) -> Self {
+        match model {
+            ModelArg::BradleyTerry => Self::BradleyTerry,
+            ModelArg::PositionBiased => Self::PositionBiased,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// Photos in the synthetic campaign
+    #[arg(long, default_value_t = 30)]
+    photos: u32,
+    #[arg(long, default_value_t = 3)]
+    matchup_size: usize,
+    /// Comparisons to simulate per run
+    #[arg(long, default_value_t = 200)]
+    budget: u32,
+    #[arg(long, value_enum, default_value_t = StrategyArg::All)]
+    strategy: StrategyArg,
+    #[arg(long, value_enum, default_value_t = ModelArg::BradleyTerry)]
+    model: ModelArg,
+    /// Size of the simulated participant pool
+    #[arg(long, default_value_t = 10)]
+    participants: u32,
+    /// Per-judgment noise (standard deviation) of careful participants
+    #[arg(long, default_value_t = 1.0)]
+    noise: f64,
+    /// Utility bonus careful participants give the leftmost photo
+    #[arg(long, default_value_t = 0.0)]
+    position_bias: f64,
+    /// Share of participants who click photos in display order
+    #[arg(long, default_value_t = 0.0)]
+    lazy_share: f64,
+    /// Standard deviation of the true log-strengths
+    #[arg(long, default_value_t = 1.0)]
+    spread: f64,
+    /// Refit ratings every this many comparisons
+    #[arg(long, default_value_t = 5)]
+    refit_every: u32,
+    #[arg(long, default_value_t = 5)]
+    top_k: usize,
+    /// Kendall tau counted as "ranked"
+    #[arg(long, default_value_t = 0.8)]
+    tau_threshold: f64,
+    /// Seed of the first run; run i uses seed + i
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Independent runs per strategy
+    #[arg(long, default_value_t = 1)]
+    runs: u32,
+    /// Print full reports as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl SimulateArgs {
+    fn population(&self) -> Vec<Participant> {
+        let lazy = self.lazy_share.clamp(0.0, 1.0) * f64::from(self.participants);
+        (0..self.participants)
+            .map(|i| {
+                if f64::from(i) + 0.5 < lazy {
+                    Participant::lazy_clicker()
+                } else {
+                    Participant {
+                        noise: self.noise,
+                        position_bias: self.position_bias,
+                        lazy_rate: 0.0,
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn config(&self, strategy: Strategy, run: u32) -> SimulationConfig {
+        SimulationConfig {
+            num_photos: self.photos,
+            matchup_size: self.matchup_size,
+            budget: self.budget,
+            strategy,
+            model: self.model.into(),
+            participants: self.population(),
+            strength_spread: self.spread,
+            refit_every: self.refit_every,
+            rating_iterations: SimulationConfig::default().rating_iterations,
+            top_k: self.top_k,
+            tau_threshold: self.tau_threshold,
+            seed: self.seed + u64::from(run),
+        }
+    }
+}
+
+pub fn run(args: &SimulateArgs) -> anyhow::Result<()> {
+    let mut results = Vec::new();
+    for (name, strategy) in args.strategy.expand() {
+        let reports = (0..args.runs)
+            .map(|run| {
+                simulation::run(&args.config(strategy, run)).context(
+                    "invalid simulation: need at least matchup-size photos, \
+                     one participant and a non-zero refit interval",
+                )
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        results.push((name, reports));
+    }
+
+    if args.json {
+        let output: Vec<_> = results
+            .iter()
+            .map(|(name, reports)| json!({ "strategy": name, "runs": reports }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<24} {:>8} {:>12} {:>10} {:>16}",
+        "strategy", "tau", "top-k recall", "reached", "to threshold"
+    );
+    for (name, reports) in &results {
+        print_summary(name, reports);
+    }
+    Ok(())
+}
+
+fn print_summary(name: &str, reports: &[SimulationReport]) {
+    let runs = f64::from(u32::try_from(reports.len()).unwrap_or(u32::MAX));
+    let tau = reports.iter().map(|r| r.kendall_tau).sum::<f64>() / runs;
+    let recall = reports.iter().map(|r| r.top_k_recall).sum::<f64>() / runs;
+    let reached: Vec<u32> = reports
+        .iter()
+        .filter_map(|r| r.comparisons_to_threshold)
+        .collect();
+    let to_threshold = if reached.is_empty() {
+        "-".to_string()
+    } else {
+        let total: u64 = reached.iter().map(|&c| u64::from(c)).sum();
+        format!("{}", total / reached.len() as u64)
+    };
+
+    println!(
+        "{name:<24} {tau:>8.3} {recall:>12.3} {:>10} {to_threshold:>16}",
+        format!("{}/{}", reached.len(), reports.len()),
+    );
+}
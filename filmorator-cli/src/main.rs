@@ -0,0 +1,15 @@
+mod commands;
+
+use clap::Parser;
+
+/// Filmorator campaign tooling.
+#[derive(Parser)]
+#[command(name = "filmorator", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: commands::Command,
+}
+
+fn main() -> anyhow::Result<()> {
+    commands::run(Cli::parse().command)
+}
@@ -0,0 +1,524 @@
+use rand::seq::{IndexedRandom, SliceRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::{ComparisonResult, Matchup, MatchupKind, PhotoRating, Session};
+use crate::types::Triple;
+
+/// When and how to interleave quality-check matchups.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AttentionPolicy {
+    /// Serve a repeat of an answered triple after every this many answers. 0 disables.
+    pub repeat_every: u32,
+    /// Serve a calibration triple after every this many answers. 0 disables.
+    pub calibration_every: u32,
+    /// Minimum log-strength gap between neighbours in a calibration triple.
+    pub calibration_min_gap: f64,
+    /// Only photos at or below this uncertainty qualify for calibration.
+    pub calibration_max_uncertainty: f64,
+    /// Whether answers to check matchups feed the rating fit.
+    pub include_checks_in_fit: bool,
+}
+
+impl Default for AttentionPolicy {
+    fn default() -> Self {
+        Self {
+            repeat_every: 10,
+            calibration_every: 15,
+            calibration_min_gap: 1.5,
+            calibration_max_uncertainty: 0.25,
+            include_checks_in_fit: false,
+        }
+    }
+}
+
+/// Which kind of check, if any, is due for a session that was served
+/// `matchups` and gave `results`.
+///
+/// Each kind is due while fewer checks of it have been served than its share
+/// of the answers to regular matchups, so undoing and giving an answer again
+/// doesn't call for another check. A check that couldn't be formed when due
+/// stays due, but at most one of each kind is served per regular answer.
+/// Repeats go first when both are due.
+#[must_use]
+pub fn due_check(
+    matchups: &[Matchup],
+    results: &[ComparisonResult],
+    policy: &AttentionPolicy,
+) -> Option<CheckKind> {
+    let kinds: HashMap<Uuid, MatchupKind> = matchups.iter().map(|m| (m.id, m.kind)).collect();
+    let regular: Vec<&ComparisonResult> = results
+        .iter()
+        .filter(|r| !kinds.get(&r.matchup_id).is_some_and(|k| k.is_check()))
+        .collect();
+    let last_answer = regular.iter().map(|r| r.created_at).max();
+
+    let due = |every: u32, kind: CheckKind| {
+        let served: Vec<&Matchup> = matchups
+            .iter()
+            .filter(|m| CheckKind::of(m.kind) == Some(kind))
+            .collect();
+        let since_last_answer = served
+            .iter()
+            .any(|m| last_answer.is_some_and(|at| m.created_at >= at));
+        every > 0 && served.len() < regular.len() / every as usize && !since_last_answer
+    };
+    if due(policy.repeat_every, CheckKind::Repeat) {
+        Some(CheckKind::Repeat)
+    } else if due(policy.calibration_every, CheckKind::Calibration) {
+        Some(CheckKind::Calibration)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    Repeat,
+    Calibration,
+}
+
+impl CheckKind {
+    /// The kind of check a matchup of `kind` is; `None` for regular matchups.
+    #[must_use]
+    pub const fn of(kind: MatchupKind) -> Option<Self> {
+        match kind {
+            MatchupKind::Regular => None,
+            MatchupKind::Repeat { .. } => Some(Self::Repeat),
+            MatchupKind::Calibration => Some(Self::Calibration),
+        }
+    }
+}
+
+/// Picks an answered regular matchup to serve again, with its photos reshuffled.
+///
+/// Returns the kind to store on the new matchup and its photo indices.
+#[must_use]
+pub fn select_repeat_check(
+    matchups: &[Matchup],
+    results: &[ComparisonResult],
+) -> Option<(MatchupKind, Triple)> {
+    let answered: Vec<&Matchup> = matchups
+        .iter()
+        .filter(|m| !m.kind.is_check() && results.iter().any(|r| r.matchup_id == m.id))
+        .collect();
+
+    let mut rng = rand::rng();
+    let original = answered.choose(&mut rng)?;
+    let photo_indices = original.photo_indices.shuffled(&mut rng);
+
+    Some((
+        MatchupKind::Repeat {
+            original: original.id,
+        },
+        photo_indices,
+    ))
+}
+
+/// Picks photos whose order the model is confident about, strongest first.
+///
+/// Each photo must be at most `calibration_max_uncertainty` uncertain and at
+/// least `calibration_min_gap` weaker than the previous one.
+#[must_use]
+pub fn select_calibration_triple(
+    ratings: &[PhotoRating],
+    policy: &AttentionPolicy,
+    matchup_size: usize,
+) -> Option<Vec<u32>> {
+    let mut confident: Vec<&PhotoRating> = ratings
+        .iter()
+        .filter(|r| r.uncertainty <= policy.calibration_max_uncertainty)
+        .collect();
+    if matchup_size == 0 || confident.len() < matchup_size {
+        return None;
+    }
+    confident.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+
+    let mut starts: Vec<usize> = (0..confident.len()).collect();
+    starts.shuffle(&mut rand::rng());
+
+    starts.into_iter().find_map(|start| {
+        let mut chain = vec![confident[start]];
+        for candidate in &confident[start + 1..] {
+            if chain.len() == matchup_size {
+                break;
+            }
+            let last = chain[chain.len() - 1];
+            if last.strength - candidate.strength >= policy.calibration_min_gap {
+                chain.push(candidate);
+            }
+        }
+        (chain.len() == matchup_size).then(|| chain.iter().map(|r| r.photo_idx).collect())
+    })
+}
+
+/// Builds the check matchup that is due for a session, if any can be formed.
+///
+/// `matchups` and `results` should hold everything served to and answered by
+/// the session, checks included.
+#[must_use]
+pub fn next_check_matchup(
+    session: &Session,
+    matchups: &[Matchup],
+    results: &[ComparisonResult],
+    ratings: &[PhotoRating],
+    policy: &AttentionPolicy,
+    matchup_size: usize,
+) -> Option<Matchup> {
+    let (kind, photo_indices) = match due_check(matchups, results, policy)? {
+        CheckKind::Repeat => select_repeat_check(matchups, results)?,
+        CheckKind::Calibration => (
+            MatchupKind::Calibration,
+            Triple::try_from(select_calibration_triple(ratings, policy, matchup_size)?.as_slice())
+                .ok()?,
+        ),
+    };
+    Some(Matchup::check(session, photo_indices, kind))
+}
+
+/// Results that should feed the rating fit under `policy`.
+#[must_use]
+pub fn results_for_fit<'a>(
+    matchups: &[Matchup],
+    results: &'a [ComparisonResult],
+    policy: &AttentionPolicy,
+) -> Vec<&'a ComparisonResult> {
+    if policy.include_checks_in_fit {
+        return results.iter().collect();
+    }
+    let checks: Vec<Uuid> = matchups
+        .iter()
+        .filter(|m| m.kind.is_check())
+        .map(|m| m.id)
+        .collect();
+    results
+        .iter()
+        .filter(|r| !checks.contains(&r.matchup_id))
+        .collect()
+}
+
+/// How well a session agrees with itself and with confident model predictions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionConsistency {
+    pub repeat_checks: u32,
+    /// Share of pairs ordered the same way on the original and the repeat.
+    pub repeat_agreement: Option<f64>,
+    pub calibration_checks: u32,
+    /// Share of pairs ordered the way the ratings predicted.
+    pub calibration_agreement: Option<f64>,
+    /// Agreement over all check pairs combined. `None` until a check is answered.
+    pub score: Option<f64>,
+}
+
+/// Scores a session's answered checks against its originals and `ratings`.
+///
+/// Calibration answers are judged against the current ratings, so scores drift
+/// as the fit improves.
+#[must_use]
+pub fn self_consistency(
+    matchups: &[Matchup],
+    results: &[ComparisonResult],
+    ratings: &[PhotoRating],
+) -> SessionConsistency {
+    let kinds: HashMap<Uuid, MatchupKind> = matchups.iter().map(|m| (m.id, m.kind)).collect();
+    let by_matchup: HashMap<Uuid, &ComparisonResult> =
+        results.iter().map(|r| (r.matchup_id, r)).collect();
+    let strength: HashMap<u32, f64> = ratings.iter().map(|r| (r.photo_idx, r.strength)).collect();
+
+    let mut report = SessionConsistency::default();
+    let (mut repeat_agree, mut repeat_total) = (0_u32, 0_u32);
+    let (mut calibration_agree, mut calibration_total) = (0_u32, 0_u32);
+
+    for result in results {
+        match kinds.get(&result.matchup_id) {
+            Some(MatchupKind::Repeat { original }) => {
+                let Some(first) = by_matchup.get(original) else {
+                    continue;
+                };
+                let (agree, total) = pairwise_agreement(
+                    &first.ranked_photo_indices.indices(),
+                    &result.ranked_photo_indices.indices(),
+                );
+                report.repeat_checks += 1;
+                repeat_agree += agree;
+                repeat_total += total;
+            }
+            Some(MatchupKind::Calibration) => {
+                let ranked = result.ranked_photo_indices.indices();
+                let mut expected = ranked;
+                expected.sort_by(|a, b| {
+                    let s = |idx: &u32| strength.get(idx).copied().unwrap_or(0.0);
+                    s(b).total_cmp(&s(a))
+                });
+                let (agree, total) = pairwise_agreement(&expected, &ranked);
+                report.calibration_checks += 1;
+                calibration_agree += agree;
+                calibration_total += total;
+            }
+            Some(MatchupKind::Regular) | None => {}
+        }
+    }
+
+    let share = |agree: u32, total: u32| (total > 0).then(|| f64::from(agree) / f64::from(total));
+    report.repeat_agreement = share(repeat_agree, repeat_total);
+    report.calibration_agreement = share(calibration_agree, calibration_total);
+    report.score = share(
+        repeat_agree + calibration_agree,
+        repeat_total + calibration_total,
+    );
+    report
+}
+
+/// Counts pairs from `reference` that `ranking` orders the same way.
+fn pairwise_agreement(reference: &[u32], ranking: &[u32]) -> (u32, u32) {
+    let position = |idx: u32| ranking.iter().position(|&r| r == idx);
+    let mut agree = 0;
+    let mut total = 0;
+    for (i, &winner) in reference.iter().enumerate() {
+        for &loser in &reference[i + 1..] {
+            if let (Some(w), Some(l)) = (position(winner), position(loser)) {
+                total += 1;
+                if w < l {
+                    agree += 1;
+                }
+            }
+        }
+    }
+    (agree, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::triple;
+
+    fn rating(photo_idx: u32, strength: f64, uncertainty: f64) -> PhotoRating {
+        PhotoRating {
+            photo_idx,
+            strength,
+            uncertainty,
+        }
+    }
+
+    /// Serves and answers a regular matchup.
+    fn answer(session: &Session, matchups: &mut Vec<Matchup>, results: &mut Vec<ComparisonResult>) {
+        let matchup = Matchup::new(session, triple([0, 1, 2]), false);
+        results.push(ComparisonResult::new(
+            matchup.id,
+            session.id,
+            triple([0, 1, 2]),
+        ));
+        matchups.push(matchup);
+    }
+
+    #[test]
+    fn checks_fall_due_on_schedule() {
+        let session = Session::default();
+        let policy = AttentionPolicy {
+            repeat_every: 4,
+            calibration_every: 6,
+            ..AttentionPolicy::default()
+        };
+        let (mut matchups, mut results) = (Vec::new(), Vec::new());
+        let mut due = Vec::new();
+        for _ in 0..12 {
+            answer(&session, &mut matchups, &mut results);
+            while let Some(kind) = due_check(&matchups, &results, &policy) {
+                due.push((results.len(), kind));
+                let kind = match kind {
+                    CheckKind::Repeat => MatchupKind::Repeat {
+                        original: matchups[0].id,
+                    },
+                    CheckKind::Calibration => MatchupKind::Calibration,
+                };
+                let check = Matchup::check(&session, triple([0, 1, 2]), kind);
+                results.push(ComparisonResult::new(
+                    check.id,
+                    session.id,
+                    triple([0, 1, 2]),
+                ));
+                matchups.push(check);
+            }
+        }
+        // Answers to checks don't count towards the schedule
+        let regular = |(n, kind)| (n - due.iter().take_while(|&&(m, _)| m < n).count(), kind);
+        let due: Vec<_> = due.iter().copied().map(regular).collect();
+        assert_eq!(
+            due,
+            [
+                (4, CheckKind::Repeat),
+                (6, CheckKind::Calibration),
+                (8, CheckKind::Repeat),
+                (12, CheckKind::Repeat),
+                (12, CheckKind::Calibration),
+            ]
+        );
+    }
+
+    #[test]
+    fn answering_again_after_undo_schedules_no_second_check() {
+        let session = Session::default();
+        let policy = AttentionPolicy {
+            repeat_every: 2,
+            calibration_every: 0,
+            ..AttentionPolicy::default()
+        };
+        let (mut matchups, mut results) = (Vec::new(), Vec::new());
+        answer(&session, &mut matchups, &mut results);
+        answer(&session, &mut matchups, &mut results);
+        assert_eq!(
+            due_check(&matchups, &results, &policy),
+            Some(CheckKind::Repeat)
+        );
+        matchups.push(Matchup::check(
+            &session,
+            triple([2, 1, 0]),
+            MatchupKind::Repeat {
+                original: matchups[0].id,
+            },
+        ));
+
+        // Undo the second answer, then answer the same matchup again
+        let undone = results.pop().unwrap();
+        results.push(ComparisonResult::new(
+            undone.matchup_id,
+            session.id,
+            triple([2, 1, 0]),
+        ));
+        assert_eq!(due_check(&matchups, &results, &policy), None);
+    }
+
+    #[test]
+    fn calibration_stays_due_until_it_can_be_formed() {
+        let session = Session::default();
+        let policy = AttentionPolicy {
+            repeat_every: 0,
+            calibration_every: 3,
+            ..AttentionPolicy::default()
+        };
+        let (mut matchups, mut results) = (Vec::new(), Vec::new());
+        for _ in 0..6 {
+            answer(&session, &mut matchups, &mut results);
+        }
+        assert_eq!(
+            due_check(&matchups, &results, &policy),
+            Some(CheckKind::Calibration)
+        );
+
+        // Two are owed, but only one is served before the next answer
+        matchups.push(Matchup::check(
+            &session,
+            triple([0, 1, 2]),
+            MatchupKind::Calibration,
+        ));
+        assert_eq!(due_check(&matchups, &results, &policy), None);
+        answer(&session, &mut matchups, &mut results);
+        assert_eq!(
+            due_check(&matchups, &results, &policy),
+            Some(CheckKind::Calibration)
+        );
+    }
+
+    #[test]
+    fn repeat_check_reuses_answered_triple() {
+        let session = Session::default();
+        let answered = Matchup::new(&session, triple([0, 1, 2]), true);
+        let pending = Matchup::new(&session, triple([3, 4, 5]), true);
+        let result = ComparisonResult::new(answered.id, session.id, triple([2, 0, 1]));
+
+        let (kind, indices) = select_repeat_check(&[answered.clone(), pending], &[result]).unwrap();
+        assert_eq!(
+            kind,
+            MatchupKind::Repeat {
+                original: answered.id
+            }
+        );
+        let mut indices = indices.indices();
+        indices.sort_unstable();
+        assert_eq!(indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn next_check_matchup_repeats_when_due() {
+        let session = Session::default();
+        let policy = AttentionPolicy {
+            repeat_every: 2,
+            ..AttentionPolicy::default()
+        };
+        let matchups = vec![
+            Matchup::new(&session, triple([0, 1, 2]), true),
+            Matchup::new(&session, triple([3, 4, 5]), true),
+        ];
+        let mut results = vec![ComparisonResult::new(
+            matchups[0].id,
+            session.id,
+            triple([0, 1, 2]),
+        )];
+        assert!(next_check_matchup(&session, &matchups, &results, &[], &policy, 3).is_none());
+
+        results.push(ComparisonResult::new(
+            matchups[1].id,
+            session.id,
+            triple([5, 4, 3]),
+        ));
+        let check = next_check_matchup(&session, &matchups, &results, &[], &policy, 3).unwrap();
+        assert!(matches!(check.kind, MatchupKind::Repeat { .. }));
+        assert!(!check.is_seed);
+    }
+
+    #[test]
+    fn calibration_needs_confident_well_separated_photos() {
+        let policy = AttentionPolicy::default();
+        let ratings = vec![
+            rating(0, 3.0, 0.1),
+            rating(1, 2.5, 0.1),
+            rating(2, 1.0, 0.1),
+            rating(3, -1.0, 0.1),
+            rating(4, 0.0, 0.9),
+        ];
+        let triple = select_calibration_triple(&ratings, &policy, 3).unwrap();
+        assert_eq!(triple.len(), 3);
+        assert_eq!(triple[2], 3);
+        assert!(!triple.contains(&4));
+
+        let unsure: Vec<PhotoRating> = (0..5).map(PhotoRating::new).collect();
+        assert_eq!(select_calibration_triple(&unsure, &policy, 3), None);
+    }
+
+    #[test]
+    fn consistency_scores_repeats_and_calibration() {
+        let session = Session::default();
+        let original = Matchup::new(&session, triple([0, 1, 2]), true);
+        let repeat = Matchup::check(
+            &session,
+            triple([2, 1, 0]),
+            MatchupKind::Repeat {
+                original: original.id,
+            },
+        );
+        let calibration = Matchup::check(&session, triple([3, 4, 5]), MatchupKind::Calibration);
+        let results = vec![
+            ComparisonResult::new(original.id, session.id, triple([0, 1, 2])),
+            // Swaps 1 and 2: two of three pairs agree.
+            ComparisonResult::new(repeat.id, session.id, triple([0, 2, 1])),
+            ComparisonResult::new(calibration.id, session.id, triple([3, 4, 5])),
+        ];
+        let ratings = vec![
+            rating(3, 2.0, 0.1),
+            rating(4, 0.0, 0.1),
+            rating(5, -2.0, 0.1),
+        ];
+        let matchups = [original, repeat, calibration];
+
+        let report = self_consistency(&matchups, &results, &ratings);
+        assert_eq!(report.repeat_checks, 1);
+        assert_eq!(report.calibration_checks, 1);
+        assert!((report.repeat_agreement.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert!((report.calibration_agreement.unwrap() - 1.0).abs() < 1e-9);
+        assert!((report.score.unwrap() - 5.0 / 6.0).abs() < 1e-9);
+
+        let fit = results_for_fit(&matchups, &results, &AttentionPolicy::default());
+        assert_eq!(fit.len(), 1);
+    }
+}
@@ -0,0 +1,827 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Read, Write};
+use uuid::Uuid;
+
+use crate::identity::{content_hash, is_content_hash, PhotoSync};
+use crate::manifest::Manifest;
+use crate::models::{
+    Campaign, CampaignStatus, ComparisonResult, Matchup, Photo, PhotoRating, Session,
+};
+use crate::refit::RatingFit;
+use crate::repository::Repository;
+
+/// Layout version written into every bundle's checksums.
+///
+/// - 1: `manifest.json` held the checksums.
+/// - 2: `checksums.json` holds them, and `manifest.json` is the campaign's [`Manifest`].
+pub const BUNDLE_FORMAT: u32 = 2;
+
+const CHECKSUMS: &str = "checksums.json";
+const MANIFEST: &str = "manifest.json";
+const CAMPAIGN: &str = "campaign.json";
+const PHOTOS: &str = "photos.json";
+const SESSIONS: &str = "sessions.json";
+const MATCHUPS: &str = "matchups.json";
+const COMPARISONS: &str = "comparisons.json";
+const RATINGS: &str = "ratings.json";
+const IMAGES: &str = "images/";
+
+/// A campaign and everything its ranking was built from, portable between servers.
+///
+/// Stored as a tar archive of JSON files plus `images/<filename>` for the
+/// original images, if included. `checksums.json` lists the SHA-256 of every
+/// other file, so a bundle that was truncated or edited is refused on read.
+/// The owner secret hash is never included.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub campaign: Campaign,
+    /// The campaign's manifest, if it has one; its photos are the bundle's.
+    pub manifest: Option<Manifest>,
+    pub exported_at: DateTime<Utc>,
+    /// Every photo in index order; comparisons refer to photos by index.
+    pub photos: Vec<Photo>,
+    pub sessions: Vec<Session>,
+    pub matchups: Vec<Matchup>,
+    /// Every comparison, superseded ones included, oldest first.
+    pub comparisons: Vec<ComparisonResult>,
+    pub ratings: Vec<RatingSnapshot>,
+    /// Original image bytes by filename; empty unless exported with images.
+    pub images: BTreeMap<String, Vec<u8>>,
+}
+
+/// A session's stored ratings and the fit behind them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatingSnapshot {
+    pub session_id: Uuid,
+    pub fit: RatingFit,
+    pub ratings: Vec<PhotoRating>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Checksums {
+    format: u32,
+    campaign_id: String,
+    exported_at: DateTime<Utc>,
+    /// Hex SHA-256 of every other file, by path.
+    files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{path}: {source}")]
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("unsupported bundle format {0}")]
+    UnsupportedFormat(u32),
+    #[error("{0} is missing")]
+    MissingFile(String),
+    #[error("{0} is not listed in checksums.json")]
+    UnlistedFile(String),
+    #[error("{0} appears more than once")]
+    DuplicateFile(String),
+    #[error("{0} does not match its hash")]
+    HashMismatch(String),
+    #[error("{file} is for campaign {found}, but the bundle holds {campaign}")]
+    CampaignMismatch {
+        file: &'static str,
+        found: String,
+        campaign: String,
+    },
+    #[error("photo {0} differs from the manifest")]
+    ManifestPhoto(usize),
+    #[error("photos are not numbered 0 to {0} in order")]
+    PhotoOrder(usize),
+    #[error("photo {0} belongs to another campaign")]
+    PhotoCampaign(u32),
+    #[error("matchup {0} belongs to another campaign")]
+    MatchupCampaign(Uuid),
+    #[error("{file} refers to {id}, which is not in the bundle")]
+    Dangling { file: &'static str, id: Uuid },
+    #[error("image {0} is not one of the bundle's photos")]
+    UnknownImage(String),
+}
+
+impl Bundle {
+    /// Writes the bundle as a tar archive.
+    ///
+    /// # Errors
+    ///
+    /// Any error from `writer`.
+    pub fn write(&self, writer: impl Write) -> io::Result<()> {
+        let mut files: Vec<(String, &[u8])> = Vec::new();
+        let manifest = self.manifest.as_ref().map(to_json).transpose()?;
+        let json = [
+            (CAMPAIGN, Some(to_json(&self.campaign)?)),
+            (MANIFEST, manifest),
+            (PHOTOS, Some(to_json(&self.photos)?)),
+            (SESSIONS, Some(to_json(&self.sessions)?)),
+            (MATCHUPS, Some(to_json(&self.matchups)?)),
+            (COMPARISONS, Some(to_json(&self.comparisons)?)),
+            (RATINGS, Some(to_json(&self.ratings)?)),
+        ];
+        for (path, bytes) in &json {
+            if let Some(bytes) = bytes {
+                files.push(((*path).to_string(), bytes));
+            }
+        }
+        for (filename, bytes) in &self.images {
+            files.push((format!("{IMAGES}{filename}"), bytes));
+        }
+
+        let checksums = Checksums {
+            format: BUNDLE_FORMAT,
+            campaign_id: self.campaign.id.clone(),
+            exported_at: self.exported_at,
+            files: files
+                .iter()
+                .map(|(path, bytes)| (path.clone(), content_hash(bytes)))
+                .collect(),
+        };
+        let checksums = to_json(&checksums)?;
+
+        let mtime = u64::try_from(self.exported_at.timestamp()).unwrap_or(0);
+        let mut archive = tar::Builder::new(writer);
+        for (path, bytes) in std::iter::once((CHECKSUMS.to_string(), &checksums[..])).chain(files) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            archive.append_data(&mut header, path, bytes)?;
+        }
+        archive.into_inner()?.flush()
+    }
+
+    /// Reads and verifies a bundle written by [`Self::write`].
+    ///
+    /// # Errors
+    ///
+    /// If the archive is unreadable, a file is missing, unlisted or doesn't
+    /// match its hash, an image doesn't match its photo's content hash, or a
+    /// record refers to one that isn't in the bundle.
+    pub fn read(reader: impl Read) -> Result<Self, BundleError> {
+        let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_dir() {
+                continue;
+            }
+            let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            if files.insert(path.clone(), bytes).is_some() {
+                return Err(BundleError::DuplicateFile(path));
+            }
+        }
+
+        if !files.contains_key(CHECKSUMS) {
+            // Format 1 kept its checksums in manifest.json
+            if let Some(format) = files
+                .get(MANIFEST)
+                .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(bytes).ok())
+                .and_then(|manifest| manifest.get("format")?.as_u64())
+            {
+                return Err(BundleError::UnsupportedFormat(
+                    u32::try_from(format).unwrap_or(u32::MAX),
+                ));
+            }
+        }
+        let checksums: Checksums = from_json(&mut files, CHECKSUMS)?;
+        if checksums.format != BUNDLE_FORMAT {
+            return Err(BundleError::UnsupportedFormat(checksums.format));
+        }
+        if let Some(path) = files.keys().find(|p| !checksums.files.contains_key(*p)) {
+            return Err(BundleError::UnlistedFile(path.clone()));
+        }
+        for (path, hash) in &checksums.files {
+            let bytes = files
+                .get(path)
+                .ok_or_else(|| BundleError::MissingFile(path.clone()))?;
+            if content_hash(bytes) != *hash {
+                return Err(BundleError::HashMismatch(path.clone()));
+            }
+        }
+
+        let manifest = if files.contains_key(MANIFEST) {
+            Some(from_json(&mut files, MANIFEST)?)
+        } else {
+            None
+        };
+        let bundle = Self {
+            campaign: from_json(&mut files, CAMPAIGN)?,
+            manifest,
+            exported_at: checksums.exported_at,
+            photos: from_json(&mut files, PHOTOS)?,
+            sessions: from_json(&mut files, SESSIONS)?,
+            matchups: from_json(&mut files, MATCHUPS)?,
+            comparisons: from_json(&mut files, COMPARISONS)?,
+            ratings: from_json(&mut files, RATINGS)?,
+            images: files
+                .into_iter()
+                .filter_map(|(path, bytes)| Some((path.strip_prefix(IMAGES)?.to_string(), bytes)))
+                .collect(),
+        };
+        if bundle.campaign.id != checksums.campaign_id {
+            return Err(BundleError::CampaignMismatch {
+                file: CHECKSUMS,
+                found: checksums.campaign_id,
+                campaign: bundle.campaign.id,
+            });
+        }
+        bundle.check_manifest()?;
+        bundle.check_references()?;
+        Ok(bundle)
+    }
+
+    /// The manifest, if any, must describe the bundle's campaign and photos.
+    fn check_manifest(&self) -> Result<(), BundleError> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(());
+        };
+        if manifest.id() != self.campaign.id {
+            return Err(BundleError::CampaignMismatch {
+                file: MANIFEST,
+                found: manifest.id().to_string(),
+                campaign: self.campaign.id.clone(),
+            });
+        }
+        let listed = manifest.photos();
+        if let Some(i) = (0..listed.len().max(self.photos.len())).find(|&i| {
+            !matches!(
+                (listed.get(i), self.photos.get(i)),
+                (Some(l), Some(p)) if l.filename == p.filename && l.file_hash == p.file_hash
+            )
+        }) {
+            return Err(BundleError::ManifestPhoto(i));
+        }
+        Ok(())
+    }
+
+    fn check_references(&self) -> Result<(), BundleError> {
+        if self.photos.iter().zip(0..).any(|(p, i)| p.position != i) {
+            return Err(BundleError::PhotoOrder(self.photos.len()));
+        }
+        if let Some(photo) = self
+            .photos
+            .iter()
+            .find(|p| p.campaign_id != self.campaign.id)
+        {
+            return Err(BundleError::PhotoCampaign(photo.position));
+        }
+        for (filename, bytes) in &self.images {
+            let photo = self
+                .photos
+                .iter()
+                .find(|p| p.filename == *filename)
+                .ok_or_else(|| BundleError::UnknownImage(filename.clone()))?;
+            if is_content_hash(&photo.file_hash) && content_hash(bytes) != photo.file_hash {
+                return Err(BundleError::HashMismatch(format!("{IMAGES}{filename}")));
+            }
+        }
+
+        let sessions: HashSet<Uuid> = self
+            .sessions
+            .iter()
+            .filter(|s| s.campaign_id == self.campaign.id)
+            .map(|s| s.id)
+            .collect();
+        if let Some(session) = self.sessions.iter().find(|s| !sessions.contains(&s.id)) {
+            return Err(BundleError::Dangling {
+                file: SESSIONS,
+                id: session.id,
+            });
+        }
+        let matchups: HashSet<Uuid> = self.matchups.iter().map(|m| m.id).collect();
+        let comparisons: HashSet<Uuid> = self.comparisons.iter().map(|c| c.id).collect();
+        let dangling = |file, id| Err(BundleError::Dangling { file, id });
+        for matchup in &self.matchups {
+            if !sessions.contains(&matchup.session_id) {
+                return dangling(MATCHUPS, matchup.session_id);
+            }
+            if matchup.campaign_id != self.campaign.id {
+                return Err(BundleError::MatchupCampaign(matchup.id));
+            }
+        }
+        for comparison in &self.comparisons {
+            if !sessions.contains(&comparison.session_id) {
+                return dangling(COMPARISONS, comparison.session_id);
+            }
+            if !matchups.contains(&comparison.matchup_id) {
+                return dangling(COMPARISONS, comparison.matchup_id);
+            }
+            if let Some(id) = comparison
+                .superseded_by
+                .filter(|id| !comparisons.contains(id))
+            {
+                return dangling(COMPARISONS, id);
+            }
+        }
+        for snapshot in &self.ratings {
+            if !sessions.contains(&snapshot.session_id) {
+                return dangling(RATINGS, snapshot.session_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_json(value: &impl Serialize) -> io::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(value).map_err(io::Error::other)
+}
+
+fn from_json<T: DeserializeOwned>(
+    files: &mut BTreeMap<String, Vec<u8>>,
+    path: &str,
+) -> Result<T, BundleError> {
+    let bytes = files
+        .remove(path)
+        .ok_or_else(|| BundleError::MissingFile(path.to_string()))?;
+    serde_json::from_slice(&bytes).map_err(|source| BundleError::Json {
+        path: path.to_string(),
+        source,
+    })
+}
+
+/// Collects a campaign for a bundle, without its manifest or images, which
+/// live in the image store; `None` for an unknown campaign.
+///
+/// # Errors
+///
+/// Any repository error.
+pub async fn export<R: Repository>(
+    repo: &R,
+    campaign_id: &str,
+) -> Result<Option<Bundle>, R::Error> {
+    let Some(campaign) = repo.campaign(campaign_id).await? else {
+        return Ok(None);
+    };
+    let sessions = repo.campaign_sessions(campaign_id).await?;
+    let mut matchups = Vec::new();
+    let mut comparisons = Vec::new();
+    let mut ratings = Vec::new();
+    for session in &sessions {
+        matchups.extend(repo.session_matchups(session.id).await?);
+        comparisons.extend(repo.comparison_history(session.id).await?);
+        if let Some(fit) = repo.rating_fit(session.id).await? {
+            ratings.push(RatingSnapshot {
+                session_id: session.id,
+                fit,
+                ratings: repo.session_ratings(session.id).await?,
+            });
+        }
+    }
+    comparisons.sort_by_key(|c| c.created_at);
+
+    Ok(Some(Bundle {
+        campaign: Campaign {
+            owner_secret_hash: String::new(),
+            ..campaign
+        },
+        manifest: None,
+        exported_at: Utc::now(),
+        photos: repo.photos(campaign_id).await?,
+        sessions,
+        matchups,
+        comparisons,
+        ratings,
+        images: BTreeMap::new(),
+    }))
+}
+
+/// What an [`import`] stored; all zero when the bundle was imported before.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub campaign_created: bool,
+    pub photos_added: usize,
+    pub sessions_added: usize,
+    pub matchups_added: usize,
+    pub comparisons_added: usize,
+    pub ratings_added: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError<E> {
+    #[error("campaign {0} already exists with another owner")]
+    CampaignOwner(String),
+    #[error("photo {position} is {existing} here but {imported} in the bundle")]
+    PhotoMismatch {
+        position: u32,
+        existing: String,
+        imported: String,
+    },
+    #[error("session {0} already exists in another campaign")]
+    SessionCampaign(Uuid),
+    #[error(transparent)]
+    Repository(E),
+}
+
+/// Where a comparison's history is replayed from.
+enum Step<'a> {
+    Submit(&'a ComparisonResult),
+    Revise(Uuid, &'a ComparisonResult),
+    Retract(Uuid),
+}
+
+/// Stores a bundle's records that `repo` doesn't have yet.
+///
+/// Re-importing the same bundle changes nothing. Comparison history is
+/// replayed through the repository in time order, so the event log records
+/// the submissions, revisions and retractions as they happened; exclusions
+/// are logged as of the import. Ratings are only stored for sessions that
+/// have none, and the manifest and images are left to the caller.
+///
+/// A new campaign is owned by `owner_secret_hash`; an existing one must be.
+///
+/// # Errors
+///
+/// Refuses a campaign that exists with another owner, photos that differ
+/// from the ones already stored at the same index, and sessions of another
+/// campaign. Photos and the campaign are checked before anything is written.
+pub async fn import<R: Repository>(
+    repo: &R,
+    bundle: &Bundle,
+    owner_secret_hash: &str,
+) -> Result<ImportReport, ImportError<R::Error>> {
+    let mut report = ImportReport::default();
+    let campaign = &bundle.campaign;
+
+    let existing = repo
+        .campaign(&campaign.id)
+        .await
+        .map_err(ImportError::Repository)?;
+    if existing
+        .as_ref()
+        .is_some_and(|c| c.owner_secret_hash != owner_secret_hash)
+    {
+        return Err(ImportError::CampaignOwner(campaign.id.clone()));
+    }
+    let photos = repo
+        .photos(&campaign.id)
+        .await
+        .map_err(ImportError::Repository)?;
+    for (stored, imported) in photos.iter().zip(&bundle.photos) {
+        if stored.file_hash != imported.file_hash {
+            return Err(ImportError::PhotoMismatch {
+                position: imported.position,
+                existing: stored.filename.clone(),
+                imported: imported.filename.clone(),
+            });
+        }
+    }
+
+    if existing.is_none() {
+        // Opened first so sessions have a campaign; the status follows at the end
+        let opened = Campaign {
+            owner_secret_hash: owner_secret_hash.to_string(),
+            status: CampaignStatus::Active,
+            closed_at: None,
+            reopened_at: None,
+            ..campaign.clone()
+        };
+        repo.save_campaign(&opened)
+            .await
+            .map_err(ImportError::Repository)?;
+        report.campaign_created = true;
+    }
+
+    if let Some(added) = bundle.photos.get(photos.len()..).filter(|a| !a.is_empty()) {
+        let sync = PhotoSync {
+            photos: photos.iter().chain(added).cloned().collect(),
+            added: added.iter().map(|p| p.position).collect(),
+            renamed: Vec::new(),
+            rehashed: Vec::new(),
+        };
+        repo.apply_photo_sync(&sync)
+            .await
+            .map_err(ImportError::Repository)?;
+        report.photos_added = added.len();
+    }
+
+    for session in &bundle.sessions {
+        if repo
+            .import_session(session)
+            .await
+            .map_err(ImportError::Repository)?
+        {
+            report.sessions_added += 1;
+            if session.excluded {
+                repo.set_session_excluded(session.id, true)
+                    .await
+                    .map_err(ImportError::Repository)?;
+            }
+            continue;
+        }
+        let stored = repo
+            .session_campaign(session.id)
+            .await
+            .map_err(ImportError::Repository)?;
+        if stored.is_none_or(|c| c.id != campaign.id) {
+            return Err(ImportError::SessionCampaign(session.id));
+        }
+    }
+
+    import_records(repo, bundle, &mut report)
+        .await
+        .map_err(ImportError::Repository)?;
+    if report.campaign_created {
+        restore_status(repo, campaign)
+            .await
+            .map_err(ImportError::Repository)?;
+    }
+
+    Ok(report)
+}
+
+/// Stores the matchups, comparison history and ratings `repo` doesn't have yet.
+async fn import_records<R: Repository>(
+    repo: &R,
+    bundle: &Bundle,
+    report: &mut ImportReport,
+) -> Result<(), R::Error> {
+    for matchup in &bundle.matchups {
+        if repo.matchup(matchup.id).await?.is_none() {
+            repo.create_matchup(matchup).await?;
+            report.matchups_added += 1;
+        }
+    }
+
+    for (at, step) in history(&bundle.comparisons) {
+        let stored = match step {
+            Step::Submit(comparison) => repo.save_comparison(&active(comparison)).await?,
+            Step::Revise(id, revision) => repo.revise_comparison(id, &active(revision)).await?,
+            Step::Retract(id) => {
+                repo.retract_comparison(id, at).await?;
+                false
+            }
+        };
+        if stored {
+            report.comparisons_added += 1;
+        }
+    }
+
+    for snapshot in &bundle.ratings {
+        if repo.rating_fit(snapshot.session_id).await?.is_none() {
+            repo.save_rating_fit(snapshot.session_id, &snapshot.ratings, &snapshot.fit)
+                .await?;
+            report.ratings_added += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Moves a newly imported campaign from active to its exported status.
+async fn restore_status<R: Repository>(repo: &R, campaign: &Campaign) -> Result<(), R::Error> {
+    if campaign.status == CampaignStatus::Reopened {
+        // Log the close the reopen follows
+        let closed = Campaign {
+            status: CampaignStatus::Closed,
+            reopened_at: None,
+            ..campaign.clone()
+        };
+        repo.save_campaign(&closed).await?;
+    }
+    if campaign.status != CampaignStatus::Active {
+        repo.save_campaign(campaign).await?;
+    }
+    Ok(())
+}
+
+/// The submissions, revisions and retractions behind `comparisons`, in time order.
+fn history(comparisons: &[ComparisonResult]) -> Vec<(DateTime<Utc>, Step<'_>)> {
+    let revisions: HashSet<Uuid> = comparisons.iter().filter_map(|c| c.superseded_by).collect();
+    let mut steps = Vec::new();
+    for comparison in comparisons {
+        if !revisions.contains(&comparison.id) {
+            steps.push((comparison.created_at, 0, Step::Submit(comparison)));
+        }
+        let Some(at) = comparison.superseded_at else {
+            continue;
+        };
+        let step = match comparison.superseded_by {
+            Some(id) => match comparisons.iter().find(|c| c.id == id) {
+                Some(revision) => Step::Revise(comparison.id, revision),
+                None => continue,
+            },
+            None => Step::Retract(comparison.id),
+        };
+        steps.push((at, 1, step));
+    }
+    steps.sort_by_key(|(at, order, _)| (*at, *order));
+    steps.into_iter().map(|(at, _, step)| (at, step)).collect()
+}
+
+/// A copy as it was stored, before anything superseded it.
+fn active(comparison: &ComparisonResult) -> ComparisonResult {
+    ComparisonResult {
+        superseded_at: None,
+        superseded_by: None,
+        ..comparison.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestPhoto;
+    use crate::models::owner_secret_hash;
+    use crate::pool::MatchupPool;
+    use crate::repository::{block_on, synced_photos, MemoryRepository};
+    use crate::types::triple;
+
+    const SECRET: &str = "s3cret";
+
+    fn image(i: u8) -> Vec<u8> {
+        vec![i; 16]
+    }
+
+    fn repository() -> MemoryRepository {
+        let files = (0..4).map(|i| (format!("{i:02}.jpg"), image(i)));
+        MemoryRepository::new().with_photos(synced_photos("trip", files))
+    }
+
+    /// A closed campaign with one revised, one retracted and one kept answer
+    /// from an excluded session, and that session's ratings.
+    fn campaign(repo: &MemoryRepository) -> Bundle {
+        let campaign = Campaign::new(
+            "trip".to_string(),
+            "Trip".to_string(),
+            owner_secret_hash(SECRET),
+        );
+        block_on(repo.save_campaign(&campaign)).unwrap();
+        let session = Session::new(campaign.id.clone());
+        assert!(block_on(repo.import_session(&session)).unwrap());
+        assert!(block_on(repo.set_session_excluded(session.id, true)).unwrap());
+
+        let answers: Vec<ComparisonResult> = [[0, 1, 2], [1, 2, 3], [0, 2, 3]]
+            .into_iter()
+            .map(|photos| {
+                let matchup = Matchup::new(&session, triple(photos), true);
+                block_on(repo.create_matchup(&matchup)).unwrap();
+                let answer = ComparisonResult::new(matchup.id, session.id, triple(photos));
+                assert!(block_on(repo.save_comparison(&answer)).unwrap());
+                answer
+            })
+            .collect();
+        let revision = answers[0].revision(triple([2, 1, 0]));
+        assert!(block_on(repo.revise_comparison(answers[0].id, &revision)).unwrap());
+        assert!(block_on(repo.retract_comparison(answers[1].id, Utc::now())).unwrap());
+
+        let ratings = [PhotoRating::new(0), PhotoRating::new(1)];
+        let fit = RatingFit {
+            version: 0,
+            model_version: 1,
+            comparisons: 2,
+            iterations: 10,
+            first_position_advantage: 0.0,
+            fitted_at: Utc::now(),
+        };
+        block_on(repo.save_rating_fit(session.id, &ratings, &fit)).unwrap();
+
+        let mut closed = campaign;
+        closed.close(Utc::now()).unwrap();
+        block_on(repo.save_campaign(&closed)).unwrap();
+        block_on(export(repo, "trip")).unwrap().unwrap()
+    }
+
+    fn archive(bundle: &Bundle) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bundle.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trips_through_an_archive() {
+        let mut bundle = campaign(&repository());
+        bundle.images.insert("01.jpg".to_string(), image(1));
+
+        let bytes = archive(&bundle);
+        let hash = owner_secret_hash(SECRET);
+        assert!(!bytes.windows(hash.len()).any(|w| w == hash.as_bytes()));
+
+        let read = Bundle::read(bytes.as_slice()).unwrap();
+        assert_eq!(read.campaign, bundle.campaign);
+        assert_eq!(read.manifest, None);
+        assert_eq!(read.exported_at, bundle.exported_at);
+        assert_eq!(read.comparisons, bundle.comparisons);
+        assert_eq!(read.photos.len(), 4);
+        assert_eq!(read.sessions.len(), 1);
+        assert_eq!(read.matchups.len(), 3);
+        assert_eq!(read.ratings.len(), 1);
+        assert_eq!(read.images, bundle.images);
+    }
+
+    #[test]
+    fn refuses_tampered_bundles() {
+        let bundle = campaign(&repository());
+        let bytes = archive(&bundle);
+
+        // Same length, so the archive stays readable and only the hash catches it
+        let mut tampered = bytes.clone();
+        let at = tampered.windows(4).position(|w| w == b"Trip").unwrap();
+        tampered[at..at + 4].copy_from_slice(b"Trap");
+        assert!(matches!(
+            Bundle::read(tampered.as_slice()),
+            Err(BundleError::HashMismatch(path)) if path == CAMPAIGN
+        ));
+
+        let mut wrong_image = bundle;
+        wrong_image.images.insert("01.jpg".to_string(), image(2));
+        assert!(matches!(
+            Bundle::read(archive(&wrong_image).as_slice()),
+            Err(BundleError::HashMismatch(path)) if path == "images/01.jpg"
+        ));
+    }
+
+    #[test]
+    fn import_rebuilds_the_campaign_once() {
+        let bundle = campaign(&repository());
+        let target = repository();
+
+        let report = block_on(import(&target, &bundle, &owner_secret_hash(SECRET))).unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                campaign_created: true,
+                photos_added: 0,
+                sessions_added: 1,
+                matchups_added: 3,
+                comparisons_added: 4,
+                ratings_added: 1,
+            }
+        );
+        let again = block_on(import(&target, &bundle, &owner_secret_hash(SECRET))).unwrap();
+        assert_eq!(again, ImportReport::default());
+
+        let copy = block_on(export(&target, "trip")).unwrap().unwrap();
+        assert_eq!(copy.campaign, bundle.campaign);
+        assert_eq!(copy.comparisons, bundle.comparisons);
+        assert!(copy.sessions[0].excluded);
+        let events = block_on(target.events(0)).unwrap();
+        let replayed = crate::events::replay(&events).unwrap();
+        assert_eq!(replayed.comparisons(), bundle.comparisons.as_slice());
+        assert_eq!(replayed.campaign_status("trip"), CampaignStatus::Closed);
+    }
+
+    #[test]
+    fn import_refuses_other_photos_and_owners() {
+        let bundle = campaign(&repository());
+
+        let renumbered = MemoryRepository::new()
+            .with_photos(synced_photos("trip", [("other.jpg".to_string(), image(9))]));
+        assert!(matches!(
+            block_on(import(&renumbered, &bundle, &owner_secret_hash(SECRET))),
+            Err(ImportError::PhotoMismatch { position: 0, .. })
+        ));
+        assert!(block_on(renumbered.campaign("trip")).unwrap().is_none());
+
+        let empty = MemoryRepository::new();
+        let report = block_on(import(&empty, &bundle, &owner_secret_hash(SECRET))).unwrap();
+        assert_eq!(report.photos_added, 4);
+        assert!(block_on(empty.campaign("trip"))
+            .unwrap()
+            .unwrap()
+            .is_owned_by(SECRET));
+        assert!(matches!(
+            block_on(import(&empty, &bundle, &owner_secret_hash("guess"))),
+            Err(ImportError::CampaignOwner(id)) if id == "trip"
+        ));
+    }
+
+    #[test]
+    fn carries_a_manifest_of_the_same_photos() {
+        let mut bundle = campaign(&repository());
+        let photos: Vec<ManifestPhoto> = bundle
+            .photos
+            .iter()
+            .map(|p| ManifestPhoto {
+                filename: p.filename.clone(),
+                file_hash: p.file_hash.clone(),
+                width: 3000,
+                height: 2000,
+            })
+            .collect();
+        let pool = MatchupPool::generate_seed(4, 3, 1).unwrap();
+        let manifest =
+            Manifest::new("trip".into(), "Trip".into(), photos.clone(), pool, 3).unwrap();
+        bundle.manifest = Some(manifest.clone());
+
+        let read = Bundle::read(archive(&bundle).as_slice()).unwrap();
+        assert_eq!(read.manifest, Some(manifest));
+
+        let pool = MatchupPool::generate_seed(3, 3, 1).unwrap();
+        bundle.manifest = Some(
+            Manifest::new("trip".into(), "Trip".into(), photos[..3].to_vec(), pool, 3).unwrap(),
+        );
+        assert!(matches!(
+            Bundle::read(archive(&bundle).as_slice()),
+            Err(BundleError::ManifestPhoto(3))
+        ));
+    }
+}
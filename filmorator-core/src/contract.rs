@@ -0,0 +1,263 @@
+//! Behaviour every [`Repository`] shares, as checks that each implementation's
+//! tests run against a fresh, migrated store.
+//!
+//! Each check panics on the first rule the repository breaks.
+#![allow(clippy::missing_panics_doc)]
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::events::replay;
+use crate::identity::{content_hash, sync_photos, ListedPhoto};
+use crate::models::{
+    Campaign, CampaignRating, CampaignStatus, ComparisonResult, Matchup, Session,
+    DEFAULT_CAMPAIGN_ID,
+};
+use crate::ranking::{Anchor, AnchorMode, PositionBias};
+use crate::repository::Repository;
+use crate::types::triple;
+
+async fn session<R: Repository>(repo: &R) -> Session {
+    repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)
+        .await
+        .unwrap()
+}
+
+async fn answered<R: Repository>(repo: &R, session: &Session) -> ComparisonResult {
+    let matchup = Matchup::new(session, triple([0, 1, 2]), false);
+    repo.create_matchup(&matchup).await.unwrap();
+    let mut result = ComparisonResult::new(matchup.id, session.id, triple([2, 0, 1]));
+    result.idempotency_key = Some(Uuid::new_v4());
+    assert!(repo.save_comparison(&result).await.unwrap());
+    result
+}
+
+pub async fn comparisons_are_unique_per_matchup_and_key<R: Repository>(repo: &R) {
+    let session = session(repo).await;
+    let first = answered(repo, &session).await;
+
+    let again = ComparisonResult::new(first.matchup_id, session.id, triple([0, 1, 2]));
+    assert!(!repo.save_comparison(&again).await.unwrap());
+    let prior = repo
+        .find_prior_submission(session.id, first.matchup_id, None)
+        .await
+        .unwrap();
+    assert_eq!(prior.map(|c| c.id), Some(first.id));
+
+    // A retried key is refused even against another matchup
+    let other = Matchup::new(&session, triple([3, 4, 5]), false);
+    repo.create_matchup(&other).await.unwrap();
+    let mut retry = ComparisonResult::new(other.id, session.id, triple([3, 4, 5]));
+    retry.idempotency_key = first.idempotency_key;
+    assert!(!repo.save_comparison(&retry).await.unwrap());
+    assert_eq!(repo.session_comparisons(session.id).await.unwrap().len(), 1);
+
+    // Unknown sessions can't store anything
+    let stranger = ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([0, 1, 2]));
+    assert!(!repo.save_comparison(&stranger).await.unwrap());
+    let orphan = Matchup::new(&Session::default(), triple([0, 1, 2]), false);
+    assert!(repo.create_matchup(&orphan).await.is_err());
+}
+
+pub async fn retracted_matchups_are_requeued_until_answered_again<R: Repository>(repo: &R) {
+    let session = session(repo).await;
+    let first = answered(repo, &session).await;
+
+    assert!(repo.retract_comparison(first.id, Utc::now()).await.unwrap());
+    assert!(!repo.retract_comparison(first.id, Utc::now()).await.unwrap());
+    let requeued = repo.requeued_matchup(session.id).await.unwrap();
+    assert_eq!(requeued.map(|m| m.id), Some(first.matchup_id));
+    assert!(repo
+        .session_comparisons(session.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let again = ComparisonResult::new(first.matchup_id, session.id, triple([0, 1, 2]));
+    assert!(repo.save_comparison(&again).await.unwrap());
+    assert!(repo.requeued_matchup(session.id).await.unwrap().is_none());
+}
+
+pub async fn failed_revision_changes_nothing<R: Repository>(repo: &R) {
+    let session = session(repo).await;
+    let first = answered(repo, &session).await;
+
+    // Colliding id: the insert fails, so the original must stay active
+    let mut clash = first.revision(triple([0, 1, 2]));
+    clash.id = first.id;
+    assert!(!repo.revise_comparison(first.id, &clash).await.unwrap());
+    let active = repo.session_comparisons(session.id).await.unwrap();
+    assert_eq!(active.len(), 1);
+    assert!(active[0].is_active());
+
+    let revision = first.revision(triple([0, 1, 2]));
+    assert!(repo.revise_comparison(first.id, &revision).await.unwrap());
+    let active = repo.session_comparisons(session.id).await.unwrap();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].id, revision.id);
+    let history = repo.comparison_history(session.id).await.unwrap();
+    assert_eq!(history.len(), 2);
+}
+
+pub async fn event_log_replays_to_the_stored_comparisons<R: Repository>(repo: &R) {
+    let session = session(repo).await;
+    let first = answered(repo, &session).await;
+    let second = answered(repo, &session).await;
+    let revision = first.revision(triple([0, 1, 2]));
+    assert!(repo.revise_comparison(first.id, &revision).await.unwrap());
+    assert!(repo
+        .retract_comparison(second.id, Utc::now())
+        .await
+        .unwrap());
+    assert!(repo.set_session_excluded(session.id, true).await.unwrap());
+
+    let events = repo.events(0).await.unwrap();
+    assert_eq!(events.len(), 5);
+    assert!(events.windows(2).all(|w| w[0].seq < w[1].seq));
+    let replayed = replay(&events).unwrap();
+    assert_eq!(
+        replayed.session_comparisons(session.id),
+        repo.session_comparisons(session.id).await.unwrap()
+    );
+    assert!(replayed.is_excluded(session.id));
+
+    // Reading after a sequence number returns only what came later
+    let later = repo.events(events[3].seq).await.unwrap();
+    assert_eq!(later, events[4..]);
+}
+
+pub async fn photos_and_anchors_are_per_campaign<R: Repository>(repo: &R) {
+    let trip = Campaign::new("trip".to_string(), "Trip".to_string(), String::new());
+    repo.save_campaign(&trip).await.unwrap();
+    let listed = |name: &str| ListedPhoto {
+        filename: name.to_string(),
+        file_hash: content_hash(name.as_bytes()),
+    };
+    let default = sync_photos(DEFAULT_CAMPAIGN_ID, &[], &[listed("a.jpg")]).unwrap();
+    repo.apply_photo_sync(&default).await.unwrap();
+    let synced = sync_photos("trip", &[], &[listed("b.jpg"), listed("c.jpg")]).unwrap();
+    repo.apply_photo_sync(&synced).await.unwrap();
+
+    assert_eq!(repo.count_photos(DEFAULT_CAMPAIGN_ID).await.unwrap(), 1);
+    assert_eq!(repo.count_photos("trip").await.unwrap(), 2);
+    assert_eq!(
+        repo.photo_filename("trip", 0).await.unwrap().as_deref(),
+        Some("b.jpg")
+    );
+
+    let anchor = Anchor {
+        photo_idx: 1,
+        strength: 0.5,
+        mode: AnchorMode::Fixed,
+    };
+    assert!(!repo
+        .upsert_anchor(DEFAULT_CAMPAIGN_ID, &anchor)
+        .await
+        .unwrap());
+    assert!(repo.upsert_anchor("trip", &anchor).await.unwrap());
+    assert!(repo.anchors(DEFAULT_CAMPAIGN_ID).await.unwrap().is_empty());
+    assert_eq!(repo.anchors("trip").await.unwrap().len(), 1);
+}
+
+pub async fn campaign_status_changes_are_logged<R: Repository>(repo: &R) {
+    let session = session(repo).await;
+    let mut campaign = repo.session_campaign(session.id).await.unwrap().unwrap();
+    assert_eq!(campaign.id, DEFAULT_CAMPAIGN_ID);
+
+    campaign.close(Utc::now()).unwrap();
+    repo.save_campaign(&campaign).await.unwrap();
+    let stored = repo.campaign(DEFAULT_CAMPAIGN_ID).await.unwrap().unwrap();
+    assert_eq!(stored.status, CampaignStatus::Closed);
+    let events = repo.events(0).await.unwrap();
+    assert_eq!(
+        replay(&events)
+            .unwrap()
+            .campaign_status(DEFAULT_CAMPAIGN_ID),
+        CampaignStatus::Closed
+    );
+}
+
+pub async fn threshold_is_recorded_once_and_never_while_closed<R: Repository>(repo: &R) {
+    // Stores may round timestamps, so compare what was read back
+    let at = Utc::now();
+    let stale = repo.campaign(DEFAULT_CAMPAIGN_ID).await.unwrap().unwrap();
+
+    assert!(repo
+        .set_threshold_reached(DEFAULT_CAMPAIGN_ID, at)
+        .await
+        .unwrap());
+    assert!(!repo
+        .set_threshold_reached(DEFAULT_CAMPAIGN_ID, Utc::now())
+        .await
+        .unwrap());
+    assert!(!repo.set_threshold_reached("nope", at).await.unwrap());
+    let reached = repo
+        .campaign(DEFAULT_CAMPAIGN_ID)
+        .await
+        .unwrap()
+        .unwrap()
+        .threshold_reached_at;
+    assert!(reached.is_some());
+
+    // Saving a copy read before the threshold keeps it
+    let mut closed = stale;
+    closed.close(Utc::now()).unwrap();
+    repo.save_campaign(&closed).await.unwrap();
+    let stored = repo.campaign(DEFAULT_CAMPAIGN_ID).await.unwrap().unwrap();
+    assert_eq!(stored.status, CampaignStatus::Closed);
+    assert_eq!(stored.threshold_reached_at, reached);
+
+    let mut closed = Campaign::new("trip".to_string(), "Trip".to_string(), String::new());
+    closed.close(at).unwrap();
+    repo.save_campaign(&closed).await.unwrap();
+    assert!(!repo.set_threshold_reached("trip", at).await.unwrap());
+}
+
+pub async fn sessions_keep_the_campaign_they_started_in<R: Repository>(repo: &R) {
+    let trip = Campaign::new("trip".to_string(), "Trip".to_string(), String::new());
+    repo.save_campaign(&trip).await.unwrap();
+    assert!(repo.touch_session(Uuid::new_v4(), "nope").await.is_err());
+
+    let session = repo.touch_session(Uuid::new_v4(), "trip").await.unwrap();
+    assert_eq!(session.campaign_id, "trip");
+    let again = repo
+        .touch_session(session.id, DEFAULT_CAMPAIGN_ID)
+        .await
+        .unwrap();
+    assert_eq!(again.campaign_id, "trip");
+
+    let rating = |photo_idx, strength| CampaignRating {
+        photo_idx,
+        strength,
+        uncertainty: 0.5,
+        comparison_count: 3,
+        updated_at: Utc::now(),
+    };
+    let bias = PositionBias {
+        first_position_advantage: 1.5,
+        raw_first_win_rate: 0.6,
+        ordered_pairs: 10,
+    };
+    repo.save_campaign_ratings("trip", &[rating(0, -1.0), rating(1, 1.0)], &bias)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.campaign_position_bias("trip").await.unwrap(),
+        Some(bias)
+    );
+    let ranking = repo.campaign_ratings("trip").await.unwrap();
+    assert_eq!(
+        ranking.iter().map(|r| r.photo_idx).collect::<Vec<_>>(),
+        [1, 0]
+    );
+    assert!(repo
+        .campaign_ratings(DEFAULT_CAMPAIGN_ID)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(repo
+        .campaign_position_bias(DEFAULT_CAMPAIGN_ID)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,382 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::models::{Campaign, CampaignError, CampaignStatus, ComparisonResult};
+
+/// A change to the data that rankings are derived from.
+///
+/// Events are only ever appended. Comparisons, exclusions and campaign status
+/// can be rebuilt from them with [`replay`], even after rows were deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A new answer, as stored; it is never superseded yet.
+    ComparisonSubmitted {
+        comparison: ComparisonResult,
+    },
+    /// An answer undone with no replacement.
+    ComparisonRetracted {
+        comparison_id: Uuid,
+    },
+    /// An answer replaced by `revision`.
+    ComparisonRevised {
+        comparison_id: Uuid,
+        revision: ComparisonResult,
+    },
+    /// The owner left a session out of (or back in) the campaign ranking.
+    SessionExcluded {
+        session_id: Uuid,
+        excluded: bool,
+    },
+    CampaignClosed {
+        campaign_id: String,
+    },
+    CampaignReopened {
+        campaign_id: String,
+    },
+}
+
+impl Event {
+    /// The event recording a change of `campaign`'s status from `before`
+    /// (`None` for a new campaign), with when it happened; `None` if the status
+    /// didn't change.
+    #[must_use]
+    pub fn campaign_transition(
+        before: Option<CampaignStatus>,
+        campaign: &Campaign,
+    ) -> Option<(DateTime<Utc>, Self)> {
+        let campaign_id = campaign.id.clone();
+        match (before.unwrap_or_default(), campaign.status) {
+            (CampaignStatus::Active | CampaignStatus::Reopened, CampaignStatus::Closed) => Some((
+                campaign.closed_at.unwrap_or_else(Utc::now),
+                Self::CampaignClosed { campaign_id },
+            )),
+            (CampaignStatus::Closed, CampaignStatus::Reopened) => Some((
+                campaign.reopened_at.unwrap_or_else(Utc::now),
+                Self::CampaignReopened { campaign_id },
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// An [`Event`] as stored, numbered in the order it was appended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub event: Event,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReplayError {
+    #[error("event {seq} is out of order")]
+    OutOfOrder { seq: u64 },
+    #[error("event {seq}: comparison {id} stored twice")]
+    DuplicateComparison { seq: u64, id: Uuid },
+    #[error("event {seq}: no active comparison {id}")]
+    UnknownComparison { seq: u64, id: Uuid },
+    #[error("event {seq}: {source}")]
+    Campaign { seq: u64, source: CampaignError },
+}
+
+/// State rebuilt from the event log: everything the derived tables are computed from.
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    comparisons: Vec<ComparisonResult>,
+    excluded: HashSet<Uuid>,
+    campaigns: HashMap<String, CampaignStatus>,
+    last_seq: Option<u64>,
+}
+
+impl Replay {
+    /// Applies the next event.
+    ///
+    /// # Errors
+    ///
+    /// If the event does not follow the ones applied so far: a sequence number
+    /// that doesn't increase, a comparison stored twice or superseded while
+    /// not active, or a campaign transition its status doesn't allow.
+    pub fn apply(&mut self, logged: &LoggedEvent) -> Result<(), ReplayError> {
+        let seq = logged.seq;
+        if self.last_seq.is_some_and(|last| seq <= last) {
+            return Err(ReplayError::OutOfOrder { seq });
+        }
+
+        match &logged.event {
+            Event::ComparisonSubmitted { comparison } => self.insert(seq, comparison)?,
+            Event::ComparisonRetracted { comparison_id } => {
+                self.active_mut(seq, *comparison_id)?
+                    .supersede(logged.at, None);
+            }
+            Event::ComparisonRevised {
+                comparison_id,
+                revision,
+            } => {
+                self.active_mut(seq, *comparison_id)?
+                    .supersede(logged.at, Some(revision.id));
+                self.insert(seq, revision)?;
+            }
+            Event::SessionExcluded {
+                session_id,
+                excluded,
+            } => {
+                if *excluded {
+                    self.excluded.insert(*session_id);
+                } else {
+                    self.excluded.remove(session_id);
+                }
+            }
+            Event::CampaignClosed { campaign_id } => {
+                let status = self.campaigns.entry(campaign_id.clone()).or_default();
+                *status = status
+                    .close()
+                    .map_err(|source| ReplayError::Campaign { seq, source })?;
+            }
+            Event::CampaignReopened { campaign_id } => {
+                let status = self.campaigns.entry(campaign_id.clone()).or_default();
+                *status = status
+                    .reopen()
+                    .map_err(|source| ReplayError::Campaign { seq, source })?;
+            }
+        }
+
+        self.last_seq = Some(seq);
+        Ok(())
+    }
+
+    fn insert(&mut self, seq: u64, comparison: &ComparisonResult) -> Result<(), ReplayError> {
+        let id = comparison.id;
+        if self.comparisons.iter().any(|c| c.id == id) {
+            return Err(ReplayError::DuplicateComparison { seq, id });
+        }
+        self.comparisons.push(comparison.clone());
+        Ok(())
+    }
+
+    fn active_mut(&mut self, seq: u64, id: Uuid) -> Result<&mut ComparisonResult, ReplayError> {
+        self.comparisons
+            .iter_mut()
+            .find(|c| c.id == id && c.is_active())
+            .ok_or(ReplayError::UnknownComparison { seq, id })
+    }
+
+    /// Every comparison ever stored, superseded ones included, in log order.
+    #[must_use]
+    pub fn comparisons(&self) -> &[ComparisonResult] {
+        &self.comparisons
+    }
+
+    /// Active comparisons of a session, oldest first.
+    #[must_use]
+    pub fn session_comparisons(&self, session_id: Uuid) -> Vec<ComparisonResult> {
+        let mut active: Vec<ComparisonResult> = self
+            .comparisons
+            .iter()
+            .filter(|c| c.session_id == session_id && c.is_active())
+            .cloned()
+            .collect();
+        active.sort_by_key(|c| c.created_at);
+        active
+    }
+
+    /// Sessions with at least one stored comparison, in the order they first answered.
+    #[must_use]
+    pub fn sessions(&self) -> Vec<Uuid> {
+        let mut sessions = Vec::new();
+        for comparison in &self.comparisons {
+            if !sessions.contains(&comparison.session_id) {
+                sessions.push(comparison.session_id);
+            }
+        }
+        sessions
+    }
+
+    #[must_use]
+    pub fn is_excluded(&self, session_id: Uuid) -> bool {
+        self.excluded.contains(&session_id)
+    }
+
+    /// A campaign's status; [`CampaignStatus::Active`] if it was never closed.
+    #[must_use]
+    pub fn campaign_status(&self, campaign_id: &str) -> CampaignStatus {
+        self.campaigns.get(campaign_id).copied().unwrap_or_default()
+    }
+}
+
+/// Rebuilds state from the whole log.
+///
+/// Derived tables follow from the result: per-session ratings come from
+/// [`crate::refit::fit_ratings`] over [`Replay::session_comparisons`].
+///
+/// # Errors
+///
+/// The first event that doesn't follow from the ones before it; see [`Replay::apply`].
+pub fn replay<'a>(
+    events: impl IntoIterator<Item = &'a LoggedEvent>,
+) -> Result<Replay, ReplayError> {
+    let mut state = Replay::default();
+    for event in events {
+        state.apply(event)?;
+    }
+    Ok(state)
+}
+
+/// Rebuilds state as it was at `at`, ignoring later events.
+///
+/// # Errors
+///
+/// As for [`replay`].
+pub fn replay_until<'a>(
+    events: impl IntoIterator<Item = &'a LoggedEvent>,
+    at: DateTime<Utc>,
+) -> Result<Replay, ReplayError> {
+    replay(events.into_iter().filter(|e| e.at <= at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::triple;
+    use chrono::TimeDelta;
+
+    fn logged(events: Vec<Event>) -> Vec<LoggedEvent> {
+        let start = Utc::now();
+        events
+            .into_iter()
+            .zip(1..)
+            .map(|(event, seq)| LoggedEvent {
+                seq,
+                at: start + TimeDelta::seconds(i64::try_from(seq).unwrap()),
+                event,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn revisions_and_retractions_supersede() {
+        let session_id = Uuid::new_v4();
+        let first = ComparisonResult::new(Uuid::new_v4(), session_id, triple([0, 1, 2]));
+        let second = ComparisonResult::new(Uuid::new_v4(), session_id, triple([3, 4, 5]));
+        let revision = first.revision(triple([2, 1, 0]));
+        let events = logged(vec![
+            Event::ComparisonSubmitted {
+                comparison: first.clone(),
+            },
+            Event::ComparisonSubmitted {
+                comparison: second.clone(),
+            },
+            Event::ComparisonRevised {
+                comparison_id: first.id,
+                revision: revision.clone(),
+            },
+            Event::ComparisonRetracted {
+                comparison_id: second.id,
+            },
+        ]);
+
+        let state = replay(&events).unwrap();
+        assert_eq!(state.comparisons().len(), 3);
+        let active = state.session_comparisons(session_id);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, revision.id);
+        let superseded = &state.comparisons()[0];
+        assert_eq!(superseded.superseded_by, Some(revision.id));
+        assert_eq!(superseded.superseded_at, Some(events[2].at));
+        assert_eq!(state.sessions(), vec![session_id]);
+    }
+
+    #[test]
+    fn replays_to_a_point_in_time() {
+        let session_id = Uuid::new_v4();
+        let first = ComparisonResult::new(Uuid::new_v4(), session_id, triple([0, 1, 2]));
+        let events = logged(vec![
+            Event::ComparisonSubmitted {
+                comparison: first.clone(),
+            },
+            Event::SessionExcluded {
+                session_id,
+                excluded: true,
+            },
+            Event::CampaignClosed {
+                campaign_id: "c".to_string(),
+            },
+            Event::ComparisonRetracted {
+                comparison_id: first.id,
+            },
+        ]);
+
+        let before = replay_until(&events, events[1].at).unwrap();
+        assert_eq!(before.session_comparisons(session_id).len(), 1);
+        assert!(before.is_excluded(session_id));
+        assert_eq!(before.campaign_status("c"), CampaignStatus::Active);
+
+        let after = replay(&events).unwrap();
+        assert!(after.session_comparisons(session_id).is_empty());
+        assert_eq!(after.campaign_status("c"), CampaignStatus::Closed);
+    }
+
+    #[test]
+    fn rejects_events_that_do_not_follow() {
+        let comparison = ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([0, 1, 2]));
+        let id = comparison.id;
+
+        let twice = logged(vec![
+            Event::ComparisonSubmitted {
+                comparison: comparison.clone(),
+            },
+            Event::ComparisonSubmitted { comparison },
+        ]);
+        assert_eq!(
+            replay(&twice).unwrap_err(),
+            ReplayError::DuplicateComparison { seq: 2, id }
+        );
+
+        let unknown = logged(vec![Event::ComparisonRetracted { comparison_id: id }]);
+        assert_eq!(
+            replay(&unknown).unwrap_err(),
+            ReplayError::UnknownComparison { seq: 1, id }
+        );
+
+        let reopened = logged(vec![Event::CampaignReopened {
+            campaign_id: "c".to_string(),
+        }]);
+        assert_eq!(
+            replay(&reopened).unwrap_err(),
+            ReplayError::Campaign {
+                seq: 1,
+                source: CampaignError::NotClosed
+            }
+        );
+
+        let session_id = Uuid::new_v4();
+        let mut shuffled = logged(vec![
+            Event::SessionExcluded {
+                session_id,
+                excluded: true,
+            },
+            Event::SessionExcluded {
+                session_id,
+                excluded: false,
+            },
+        ]);
+        shuffled.reverse();
+        assert_eq!(
+            replay(&shuffled).unwrap_err(),
+            ReplayError::OutOfOrder { seq: 1 }
+        );
+    }
+
+    #[test]
+    fn events_round_trip_through_json() {
+        let event = Event::ComparisonRevised {
+            comparison_id: Uuid::new_v4(),
+            revision: ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([1, 0, 2])),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "comparison_revised");
+        assert_eq!(serde_json::from_value::<Event>(json).unwrap(), event);
+    }
+}
@@ -0,0 +1,459 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::attention::{results_for_fit, AttentionPolicy};
+use crate::bundle::Bundle;
+use crate::models::{ComparisonResult, Matchup, Session};
+use crate::provenance::DeviceClass;
+use crate::ranking::{standard_errors, Anchor};
+use crate::refit::fit_ratings;
+
+/// Fit iterations for [`result_rows`], as the rating worker uses.
+pub const RESULT_ITERATIONS: u32 = 50;
+
+/// Two-sided 95% normal quantile, for [`ResultRow::ci_low`] and [`ResultRow::ci_high`].
+const Z_95: f64 = 1.96;
+
+/// Rows of a spreadsheet export, as CSV or as a JSON array of objects.
+///
+/// Columns are the struct's fields in declaration order and keep their names
+/// in both formats. They are only ever appended to, so scripts keep working.
+pub trait Table: Serialize {
+    const COLUMNS: &'static [&'static str];
+
+    /// The row's cells in column order. Lists are joined with `;` and
+    /// missing values left empty.
+    fn cells(&self) -> Vec<String>;
+}
+
+/// One stored answer, superseded ones included.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComparisonRow {
+    pub comparison_id: Uuid,
+    pub session_id: Uuid,
+    /// Whether the owner left the session out of the ranking.
+    pub session_excluded: bool,
+    pub matchup_id: Uuid,
+    /// `regular`, `repeat` or `calibration`; only regular answers are ranked.
+    pub matchup_kind: &'static str,
+    /// Photo indices, best first.
+    pub ranked_photo_indices: Vec<u32>,
+    /// Filenames in the same order.
+    pub ranked_filenames: Vec<String>,
+    /// Photo indices left to right as displayed, if the client reported it.
+    pub displayed_order: Option<Vec<u32>>,
+    pub created_at: DateTime<Utc>,
+    /// When the answer was undone or revised.
+    pub superseded_at: Option<DateTime<Utc>>,
+    /// The revision that replaced the answer; empty if it was undone.
+    pub superseded_by: Option<Uuid>,
+    pub decision_ms: Option<u32>,
+    pub viewport_width: Option<u32>,
+    pub viewport_height: Option<u32>,
+    /// `phone`, `tablet` or `desktop`.
+    pub device_class: Option<&'static str>,
+    pub previews_loaded: Option<bool>,
+    pub zoomed: Option<bool>,
+    /// Time from the server serving the matchup to the answer.
+    pub latency_ms: Option<u32>,
+}
+
+impl Table for ComparisonRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "comparison_id",
+        "session_id",
+        "session_excluded",
+        "matchup_id",
+        "matchup_kind",
+        "ranked_photo_indices",
+        "ranked_filenames",
+        "displayed_order",
+        "created_at",
+        "superseded_at",
+        "superseded_by",
+        "decision_ms",
+        "viewport_width",
+        "viewport_height",
+        "device_class",
+        "previews_loaded",
+        "zoomed",
+        "latency_ms",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.comparison_id.to_string(),
+            self.session_id.to_string(),
+            self.session_excluded.to_string(),
+            self.matchup_id.to_string(),
+            self.matchup_kind.to_string(),
+            join(&self.ranked_photo_indices),
+            join(&self.ranked_filenames),
+            self.displayed_order
+                .as_deref()
+                .map(join)
+                .unwrap_or_default(),
+            timestamp(self.created_at),
+            self.superseded_at.map(timestamp).unwrap_or_default(),
+            optional(self.superseded_by),
+            optional(self.decision_ms),
+            optional(self.viewport_width),
+            optional(self.viewport_height),
+            optional(self.device_class),
+            optional(self.previews_loaded),
+            optional(self.zoomed),
+            optional(self.latency_ms),
+        ]
+    }
+}
+
+/// A photo's place in the campaign ranking.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResultRow {
+    /// 1 for the strongest photo; ties get consecutive ranks.
+    pub rank: usize,
+    pub photo_idx: u32,
+    pub filename: String,
+    /// Log-strength on the scale of `/api/ranking`.
+    pub strength: f64,
+    /// Standard error of `strength`; see [`standard_errors`]. `None` if the
+    /// answers say nothing about it, such as for a photo that always won.
+    pub std_error: Option<f64>,
+    /// Approximate 95% confidence interval, `strength` ± 1.96 × `std_error`.
+    pub ci_low: Option<f64>,
+    pub ci_high: Option<f64>,
+    /// Pairs won and lost: a first place in a triple is two wins.
+    pub wins: u32,
+    pub losses: u32,
+    /// Ranked answers the photo appeared in.
+    pub appearances: u32,
+}
+
+impl Table for ResultRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "rank",
+        "photo_idx",
+        "filename",
+        "strength",
+        "std_error",
+        "ci_low",
+        "ci_high",
+        "wins",
+        "losses",
+        "appearances",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.rank.to_string(),
+            self.photo_idx.to_string(),
+            self.filename.clone(),
+            self.strength.to_string(),
+            optional(self.std_error),
+            optional(self.ci_low),
+            optional(self.ci_high),
+            self.wins.to_string(),
+            self.losses.to_string(),
+            self.appearances.to_string(),
+        ]
+    }
+}
+
+/// Every comparison in the bundle, oldest first.
+#[must_use]
+pub fn comparison_rows(bundle: &Bundle) -> Vec<ComparisonRow> {
+    let matchups: HashMap<Uuid, &Matchup> = bundle.matchups.iter().map(|m| (m.id, m)).collect();
+    let sessions: HashMap<Uuid, &Session> = bundle.sessions.iter().map(|s| (s.id, s)).collect();
+    let filename = |idx: &u32| {
+        bundle
+            .photos
+            .get(*idx as usize)
+            .map(|p| p.filename.clone())
+            .unwrap_or_default()
+    };
+
+    bundle
+        .comparisons
+        .iter()
+        .map(|c| ComparisonRow {
+            comparison_id: c.id,
+            session_id: c.session_id,
+            session_excluded: sessions.get(&c.session_id).is_some_and(|s| s.excluded),
+            matchup_id: c.matchup_id,
+            matchup_kind: matchups
+                .get(&c.matchup_id)
+                .map_or("regular", |m| m.kind.as_str()),
+            ranked_photo_indices: c.ranked_photo_indices.indices().to_vec(),
+            ranked_filenames: c
+                .ranked_photo_indices
+                .indices()
+                .iter()
+                .map(filename)
+                .collect(),
+            displayed_order: c.displayed_order.map(|order| order.indices().to_vec()),
+            created_at: c.created_at,
+            superseded_at: c.superseded_at,
+            superseded_by: c.superseded_by,
+            decision_ms: c.provenance.decision_ms,
+            viewport_width: c.provenance.viewport.map(|v| v.width),
+            viewport_height: c.provenance.viewport.map(|v| v.height),
+            device_class: c.provenance.device_class.map(DeviceClass::as_str),
+            previews_loaded: c.provenance.previews_loaded,
+            zoomed: c.provenance.zoomed,
+            latency_ms: c.latency_ms,
+        })
+        .collect()
+}
+
+/// The campaign ranking, strongest first: one fit over the active answers of
+/// every session that isn't excluded, leaving quality checks out as the
+/// rating worker does. `None` if there are too many photos to fit.
+#[must_use]
+pub fn result_rows(
+    bundle: &Bundle,
+    anchors: Vec<Anchor>,
+    iterations: u32,
+) -> Option<Vec<ResultRow>> {
+    let excluded: HashSet<Uuid> = bundle
+        .sessions
+        .iter()
+        .filter(|s| s.excluded)
+        .map(|s| s.id)
+        .collect();
+    let active: Vec<ComparisonResult> = bundle
+        .comparisons
+        .iter()
+        .filter(|c| c.is_active() && !excluded.contains(&c.session_id))
+        .cloned()
+        .collect();
+    let num_photos = u32::try_from(bundle.photos.len()).ok()?;
+    let (ratings, _) = fit_ratings(num_photos, &bundle.matchups, &active, anchors, iterations)?;
+
+    let mut wins = vec![0; bundle.photos.len()];
+    let mut losses = vec![0; bundle.photos.len()];
+    let mut appearances = vec![0; bundle.photos.len()];
+    let mut pairs = Vec::new();
+    for comparison in results_for_fit(&bundle.matchups, &active, &AttentionPolicy::default()) {
+        for idx in comparison.ranked_photo_indices.indices() {
+            if let Some(count) = appearances.get_mut(idx as usize) {
+                *count += 1;
+            }
+        }
+        for (winner, loser) in comparison.to_pairwise() {
+            if let Some(count) = wins.get_mut(winner as usize) {
+                *count += 1;
+            }
+            if let Some(count) = losses.get_mut(loser as usize) {
+                *count += 1;
+            }
+            pairs.push((winner, loser));
+        }
+    }
+
+    let mut strengths = vec![0.0; bundle.photos.len()];
+    for rating in &ratings {
+        if let Some(strength) = strengths.get_mut(rating.photo_idx as usize) {
+            *strength = rating.strength;
+        }
+    }
+    let errors = standard_errors(&strengths, &pairs);
+
+    Some(
+        ratings
+            .iter()
+            .zip(1..)
+            .filter_map(|(rating, rank)| {
+                let i = rating.photo_idx as usize;
+                let std_error = *errors.get(i)?;
+                Some(ResultRow {
+                    rank,
+                    photo_idx: rating.photo_idx,
+                    filename: bundle.photos.get(i)?.filename.clone(),
+                    strength: rating.strength,
+                    std_error,
+                    ci_low: std_error.map(|se| Z_95.mul_add(-se, rating.strength)),
+                    ci_high: std_error.map(|se| Z_95.mul_add(se, rating.strength)),
+                    wins: wins[i],
+                    losses: losses[i],
+                    appearances: appearances[i],
+                })
+            })
+            .collect(),
+    )
+}
+
+/// `rows` as CSV with a header line, quoting cells as RFC 4180 requires.
+#[must_use]
+pub fn to_csv<T: Table>(rows: &[T]) -> String {
+    let mut csv = String::new();
+    let header = T::COLUMNS.iter().map(ToString::to_string).collect();
+    for record in std::iter::once(header).chain(rows.iter().map(Table::cells)) {
+        let line: Vec<String> = record.iter().map(|cell| quote(cell)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn join(items: &[impl ToString]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn optional(value: Option<impl ToString>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// As serde writes it, so both formats agree.
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Campaign, MatchupKind, Photo};
+    use crate::provenance::{Provenance, Viewport};
+    use crate::repository::synced_photos;
+    use crate::types::triple;
+
+    fn photos(filenames: &[&str]) -> Vec<Photo> {
+        synced_photos(
+            "c",
+            filenames.iter().map(|f| ((*f).to_string(), f.as_bytes())),
+        )
+    }
+
+    fn bundle(photos: Vec<Photo>) -> Bundle {
+        Bundle {
+            campaign: Campaign::new("c".to_string(), "C".to_string(), String::new()),
+            manifest: None,
+            exported_at: Utc::now(),
+            photos,
+            sessions: Vec::new(),
+            matchups: Vec::new(),
+            comparisons: Vec::new(),
+            ratings: Vec::new(),
+            images: std::collections::BTreeMap::new(),
+        }
+    }
+
+    /// Adds a session answering each triple with its first photo best.
+    fn answer(bundle: &mut Bundle, triples: &[[u32; 3]], excluded: bool) -> Session {
+        let session = Session {
+            excluded,
+            ..Session::new("c".to_string())
+        };
+        for &photos in triples {
+            let matchup = Matchup::new(&session, triple(photos), false);
+            bundle.comparisons.push(ComparisonResult::new(
+                matchup.id,
+                session.id,
+                triple(photos),
+            ));
+            bundle.matchups.push(matchup);
+        }
+        bundle.sessions.push(session.clone());
+        session
+    }
+
+    #[test]
+    fn comparison_rows_flatten_provenance_and_history() {
+        let mut bundle = bundle(photos(&["a.jpg", "b,c.jpg", "d.jpg"]));
+        let session = answer(&mut bundle, &[[1, 0, 2]], false);
+        bundle.comparisons[0].provenance = Provenance {
+            decision_ms: Some(2400),
+            viewport: Some(Viewport {
+                width: 390,
+                height: 844,
+            }),
+            device_class: Some(DeviceClass::Phone),
+            previews_loaded: Some(true),
+            zoomed: None,
+        };
+        bundle.comparisons[0].latency_ms = Some(3100);
+        let revision = bundle.comparisons[0].revision(triple([0, 1, 2]));
+        bundle.comparisons[0].supersede(revision.created_at, Some(revision.id));
+        bundle.comparisons.push(revision.clone());
+
+        let rows = comparison_rows(&bundle);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].session_id, session.id);
+        assert_eq!(rows[0].ranked_filenames, vec!["b,c.jpg", "a.jpg", "d.jpg"]);
+        assert_eq!(rows[0].superseded_by, Some(revision.id));
+        assert_eq!(rows[0].device_class, Some("phone"));
+        assert_eq!(rows[1].superseded_at, None);
+
+        let cells = rows[0].cells();
+        assert_eq!(cells.len(), ComparisonRow::COLUMNS.len());
+        assert_eq!(cells[5], "1;0;2");
+        assert_eq!(cells[12], "390");
+        assert_eq!(cells[16], "");
+        assert_eq!(cells[17], "3100");
+    }
+
+    #[test]
+    fn results_rank_active_answers_of_included_sessions() {
+        let mut bundle = bundle(photos(&["a.jpg", "b.jpg", "c.jpg", "d.jpg"]));
+        answer(&mut bundle, &[[0, 1, 2], [0, 2, 3], [1, 2, 3]], false);
+        // An excluded session and a check that disagree are both left out
+        answer(&mut bundle, &[[3, 2, 1], [3, 2, 0]], true);
+        let session = answer(&mut bundle, &[], false);
+        let check = Matchup::check(&session, triple([3, 2, 1]), MatchupKind::Calibration);
+        bundle.comparisons.push(ComparisonResult::new(
+            check.id,
+            session.id,
+            triple([3, 2, 1]),
+        ));
+        bundle.matchups.push(check);
+
+        let rows = result_rows(&bundle, Vec::new(), RESULT_ITERATIONS).unwrap();
+        let order: Vec<&str> = rows.iter().map(|r| r.filename.as_str()).collect();
+        assert_eq!(order, vec!["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+        assert_eq!(rows[0].rank, 1);
+        assert_eq!(
+            (rows[0].wins, rows[0].losses, rows[0].appearances),
+            (4, 0, 2)
+        );
+        assert_eq!(
+            (rows[3].wins, rows[3].losses, rows[3].appearances),
+            (0, 4, 2)
+        );
+        let (low, high) = (rows[1].ci_low.unwrap(), rows[1].ci_high.unwrap());
+        let half_width = Z_95 * rows[1].std_error.unwrap();
+        assert!((rows[1].strength - low - half_width).abs() < 1e-9);
+        assert!((high - rows[1].strength - half_width).abs() < 1e-9);
+    }
+
+    #[test]
+    fn csv_quotes_cells_that_need_it() {
+        let mut bundle = bundle(photos(&["plain.jpg", "say \"cheese\".jpg", "x.jpg"]));
+        answer(&mut bundle, &[[1, 0, 2]], false);
+
+        let csv = to_csv(&result_rows(&bundle, Vec::new(), RESULT_ITERATIONS).unwrap());
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), ResultRow::COLUMNS.join(","));
+        assert!(csv.contains("\"say \"\"cheese\"\".jpg\""));
+        assert_eq!(lines.count(), 3);
+
+        let json = serde_json::to_value(comparison_rows(&bundle)).unwrap();
+        let keys: Vec<&String> = json[0].as_object().unwrap().keys().collect();
+        assert_eq!(keys.len(), ComparisonRow::COLUMNS.len());
+        assert!(ComparisonRow::COLUMNS
+            .iter()
+            .all(|c| json[0].get(c).is_some()));
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::{ComparisonResult, Matchup};
+
+/// Default cap on how much more often the most-shown photo may appear than the least-shown one.
+pub const DEFAULT_MAX_EXPOSURE_RATIO: f64 = 2.0;
+
+/// How often a single photo has been put in front of participants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhotoExposure {
+    /// Matchups containing this photo that were served.
+    pub shown: u32,
+    /// Served matchups containing this photo that received a ranking.
+    pub answered: u32,
+}
+
+/// Limits on exposure imbalance between photos.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExposurePolicy {
+    /// Maximum allowed `max(shown) / max(min(shown), 1)`.
+    pub max_ratio: f64,
+}
+
+impl Default for ExposurePolicy {
+    fn default() -> Self {
+        Self {
+            max_ratio: DEFAULT_MAX_EXPOSURE_RATIO,
+        }
+    }
+}
+
+/// Per-photo exposure counts, indexed by photo position.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExposureTracker {
+    counts: Vec<PhotoExposure>,
+}
+
+impl ExposureTracker {
+    #[must_use]
+    pub fn new(num_photos: u32) -> Self {
+        Self {
+            counts: vec![PhotoExposure::default(); num_photos as usize],
+        }
+    }
+
+    /// Rebuilds counts from stored matchups and the results recorded against them.
+    ///
+    /// Seed matchups are stored in bulk ahead of time, so a seed counts as shown
+    /// only once it has been served or answered; every other matchup is stored as
+    /// it is served.
+    #[must_use]
+    pub fn from_history(
+        num_photos: u32,
+        matchups: &[Matchup],
+        results: &[ComparisonResult],
+    ) -> Self {
+        let answered: HashSet<Uuid> = results.iter().map(|r| r.matchup_id).collect();
+        let mut tracker = Self::new(num_photos);
+        for matchup in matchups {
+            if !matchup.is_seed || matchup.served_at.is_some() || answered.contains(&matchup.id) {
+                tracker.record_shown(&matchup.photo_indices.indices());
+            }
+        }
+        for result in results {
+            tracker.record_answered(&result.ranked_photo_indices.indices());
+        }
+        tracker
+    }
+
+    /// Counts one served matchup. Out-of-range indices are ignored.
+    pub fn record_shown(&mut self, photo_indices: &[u32]) {
+        for &idx in photo_indices {
+            if let Some(count) = self.counts.get_mut(idx as usize) {
+                count.shown += 1;
+            }
+        }
+    }
+
+    /// Counts one answered matchup. Out-of-range indices are ignored.
+    pub fn record_answered(&mut self, photo_indices: &[u32]) {
+        for &idx in photo_indices {
+            if let Some(count) = self.counts.get_mut(idx as usize) {
+                count.answered += 1;
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, photo_idx: u32) -> Option<PhotoExposure> {
+        self.counts.get(photo_idx as usize).copied()
+    }
+
+    #[must_use]
+    pub fn counts(&self) -> &[PhotoExposure] {
+        &self.counts
+    }
+
+    #[must_use]
+    pub fn min_shown(&self) -> u32 {
+        self.counts.iter().map(|c| c.shown).min().unwrap_or(0)
+    }
+
+    /// Whether showing `photo_idx` once more keeps the shown ratio within `policy`.
+    ///
+    /// The least-shown count is floored at 1 so that a campaign with unseen
+    /// photos still allows a small head start instead of freezing everything.
+    #[must_use]
+    pub fn allows(&self, photo_idx: u32, policy: &ExposurePolicy) -> bool {
+        self.allows_above(photo_idx, self.min_shown(), *policy)
+    }
+
+    /// Like [`allows`](Self::allows), but against the least-shown of `among`
+    /// rather than of every photo, for matchups that can only ever draw on some.
+    #[must_use]
+    pub fn allows_among(&self, photo_idx: u32, among: &[u32], policy: &ExposurePolicy) -> bool {
+        let least = among
+            .iter()
+            .filter_map(|&idx| self.get(idx))
+            .map(|c| c.shown)
+            .min()
+            .unwrap_or(0);
+        self.allows_above(photo_idx, least, *policy)
+    }
+
+    fn allows_above(&self, photo_idx: u32, least_shown: u32, policy: ExposurePolicy) -> bool {
+        let Some(count) = self.get(photo_idx) else {
+            return false;
+        };
+        let floor = f64::from(least_shown.max(1));
+        f64::from(count.shown + 1) <= floor * policy.max_ratio
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> ExposureStats {
+        let shown = self.counts.iter().map(|c| c.shown);
+        let answered = self.counts.iter().map(|c| c.answered);
+        let min_shown = shown.clone().min().unwrap_or(0);
+        let max_shown = shown.clone().max().unwrap_or(0);
+        let total_shown: u64 = shown.map(u64::from).sum();
+
+        #[allow(clippy::cast_precision_loss)]
+        let mean_shown = if self.counts.is_empty() {
+            0.0
+        } else {
+            total_shown as f64 / self.counts.len() as f64
+        };
+
+        ExposureStats {
+            min_shown,
+            max_shown,
+            mean_shown,
+            min_answered: answered.clone().min().unwrap_or(0),
+            max_answered: answered.max().unwrap_or(0),
+            unseen: self.counts.iter().filter(|c| c.shown == 0).count(),
+            ratio: f64::from(max_shown) / f64::from(min_shown.max(1)),
+        }
+    }
+}
+
+/// Exposure summary for the owner dashboard.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExposureStats {
+    pub min_shown: u32,
+    pub max_shown: u32,
+    pub mean_shown: f64,
+    pub min_answered: u32,
+    pub max_answered: u32,
+    /// Photos that have never been served.
+    pub unseen: usize,
+    /// `max_shown / max(min_shown, 1)`, comparable to [`ExposurePolicy::max_ratio`].
+    pub ratio: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Session;
+    use crate::types::triple;
+    use chrono::Utc;
+
+    #[test]
+    fn history_counts_shown_and_answered() {
+        let session = Session::default();
+        let first = Matchup::new(&session, triple([0, 1, 2]), true);
+        let second = Matchup::new(&session, triple([2, 3, 4]), false);
+        let answered = ComparisonResult::new(first.id, session.id, triple([1, 0, 2]));
+
+        let tracker = ExposureTracker::from_history(5, &[first, second], &[answered]);
+
+        assert_eq!(
+            tracker.get(2),
+            Some(PhotoExposure {
+                shown: 2,
+                answered: 1
+            })
+        );
+        assert_eq!(
+            tracker.get(4),
+            Some(PhotoExposure {
+                shown: 1,
+                answered: 0
+            })
+        );
+        assert_eq!(tracker.get(5), None);
+    }
+
+    #[test]
+    fn history_skips_seeds_not_yet_served() {
+        let session = Session::default();
+        let answered = Matchup::new(&session, triple([0, 1, 2]), true);
+        let queued = Matchup::new(&session, triple([2, 3, 4]), true);
+        let on_screen = Matchup {
+            served_at: Some(Utc::now()),
+            ..Matchup::new(&session, triple([4, 5, 6]), true)
+        };
+        let result = ComparisonResult::new(answered.id, session.id, triple([0, 1, 2]));
+
+        let tracker = ExposureTracker::from_history(7, &[answered, queued, on_screen], &[result]);
+
+        assert_eq!(tracker.get(2).map(|c| c.shown), Some(1));
+        assert_eq!(tracker.get(3).map(|c| c.shown), Some(0));
+        assert_eq!(tracker.get(5).map(|c| c.shown), Some(1));
+    }
+
+    #[test]
+    fn allows_respects_ratio_against_least_shown() {
+        let mut tracker = ExposureTracker::new(3);
+        tracker.record_shown(&[0, 1]);
+        tracker.record_shown(&[0, 1]);
+        let policy = ExposurePolicy { max_ratio: 2.0 };
+
+        // Photo 2 is unseen, so the floor is 1 and the cap is 2 showings.
+        assert!(!tracker.allows(0, &policy));
+        assert!(tracker.allows(2, &policy));
+
+        tracker.record_shown(&[2]);
+        tracker.record_shown(&[2]);
+        assert!(tracker.allows(0, &policy));
+    }
+
+    #[test]
+    fn allows_among_ignores_photos_outside_the_set() {
+        let mut tracker = ExposureTracker::new(3);
+        tracker.record_shown(&[0, 1]);
+        tracker.record_shown(&[0, 1]);
+        for _ in 0..3 {
+            tracker.record_shown(&[1]);
+        }
+        let policy = ExposurePolicy { max_ratio: 2.0 };
+
+        // Photo 2 is never drawn on, so it doesn't hold the others back.
+        assert!(!tracker.allows(0, &policy));
+        assert!(tracker.allows_among(0, &[0, 1], &policy));
+        assert!(!tracker.allows_among(1, &[0, 1], &policy));
+    }
+
+    #[test]
+    fn stats_summarize_imbalance() {
+        let mut tracker = ExposureTracker::new(4);
+        tracker.record_shown(&[0, 1, 2]);
+        tracker.record_shown(&[0, 1, 2]);
+        tracker.record_answered(&[0, 1, 2]);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.min_shown, 0);
+        assert_eq!(stats.max_shown, 2);
+        assert_eq!(stats.unseen, 1);
+        assert_eq!(stats.max_answered, 1);
+        assert!((stats.mean_shown - 1.5).abs() < f64::EPSILON);
+        assert!((stats.ratio - 2.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::models::PhotoRating;
+
+/// How matchups treat photo groups (rolls, series).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupMode {
+    /// Every matchup draws from a single group, for "best of each roll".
+    Within,
+    /// Matchups draw from different groups where possible, tying groups together.
+    Across,
+    /// Both kinds of matchups, so per-group and overall rankings firm up together.
+    Mixed,
+}
+
+impl GroupMode {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Within => "within",
+            Self::Across => "across",
+            Self::Mixed => "mixed",
+        }
+    }
+}
+
+impl FromStr for GroupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "within" => Ok(Self::Within),
+            "across" => Ok(Self::Across),
+            "mixed" => Ok(Self::Mixed),
+            other => Err(format!("unknown group mode: {other}")),
+        }
+    }
+}
+
+/// Group label for a photo stored under a directory, e.g. `roll-03/12.jpg` → `roll-03`.
+#[must_use]
+pub fn group_label_from_filename(filename: &str) -> Option<&str> {
+    filename
+        .rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .filter(|dir| !dir.is_empty())
+}
+
+/// Photo group membership, indexed by photo position.
+///
+/// Photos without a label form one ungrouped group of their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhotoGroups {
+    labels: Vec<Option<String>>,
+    members: Vec<Vec<u32>>,
+}
+
+impl PhotoGroups {
+    /// Builds groups from one optional label per photo, in photo index order.
+    #[must_use]
+    pub fn from_labels<S: AsRef<str>>(photo_labels: &[Option<S>]) -> Self {
+        let mut labels: Vec<Option<String>> = Vec::new();
+        let mut members: Vec<Vec<u32>> = Vec::new();
+
+        for (photo_idx, label) in (0..).zip(photo_labels) {
+            let label = label.as_ref().map(AsRef::as_ref);
+            let group = if let Some(pos) = labels.iter().position(|l| l.as_deref() == label) {
+                pos
+            } else {
+                labels.push(label.map(str::to_string));
+                members.push(Vec::new());
+                labels.len() - 1
+            };
+            members[group].push(photo_idx);
+        }
+
+        Self { labels, members }
+    }
+
+    /// Number of groups.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    #[must_use]
+    pub fn label(&self, group: usize) -> Option<&str> {
+        self.labels.get(group)?.as_deref()
+    }
+
+    #[must_use]
+    pub fn members(&self, group: usize) -> &[u32] {
+        self.members.get(group).map_or(&[], Vec::as_slice)
+    }
+
+    #[must_use]
+    pub fn group_of(&self, photo_idx: u32) -> Option<usize> {
+        self.members.iter().position(|m| m.contains(&photo_idx))
+    }
+}
+
+/// One group's photos, strongest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupRanking {
+    pub group: Option<String>,
+    pub ratings: Vec<PhotoRating>,
+}
+
+/// Splits globally fitted ratings into per-group rankings.
+///
+/// Strengths stay on the global scale, so they can be compared across groups.
+#[must_use]
+pub fn group_rankings(ratings: &[PhotoRating], groups: &PhotoGroups) -> Vec<GroupRanking> {
+    (0..groups.len())
+        .map(|group| {
+            let members = groups.members(group);
+            let mut ratings: Vec<PhotoRating> = ratings
+                .iter()
+                .filter(|r| members.contains(&r.photo_idx))
+                .copied()
+                .collect();
+            ratings.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+            GroupRanking {
+                group: groups.label(group).map(str::to_string),
+                ratings,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_from_directories() {
+        assert_eq!(group_label_from_filename("roll-03/12.jpg"), Some("roll-03"));
+        assert_eq!(group_label_from_filename("a/b/c.jpg"), Some("a/b"));
+        assert_eq!(group_label_from_filename("12.jpg"), None);
+    }
+
+    #[test]
+    fn groups_collect_members_in_order() {
+        let groups = PhotoGroups::from_labels(&[Some("a"), None, Some("b"), Some("a")]);
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups.label(0), Some("a"));
+        assert_eq!(groups.members(0), &[0, 3]);
+        assert_eq!(groups.label(1), None);
+        assert_eq!(groups.group_of(2), Some(2));
+        assert_eq!("across".parse::<GroupMode>(), Ok(GroupMode::Across));
+    }
+
+    #[test]
+    fn rankings_split_by_group() {
+        let groups = PhotoGroups::from_labels(&[Some("a"), Some("b"), Some("a")]);
+        let ratings: Vec<PhotoRating> = (0..3)
+            .map(|photo_idx| PhotoRating {
+                photo_idx,
+                strength: f64::from(photo_idx),
+                uncertainty: 0.5,
+            })
+            .collect();
+
+        let rankings = group_rankings(&ratings, &groups);
+        let order: Vec<u32> = rankings[0].ratings.iter().map(|r| r.photo_idx).collect();
+        assert_eq!(order, vec![2, 0]);
+        assert_eq!(rankings[1].group.as_deref(), Some("b"));
+    }
+}
@@ -0,0 +1,234 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::groups::group_label_from_filename;
+use crate::models::Photo;
+
+/// Hex-encoded SHA-256 of a photo's bytes: its identity within a campaign.
+#[must_use]
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Whether `hash` looks like a [`content_hash`]. Photos synced before content
+/// hashing stored their filename instead.
+#[must_use]
+pub fn is_content_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// A photo found in storage, with the hash of its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedPhoto {
+    pub filename: String,
+    pub file_hash: String,
+}
+
+/// The campaign's photos after a sync, and what changed.
+#[derive(Debug, Clone)]
+pub struct PhotoSync {
+    /// Every photo, in index order. Existing photos keep their index.
+    pub photos: Vec<Photo>,
+    /// Indices of photos seen for the first time, appended after the existing ones.
+    pub added: Vec<u32>,
+    /// Indices of photos whose content moved to another filename.
+    pub renamed: Vec<u32>,
+    /// Indices of photos synced before content hashing that now have a real hash.
+    pub rehashed: Vec<u32>,
+}
+
+impl PhotoSync {
+    #[must_use]
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty() && self.renamed.is_empty() && self.rehashed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SyncError {
+    #[error("photo {photo_idx} ({filename}) is no longer in storage")]
+    Missing { photo_idx: u32, filename: String },
+    #[error("{first} and {second} have identical content")]
+    DuplicateContent { first: String, second: String },
+}
+
+/// Matches storage against a campaign's existing photos by content hash.
+///
+/// Comparisons refer to photos by index, so indices never change: renamed files
+/// keep theirs and new files are appended. Photos stored before content hashing
+/// are matched by filename once, adopting the listed hash.
+///
+/// # Errors
+///
+/// Refuses rather than remap when an existing photo is gone from storage, since
+/// its comparisons would point at nothing, or when two files share content.
+pub fn sync_photos(
+    campaign_id: &str,
+    existing: &[Photo],
+    listed: &[ListedPhoto],
+) -> Result<PhotoSync, SyncError> {
+    let mut by_hash: HashMap<&str, &ListedPhoto> = HashMap::new();
+    for photo in listed {
+        if let Some(first) = by_hash.insert(&photo.file_hash, photo) {
+            return Err(SyncError::DuplicateContent {
+                first: first.filename.clone(),
+                second: photo.filename.clone(),
+            });
+        }
+    }
+    let by_filename: HashMap<&str, &ListedPhoto> =
+        listed.iter().map(|p| (p.filename.as_str(), p)).collect();
+
+    let mut photos = Vec::with_capacity(listed.len().max(existing.len()));
+    let mut matched: Vec<&str> = Vec::with_capacity(existing.len());
+    let (mut renamed, mut rehashed) = (Vec::new(), Vec::new());
+
+    for photo in existing {
+        let found = by_hash.get(photo.file_hash.as_str()).or_else(|| {
+            (!is_content_hash(&photo.file_hash))
+                .then(|| by_filename.get(photo.filename.as_str()))
+                .flatten()
+        });
+        let Some(found) = found else {
+            return Err(SyncError::Missing {
+                photo_idx: photo.position,
+                filename: photo.filename.clone(),
+            });
+        };
+
+        if found.file_hash != photo.file_hash {
+            rehashed.push(photo.position);
+        } else if found.filename != photo.filename {
+            renamed.push(photo.position);
+        }
+        matched.push(&found.file_hash);
+        photos.push(Photo {
+            filename: found.filename.clone(),
+            file_hash: found.file_hash.clone(),
+            group: group_label_from_filename(&found.filename).map(str::to_string),
+            ..photo.clone()
+        });
+    }
+
+    let mut next = existing
+        .iter()
+        .map(|p| p.position.saturating_add(1))
+        .max()
+        .unwrap_or(0);
+    let mut added = Vec::new();
+    for photo in listed {
+        if matched.contains(&photo.file_hash.as_str()) {
+            continue;
+        }
+        added.push(next);
+        photos.push(Photo {
+            id: Uuid::new_v4(),
+            campaign_id: campaign_id.to_string(),
+            filename: photo.filename.clone(),
+            file_hash: photo.file_hash.clone(),
+            position: next,
+            group: group_label_from_filename(&photo.filename).map(str::to_string),
+        });
+        next = next.saturating_add(1);
+    }
+
+    Ok(PhotoSync {
+        photos,
+        added,
+        renamed,
+        rehashed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DEFAULT_CAMPAIGN_ID;
+
+    fn listed(filename: &str, content: &str) -> ListedPhoto {
+        ListedPhoto {
+            filename: filename.to_string(),
+            file_hash: content_hash(content.as_bytes()),
+        }
+    }
+
+    fn existing(listed: &[ListedPhoto]) -> Vec<Photo> {
+        sync_photos(DEFAULT_CAMPAIGN_ID, &[], listed)
+            .unwrap()
+            .photos
+    }
+
+    #[test]
+    fn content_hash_is_hex_sha256() {
+        let hash = content_hash(b"abc");
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(is_content_hash(&hash));
+        assert!(!is_content_hash("roll-1/01.jpg"));
+    }
+
+    #[test]
+    fn new_and_renamed_files_keep_existing_indices() {
+        let photos = existing(&[listed("b.jpg", "B"), listed("c.jpg", "C")]);
+
+        // "a.jpg" sorts first and "c.jpg" was renamed; neither may shift indices
+        let sync = sync_photos(
+            DEFAULT_CAMPAIGN_ID,
+            &photos,
+            &[
+                listed("a.jpg", "A"),
+                listed("b.jpg", "B"),
+                listed("roll/c.jpg", "C"),
+            ],
+        )
+        .unwrap();
+
+        let names: Vec<&str> = sync.photos.iter().map(|p| p.filename.as_str()).collect();
+        assert_eq!(names, vec!["b.jpg", "roll/c.jpg", "a.jpg"]);
+        assert_eq!(sync.added, vec![2]);
+        assert_eq!(sync.renamed, vec![1]);
+        assert_eq!(sync.photos[1].id, photos[1].id);
+        assert_eq!(sync.photos[1].group.as_deref(), Some("roll"));
+    }
+
+    #[test]
+    fn refuses_missing_or_duplicate_content() {
+        let photos = existing(&[listed("a.jpg", "A"), listed("b.jpg", "B")]);
+
+        assert_eq!(
+            sync_photos(DEFAULT_CAMPAIGN_ID, &photos, &[listed("a.jpg", "A")]).unwrap_err(),
+            SyncError::Missing {
+                photo_idx: 1,
+                filename: "b.jpg".into()
+            }
+        );
+        assert!(matches!(
+            sync_photos(
+                DEFAULT_CAMPAIGN_ID,
+                &[],
+                &[listed("a.jpg", "A"), listed("copy.jpg", "A")]
+            ),
+            Err(SyncError::DuplicateContent { .. })
+        ));
+    }
+
+    #[test]
+    fn legacy_photos_adopt_content_hash_by_filename() {
+        let mut photos = existing(&[listed("a.jpg", "A")]);
+        photos[0].file_hash = "a.jpg".into();
+
+        let sync = sync_photos(DEFAULT_CAMPAIGN_ID, &photos, &[listed("a.jpg", "A")]).unwrap();
+        assert_eq!(sync.rehashed, vec![0]);
+        assert_eq!(sync.photos[0].file_hash, content_hash(b"A"));
+        assert!(!sync.is_unchanged());
+    }
+}
@@ -1,3 +1,24 @@
+pub mod attention;
+pub mod bundle;
+#[cfg(any(test, feature = "contract"))]
+pub mod contract;
+pub mod events;
+pub mod export;
+pub mod exposure;
+pub mod groups;
+pub mod identity;
+pub mod manifest;
 pub mod matchup;
 pub mod models;
+pub mod planner;
+pub mod pool;
+pub mod progress;
+pub mod provenance;
 pub mod ranking;
+pub mod refit;
+pub mod repository;
+pub mod scheduler;
+pub mod simulation;
+pub mod submission;
+pub mod types;
+pub mod undo;
@@ -0,0 +1,396 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::identity::{content_hash, is_content_hash};
+use crate::pool::{MatchupPool, PoolAlgorithm, PoolError};
+
+/// Schema version written by this build. Older versions are migrated on load.
+///
+/// - 1: `matchup_pool` is a bare list of matchups, and there is no digest.
+/// - 2: `matchup_pool` is a [`MatchupPool`] with generation metadata, and
+///   `digest` covers every other field.
+pub const MANIFEST_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestError {
+    #[error("invalid manifest JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("manifest schema_version must be a whole number")]
+    InvalidVersion,
+    #[error("manifest schema version {found} is newer than supported version {supported}")]
+    UnsupportedVersion { found: u64, supported: u32 },
+    #[error("manifest digest does not match its content")]
+    DigestMismatch,
+    #[error("manifest {0} is empty")]
+    Empty(&'static str),
+    #[error("photo {0} appears more than once")]
+    DuplicateFilename(String),
+    #[error("photos {first} and {second} have the same content hash")]
+    DuplicateHash { first: String, second: String },
+    #[error("photo {0} has no valid content hash")]
+    InvalidHash(String),
+    #[error("photo {0} has zero width or height")]
+    InvalidDimensions(String),
+    #[error("matchup pool is for {pool} photos, manifest has {photos}")]
+    PhotoCountMismatch { pool: u32, photos: usize },
+    #[error("matchup pool uses matchups of {pool}, manifest says {manifest}")]
+    MatchupSizeMismatch { pool: usize, manifest: usize },
+    #[error("completion target must be at least 1")]
+    InvalidCompletionTarget,
+    #[error("invalid matchup pool: {0}")]
+    Pool(#[from] PoolError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestPhoto {
+    pub filename: String,
+    /// [`content_hash`] of the original.
+    pub file_hash: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The immutable description of a campaign stored at `campaigns/{id}/manifest.json`:
+/// its photos, in index order, and its matchup pool.
+///
+/// Status is not part of it; that lives in the database. A manifest can only be
+/// built or loaded through validation, and serializes with a digest of its
+/// content that is checked on load.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Value", into = "Value")]
+pub struct Manifest {
+    content: Content,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Content {
+    schema_version: u32,
+    id: String,
+    name: String,
+    created_at: DateTime<Utc>,
+    photos: Vec<ManifestPhoto>,
+    matchup_pool: MatchupPool,
+    matchup_size: usize,
+    completion_target: u32,
+}
+
+impl Manifest {
+    /// # Errors
+    ///
+    /// Returns the first [`ManifestError`] found by validation.
+    pub fn new(
+        id: String,
+        name: String,
+        photos: Vec<ManifestPhoto>,
+        matchup_pool: MatchupPool,
+        completion_target: u32,
+    ) -> Result<Self, ManifestError> {
+        let content = Content {
+            schema_version: MANIFEST_SCHEMA_VERSION,
+            id,
+            name,
+            created_at: Utc::now(),
+            photos,
+            matchup_size: matchup_pool.matchup_size(),
+            matchup_pool,
+            completion_target,
+        };
+        validate(&content)?;
+        Ok(Self { content })
+    }
+
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.content.id
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.content.name
+    }
+
+    #[must_use]
+    pub const fn created_at(&self) -> DateTime<Utc> {
+        self.content.created_at
+    }
+
+    /// Photos in index order: a photo's position is the index comparisons use.
+    #[must_use]
+    pub fn photos(&self) -> &[ManifestPhoto] {
+        &self.content.photos
+    }
+
+    #[must_use]
+    pub const fn matchup_pool(&self) -> &MatchupPool {
+        &self.content.matchup_pool
+    }
+
+    #[must_use]
+    pub const fn matchup_size(&self) -> usize {
+        self.content.matchup_size
+    }
+
+    #[must_use]
+    pub const fn completion_target(&self) -> u32 {
+        self.content.completion_target
+    }
+
+    /// Hex SHA-256 of the manifest's content, excluding the digest itself.
+    #[must_use]
+    pub fn digest(&self) -> String {
+        // Struct fields serialize in declaration order, so this is deterministic
+        let bytes = serde_json::to_vec(&self.content).unwrap_or_default();
+        content_hash(&bytes)
+    }
+}
+
+impl From<Manifest> for Value {
+    fn from(manifest: Manifest) -> Self {
+        let digest = manifest.digest();
+        let mut value = serde_json::to_value(manifest.content).unwrap_or(Self::Null);
+        if let Self::Object(fields) = &mut value {
+            fields.insert("digest".to_string(), Self::String(digest));
+        }
+        value
+    }
+}
+
+impl TryFrom<Value> for Manifest {
+    type Error = ManifestError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let (mut value, verify_digest) = migrate(value)?;
+        let digest = match &mut value {
+            Value::Object(fields) => fields.remove("digest"),
+            _ => None,
+        };
+
+        let content: Content = serde_json::from_value(value)?;
+        validate(&content)?;
+        let manifest = Self { content };
+
+        if verify_digest && digest.as_ref().and_then(Value::as_str) != Some(&manifest.digest()) {
+            return Err(ManifestError::DigestMismatch);
+        }
+        Ok(manifest)
+    }
+}
+
+/// Brings a stored manifest up to [`MANIFEST_SCHEMA_VERSION`], one version at a
+/// time. Returns whether the digest must be checked: versions that had none
+/// get a fresh one instead.
+fn migrate(mut value: Value) -> Result<(Value, bool), ManifestError> {
+    let version = value
+        .get("schema_version")
+        .map_or(Some(1), Value::as_u64)
+        .ok_or(ManifestError::InvalidVersion)?;
+    if version > u64::from(MANIFEST_SCHEMA_VERSION) {
+        return Err(ManifestError::UnsupportedVersion {
+            found: version,
+            supported: MANIFEST_SCHEMA_VERSION,
+        });
+    }
+
+    let verify_digest = version >= 2;
+    if version < 2 {
+        value = migrate_v1(value)?;
+    }
+    Ok((value, verify_digest))
+}
+
+/// Version 1 kept the pool as bare matchups; wrap them as a custom pool dated
+/// with the manifest.
+fn migrate_v1(mut value: Value) -> Result<Value, ManifestError> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct V1 {
+        id: String,
+        name: String,
+        created_at: DateTime<Utc>,
+        photos: Vec<ManifestPhoto>,
+        matchup_pool: Vec<Vec<u32>>,
+        matchup_size: usize,
+        completion_target: u32,
+    }
+
+    if let Value::Object(fields) = &mut value {
+        fields.remove("schema_version");
+    }
+    let v1: V1 = serde_json::from_value(value)?;
+    let num_photos = u32::try_from(v1.photos.len()).unwrap_or(u32::MAX);
+    let pool = MatchupPool::new(
+        num_photos,
+        v1.matchup_size,
+        v1.matchup_pool,
+        PoolAlgorithm::Custom,
+        None,
+    )?
+    .with_generated_at(v1.created_at);
+
+    Ok(serde_json::to_value(Content {
+        schema_version: 2,
+        id: v1.id,
+        name: v1.name,
+        created_at: v1.created_at,
+        photos: v1.photos,
+        matchup_pool: pool,
+        matchup_size: v1.matchup_size,
+        completion_target: v1.completion_target,
+    })?)
+}
+
+fn validate(content: &Content) -> Result<(), ManifestError> {
+    if content.id.trim().is_empty() {
+        return Err(ManifestError::Empty("id"));
+    }
+    if content.name.trim().is_empty() {
+        return Err(ManifestError::Empty("name"));
+    }
+    if content.photos.is_empty() {
+        return Err(ManifestError::Empty("photo list"));
+    }
+
+    let mut filenames = HashSet::with_capacity(content.photos.len());
+    for photo in &content.photos {
+        if !filenames.insert(photo.filename.as_str()) {
+            return Err(ManifestError::DuplicateFilename(photo.filename.clone()));
+        }
+        if !is_content_hash(&photo.file_hash) {
+            return Err(ManifestError::InvalidHash(photo.filename.clone()));
+        }
+        if photo.width == 0 || photo.height == 0 {
+            return Err(ManifestError::InvalidDimensions(photo.filename.clone()));
+        }
+    }
+    for (i, photo) in content.photos.iter().enumerate() {
+        if let Some(other) = content.photos[i + 1..]
+            .iter()
+            .find(|p| p.file_hash == photo.file_hash)
+        {
+            return Err(ManifestError::DuplicateHash {
+                first: photo.filename.clone(),
+                second: other.filename.clone(),
+            });
+        }
+    }
+
+    let pool = &content.matchup_pool;
+    if pool.num_photos() as usize != content.photos.len() {
+        return Err(ManifestError::PhotoCountMismatch {
+            pool: pool.num_photos(),
+            photos: content.photos.len(),
+        });
+    }
+    if pool.matchup_size() != content.matchup_size {
+        return Err(ManifestError::MatchupSizeMismatch {
+            pool: pool.matchup_size(),
+            manifest: content.matchup_size,
+        });
+    }
+    if content.completion_target == 0 {
+        return Err(ManifestError::InvalidCompletionTarget);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photos(n: u32) -> Vec<ManifestPhoto> {
+        (0..n)
+            .map(|i| ManifestPhoto {
+                filename: format!("{i:03}.jpg"),
+                file_hash: content_hash(&i.to_le_bytes()),
+                width: 3000,
+                height: 2000,
+            })
+            .collect()
+    }
+
+    fn manifest() -> Manifest {
+        let pool = MatchupPool::generate_seed(6, 3, 1).unwrap();
+        Manifest::new("c1".into(), "Roll 3".into(), photos(6), pool, 3).unwrap()
+    }
+
+    #[test]
+    fn round_trips_with_digest() {
+        let manifest = manifest();
+        let json = serde_json::to_value(&manifest).unwrap();
+
+        assert_eq!(json["schema_version"], MANIFEST_SCHEMA_VERSION);
+        assert_eq!(json["digest"], manifest.digest());
+        assert_eq!(serde_json::from_value::<Manifest>(json).unwrap(), manifest);
+    }
+
+    #[test]
+    fn detects_tampering_and_unknown_versions() {
+        let mut json = serde_json::to_value(manifest()).unwrap();
+        json["name"] = "Roll 4".into();
+        let err = Manifest::try_from(json.clone()).unwrap_err();
+        assert!(matches!(err, ManifestError::DigestMismatch));
+
+        json["schema_version"] = 99.into();
+        let err = Manifest::try_from(json).unwrap_err();
+        assert!(matches!(
+            err,
+            ManifestError::UnsupportedVersion { found: 99, .. }
+        ));
+    }
+
+    #[test]
+    fn validation_is_strict() {
+        let pool = MatchupPool::generate_seed(6, 3, 1).unwrap();
+        let new = |photos| Manifest::new("c1".into(), "Roll".into(), photos, pool.clone(), 3);
+
+        assert!(matches!(
+            new(photos(5)),
+            Err(ManifestError::PhotoCountMismatch { pool: 6, photos: 5 })
+        ));
+        let mut duplicated = photos(6);
+        duplicated[5].file_hash = duplicated[0].file_hash.clone();
+        assert!(matches!(
+            new(duplicated),
+            Err(ManifestError::DuplicateHash { .. })
+        ));
+
+        let mut json = serde_json::to_value(manifest()).unwrap();
+        json["owner_secret"] = "leaked".into();
+        assert!(matches!(
+            Manifest::try_from(json),
+            Err(ManifestError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let v1 = serde_json::json!({
+            "id": "c1",
+            "name": "Roll 3",
+            "created_at": "2025-01-01T00:00:00Z",
+            "photos": photos(3),
+            "matchup_pool": [[0, 1, 2], [2, 0, 1]],
+            "matchup_size": 3,
+            "completion_target": 3
+        });
+
+        let manifest = Manifest::try_from(v1).unwrap();
+        assert_eq!(manifest.matchup_pool().len(), 2);
+        assert_eq!(
+            manifest.matchup_pool().generation().algorithm,
+            PoolAlgorithm::Custom
+        );
+        assert_eq!(
+            manifest.matchup_pool().generation().generated_at,
+            manifest.created_at()
+        );
+
+        let reloaded: Manifest =
+            serde_json::from_value(serde_json::to_value(&manifest).unwrap()).unwrap();
+        assert_eq!(reloaded, manifest);
+    }
+}
@@ -1,11 +1,26 @@
 use rand::seq::SliceRandom;
-use std::collections::HashSet;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
 use std::hash::BuildHasher;
 
+use crate::exposure::{ExposurePolicy, ExposureTracker};
+use crate::groups::{GroupMode, PhotoGroups};
 use crate::models::PhotoRating;
+use crate::types::Triple;
 
 #[must_use]
 pub fn generate_seed_matchups(num_photos: u32, matchup_size: usize) -> Vec<Vec<u32>> {
+    generate_seed_matchups_with_rng(num_photos, matchup_size, &mut rand::rng())
+}
+
+/// [`generate_seed_matchups`] with a caller-supplied RNG, for reproducible pools.
+#[must_use]
+pub fn generate_seed_matchups_with_rng<R: Rng + ?Sized>(
+    num_photos: u32,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
     let Some(size_u32) = u32::try_from(matchup_size).ok() else {
         return vec![];
     };
@@ -13,7 +28,6 @@ pub fn generate_seed_matchups(num_photos: u32, matchup_size: usize) -> Vec<Vec<u
         return vec![];
     }
 
-    let mut rng = rand::rng();
     let mut indices: Vec<u32> = (0..num_photos).collect();
     let mut matchups = Vec::new();
 
@@ -21,7 +35,7 @@ pub fn generate_seed_matchups(num_photos: u32, matchup_size: usize) -> Vec<Vec<u
     let rounds = (u32::BITS - (num_photos - 1).leading_zeros()) as usize + 1;
 
     for _ in 0..rounds {
-        indices.shuffle(&mut rng);
+        indices.shuffle(rng);
 
         for chunk in indices.chunks(matchup_size) {
             if chunk.len() == matchup_size {
@@ -33,6 +47,93 @@ pub fn generate_seed_matchups(num_photos: u32, matchup_size: usize) -> Vec<Vec<u
     matchups
 }
 
+/// Like [`generate_seed_matchups`], but keeps per-photo exposure within `policy`.
+///
+/// Photos already over the ratio are held back, and when a round cannot be split
+/// evenly the most-shown photos sit it out instead of random ones. Exposure is
+/// projected as matchups are generated, so `exposure` should hold the counts
+/// served so far.
+#[must_use]
+pub fn generate_balanced_seed_matchups(
+    exposure: &ExposureTracker,
+    policy: &ExposurePolicy,
+    matchup_size: usize,
+) -> Vec<Vec<u32>> {
+    generate_balanced_seed_matchups_with_rng(exposure, policy, matchup_size, &mut rand::rng())
+}
+
+/// [`generate_balanced_seed_matchups`] with a caller-supplied RNG.
+#[must_use]
+pub fn generate_balanced_seed_matchups_with_rng<R: Rng + ?Sized>(
+    exposure: &ExposureTracker,
+    policy: &ExposurePolicy,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
+    let Ok(num_photos) = u32::try_from(exposure.counts().len()) else {
+        return vec![];
+    };
+    if matchup_size == 0 || (num_photos as usize) < matchup_size {
+        return vec![];
+    }
+
+    let photos: Vec<u32> = (0..num_photos).collect();
+    let mut projected = exposure.clone();
+    let mut matchups = Vec::new();
+
+    for _ in 0..seed_rounds(num_photos) {
+        matchups.extend(balanced_round(
+            &photos,
+            &photos,
+            &mut projected,
+            *policy,
+            matchup_size,
+            rng,
+        ));
+    }
+
+    matchups
+}
+
+/// ceil(log2(n)) + 1 rounds ensures good coverage via integer math.
+fn seed_rounds(num_photos: u32) -> usize {
+    (u32::BITS - num_photos.saturating_sub(1).leading_zeros()) as usize + 1
+}
+
+/// One round of matchups over the `photos` that `policy` allows, measured
+/// against the least-shown of `among`, recording them in `projected`.
+///
+/// When the eligible photos can't be split evenly, the most-shown sit out.
+fn balanced_round<R: Rng + ?Sized>(
+    photos: &[u32],
+    among: &[u32],
+    projected: &mut ExposureTracker,
+    policy: ExposurePolicy,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
+    let mut eligible: Vec<u32> = photos
+        .iter()
+        .copied()
+        .filter(|&idx| projected.allows_among(idx, among, &policy))
+        .collect();
+
+    // Shuffle first so the stable sort breaks exposure ties randomly.
+    eligible.shuffle(rng);
+    eligible.sort_by_key(|&idx| projected.get(idx).map_or(0, |c| c.shown));
+    eligible.truncate(eligible.len() - eligible.len() % matchup_size);
+    eligible.shuffle(rng);
+
+    let matchups: Vec<Vec<u32>> = eligible
+        .chunks_exact(matchup_size)
+        .map(<[u32]>::to_vec)
+        .collect();
+    for matchup in &matchups {
+        projected.record_shown(matchup);
+    }
+    matchups
+}
+
 #[must_use]
 pub fn select_dynamic_matchup<S: BuildHasher>(
     ratings: &[PhotoRating],
@@ -74,6 +175,520 @@ pub fn select_dynamic_matchup<S: BuildHasher>(
     (selected.len() == matchup_size).then_some(selected)
 }
 
+/// Like [`select_dynamic_matchup`], but only draws from photos that `policy` allows.
+///
+/// If too few photos are eligible, falls back to the least-shown photos so the
+/// under-exposed ones catch up.
+#[must_use]
+pub fn select_balanced_dynamic_matchup<S: BuildHasher>(
+    ratings: &[PhotoRating],
+    compared_pairs: &HashSet<(u32, u32), S>,
+    exposure: &ExposureTracker,
+    policy: &ExposurePolicy,
+    matchup_size: usize,
+) -> Option<Vec<u32>> {
+    let eligible: Vec<PhotoRating> = ratings
+        .iter()
+        .filter(|r| exposure.allows(r.photo_idx, policy))
+        .copied()
+        .collect();
+
+    if eligible.len() >= matchup_size {
+        return select_dynamic_matchup(&eligible, compared_pairs, matchup_size);
+    }
+
+    if ratings.len() < matchup_size {
+        return None;
+    }
+
+    let mut least_shown: Vec<&PhotoRating> = ratings.iter().collect();
+    least_shown.sort_by(|a, b| {
+        let shown = |r: &PhotoRating| exposure.get(r.photo_idx).map_or(0, |c| c.shown);
+        shown(a)
+            .cmp(&shown(b))
+            .then(b.uncertainty.total_cmp(&a.uncertainty))
+    });
+
+    Some(
+        least_shown
+            .into_iter()
+            .take(matchup_size)
+            .map(|r| r.photo_idx)
+            .collect(),
+    )
+}
+
+/// Seed matchups that respect photo groups according to `mode`, keeping
+/// per-photo exposure within `policy` like [`generate_balanced_seed_matchups`].
+///
+/// `Within` deals balanced rounds inside every group big enough for a matchup,
+/// measuring exposure only against photos in such groups. `Across` deals each
+/// round's photos from the fullest groups so a matchup never repeats a group
+/// while enough groups have photos left; the remainder is matched up
+/// regardless. `Mixed` returns both, shuffled.
+#[must_use]
+pub fn generate_grouped_seed_matchups(
+    groups: &PhotoGroups,
+    mode: GroupMode,
+    exposure: &ExposureTracker,
+    policy: &ExposurePolicy,
+    matchup_size: usize,
+) -> Vec<Vec<u32>> {
+    generate_grouped_seed_matchups_with_rng(
+        groups,
+        mode,
+        exposure,
+        policy,
+        matchup_size,
+        &mut rand::rng(),
+    )
+}
+
+/// [`generate_grouped_seed_matchups`] with a caller-supplied RNG.
+#[must_use]
+pub fn generate_grouped_seed_matchups_with_rng<R: Rng + ?Sized>(
+    groups: &PhotoGroups,
+    mode: GroupMode,
+    exposure: &ExposureTracker,
+    policy: &ExposurePolicy,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
+    if matchup_size == 0 {
+        return vec![];
+    }
+    let mut projected = exposure.clone();
+    match mode {
+        GroupMode::Within => within_group_seeds(groups, &mut projected, *policy, matchup_size, rng),
+        GroupMode::Across => across_group_seeds(groups, &mut projected, *policy, matchup_size, rng),
+        GroupMode::Mixed => {
+            let mut matchups =
+                within_group_seeds(groups, &mut projected, *policy, matchup_size, rng);
+            matchups.extend(across_group_seeds(
+                groups,
+                &mut projected,
+                *policy,
+                matchup_size,
+                rng,
+            ));
+            matchups.shuffle(rng);
+            matchups
+        }
+    }
+}
+
+/// Photos in groups big enough for a matchup of their own.
+fn photos_in_full_groups(groups: &PhotoGroups, matchup_size: usize) -> Vec<u32> {
+    (0..groups.len())
+        .map(|group| groups.members(group))
+        .filter(|members| members.len() >= matchup_size)
+        .flatten()
+        .copied()
+        .collect()
+}
+
+fn within_group_seeds<R: Rng + ?Sized>(
+    groups: &PhotoGroups,
+    projected: &mut ExposureTracker,
+    policy: ExposurePolicy,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
+    let among = photos_in_full_groups(groups, matchup_size);
+    let rounds: Vec<usize> = (0..groups.len())
+        .map(|group| {
+            let members = groups.members(group);
+            match u32::try_from(members.len()) {
+                Ok(len) if members.len() >= matchup_size => seed_rounds(len),
+                _ => 0,
+            }
+        })
+        .collect();
+
+    // Rounds take turns between groups, so no group runs ahead on exposure
+    let mut matchups = Vec::new();
+    for round in 0..rounds.iter().copied().max().unwrap_or(0) {
+        for (group, &group_rounds) in rounds.iter().enumerate() {
+            if round < group_rounds {
+                matchups.extend(balanced_round(
+                    groups.members(group),
+                    &among,
+                    projected,
+                    policy,
+                    matchup_size,
+                    rng,
+                ));
+            }
+        }
+    }
+    matchups
+}
+
+fn across_group_seeds<R: Rng + ?Sized>(
+    groups: &PhotoGroups,
+    projected: &mut ExposureTracker,
+    policy: ExposurePolicy,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
+    let total: usize = (0..groups.len()).map(|g| groups.members(g).len()).sum();
+    let Ok(num_photos) = u32::try_from(total) else {
+        return vec![];
+    };
+    if total < matchup_size {
+        return vec![];
+    }
+
+    let mut matchups = Vec::new();
+
+    for _ in 0..seed_rounds(num_photos) {
+        let shown = |idx: u32| projected.get(idx).map_or(0, |c| c.shown);
+        let mut decks: Vec<Vec<u32>> = (0..groups.len())
+            .map(|g| {
+                let mut deck: Vec<u32> = groups
+                    .members(g)
+                    .iter()
+                    .copied()
+                    .filter(|&idx| projected.allows(idx, &policy))
+                    .collect();
+                deck.shuffle(rng);
+                // Least shown on top, ties in random order
+                deck.sort_by_key(|&idx| std::cmp::Reverse(shown(idx)));
+                deck
+            })
+            .collect();
+
+        let mut round = Vec::new();
+        loop {
+            decks.shuffle(rng);
+            decks.sort_by_key(|deck| std::cmp::Reverse(deck.len()));
+            if decks.iter().filter(|d| !d.is_empty()).count() < matchup_size {
+                break;
+            }
+            round.push(
+                decks[..matchup_size]
+                    .iter_mut()
+                    .filter_map(Vec::pop)
+                    .collect::<Vec<u32>>(),
+            );
+        }
+
+        let mut rest: Vec<u32> = decks.into_iter().flatten().collect();
+        rest.shuffle(rng);
+        rest.sort_by_key(|&idx| shown(idx));
+        round.extend(rest.chunks_exact(matchup_size).map(<[u32]>::to_vec));
+
+        for matchup in &round {
+            projected.record_shown(matchup);
+        }
+        matchups.extend(round);
+    }
+
+    matchups
+}
+
+/// Dynamic matchup that respects photo groups according to `mode`, drawing
+/// only on photos that `policy` allows while enough of them are left.
+///
+/// `Within` runs [`select_dynamic_matchup`] in each group and keeps the most
+/// uncertain result. `Across` takes the most uncertain photo of each group,
+/// topping up from any group if there are fewer groups than `matchup_size`.
+/// `Mixed` keeps whichever of the two is more uncertain overall.
+#[must_use]
+pub fn select_grouped_dynamic_matchup<S: BuildHasher>(
+    ratings: &[PhotoRating],
+    compared_pairs: &HashSet<(u32, u32), S>,
+    groups: &PhotoGroups,
+    mode: GroupMode,
+    exposure: &ExposureTracker,
+    policy: &ExposurePolicy,
+    matchup_size: usize,
+) -> Option<Vec<u32>> {
+    let among = match mode {
+        GroupMode::Within => photos_in_full_groups(groups, matchup_size),
+        GroupMode::Across | GroupMode::Mixed => ratings.iter().map(|r| r.photo_idx).collect(),
+    };
+    let eligible: Vec<PhotoRating> = ratings
+        .iter()
+        .filter(|r| exposure.allows_among(r.photo_idx, &among, policy))
+        .copied()
+        .collect();
+
+    grouped_dynamic_matchup(&eligible, compared_pairs, groups, mode, matchup_size)
+        .or_else(|| grouped_dynamic_matchup(ratings, compared_pairs, groups, mode, matchup_size))
+}
+
+fn grouped_dynamic_matchup<S: BuildHasher>(
+    ratings: &[PhotoRating],
+    compared_pairs: &HashSet<(u32, u32), S>,
+    groups: &PhotoGroups,
+    mode: GroupMode,
+    matchup_size: usize,
+) -> Option<Vec<u32>> {
+    let within = || {
+        (0..groups.len())
+            .filter_map(|group| {
+                let members = groups.members(group);
+                let in_group: Vec<PhotoRating> = ratings
+                    .iter()
+                    .filter(|r| members.contains(&r.photo_idx))
+                    .copied()
+                    .collect();
+                select_dynamic_matchup(&in_group, compared_pairs, matchup_size)
+            })
+            .max_by(|a, b| total_uncertainty(ratings, a).total_cmp(&total_uncertainty(ratings, b)))
+    };
+    let across = || select_across_groups(ratings, groups, matchup_size);
+
+    match mode {
+        GroupMode::Within => within(),
+        GroupMode::Across => across(),
+        GroupMode::Mixed => match (within(), across()) {
+            (Some(w), Some(a)) => {
+                if total_uncertainty(ratings, &w) >= total_uncertainty(ratings, &a) {
+                    Some(w)
+                } else {
+                    Some(a)
+                }
+            }
+            (w, a) => w.or(a),
+        },
+    }
+}
+
+fn select_across_groups(
+    ratings: &[PhotoRating],
+    groups: &PhotoGroups,
+    matchup_size: usize,
+) -> Option<Vec<u32>> {
+    if matchup_size == 0 || ratings.len() < matchup_size {
+        return None;
+    }
+
+    let mut by_uncertainty: Vec<&PhotoRating> = ratings.iter().collect();
+    by_uncertainty.sort_by(|a, b| b.uncertainty.total_cmp(&a.uncertainty));
+
+    let mut selected = Vec::with_capacity(matchup_size);
+    let mut used_groups = Vec::with_capacity(matchup_size);
+    for rating in &by_uncertainty {
+        if selected.len() == matchup_size {
+            break;
+        }
+        let group = groups.group_of(rating.photo_idx);
+        if !used_groups.contains(&group) {
+            used_groups.push(group);
+            selected.push(rating.photo_idx);
+        }
+    }
+    for rating in &by_uncertainty {
+        if selected.len() == matchup_size {
+            break;
+        }
+        if !selected.contains(&rating.photo_idx) {
+            selected.push(rating.photo_idx);
+        }
+    }
+
+    Some(selected)
+}
+
+fn total_uncertainty(ratings: &[PhotoRating], photos: &[u32]) -> f64 {
+    ratings
+        .iter()
+        .filter(|r| photos.contains(&r.photo_idx))
+        .map(|r| r.uncertainty)
+        .sum()
+}
+
+/// Groups photos with similar current strength into matchups, Swiss-system style.
+///
+/// Photos are taken strongest first; each group is filled with the next photos
+/// down the standings that have not been compared with anyone already in it,
+/// falling back to the nearest photos only when no fresh opponents remain.
+/// When the photos don't split evenly, the least uncertain ones get a bye so
+/// byes rotate as ratings firm up. Ties are broken randomly.
+#[must_use]
+pub fn swiss_pairings<S: BuildHasher, R: Rng + ?Sized>(
+    ratings: &[PhotoRating],
+    compared_pairs: &HashSet<(u32, u32), S>,
+    matchup_size: usize,
+    rng: &mut R,
+) -> Vec<Vec<u32>> {
+    if matchup_size < 2 || ratings.len() < matchup_size {
+        return vec![];
+    }
+
+    let mut standings: Vec<&PhotoRating> = ratings.iter().collect();
+    standings.shuffle(rng);
+    standings.sort_by(|a, b| a.uncertainty.total_cmp(&b.uncertainty));
+    standings.drain(..standings.len() % matchup_size);
+    standings.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+
+    let mut pool: Vec<u32> = standings.into_iter().map(|r| r.photo_idx).collect();
+    let mut groups = Vec::with_capacity(pool.len() / matchup_size);
+
+    while !pool.is_empty() {
+        let mut picked = vec![0];
+        for (pos, &candidate) in pool.iter().enumerate().skip(1) {
+            if picked.len() == matchup_size {
+                break;
+            }
+            let fresh = picked
+                .iter()
+                .all(|&p| !compared_pairs.contains(&normalize_pair(pool[p], candidate)));
+            if fresh {
+                picked.push(pos);
+            }
+        }
+        for pos in 1..pool.len() {
+            if picked.len() == matchup_size {
+                break;
+            }
+            if !picked.contains(&pos) {
+                picked.push(pos);
+            }
+        }
+
+        picked.sort_unstable();
+        groups.push(picked.iter().map(|&p| pool[p]).collect());
+        for &p in picked.iter().rev() {
+            pool.remove(p);
+        }
+    }
+
+    groups
+}
+
+/// Swiss-system tournament: rounds of [`swiss_pairings`] over the current ratings.
+///
+/// A round's pairings are drawn when it starts and served in order. The round
+/// advances once `matchups_per_round` matchups have been answered, so the next
+/// round pairs photos by their updated strengths. If a round's pairings run out
+/// before enough answers arrive, fresh pairings are drawn within the same round.
+///
+/// Serializable, so a campaign's tournament can be stored between requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwissTournament {
+    matchup_size: usize,
+    matchups_per_round: u32,
+    round: u32,
+    answered_in_round: u32,
+    /// Answers counted over all rounds.
+    answered: u32,
+    pending: VecDeque<Vec<u32>>,
+}
+
+impl SwissTournament {
+    /// Returns `None` if `matchup_size` is below two or `matchups_per_round` is zero.
+    #[must_use]
+    pub fn new(matchup_size: usize, matchups_per_round: u32) -> Option<Self> {
+        if matchup_size < 2 || matchups_per_round == 0 {
+            return None;
+        }
+        Some(Self {
+            matchup_size,
+            matchups_per_round,
+            round: 0,
+            answered_in_round: 0,
+            answered: 0,
+            pending: VecDeque::new(),
+        })
+    }
+
+    #[must_use]
+    pub const fn round(&self) -> u32 {
+        self.round
+    }
+
+    #[must_use]
+    pub const fn answered_in_round(&self) -> u32 {
+        self.answered_in_round
+    }
+
+    pub fn next_matchup<S: BuildHasher, R: Rng + ?Sized>(
+        &mut self,
+        ratings: &[PhotoRating],
+        compared_pairs: &HashSet<(u32, u32), S>,
+        rng: &mut R,
+    ) -> Option<Vec<u32>> {
+        if self.pending.is_empty() {
+            self.pending = swiss_pairings(ratings, compared_pairs, self.matchup_size, rng).into();
+        }
+        self.pending.pop_front()
+    }
+
+    /// Counts answers until `answered` have been counted in all, for callers
+    /// that count answers in their history rather than as they arrive. Returns
+    /// `true` if this completed a round.
+    pub fn record_answered_until(&mut self, answered: u32) -> bool {
+        let mut completed = false;
+        while self.answered < answered {
+            completed |= self.record_answered();
+        }
+        completed
+    }
+
+    /// Counts an answered matchup. Returns `true` if this completed the round.
+    pub fn record_answered(&mut self) -> bool {
+        self.answered += 1;
+        self.answered_in_round += 1;
+        if self.answered_in_round < self.matchups_per_round {
+            return false;
+        }
+        self.round += 1;
+        self.answered_in_round = 0;
+        self.pending.clear();
+        true
+    }
+}
+
+/// Swaps an anchor photo into a matchup that has none, so the other photos are
+/// measured against a known strength.
+///
+/// The least-shown anchor replaces the most-shown photo. Returns `false` if the
+/// matchup already holds an anchor or there are no anchors.
+pub fn inject_anchor(matchup: &mut [u32], anchors: &[u32], exposure: &ExposureTracker) -> bool {
+    if matchup.iter().any(|p| anchors.contains(p)) {
+        return false;
+    }
+    let shown = |idx: u32| exposure.get(idx).map_or(0, |c| c.shown);
+    let Some(&anchor) = anchors.iter().min_by_key(|&&a| shown(a)) else {
+        return false;
+    };
+    let Some(slot) = matchup.iter_mut().max_by_key(|p| shown(**p)) else {
+        return false;
+    };
+    *slot = anchor;
+    true
+}
+
+/// The anchors that may join `matchup` without breaking `mode`: only those in
+/// its group when the matchup stays within one, as `Within` ones always do.
+#[must_use]
+pub fn anchors_for_group_mode(
+    matchup: &[u32],
+    anchors: &[u32],
+    groups: &PhotoGroups,
+    mode: GroupMode,
+) -> Vec<u32> {
+    let group = matchup.first().and_then(|&p| groups.group_of(p));
+    let within = matchup.iter().all(|&p| groups.group_of(p) == group);
+    if mode == GroupMode::Across || !within {
+        return anchors.to_vec();
+    }
+    anchors
+        .iter()
+        .copied()
+        .filter(|&a| groups.group_of(a) == group)
+        .collect()
+}
+
+/// Returns the matchup's photos in a random left-to-right display order.
+#[must_use]
+pub fn shuffle_display_order(photo_indices: Triple) -> Triple {
+    photo_indices.shuffled(&mut rand::rng())
+}
+
 #[must_use]
 pub const fn normalize_pair(a: u32, b: u32) -> (u32, u32) {
     if a < b {
@@ -140,6 +755,252 @@ mod tests {
         );
     }
 
+    #[test]
+    fn balanced_seed_matchups_leave_out_most_shown() {
+        let mut exposure = ExposureTracker::new(4);
+        exposure.record_shown(&[3]);
+        let policy = ExposurePolicy { max_ratio: 10.0 };
+
+        let matchups = generate_balanced_seed_matchups(&exposure, &policy, 3);
+        assert!(!matchups.is_empty());
+        // Four photos, triples: exactly one sits out per round, and photo 3 starts ahead.
+        let first_round = &matchups[0];
+        assert!(!first_round.contains(&3));
+    }
+
+    #[test]
+    fn balanced_seed_matchups_hold_back_overexposed() {
+        let mut exposure = ExposureTracker::new(7);
+        for _ in 0..3 {
+            exposure.record_shown(&[6]);
+        }
+        let policy = ExposurePolicy { max_ratio: 2.0 };
+
+        let matchups = generate_balanced_seed_matchups(&exposure, &policy, 3);
+        // Photo 6 sits out until the rest catch up: two rounds of two triples each.
+        assert!(matchups[..4].iter().all(|m| !m.contains(&6)));
+    }
+
+    #[test]
+    fn balanced_dynamic_matchup_skips_overexposed() {
+        let ratings: Vec<PhotoRating> = (0..5).map(PhotoRating::new).collect();
+        let mut exposure = ExposureTracker::new(5);
+        for _ in 0..4 {
+            exposure.record_shown(&[0, 1]);
+        }
+        exposure.record_shown(&[2, 3, 4]);
+        let policy = ExposurePolicy::default();
+
+        let selected =
+            select_balanced_dynamic_matchup(&ratings, &HashSet::new(), &exposure, &policy, 3)
+                .unwrap();
+        let mut sorted = selected.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn grouped_seeds_within_stay_in_group() {
+        let labels: Vec<Option<&str>> = (0..12)
+            .map(|i| Some(if i < 6 { "a" } else { "b" }))
+            .collect();
+        let groups = PhotoGroups::from_labels(&labels);
+
+        let matchups = generate_grouped_seed_matchups(
+            &groups,
+            GroupMode::Within,
+            &ExposureTracker::new(12),
+            &ExposurePolicy::default(),
+            3,
+        );
+        assert!(!matchups.is_empty());
+        for matchup in &matchups {
+            let group = groups.group_of(matchup[0]);
+            assert!(matchup.iter().all(|&p| groups.group_of(p) == group));
+        }
+    }
+
+    #[test]
+    fn grouped_seeds_across_mix_groups() {
+        let labels: Vec<Option<String>> = (0..9).map(|i| Some(format!("roll-{}", i % 3))).collect();
+        let groups = PhotoGroups::from_labels(&labels);
+
+        let matchups = generate_grouped_seed_matchups(
+            &groups,
+            GroupMode::Across,
+            &ExposureTracker::new(9),
+            &ExposurePolicy::default(),
+            3,
+        );
+        assert!(!matchups.is_empty());
+        for matchup in &matchups {
+            let distinct: HashSet<_> = matchup.iter().map(|&p| groups.group_of(p)).collect();
+            assert_eq!(distinct.len(), 3);
+        }
+    }
+
+    #[test]
+    fn grouped_dynamic_across_picks_distinct_groups() {
+        let groups = PhotoGroups::from_labels(&[Some("a"), Some("a"), Some("b"), Some("c")]);
+        let mut ratings: Vec<PhotoRating> = (0..4).map(PhotoRating::new).collect();
+        ratings[3].uncertainty = 0.1;
+
+        let selected = select_grouped_dynamic_matchup(
+            &ratings,
+            &HashSet::new(),
+            &groups,
+            GroupMode::Across,
+            &ExposureTracker::new(4),
+            &ExposurePolicy::default(),
+            3,
+        )
+        .unwrap();
+        let distinct: HashSet<_> = selected.iter().map(|&p| groups.group_of(p)).collect();
+        assert_eq!(distinct.len(), 3);
+    }
+
+    #[test]
+    fn grouped_seeds_within_hold_back_overexposed() {
+        let labels: Vec<Option<&str>> = (0..7)
+            .map(|i| Some(if i < 6 { "a" } else { "b" }))
+            .collect();
+        let groups = PhotoGroups::from_labels(&labels);
+        let mut exposure = ExposureTracker::new(7);
+        for _ in 0..3 {
+            exposure.record_shown(&[5]);
+        }
+        let policy = ExposurePolicy { max_ratio: 2.0 };
+
+        let matchups =
+            generate_grouped_seed_matchups(&groups, GroupMode::Within, &exposure, &policy, 3);
+        // Photo 6 is alone in its group, so only photo 5 is held back: one triple per round
+        // until the rest of group "a" catches up.
+        assert!(matchups[..2].iter().all(|m| !m.contains(&5)));
+        assert!(matchups.iter().all(|m| !m.contains(&6)));
+    }
+
+    #[test]
+    fn grouped_dynamic_skips_overexposed() {
+        let groups = PhotoGroups::from_labels(&[Some("a"), Some("a"), Some("b"), Some("c")]);
+        let ratings: Vec<PhotoRating> = (0..4).map(PhotoRating::new).collect();
+        let mut exposure = ExposureTracker::new(4);
+        for _ in 0..3 {
+            exposure.record_shown(&[0]);
+        }
+        exposure.record_shown(&[1, 2, 3]);
+
+        let selected = select_grouped_dynamic_matchup(
+            &ratings,
+            &HashSet::new(),
+            &groups,
+            GroupMode::Across,
+            &exposure,
+            &ExposurePolicy::default(),
+            3,
+        )
+        .unwrap();
+        let mut sorted = selected.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, vec![1, 2, 3]);
+    }
+
+    fn rated(strengths: &[f64]) -> Vec<PhotoRating> {
+        strengths
+            .iter()
+            .zip(0..)
+            .map(|(&strength, photo_idx)| PhotoRating {
+                photo_idx,
+                strength,
+                uncertainty: 1.0,
+            })
+            .collect()
+    }
+
+    fn sorted_groups(mut groups: Vec<Vec<u32>>) -> Vec<Vec<u32>> {
+        for group in &mut groups {
+            group.sort_unstable();
+        }
+        groups
+    }
+
+    #[test]
+    fn swiss_pairings_group_similar_scores() {
+        let ratings = rated(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        let groups = swiss_pairings(&ratings, &HashSet::new(), 3, &mut rand::rng());
+        assert_eq!(sorted_groups(groups), vec![vec![3, 4, 5], vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn swiss_pairings_avoid_rematches() {
+        let ratings = rated(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        let compared = extract_compared_pairs(&[(5, 4)]);
+        let groups = swiss_pairings(&ratings, &compared, 3, &mut rand::rng());
+        assert_eq!(sorted_groups(groups), vec![vec![2, 3, 5], vec![0, 1, 4]]);
+    }
+
+    #[test]
+    fn swiss_byes_go_to_least_uncertain() {
+        let mut ratings = rated(&[0.0, 1.0, 2.0, 3.0]);
+        ratings[2].uncertainty = 0.2;
+        let groups = swiss_pairings(&ratings, &HashSet::new(), 3, &mut rand::rng());
+        assert_eq!(sorted_groups(groups), vec![vec![0, 1, 3]]);
+    }
+
+    #[test]
+    fn swiss_tournament_advances_after_configured_answers() {
+        let ratings = rated(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mut swiss = SwissTournament::new(3, 2).unwrap();
+        let mut rng = rand::rng();
+
+        assert!(swiss
+            .next_matchup(&ratings, &HashSet::new(), &mut rng)
+            .is_some());
+        assert!(!swiss.record_answered());
+        assert_eq!(swiss.answered_in_round(), 1);
+        assert!(swiss.record_answered());
+        assert_eq!(swiss.round(), 1);
+        assert_eq!(swiss.answered_in_round(), 0);
+        assert!(SwissTournament::new(3, 0).is_none());
+
+        // Catching up from a count is the same as counting one by one
+        let mut caught_up = SwissTournament::new(3, 2).unwrap();
+        assert!(caught_up.record_answered_until(5));
+        assert_eq!((caught_up.round(), caught_up.answered_in_round()), (2, 1));
+        assert!(!caught_up.record_answered_until(5));
+        assert!(!caught_up.record_answered_until(3));
+        assert_eq!(caught_up.round(), 2);
+    }
+
+    #[test]
+    fn inject_anchor_replaces_most_shown() {
+        let mut exposure = ExposureTracker::new(6);
+        exposure.record_shown(&[1, 1, 5]);
+        let mut matchup = vec![0, 1, 2];
+
+        assert!(inject_anchor(&mut matchup, &[4, 5], &exposure));
+        assert_eq!(matchup, vec![0, 4, 2]);
+        assert!(!inject_anchor(&mut matchup, &[4, 5], &exposure));
+    }
+
+    #[test]
+    fn anchors_stay_in_a_within_matchups_group() {
+        let groups = PhotoGroups::from_labels(&[Some("a"), Some("a"), Some("a"), Some("b")]);
+
+        let same = anchors_for_group_mode(&[0, 1, 2], &[1, 3], &groups, GroupMode::Within);
+        assert_eq!(same, vec![1]);
+        let mixed = anchors_for_group_mode(&[0, 1, 3], &[1, 3], &groups, GroupMode::Mixed);
+        assert_eq!(mixed, vec![1, 3]);
+        let across = anchors_for_group_mode(&[0, 1, 2], &[1, 3], &groups, GroupMode::Across);
+        assert_eq!(across, vec![1, 3]);
+    }
+
+    #[test]
+    fn display_order_is_permutation() {
+        let mut order = shuffle_display_order(crate::types::triple([4, 9, 2])).indices();
+        order.sort_unstable();
+        assert_eq!(order, [2, 4, 9]);
+    }
+
     #[test]
     fn normalize_pair_orders_correctly() {
         assert_eq!(normalize_pair(5, 3), (3, 5));
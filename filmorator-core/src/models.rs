@@ -1,28 +1,230 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::identity::content_hash;
+use crate::provenance::Provenance;
+use crate::types::{Ranking, Triple};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Photo {
     pub id: Uuid,
+    pub campaign_id: String,
     pub filename: String,
     pub file_hash: String,
     pub position: u32,
+    /// Roll or series the photo belongs to, if any.
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// Campaign that data from before campaigns existed was migrated into.
+pub const DEFAULT_CAMPAIGN_ID: &str = "default";
+
+/// A set of photos ranked together, with its own participants and ranking.
+///
+/// Photos and the matchup pool are immutable once published; only the status
+/// and threshold change, through the transition methods.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Campaign {
+    pub id: String,
+    pub name: String,
+    /// [`owner_secret_hash`] of the secret in the owner's management URL; the
+    /// secret itself is never stored. Never serialized, so it stays out of
+    /// bundles and responses.
+    #[serde(skip_serializing, default)]
+    pub owner_secret_hash: String,
+    pub status: CampaignStatus,
+    /// When the comparison graph first met the statistical threshold.
+    pub threshold_reached_at: Option<DateTime<Utc>>,
+    /// Most recent close.
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Most recent reopen.
+    #[serde(default)]
+    pub reopened_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Campaign {
+    #[must_use]
+    pub fn new(id: String, name: String, owner_secret_hash: String) -> Self {
+        Self {
+            id,
+            name,
+            owner_secret_hash,
+            status: CampaignStatus::Active,
+            threshold_reached_at: None,
+            closed_at: None,
+            reopened_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Whether `secret` is the owner's.
+    #[must_use]
+    pub fn is_owned_by(&self, secret: &str) -> bool {
+        owner_secret_hash(secret) == self.owner_secret_hash
+    }
+
+    /// # Errors
+    ///
+    /// [`CampaignError::Closed`] if the campaign is already closed.
+    pub fn close(&mut self, at: DateTime<Utc>) -> Result<(), CampaignError> {
+        self.status = self.status.close()?;
+        self.closed_at = Some(at);
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// [`CampaignError::NotClosed`] unless the campaign is closed.
+    pub fn reopen(&mut self, at: DateTime<Utc>) -> Result<(), CampaignError> {
+        self.status = self.status.reopen()?;
+        self.reopened_at = Some(at);
+        Ok(())
+    }
+
+    /// Records when the statistical threshold was first met.
+    ///
+    /// # Errors
+    ///
+    /// [`CampaignError::Closed`] if the campaign is closed, since no comparison
+    /// can have tipped it over, and [`CampaignError::ThresholdAlreadyReached`]
+    /// if it was recorded before.
+    pub fn mark_threshold_reached(&mut self, at: DateTime<Utc>) -> Result<(), CampaignError> {
+        self.ensure_accepting()?;
+        if self.threshold_reached_at.is_some() {
+            return Err(CampaignError::ThresholdAlreadyReached);
+        }
+        self.threshold_reached_at = Some(at);
+        Ok(())
+    }
+
+    /// Guard for the matchup and comparison paths.
+    ///
+    /// # Errors
+    ///
+    /// [`CampaignError::Closed`] if the campaign does not accept comparisons.
+    pub const fn ensure_accepting(&self) -> Result<(), CampaignError> {
+        if self.status.accepts_comparisons() {
+            Ok(())
+        } else {
+            Err(CampaignError::Closed)
+        }
+    }
+}
+
+/// What a campaign stores of its owner's secret.
+#[must_use]
+pub fn owner_secret_hash(secret: &str) -> String {
+    content_hash(secret.as_bytes())
+}
+
+/// Campaign lifecycle: `Active → Closed → Reopened → Closed → …`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignStatus {
+    /// Accepting comparisons.
+    #[default]
+    Active,
+    /// Closed by the owner; the ranking is final until reopened.
+    Closed,
+    /// Accepting comparisons again after a close.
+    Reopened,
+}
+
+impl CampaignStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Closed => "closed",
+            Self::Reopened => "reopened",
+        }
+    }
+
+    #[must_use]
+    pub const fn accepts_comparisons(self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+
+    /// # Errors
+    ///
+    /// [`CampaignError::Closed`] if already closed.
+    pub const fn close(self) -> Result<Self, CampaignError> {
+        match self {
+            Self::Active | Self::Reopened => Ok(Self::Closed),
+            Self::Closed => Err(CampaignError::Closed),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// [`CampaignError::NotClosed`] unless closed.
+    pub const fn reopen(self) -> Result<Self, CampaignError> {
+        match self {
+            Self::Closed => Ok(Self::Reopened),
+            Self::Active | Self::Reopened => Err(CampaignError::NotClosed),
+        }
+    }
+}
+
+impl FromStr for CampaignStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(Self::Active),
+            "closed" => Ok(Self::Closed),
+            "reopened" => Ok(Self::Reopened),
+            other => Err(format!("unknown campaign status: {other}")),
+        }
+    }
+}
+
+/// A campaign lifecycle transition that is not allowed from the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum CampaignError {
+    #[error("campaign is closed")]
+    Closed,
+    #[error("campaign is not closed")]
+    NotClosed,
+    #[error("campaign threshold already reached")]
+    ThresholdAlreadyReached,
+}
+
+/// A photo's rating aggregated over every non-excluded session of a campaign.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CampaignRating {
+    pub photo_idx: u32,
+    pub strength: f64,
+    pub uncertainty: f64,
+    /// Comparisons involving the photo that the rating is based on.
+    pub comparison_count: u32,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub id: Uuid,
+    pub campaign_id: String,
+    /// Set by the owner to leave a suspicious session out of the campaign ranking.
+    #[serde(default)]
+    pub excluded: bool,
     pub created_at: DateTime<Utc>,
     pub last_active_at: DateTime<Utc>,
 }
 
 impl Session {
     #[must_use]
-    pub fn new() -> Self {
+    pub fn new(campaign_id: String) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
+            campaign_id,
+            excluded: false,
             created_at: now,
             last_active_at: now,
         }
@@ -31,7 +233,7 @@ impl Session {
 
 impl Default for Session {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_CAMPAIGN_ID.to_string())
     }
 }
 
@@ -39,55 +241,273 @@ impl Default for Session {
 pub struct Matchup {
     pub id: Uuid,
     pub session_id: Uuid,
-    pub photo_indices: Vec<u32>,
+    /// The session's campaign, whose photos the indices refer to.
+    pub campaign_id: String,
+    pub photo_indices: Triple,
     pub is_seed: bool,
+    #[serde(default)]
+    pub kind: MatchupKind,
     pub created_at: DateTime<Utc>,
+    /// When the server last sent it to the session; `None` until then.
+    #[serde(default)]
+    pub served_at: Option<DateTime<Utc>>,
 }
 
 impl Matchup {
     #[must_use]
-    pub fn new(session_id: Uuid, photo_indices: Vec<u32>, is_seed: bool) -> Self {
+    pub fn new(session: &Session, photo_indices: Triple, is_seed: bool) -> Self {
         Self {
             id: Uuid::new_v4(),
-            session_id,
+            session_id: session.id,
+            campaign_id: session.campaign_id.clone(),
             photo_indices,
             is_seed,
+            kind: MatchupKind::Regular,
             created_at: Utc::now(),
+            served_at: None,
+        }
+    }
+
+    /// A quality-check matchup. Never a seed.
+    #[must_use]
+    pub fn check(session: &Session, photo_indices: Triple, kind: MatchupKind) -> Self {
+        Self {
+            kind,
+            ..Self::new(session, photo_indices, false)
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What a matchup is for. Checks measure participant quality rather than photos.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MatchupKind {
+    #[default]
+    Regular,
+    /// Re-serves a triple the session already answered, to test self-consistency.
+    Repeat { original: Uuid },
+    /// Photos the model is confident about, with an expected ordering.
+    Calibration,
+}
+
+impl MatchupKind {
+    #[must_use]
+    pub const fn is_check(self) -> bool {
+        !matches!(self, Self::Regular)
+    }
+
+    /// Storage tag, paired with [`Self::repeat_of`].
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Regular => "regular",
+            Self::Repeat { .. } => "repeat",
+            Self::Calibration => "calibration",
+        }
+    }
+
+    #[must_use]
+    pub const fn repeat_of(self) -> Option<Uuid> {
+        match self {
+            Self::Repeat { original } => Some(original),
+            Self::Regular | Self::Calibration => None,
+        }
+    }
+
+    /// Rebuilds a kind from its storage columns. `None` if they are inconsistent.
+    #[must_use]
+    pub fn from_parts(kind: &str, repeat_of: Option<Uuid>) -> Option<Self> {
+        match (kind, repeat_of) {
+            ("regular", None) => Some(Self::Regular),
+            ("repeat", Some(original)) => Some(Self::Repeat { original }),
+            ("calibration", None) => Some(Self::Calibration),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ComparisonResult {
     pub id: Uuid,
     pub matchup_id: Uuid,
     pub session_id: Uuid,
-    pub ranked_photo_indices: Vec<u32>,
+    /// Best first.
+    pub ranked_photo_indices: Triple,
+    /// Left-to-right order the photos were shown in, when the client reported it.
+    #[serde(default)]
+    pub displayed_order: Option<Triple>,
+    /// Client-chosen key that makes retries of the same submission safe.
+    #[serde(default)]
+    pub idempotency_key: Option<Uuid>,
+    /// How the answer was given, as far as the client reported it.
+    #[serde(default)]
+    pub provenance: Provenance,
+    /// Time from the server last serving the matchup to receiving this answer.
+    /// Unlike `provenance.decision_ms`, it is not up to the client.
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
     pub created_at: DateTime<Utc>,
+    /// Set when the answer was retracted or revised; superseded answers are kept
+    /// for audit but no longer count.
+    #[serde(default)]
+    pub superseded_at: Option<DateTime<Utc>>,
+    /// The revision that replaced this answer; `None` if it was retracted.
+    #[serde(default)]
+    pub superseded_by: Option<Uuid>,
+}
+
+/// How a submission relates to an answer already stored for its matchup or key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resubmission {
+    /// Same matchup, same ranking: a retry. Return the stored answer.
+    Replay,
+    /// A different ranking, the key was used for another matchup, or the
+    /// stored answer has since been retracted or revised.
+    Conflict,
 }
 
 impl ComparisonResult {
     #[must_use]
-    pub fn new(matchup_id: Uuid, session_id: Uuid, ranked_photo_indices: Vec<u32>) -> Self {
+    pub fn new(matchup_id: Uuid, session_id: Uuid, ranked_photo_indices: Triple) -> Self {
         Self {
             id: Uuid::new_v4(),
             matchup_id,
             session_id,
             ranked_photo_indices,
+            displayed_order: None,
+            idempotency_key: None,
+            provenance: Provenance::default(),
+            latency_ms: None,
             created_at: Utc::now(),
+            superseded_at: None,
+            superseded_by: None,
+        }
+    }
+
+    /// An answer whose ranking is already known to permute its matchup.
+    #[must_use]
+    pub fn from_ranking(matchup_id: Uuid, session_id: Uuid, ranking: &Ranking) -> Self {
+        Self::new(matchup_id, session_id, ranking.ranked())
+    }
+
+    #[must_use]
+    pub const fn with_displayed_order(mut self, displayed_order: Triple) -> Self {
+        self.displayed_order = Some(displayed_order);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_idempotency_key(mut self, key: Uuid) -> Self {
+        self.idempotency_key = Some(key);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Sets [`Self::latency_ms`] from when the matchup was served, if it was.
+    #[must_use]
+    pub fn with_latency_since(mut self, served_at: Option<DateTime<Utc>>) -> Self {
+        self.latency_ms =
+            served_at.and_then(|at| u32::try_from((self.created_at - at).num_milliseconds()).ok());
+        self
+    }
+
+    /// Server-measured latency, or the client's report for answers recorded
+    /// before the server measured it.
+    #[must_use]
+    pub fn decision_latency_ms(&self) -> Option<u32> {
+        self.latency_ms.or(self.provenance.decision_ms)
+    }
+
+    /// Classifies a new submission against this stored answer.
+    #[must_use]
+    pub fn resubmission(&self, matchup_id: Uuid, ranked_photo_indices: Triple) -> Resubmission {
+        if self.is_active()
+            && self.matchup_id == matchup_id
+            && self.ranked_photo_indices == ranked_photo_indices
+        {
+            Resubmission::Replay
+        } else {
+            Resubmission::Conflict
         }
     }
 
+    #[must_use]
+    pub const fn is_active(&self) -> bool {
+        self.superseded_at.is_none()
+    }
+
+    /// A replacement answer for the same matchup, for revising this one.
+    #[must_use]
+    pub fn revision(&self, ranked_photo_indices: Triple) -> Self {
+        Self::new(self.matchup_id, self.session_id, ranked_photo_indices)
+    }
+
+    /// Marks this answer retracted (`by` is `None`) or replaced by revision `by`.
+    pub fn supersede(&mut self, at: DateTime<Utc>, by: Option<Uuid>) {
+        self.superseded_at = Some(at);
+        self.superseded_by = by;
+    }
+
     #[must_use]
     pub fn to_pairwise(&self) -> Vec<(u32, u32)> {
-        let mut pairs = Vec::new();
-        for (i, &winner) in self.ranked_photo_indices.iter().enumerate() {
-            for &loser in &self.ranked_photo_indices[i + 1..] {
-                pairs.push((winner, loser));
-            }
+        pairwise(&self.ranked_photo_indices.indices())
+    }
+
+    /// Like [`Self::to_pairwise`], tagging each pair with which photo was shown first.
+    #[must_use]
+    pub fn to_positioned_pairwise(&self) -> Vec<(u32, u32, PairPosition)> {
+        let displayed = self.displayed_order.map(|order| order.indices());
+        positioned_pairwise(
+            &self.ranked_photo_indices.indices(),
+            displayed.as_ref().map(<[u32; 3]>::as_slice),
+        )
+    }
+}
+
+/// Every (winner, loser) pair implied by a ranking of any size, best first.
+#[must_use]
+pub fn pairwise(ranked: &[u32]) -> Vec<(u32, u32)> {
+    let mut pairs = Vec::new();
+    for (i, &winner) in ranked.iter().enumerate() {
+        for &loser in &ranked[i + 1..] {
+            pairs.push((winner, loser));
         }
-        pairs
     }
+    pairs
+}
+
+/// Like [`pairwise`], tagging each pair with which photo `displayed` showed first.
+#[must_use]
+pub fn positioned_pairwise(
+    ranked: &[u32],
+    displayed: Option<&[u32]>,
+) -> Vec<(u32, u32, PairPosition)> {
+    let position =
+        |idx: u32| displayed.and_then(|order| order.iter().position(|&shown| shown == idx));
+    pairwise(ranked)
+        .into_iter()
+        .map(|(winner, loser)| {
+            let tag = match (position(winner), position(loser)) {
+                (Some(w), Some(l)) if w < l => PairPosition::WinnerFirst,
+                (Some(_), Some(_)) => PairPosition::LoserFirst,
+                _ => PairPosition::Unknown,
+            };
+            (winner, loser, tag)
+        })
+        .collect()
+}
+
+/// Which side of a pairwise outcome was displayed earlier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PairPosition {
+    WinnerFirst,
+    LoserFirst,
+    Unknown,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -111,15 +531,121 @@ impl PhotoRating {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::triple;
+
+    #[test]
+    fn resubmission_replays_only_identical_active_answers() {
+        let stored = ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([3, 1, 2]));
+
+        assert_eq!(
+            stored.resubmission(stored.matchup_id, triple([3, 1, 2])),
+            Resubmission::Replay
+        );
+        assert_eq!(
+            stored.resubmission(stored.matchup_id, triple([1, 3, 2])),
+            Resubmission::Conflict
+        );
+        assert_eq!(
+            stored.resubmission(Uuid::new_v4(), triple([3, 1, 2])),
+            Resubmission::Conflict
+        );
+
+        let mut retracted = stored.clone();
+        retracted.supersede(Utc::now(), None);
+        assert_eq!(
+            retracted.resubmission(stored.matchup_id, triple([3, 1, 2])),
+            Resubmission::Conflict
+        );
+    }
 
     #[test]
     fn comparison_result_to_pairwise() {
         let result = ComparisonResult::new(
             Uuid::new_v4(),
             Uuid::new_v4(),
-            vec![3, 1, 2], // 3 > 1 > 2
+            triple([3, 1, 2]), // 3 > 1 > 2
         );
         let pairs = result.to_pairwise();
         assert_eq!(pairs, vec![(3, 1), (3, 2), (1, 2)]);
     }
+
+    #[test]
+    fn matchup_kind_round_trips_storage_parts() {
+        let original = Uuid::new_v4();
+        for kind in [
+            MatchupKind::Regular,
+            MatchupKind::Repeat { original },
+            MatchupKind::Calibration,
+        ] {
+            assert_eq!(
+                MatchupKind::from_parts(kind.as_str(), kind.repeat_of()),
+                Some(kind)
+            );
+        }
+        assert_eq!(MatchupKind::from_parts("repeat", None), None);
+    }
+
+    #[test]
+    fn positioned_pairwise_uses_displayed_order() {
+        let result = ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([3, 1, 2]))
+            .with_displayed_order(triple([1, 2, 3]));
+        let pairs = result.to_positioned_pairwise();
+        assert_eq!(
+            pairs,
+            vec![
+                (3, 1, PairPosition::LoserFirst),
+                (3, 2, PairPosition::LoserFirst),
+                (1, 2, PairPosition::WinnerFirst),
+            ]
+        );
+
+        let unordered = ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([3, 1, 2]));
+        assert!(unordered
+            .to_positioned_pairwise()
+            .iter()
+            .all(|&(_, _, position)| position == PairPosition::Unknown));
+        assert_eq!(
+            positioned_pairwise(&[3, 1], Some(&[1, 3])),
+            vec![(3, 1, PairPosition::LoserFirst)]
+        );
+    }
+
+    #[test]
+    fn campaign_status_round_trips() {
+        for status in [
+            CampaignStatus::Active,
+            CampaignStatus::Closed,
+            CampaignStatus::Reopened,
+        ] {
+            assert_eq!(status.as_str().parse::<CampaignStatus>(), Ok(status));
+        }
+        assert!("archived".parse::<CampaignStatus>().is_err());
+
+        let campaign = Campaign::new("c1".into(), "Roll 3".into(), "hash".into());
+        let json = serde_json::to_value(&campaign).unwrap();
+        assert_eq!(json["status"], "active");
+    }
+
+    #[test]
+    fn campaign_lifecycle_transitions() {
+        let mut campaign = Campaign::new("c1".into(), "Roll 3".into(), "hash".into());
+        let at = Utc::now();
+
+        assert_eq!(campaign.reopen(at), Err(CampaignError::NotClosed));
+        campaign.mark_threshold_reached(at).unwrap();
+        assert_eq!(
+            campaign.mark_threshold_reached(at),
+            Err(CampaignError::ThresholdAlreadyReached)
+        );
+
+        campaign.close(at).unwrap();
+        assert_eq!(campaign.closed_at, Some(at));
+        assert_eq!(campaign.ensure_accepting(), Err(CampaignError::Closed));
+        assert_eq!(campaign.close(at), Err(CampaignError::Closed));
+
+        campaign.reopen(at).unwrap();
+        assert_eq!(campaign.status, CampaignStatus::Reopened);
+        assert_eq!(campaign.reopened_at, Some(at));
+        assert_eq!(campaign.ensure_accepting(), Ok(()));
+    }
 }
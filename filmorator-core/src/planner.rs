@@ -0,0 +1,409 @@
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::{self, Participant, RatingModel, SimulationConfig, Strategy};
+
+/// What a campaign owner wants out of a campaign, plus assumptions about the crowd.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanRequest {
+    pub num_photos: u32,
+    pub matchup_size: usize,
+    /// Size of the top set the owner cares about, e.g. 10 for "my top 10".
+    pub top_k: usize,
+    /// Top-k recall to reach: the share of the true top `top_k` photos that
+    /// end up in the estimated top `top_k`.
+    pub recall: f64,
+    /// Share of simulated campaigns that must reach `recall`.
+    pub confidence: f64,
+    /// Matchups a typical participant answers before leaving.
+    pub comparisons_per_participant: u32,
+    /// Per-judgment noise of participants; see [`Participant::thurstonian`].
+    pub noise: f64,
+    /// Standard deviation of the true log-strengths of the photos.
+    pub strength_spread: f64,
+    pub strategy: Strategy,
+    pub model: RatingModel,
+    /// Simulated campaigns to average over.
+    pub runs: u32,
+    /// Give up beyond this many comparisons; defaults to a multiple of the lower bound.
+    pub max_comparisons: Option<u32>,
+    pub seed: u64,
+}
+
+impl Default for PlanRequest {
+    fn default() -> Self {
+        Self {
+            num_photos: 30,
+            matchup_size: 3,
+            top_k: 10,
+            recall: 0.9,
+            confidence: 0.8,
+            comparisons_per_participant: 20,
+            noise: 1.0,
+            strength_spread: 1.0,
+            strategy: Strategy::Balanced,
+            model: RatingModel::PositionBiased,
+            runs: 8,
+            max_comparisons: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Top-k recall across runs after a given number of comparisons.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecallPoint {
+    pub comparisons: u32,
+    /// Mean over runs.
+    pub top_k_recall: f64,
+    /// Share of runs whose recall reached the requested `recall`.
+    pub reached: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    /// Comparisons a noiseless crowd would need to fully sort the photos.
+    pub lower_bound_comparisons: u32,
+    /// Comparisons a noiseless crowd would need to reach the requested recall
+    /// with the requested confidence; see [`target_lower_bound_comparisons`].
+    pub target_lower_bound_comparisons: u32,
+    /// Estimated comparisons for a `confidence` share of runs to reach the
+    /// target recall; `None` if they did not within `searched_up_to`.
+    pub comparisons: Option<u32>,
+    /// Participants needed to supply `comparisons`.
+    pub participants: Option<u32>,
+    pub searched_up_to: u32,
+    pub recall_curve: Vec<RecallPoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+pub enum PlanError {
+    #[error("matchup size must be at least 2, got {0}")]
+    MatchupSize(usize),
+    #[error("{num_photos} photos are too few for matchups of {matchup_size}")]
+    TooFewPhotos {
+        num_photos: u32,
+        matchup_size: usize,
+    },
+    #[error("top-k must be between 1 and the {num_photos} photos, got {top_k}")]
+    TopK { top_k: usize, num_photos: u32 },
+    #[error("{name} must be above 0 and at most 1, got {value}")]
+    NotAShare { name: &'static str, value: f64 },
+    #[error("at least one run is needed")]
+    NoRuns,
+}
+
+/// Multiple of the lower bound searched when no `max_comparisons` is given.
+const DEFAULT_SEARCH_FACTOR: u32 = 20;
+
+/// Checkpoints per simulated run; keeps refits affordable for large campaigns.
+const CHECKPOINTS_PER_RUN: u32 = 40;
+
+/// Estimates the comparisons and participants a campaign needs.
+///
+/// Starts from the information-theoretic bound — sorting `n` photos takes
+/// `log2(n!)` bits and a ranked matchup of size `m` yields at most `log2(m!)` —
+/// then runs seeded simulations with noisy participants up to a budget and
+/// reports the first checkpoint where at least a `confidence` share of the runs
+/// have reached top-k `recall`.
+///
+/// # Errors
+///
+/// Refuses a matchup size below two, fewer photos than `matchup_size`, a
+/// `top_k` of zero or above `num_photos`, a `recall` or `confidence` outside
+/// `(0, 1]`, and zero `runs`.
+pub fn plan(request: &PlanRequest) -> Result<Plan, PlanError> {
+    validate(request)?;
+
+    let lower_bound = lower_bound_comparisons(request.num_photos, request.matchup_size);
+    let budget = request
+        .max_comparisons
+        .unwrap_or_else(|| lower_bound.saturating_mul(DEFAULT_SEARCH_FACTOR))
+        .max(1);
+    let refit_every = (budget / CHECKPOINTS_PER_RUN).max(5);
+
+    // Per checkpoint: comparisons, summed recall and runs that reached the target
+    let mut totals: Vec<(u32, f64, u32)> = Vec::new();
+    for run in 0..request.runs {
+        let report = simulation::run(&SimulationConfig {
+            num_photos: request.num_photos,
+            matchup_size: request.matchup_size,
+            budget,
+            strategy: request.strategy,
+            model: request.model,
+            participants: vec![Participant::thurstonian(request.noise)],
+            strength_spread: request.strength_spread,
+            refit_every,
+            top_k: request.top_k,
+            seed: request.seed.wrapping_add(u64::from(run)),
+            ..SimulationConfig::default()
+        })
+        // With a participant and a non-zero refit interval, too few photos is
+        // the only thing the simulation refuses, and validation caught that.
+        .ok_or(PlanError::TooFewPhotos {
+            num_photos: request.num_photos,
+            matchup_size: request.matchup_size,
+        })?;
+
+        // Runs share checkpoint positions; a run that stopped early keeps its last recall.
+        let reached = |recall: f64| u32::from(recall >= request.recall);
+        for (i, checkpoint) in report.checkpoints.iter().enumerate() {
+            let recall = checkpoint.top_k_recall;
+            match totals.get_mut(i) {
+                Some((_, sum, hits)) => {
+                    *sum += recall;
+                    *hits += reached(recall);
+                }
+                None => totals.push((checkpoint.comparisons, recall, reached(recall))),
+            }
+        }
+        for (_, sum, hits) in totals.iter_mut().skip(report.checkpoints.len()) {
+            *sum += report.top_k_recall;
+            *hits += reached(report.top_k_recall);
+        }
+    }
+
+    let runs = f64::from(request.runs);
+    let recall_curve: Vec<RecallPoint> = totals
+        .into_iter()
+        .map(|(comparisons, sum, hits)| RecallPoint {
+            comparisons,
+            top_k_recall: sum / runs,
+            reached: f64::from(hits) / runs,
+        })
+        .collect();
+
+    let comparisons = recall_curve
+        .iter()
+        .find(|p| p.reached >= request.confidence)
+        .map(|p| p.comparisons);
+    let participants = comparisons.map(|c| c.div_ceil(request.comparisons_per_participant.max(1)));
+
+    Ok(Plan {
+        lower_bound_comparisons: lower_bound,
+        target_lower_bound_comparisons: target_lower_bound_comparisons(request),
+        comparisons,
+        participants,
+        searched_up_to: budget,
+        recall_curve,
+    })
+}
+
+fn validate(request: &PlanRequest) -> Result<(), PlanError> {
+    if request.matchup_size < 2 {
+        return Err(PlanError::MatchupSize(request.matchup_size));
+    }
+    if (request.num_photos as usize) < request.matchup_size {
+        return Err(PlanError::TooFewPhotos {
+            num_photos: request.num_photos,
+            matchup_size: request.matchup_size,
+        });
+    }
+    if request.top_k == 0 || request.top_k > request.num_photos as usize {
+        return Err(PlanError::TopK {
+            top_k: request.top_k,
+            num_photos: request.num_photos,
+        });
+    }
+    for (name, value) in [
+        ("recall", request.recall),
+        ("confidence", request.confidence),
+    ] {
+        // Written so NaN fails too
+        if !(value > 0.0 && value <= 1.0) {
+            return Err(PlanError::NotAShare { name, value });
+        }
+    }
+    if request.runs == 0 {
+        return Err(PlanError::NoRuns);
+    }
+    Ok(())
+}
+
+/// Matchups a noiseless crowd needs before a `confidence` share of campaigns,
+/// with the true top `top_k` drawn uniformly, can reach top-k `recall`.
+///
+/// An estimated top set reaches the recall for every true top set it shares at
+/// least `ceil(recall * top_k)` photos with, so `b` bits of answers pick among
+/// `2^b` estimates and succeed with probability at most
+/// `2^b * covered / C(n, k)`. Each ranked matchup of size `m` yields at most
+/// `log2(m!)` bits.
+#[must_use]
+pub fn target_lower_bound_comparisons(request: &PlanRequest) -> u32 {
+    let n = request.num_photos;
+    let k = u32::try_from(request.top_k).unwrap_or(u32::MAX).min(n);
+    let per_matchup = log2_factorial(u32::try_from(request.matchup_size).unwrap_or(u32::MAX));
+    if per_matchup <= 0.0 || k == 0 {
+        return 0;
+    }
+    // Recall is a share of `k` photos; rounding guards against 0.28 * 25 = 7.000000000000001
+    let shared = (request.recall * f64::from(k) - 1e-9)
+        .ceil()
+        .clamp(0.0, f64::from(k));
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let shared = shared as u32;
+    let covered: f64 = (shared..=k)
+        .map(|i| (log2_binomial(k, i) + log2_binomial(n - k, k - i)).exp2())
+        .sum();
+    let bits = request.confidence.log2() + log2_binomial(n, k) - covered.log2();
+    // Saturating float-to-int cast; the bound is non-negative.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let bound = (bits.max(0.0) / per_matchup).ceil() as u32;
+    bound
+}
+
+fn log2_factorial(n: u32) -> f64 {
+    (2..=n).map(|k| f64::from(k).log2()).sum()
+}
+
+fn log2_binomial(n: u32, k: u32) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    log2_factorial(n) - log2_factorial(k) - log2_factorial(n - k)
+}
+
+/// `ceil(log2(n!) / log2(m!))`: ranked matchups of size `m` needed to sort `n`
+/// photos if every participant answered perfectly.
+#[must_use]
+pub fn lower_bound_comparisons(num_photos: u32, matchup_size: usize) -> u32 {
+    let per_matchup = log2_factorial(u32::try_from(matchup_size).unwrap_or(u32::MAX));
+    if per_matchup <= 0.0 {
+        return 0;
+    }
+    let bound = (log2_factorial(num_photos) / per_matchup).ceil();
+    // Saturating float-to-int cast; the bound is non-negative.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let bound = bound as u32;
+    bound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_bound_matches_information_bound() {
+        // log2(3!) = log2(6) bits per triple; 3 photos need one triple.
+        assert_eq!(lower_bound_comparisons(3, 3), 1);
+        // log2(10!) ≈ 21.8 bits, log2(6) ≈ 2.58 bits per triple.
+        assert_eq!(lower_bound_comparisons(10, 3), 9);
+        assert_eq!(lower_bound_comparisons(10, 1), 0);
+    }
+
+    #[test]
+    fn target_bound_counts_the_top_sets_an_estimate_covers() {
+        let request = |top_k, recall, confidence| PlanRequest {
+            num_photos: 12,
+            matchup_size: 3,
+            top_k,
+            recall,
+            confidence,
+            ..PlanRequest::default()
+        };
+        // Exact top 3 of 12: log2(C(12, 3)) = log2(220) ≈ 7.78 bits, over 3 triples
+        assert_eq!(target_lower_bound_comparisons(&request(3, 1.0, 1.0)), 4);
+        // Two of the three cover 28 top sets: log2(220 / 28) ≈ 2.97 bits
+        assert_eq!(target_lower_bound_comparisons(&request(3, 0.6, 1.0)), 2);
+        // Being right in half the campaigns halves the sets to tell apart
+        assert_eq!(target_lower_bound_comparisons(&request(3, 0.6, 0.5)), 1);
+        // The whole set is always found
+        assert_eq!(target_lower_bound_comparisons(&request(12, 1.0, 1.0)), 0);
+        // Ranking every photo is only needed to sort them
+        assert!(
+            target_lower_bound_comparisons(&request(6, 1.0, 1.0)) < lower_bound_comparisons(12, 3)
+        );
+    }
+
+    #[test]
+    fn plan_reaches_easy_target() {
+        let request = PlanRequest {
+            num_photos: 12,
+            top_k: 3,
+            recall: 0.6,
+            confidence: 0.75,
+            noise: 0.3,
+            strength_spread: 2.0,
+            comparisons_per_participant: 10,
+            runs: 4,
+            ..PlanRequest::default()
+        };
+        let plan = plan(&request).unwrap();
+        let comparisons = plan.comparisons.expect("target should be reachable");
+
+        assert_eq!(
+            plan.target_lower_bound_comparisons,
+            target_lower_bound_comparisons(&request)
+        );
+        assert!(comparisons >= plan.target_lower_bound_comparisons);
+        assert!(comparisons <= plan.searched_up_to);
+        assert_eq!(plan.participants, Some(comparisons.div_ceil(10)));
+
+        // The first point where three of the four runs reached the recall
+        let (before, from): (Vec<&RecallPoint>, Vec<&RecallPoint>) = plan
+            .recall_curve
+            .iter()
+            .partition(|p| p.comparisons < comparisons);
+        assert!(before.iter().all(|p| p.reached < 0.75));
+        assert!(from[0].reached >= 0.75);
+    }
+
+    #[test]
+    fn plan_reports_unreachable_target() {
+        let request = PlanRequest {
+            num_photos: 20,
+            recall: 1.0,
+            noise: 50.0,
+            runs: 2,
+            max_comparisons: Some(20),
+            ..PlanRequest::default()
+        };
+        let plan = plan(&request).unwrap();
+        assert_eq!(plan.comparisons, None);
+        assert_eq!(plan.participants, None);
+        assert_eq!(plan.searched_up_to, 20);
+    }
+
+    #[test]
+    fn plan_rejects_degenerate_requests() {
+        let request = PlanRequest::default();
+        let refused = |request: PlanRequest| plan(&request).unwrap_err();
+
+        assert!(matches!(
+            refused(PlanRequest {
+                num_photos: 2,
+                ..request.clone()
+            }),
+            PlanError::TooFewPhotos { .. }
+        ));
+        assert!(matches!(
+            refused(PlanRequest {
+                top_k: 31,
+                ..request.clone()
+            }),
+            PlanError::TopK { top_k: 31, .. }
+        ));
+        for recall in [0.0, 1.5, -0.1, f64::NAN] {
+            assert!(matches!(
+                refused(PlanRequest {
+                    recall,
+                    ..request.clone()
+                }),
+                PlanError::NotAShare { name: "recall", .. }
+            ));
+        }
+        assert!(matches!(
+            refused(PlanRequest {
+                confidence: f64::NAN,
+                ..request.clone()
+            }),
+            PlanError::NotAShare {
+                name: "confidence",
+                ..
+            }
+        ));
+        assert_eq!(
+            refused(PlanRequest { runs: 0, ..request }),
+            PlanError::NoRuns
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::groups::GroupMode;
+use crate::matchup::{completion_percent, generate_seed_matchups_with_rng, normalize_pair};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PoolError {
+    #[error("matchup size must be at least 2, got {0}")]
+    InvalidMatchupSize(usize),
+    #[error("matchup {matchup} has {found} photos, expected {expected}")]
+    WrongSize {
+        matchup: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("matchup {matchup} refers to photo {photo_idx}, but the campaign has {num_photos}")]
+    IndexOutOfRange {
+        matchup: usize,
+        photo_idx: u32,
+        num_photos: u32,
+    },
+    #[error("matchup {matchup} contains photo {photo_idx} more than once")]
+    DuplicatePhoto { matchup: usize, photo_idx: u32 },
+    #[error("stored coverage stats do not match the matchups")]
+    CoverageMismatch,
+}
+
+/// Algorithm that produced a [`MatchupPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum PoolAlgorithm {
+    /// [`crate::matchup::generate_seed_matchups`].
+    Seed,
+    /// [`crate::matchup::generate_balanced_seed_matchups`].
+    BalancedSeed,
+    /// [`crate::matchup::generate_grouped_seed_matchups`].
+    GroupedSeed { mode: GroupMode },
+    /// Supplied by hand or by an external tool.
+    Custom,
+}
+
+/// Pair coverage of a pool, for judging it before any comparisons come in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolCoverage {
+    /// Distinct photo pairs that share at least one matchup.
+    pub distinct_pairs: u64,
+    pub pair_percent: u8,
+    /// Fewest and most matchups any single photo appears in.
+    pub min_appearances: u32,
+    pub max_appearances: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolGeneration {
+    pub algorithm: PoolAlgorithm,
+    /// RNG seed, when the pool was generated reproducibly.
+    pub seed: Option<u64>,
+    pub generated_at: DateTime<Utc>,
+    pub coverage: PoolCoverage,
+}
+
+/// Validated set of matchups for a campaign, as stored in its manifest.
+///
+/// Every matchup has exactly `matchup_size` distinct photos, all below
+/// `num_photos`. Deserializing re-runs the validation and rejects coverage
+/// stats that don't match the matchups.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawMatchupPool")]
+pub struct MatchupPool {
+    num_photos: u32,
+    matchup_size: usize,
+    matchups: Vec<Vec<u32>>,
+    generation: PoolGeneration,
+}
+
+#[derive(Deserialize)]
+struct RawMatchupPool {
+    num_photos: u32,
+    matchup_size: usize,
+    matchups: Vec<Vec<u32>>,
+    generation: PoolGeneration,
+}
+
+impl TryFrom<RawMatchupPool> for MatchupPool {
+    type Error = PoolError;
+
+    fn try_from(raw: RawMatchupPool) -> Result<Self, Self::Error> {
+        validate(raw.num_photos, raw.matchup_size, &raw.matchups)?;
+        if coverage(raw.num_photos, &raw.matchups) != raw.generation.coverage {
+            return Err(PoolError::CoverageMismatch);
+        }
+        Ok(Self {
+            num_photos: raw.num_photos,
+            matchup_size: raw.matchup_size,
+            matchups: raw.matchups,
+            generation: raw.generation,
+        })
+    }
+}
+
+impl MatchupPool {
+    /// Validates `matchups` and records how they were generated.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PoolError`] describing the first invalid matchup.
+    pub fn new(
+        num_photos: u32,
+        matchup_size: usize,
+        matchups: Vec<Vec<u32>>,
+        algorithm: PoolAlgorithm,
+        seed: Option<u64>,
+    ) -> Result<Self, PoolError> {
+        validate(num_photos, matchup_size, &matchups)?;
+        let coverage = coverage(num_photos, &matchups);
+        Ok(Self {
+            num_photos,
+            matchup_size,
+            matchups,
+            generation: PoolGeneration {
+                algorithm,
+                seed,
+                generated_at: Utc::now(),
+                coverage,
+            },
+        })
+    }
+
+    /// Reproducible seed pool: the same arguments always give the same matchups.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PoolError::InvalidMatchupSize`] for a matchup size below two.
+    pub fn generate_seed(
+        num_photos: u32,
+        matchup_size: usize,
+        seed: u64,
+    ) -> Result<Self, PoolError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let matchups = generate_seed_matchups_with_rng(num_photos, matchup_size, &mut rng);
+        Self::new(
+            num_photos,
+            matchup_size,
+            matchups,
+            PoolAlgorithm::Seed,
+            Some(seed),
+        )
+    }
+
+    /// Backdates the generation record, for pools recovered from older formats.
+    #[must_use]
+    pub(crate) const fn with_generated_at(mut self, generated_at: DateTime<Utc>) -> Self {
+        self.generation.generated_at = generated_at;
+        self
+    }
+
+    #[must_use]
+    pub const fn num_photos(&self) -> u32 {
+        self.num_photos
+    }
+
+    #[must_use]
+    pub const fn matchup_size(&self) -> usize {
+        self.matchup_size
+    }
+
+    #[must_use]
+    pub fn matchups(&self) -> &[Vec<u32>] {
+        &self.matchups
+    }
+
+    #[must_use]
+    pub fn into_matchups(self) -> Vec<Vec<u32>> {
+        self.matchups
+    }
+
+    #[must_use]
+    pub const fn generation(&self) -> &PoolGeneration {
+        &self.generation
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.matchups.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.matchups.is_empty()
+    }
+}
+
+fn validate(num_photos: u32, matchup_size: usize, matchups: &[Vec<u32>]) -> Result<(), PoolError> {
+    if matchup_size < 2 {
+        return Err(PoolError::InvalidMatchupSize(matchup_size));
+    }
+    for (i, matchup) in matchups.iter().enumerate() {
+        if matchup.len() != matchup_size {
+            return Err(PoolError::WrongSize {
+                matchup: i,
+                expected: matchup_size,
+                found: matchup.len(),
+            });
+        }
+        let mut seen = HashSet::with_capacity(matchup_size);
+        for &photo_idx in matchup {
+            if photo_idx >= num_photos {
+                return Err(PoolError::IndexOutOfRange {
+                    matchup: i,
+                    photo_idx,
+                    num_photos,
+                });
+            }
+            if !seen.insert(photo_idx) {
+                return Err(PoolError::DuplicatePhoto {
+                    matchup: i,
+                    photo_idx,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn coverage(num_photos: u32, matchups: &[Vec<u32>]) -> PoolCoverage {
+    let mut pairs = HashSet::new();
+    let mut appearances = vec![0u32; num_photos as usize];
+    for matchup in matchups {
+        for (i, &a) in matchup.iter().enumerate() {
+            if let Some(count) = appearances.get_mut(a as usize) {
+                *count += 1;
+            }
+            for &b in &matchup[i + 1..] {
+                pairs.insert(normalize_pair(a, b));
+            }
+        }
+    }
+    let distinct_pairs = pairs.len() as u64;
+    PoolCoverage {
+        distinct_pairs,
+        pair_percent: completion_percent(distinct_pairs, num_photos),
+        min_appearances: appearances.iter().copied().min().unwrap_or(0),
+        max_appearances: appearances.iter().copied().max().unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_matchups() {
+        let new = |matchups| MatchupPool::new(5, 3, matchups, PoolAlgorithm::Custom, None);
+
+        assert_eq!(
+            new(vec![vec![0, 1]]),
+            Err(PoolError::WrongSize {
+                matchup: 0,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            new(vec![vec![0, 1, 2], vec![3, 4, 5]]),
+            Err(PoolError::IndexOutOfRange {
+                matchup: 1,
+                photo_idx: 5,
+                num_photos: 5
+            })
+        );
+        assert_eq!(
+            new(vec![vec![0, 2, 2]]),
+            Err(PoolError::DuplicatePhoto {
+                matchup: 0,
+                photo_idx: 2
+            })
+        );
+    }
+
+    #[test]
+    fn seeded_generation_is_reproducible() {
+        let a = MatchupPool::generate_seed(12, 3, 7).unwrap();
+        let b = MatchupPool::generate_seed(12, 3, 7).unwrap();
+
+        assert_eq!(a.matchups(), b.matchups());
+        assert_eq!(a.generation().seed, Some(7));
+        assert!(a.generation().coverage.min_appearances > 0);
+    }
+
+    #[test]
+    fn serde_round_trip_revalidates() {
+        let pool = MatchupPool::generate_seed(9, 3, 1).unwrap();
+        let json = serde_json::to_string(&pool).unwrap();
+        assert_eq!(serde_json::from_str::<MatchupPool>(&json).unwrap(), pool);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["matchups"][0][1] = value["matchups"][0][0].clone();
+        assert!(serde_json::from_value::<MatchupPool>(value).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::models::ComparisonResult;
+
+/// Default `completion_target`: each pool matchup answered three times on average.
+pub const DEFAULT_COMPLETION_TARGET: u32 = 3;
+
+const CONNECTIVITY_MAX_ITERATIONS: u32 = 1000;
+const CONNECTIVITY_TOLERANCE: f64 = 1e-9;
+
+/// Size of the matchup pool [`crate::matchup::generate_seed_matchups`] produces.
+#[must_use]
+pub const fn seed_pool_size(num_photos: u32, matchup_size: usize) -> usize {
+    if matchup_size == 0 || (num_photos as usize) < matchup_size {
+        return 0;
+    }
+    let rounds = (u32::BITS - (num_photos - 1).leading_zeros()) as usize + 1;
+    rounds * (num_photos as usize / matchup_size)
+}
+
+/// Comparisons needed for a campaign to count as complete:
+/// `matchup_pool.len() * completion_target`.
+#[must_use]
+pub fn target_comparisons(pool_size: usize, completion_target: u32) -> u32 {
+    u32::try_from(pool_size)
+        .unwrap_or(u32::MAX)
+        .saturating_mul(completion_target)
+}
+
+/// Progress measures based on the comparison graph rather than raw pair coverage.
+///
+/// Photos are nodes and every pair ranked against each other is an edge.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GraphProgress {
+    pub comparisons: u32,
+    pub target_comparisons: u32,
+    /// Mean number of answered matchups each photo appeared in.
+    pub comparisons_per_photo: f64,
+    pub target_per_photo: f64,
+    /// `comparisons` against `target_comparisons`, capped at 100.
+    pub target_percent: u8,
+    /// Photos that appeared in at least one answered matchup.
+    pub photos_compared: u32,
+    pub components: u32,
+    /// Second-smallest eigenvalue of the graph Laplacian: zero while the graph is
+    /// disconnected, `num_photos` for a complete graph.
+    pub algebraic_connectivity: f64,
+    /// Mean shortest path, in comparisons, between photos that are connected.
+    pub average_path_length: Option<f64>,
+    /// Every photo compared, the graph connected and the target met.
+    pub threshold_reached: bool,
+}
+
+/// Computes [`GraphProgress`] for answered comparisons among `num_photos` photos.
+#[must_use]
+pub fn graph_progress(
+    num_photos: u32,
+    results: &[ComparisonResult],
+    target_comparisons: u32,
+) -> GraphProgress {
+    let graph = ComparisonGraph::new(num_photos as usize, results);
+    let comparisons = u32::try_from(results.len()).unwrap_or(u32::MAX);
+    let per_matchup = results
+        .first()
+        .map_or(0, |r| r.ranked_photo_indices.indices().len());
+    let appearances = results.len() * per_matchup;
+
+    #[allow(clippy::cast_precision_loss)]
+    let (comparisons_per_photo, target_per_photo) = if num_photos == 0 {
+        (0.0, 0.0)
+    } else {
+        let n = f64::from(num_photos);
+        (
+            appearances as f64 / n,
+            f64::from(target_comparisons) * per_matchup as f64 / n,
+        )
+    };
+
+    let target_percent = if target_comparisons == 0 {
+        100
+    } else {
+        let percent = u64::from(comparisons) * 100 / u64::from(target_comparisons);
+        u8::try_from(percent.min(100)).unwrap_or(100)
+    };
+
+    let photos_compared = u32::try_from(graph.photos_compared()).unwrap_or(u32::MAX);
+    let components = u32::try_from(graph.components()).unwrap_or(u32::MAX);
+
+    GraphProgress {
+        comparisons,
+        target_comparisons,
+        comparisons_per_photo,
+        target_per_photo,
+        target_percent,
+        photos_compared,
+        components,
+        algebraic_connectivity: graph.algebraic_connectivity(components),
+        average_path_length: graph.average_path_length(),
+        threshold_reached: num_photos > 0
+            && photos_compared == num_photos
+            && components == 1
+            && comparisons >= target_comparisons,
+    }
+}
+
+/// Undirected graph of photos joined by at least one pairwise comparison.
+struct ComparisonGraph {
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl ComparisonGraph {
+    fn new(num_photos: usize, results: &[ComparisonResult]) -> Self {
+        let mut adjacency = vec![Vec::new(); num_photos];
+        for (a, b) in results.iter().flat_map(ComparisonResult::to_pairwise) {
+            let (a, b) = (a as usize, b as usize);
+            if a != b && a < num_photos && b < num_photos {
+                adjacency[a].push(b);
+                adjacency[b].push(a);
+            }
+        }
+        for neighbours in &mut adjacency {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+        Self { adjacency }
+    }
+
+    fn photos_compared(&self) -> usize {
+        self.adjacency.iter().filter(|n| !n.is_empty()).count()
+    }
+
+    fn components(&self) -> usize {
+        let mut seen = vec![false; self.adjacency.len()];
+        let mut components = 0;
+        for start in 0..self.adjacency.len() {
+            if seen[start] {
+                continue;
+            }
+            components += 1;
+            seen[start] = true;
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                for &next in &self.adjacency[node] {
+                    if !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        components
+    }
+
+    /// Power iteration on `c·I - L` restricted to vectors orthogonal to the
+    /// all-ones eigenvector, whose dominant eigenvalue is `c - λ₂`.
+    fn algebraic_connectivity(&self, components: u32) -> f64 {
+        let n = self.adjacency.len();
+        if n < 2 || components != 1 {
+            return 0.0;
+        }
+
+        let max_degree = self.adjacency.iter().map(Vec::len).max().unwrap_or(0);
+        #[allow(clippy::cast_precision_loss)]
+        let shift = 2.0 * max_degree as f64;
+
+        // Deterministic, irregular start so it isn't orthogonal to the Fiedler vector.
+        #[allow(clippy::cast_precision_loss)]
+        let mut x: Vec<f64> = (0..n)
+            .map(|i| (i.wrapping_mul(2_654_435_761) % 1_000) as f64)
+            .collect();
+        let mut estimate = 0.0;
+
+        for _ in 0..CONNECTIVITY_MAX_ITERATIONS {
+            center_and_normalize(&mut x);
+            let y: Vec<f64> = self
+                .adjacency
+                .iter()
+                .enumerate()
+                .map(|(i, neighbours)| {
+                    #[allow(clippy::cast_precision_loss)]
+                    let degree = neighbours.len() as f64;
+                    let laplacian = degree * x[i] - neighbours.iter().map(|&j| x[j]).sum::<f64>();
+                    shift * x[i] - laplacian
+                })
+                .collect();
+            let rayleigh: f64 = x.iter().zip(&y).map(|(a, b)| a * b).sum();
+            let converged = (rayleigh - estimate).abs() < CONNECTIVITY_TOLERANCE;
+            estimate = rayleigh;
+            x = y;
+            if converged {
+                break;
+            }
+        }
+
+        (shift - estimate).max(0.0)
+    }
+
+    fn average_path_length(&self) -> Option<f64> {
+        let n = self.adjacency.len();
+        let mut total: u64 = 0;
+        let mut pairs: u64 = 0;
+        let mut distance = vec![u32::MAX; n];
+        let mut queue = VecDeque::new();
+
+        for start in 0..n {
+            distance.fill(u32::MAX);
+            distance[start] = 0;
+            queue.push_back(start);
+            while let Some(node) = queue.pop_front() {
+                for &next in &self.adjacency[node] {
+                    if distance[next] == u32::MAX {
+                        distance[next] = distance[node] + 1;
+                        total += u64::from(distance[next]);
+                        pairs += 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        (pairs > 0).then(|| total as f64 / pairs as f64)
+    }
+}
+
+fn center_and_normalize(x: &mut [f64]) {
+    #[allow(clippy::cast_precision_loss)]
+    let mean = x.iter().sum::<f64>() / x.len() as f64;
+    for v in x.iter_mut() {
+        *v -= mean;
+    }
+    let norm = x.iter().map(|v| v * v).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        for v in x.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{triple, Triple};
+    use uuid::Uuid;
+
+    fn result(ranked: Triple) -> ComparisonResult {
+        ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), ranked)
+    }
+
+    #[test]
+    fn seed_pool_size_matches_generator() {
+        let generated = crate::matchup::generate_seed_matchups(10, 3).len();
+        assert_eq!(seed_pool_size(10, 3), generated);
+        assert_eq!(seed_pool_size(2, 3), 0);
+    }
+
+    #[test]
+    fn disconnected_graph_has_zero_connectivity() {
+        let results = vec![result(triple([0, 1, 2])), result(triple([3, 4, 5]))];
+        let progress = graph_progress(6, &results, 2);
+
+        assert_eq!(progress.components, 2);
+        assert!(progress.algebraic_connectivity.abs() < f64::EPSILON);
+        assert_eq!(progress.average_path_length, Some(1.0));
+        assert_eq!(progress.target_percent, 100);
+        assert!(!progress.threshold_reached);
+    }
+
+    #[test]
+    fn bowtie_graph_measures() {
+        // Triangles 0-1-2 and 2-3-4 sharing photo 2: λ₂ = 1, mean distance 14/10.
+        let results = vec![result(triple([0, 1, 2])), result(triple([2, 3, 4]))];
+        let progress = graph_progress(5, &results, 6);
+
+        assert!((progress.algebraic_connectivity - 1.0).abs() < 1e-6);
+        assert!((progress.average_path_length.unwrap() - 1.4).abs() < 1e-9);
+        assert!((progress.comparisons_per_photo - 1.2).abs() < 1e-9);
+        assert!((progress.target_per_photo - 3.6).abs() < 1e-9);
+        assert_eq!(progress.target_percent, 33);
+        assert!(!progress.threshold_reached);
+    }
+
+    #[test]
+    fn complete_graph_reaches_threshold() {
+        let results = vec![result(triple([0, 1, 2])), result(triple([2, 0, 1]))];
+        let progress = graph_progress(3, &results, 2);
+
+        assert!((progress.algebraic_connectivity - 3.0).abs() < 1e-6);
+        assert_eq!(progress.photos_compared, 3);
+        assert!(progress.threshold_reached);
+    }
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::models::{ComparisonResult, Session};
+
+/// Answers faster than this are unlikely to reflect looking at three photos.
+pub const HASTY_DECISION_MS: u32 = 1_500;
+
+/// Client-reported context of an answer, for abuse detection and bias analysis.
+///
+/// Every field is optional: older clients report nothing, and clients may
+/// withhold any of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Time from the matchup appearing on screen to the answer being submitted.
+    /// Secondary to the server's own [`ComparisonResult::latency_ms`].
+    #[serde(default)]
+    pub decision_ms: Option<u32>,
+    #[serde(default)]
+    pub viewport: Option<Viewport>,
+    #[serde(default)]
+    pub device_class: Option<DeviceClass>,
+    /// Whether every preview had replaced its thumbnail before the answer.
+    #[serde(default)]
+    pub previews_loaded: Option<bool>,
+    /// Whether the participant zoomed in on any photo.
+    #[serde(default)]
+    pub zoomed: Option<bool>,
+}
+
+impl Provenance {
+    /// Whether the client claims an answer under [`HASTY_DECISION_MS`].
+    #[must_use]
+    pub fn is_hasty(&self) -> bool {
+        self.decision_ms.is_some_and(|ms| ms < HASTY_DECISION_MS)
+    }
+}
+
+/// CSS pixel size of the browser viewport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceClass {
+    Phone,
+    Tablet,
+    Desktop,
+}
+
+impl DeviceClass {
+    /// Rough class from viewport width, for clients that don't report one.
+    #[must_use]
+    pub const fn from_viewport(viewport: Viewport) -> Self {
+        match viewport.width {
+            0..600 => Self::Phone,
+            600..1024 => Self::Tablet,
+            _ => Self::Desktop,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Phone => "phone",
+            Self::Tablet => "tablet",
+            Self::Desktop => "desktop",
+        }
+    }
+}
+
+impl FromStr for DeviceClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "phone" => Ok(Self::Phone),
+            "tablet" => Ok(Self::Tablet),
+            "desktop" => Ok(Self::Desktop),
+            other => Err(format!("unknown device class: {other}")),
+        }
+    }
+}
+
+/// Summary of the provenance of a set of answers.
+///
+/// Shares are over the answers that reported the field in question.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceStats {
+    pub answers: u32,
+    /// Median server-measured latency, from serving the matchup to the answer.
+    pub median_latency_ms: Option<u32>,
+    /// Median client-reported decision time.
+    pub median_decision_ms: Option<u32>,
+    /// Share of timed answers under [`HASTY_DECISION_MS`], by
+    /// [`ComparisonResult::decision_latency_ms`].
+    pub hasty_share: Option<f64>,
+    pub previews_loaded_share: Option<f64>,
+    pub zoomed_share: Option<f64>,
+    /// Answers per device class; answers without one are classed by viewport.
+    pub devices: Vec<DeviceCount>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceCount {
+    pub device_class: DeviceClass,
+    pub answers: u32,
+}
+
+#[must_use]
+pub fn provenance_stats(results: &[ComparisonResult]) -> ProvenanceStats {
+    let provenance: Vec<&Provenance> = results.iter().map(|r| &r.provenance).collect();
+
+    let median_latency_ms = median(results.iter().filter_map(|r| r.latency_ms).collect());
+    let median_decision_ms = median(provenance.iter().filter_map(|p| p.decision_ms).collect());
+    let hasty_share = share(
+        results
+            .iter()
+            .filter_map(ComparisonResult::decision_latency_ms)
+            .map(|ms| ms < HASTY_DECISION_MS),
+    );
+
+    let mut devices: Vec<DeviceCount> = Vec::new();
+    for p in &provenance {
+        let Some(device_class) = p
+            .device_class
+            .or(p.viewport.map(DeviceClass::from_viewport))
+        else {
+            continue;
+        };
+        match devices.iter_mut().find(|d| d.device_class == device_class) {
+            Some(count) => count.answers += 1,
+            None => devices.push(DeviceCount {
+                device_class,
+                answers: 1,
+            }),
+        }
+    }
+
+    ProvenanceStats {
+        answers: u32::try_from(results.len()).unwrap_or(u32::MAX),
+        median_latency_ms,
+        median_decision_ms,
+        hasty_share,
+        previews_loaded_share: share(provenance.iter().filter_map(|p| p.previews_loaded)),
+        zoomed_share: share(provenance.iter().filter_map(|p| p.zoomed)),
+        devices,
+    }
+}
+
+/// Provenance of one session's answers, for spotting sessions answered in bulk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionProvenance {
+    pub session_id: Uuid,
+    /// Whether the owner has left the session out of the campaign's ratings.
+    pub excluded: bool,
+    #[serde(flatten)]
+    pub stats: ProvenanceStats,
+}
+
+/// [`provenance_stats`] for each of `sessions` over its own `results`, oldest
+/// session first. Results of other sessions are ignored.
+#[must_use]
+pub fn session_provenance_stats(
+    sessions: &[Session],
+    results: &[ComparisonResult],
+) -> Vec<SessionProvenance> {
+    sessions
+        .iter()
+        .map(|session| {
+            let own: Vec<ComparisonResult> = results
+                .iter()
+                .filter(|r| r.session_id == session.id)
+                .cloned()
+                .collect();
+            SessionProvenance {
+                session_id: session.id,
+                excluded: session.excluded,
+                stats: provenance_stats(&own),
+            }
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<u32>) -> Option<u32> {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied()
+}
+
+fn share(flags: impl Iterator<Item = bool>) -> Option<f64> {
+    let (hits, total) = flags.fold((0u32, 0u32), |(hits, total), flag| {
+        (hits + u32::from(flag), total + 1)
+    });
+    (total > 0).then(|| f64::from(hits) / f64::from(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::triple;
+    use chrono::{TimeDelta, Utc};
+
+    fn answered(provenance: Provenance) -> ComparisonResult {
+        ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([0, 1, 2]))
+            .with_provenance(provenance)
+    }
+
+    #[test]
+    fn device_class_from_viewport() {
+        let class = |width| DeviceClass::from_viewport(Viewport { width, height: 800 });
+        assert_eq!(class(390), DeviceClass::Phone);
+        assert_eq!(class(800), DeviceClass::Tablet);
+        assert_eq!(class(1440), DeviceClass::Desktop);
+        assert_eq!("tablet".parse::<DeviceClass>(), Ok(DeviceClass::Tablet));
+    }
+
+    #[test]
+    fn stats_cover_reported_fields_only() {
+        let results = vec![
+            answered(Provenance {
+                decision_ms: Some(800),
+                zoomed: Some(true),
+                device_class: Some(DeviceClass::Phone),
+                ..Provenance::default()
+            }),
+            answered(Provenance {
+                decision_ms: Some(4_000),
+                zoomed: Some(false),
+                previews_loaded: Some(true),
+                viewport: Some(Viewport {
+                    width: 1440,
+                    height: 900,
+                }),
+                ..Provenance::default()
+            }),
+            answered(Provenance::default()),
+        ];
+
+        let stats = provenance_stats(&results);
+        assert_eq!(stats.answers, 3);
+        assert_eq!(stats.median_decision_ms, Some(4_000));
+        assert_eq!(stats.hasty_share, Some(0.5));
+        assert_eq!(stats.zoomed_share, Some(0.5));
+        assert_eq!(stats.previews_loaded_share, Some(1.0));
+        assert_eq!(stats.devices.len(), 2);
+        assert!(results[0].provenance.is_hasty());
+    }
+
+    #[test]
+    fn stats_are_split_by_session() {
+        let sessions = [Session::default(), Session::default(), Session::default()];
+        let by = |session: &Session, decision_ms| ComparisonResult {
+            session_id: session.id,
+            ..answered(Provenance {
+                decision_ms: Some(decision_ms),
+                ..Provenance::default()
+            })
+        };
+        let results = [
+            by(&sessions[0], 500),
+            by(&sessions[1], 6_000),
+            by(&sessions[0], 700),
+            answered(Provenance::default()),
+        ];
+
+        let stats = session_provenance_stats(&sessions, &results);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].session_id, sessions[0].id);
+        assert_eq!(stats[0].stats.answers, 2);
+        assert_eq!(stats[0].stats.hasty_share, Some(1.0));
+        assert_eq!(stats[1].stats.median_decision_ms, Some(6_000));
+        assert_eq!(stats[2].stats.answers, 0);
+    }
+
+    #[test]
+    fn server_latency_outranks_the_clients_report() {
+        let served_at = Utc::now();
+        let mut quick_claim = answered(Provenance {
+            decision_ms: Some(400),
+            ..Provenance::default()
+        });
+        quick_claim.created_at = served_at + TimeDelta::milliseconds(3_000);
+        let quick_claim = quick_claim.with_latency_since(Some(served_at));
+        let unserved = answered(Provenance::default()).with_latency_since(None);
+
+        assert_eq!(quick_claim.latency_ms, Some(3_000));
+        assert_eq!(unserved.latency_ms, None);
+        let stats = provenance_stats(&[quick_claim, unserved]);
+        assert_eq!(stats.median_latency_ms, Some(3_000));
+        assert_eq!(stats.median_decision_ms, Some(400));
+        assert_eq!(stats.hasty_share, Some(0.0));
+    }
+
+    #[test]
+    fn missing_provenance_deserializes_as_empty() {
+        let json = serde_json::to_value(ComparisonResult::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            triple([0, 1, 2]),
+        ))
+        .unwrap();
+        let mut legacy = json.clone();
+        legacy.as_object_mut().unwrap().remove("provenance");
+
+        let result: ComparisonResult = serde_json::from_value(legacy).unwrap();
+        assert_eq!(result.provenance, Provenance::default());
+    }
+}
@@ -1,4 +1,6 @@
-use crate::models::PhotoRating;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{PairPosition, PhotoRating};
 
 /// Bradley-Terry model for pairwise comparison ranking.
 ///
@@ -32,8 +34,8 @@ use crate::models::PhotoRating;
 /// - `wins_i` = total wins for item i across all comparisons
 /// - `n_ij` = number of comparisons between items i and j
 ///
-/// After each iteration, strengths are normalized to sum to N (number of items).
-/// Convergence is typically fast (50 iterations sufficient for most cases).
+/// After each iteration, strengths are normalized to sum to N (number of items),
+/// unless [`Anchor`]s fix the scale. Convergence is typically fast (50 iterations sufficient for most cases).
 ///
 /// # Multi-way Comparisons
 ///
@@ -52,6 +54,7 @@ pub struct BradleyTerry {
     num_items: u32,
     wins: Vec<Vec<u32>>,
     comparisons: Vec<Vec<u32>>,
+    anchors: Vec<Anchor>,
 }
 
 impl BradleyTerry {
@@ -63,9 +66,17 @@ impl BradleyTerry {
             num_items: num_items_u32,
             wins: vec![vec![0; num_items]; num_items],
             comparisons: vec![vec![0; num_items]; num_items],
+            anchors: Vec::new(),
         })
     }
 
+    /// Pins or priors the given photos to known strengths; see [`Anchor`].
+    #[must_use]
+    pub fn with_anchors(mut self, anchors: Vec<Anchor>) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
     pub fn record_comparison(&mut self, winner: u32, loser: u32) {
         let w = winner as usize;
         let l = loser as usize;
@@ -90,17 +101,14 @@ impl BradleyTerry {
             return Vec::new();
         }
 
-        let mut strengths: Vec<f64> = vec![1.0; n];
+        let anchors = anchor_slots(n, &self.anchors);
+        let mut strengths = initial_strengths(&anchors);
 
         for _ in 0..iterations {
             let mut new_strengths = vec![0.0; n];
 
             for i in 0..n {
                 let total_wins: u32 = self.wins[i].iter().sum();
-                if total_wins == 0 {
-                    new_strengths[i] = strengths[i];
-                    continue;
-                }
 
                 let mut denominator = 0.0;
                 for j in 0..n {
@@ -112,15 +120,13 @@ impl BradleyTerry {
                     }
                 }
 
-                new_strengths[i] = if denominator > 0.0 {
-                    f64::from(total_wins) / denominator
-                } else {
-                    strengths[i]
-                };
+                new_strengths[i] =
+                    anchored_update(anchors[i], strengths[i], f64::from(total_wins), denominator);
             }
 
+            // Anchors fix the scale; without them, normalize to sum to N
             let sum: f64 = new_strengths.iter().sum();
-            if sum > 0.0 {
+            if self.anchors.is_empty() && sum > 0.0 {
                 let scale = f64::from(self.num_items) / sum;
                 for s in &mut new_strengths {
                     *s *= scale;
@@ -147,11 +153,7 @@ impl BradleyTerry {
     }
 
     fn compute_uncertainty(&self, item_idx: usize) -> f64 {
-        let total: u32 = self.comparisons[item_idx].iter().sum();
-        if total == 0 {
-            return 1.0;
-        }
-        1.0 / (1.0 + f64::from(total).sqrt())
+        uncertainty_from_count(self.comparisons[item_idx].iter().sum())
     }
 
     #[must_use]
@@ -165,6 +167,294 @@ impl BradleyTerry {
     }
 }
 
+/// A photo whose strength is already known, e.g. from an earlier campaign.
+///
+/// Anchors put a new campaign's strengths on the scale of the campaign the
+/// prior came from. Once any anchor is set, the models stop normalizing
+/// strengths to sum to N and let the anchors fix the scale instead.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub photo_idx: u32,
+    /// Known log-strength, as in [`PhotoRating::strength`].
+    pub strength: f64,
+    #[serde(flatten)]
+    pub mode: AnchorMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AnchorMode {
+    /// Strength is held at the known value.
+    Fixed,
+    /// Strength is pulled toward the known value as if the photo had played
+    /// `weight` extra comparisons, half won and half lost, against an opponent
+    /// of exactly that strength.
+    Prior { weight: f64 },
+}
+
+fn anchor_slots(n: usize, anchors: &[Anchor]) -> Vec<Option<Anchor>> {
+    let mut slots = vec![None; n];
+    for anchor in anchors {
+        if let Some(slot) = slots.get_mut(anchor.photo_idx as usize) {
+            *slot = Some(*anchor);
+        }
+    }
+    slots
+}
+
+fn initial_strengths(anchors: &[Option<Anchor>]) -> Vec<f64> {
+    anchors
+        .iter()
+        .map(|a| a.map_or(1.0, |a| a.strength.exp()))
+        .collect()
+}
+
+/// One MM step for a single item, given its wins and MM denominator.
+fn anchored_update(anchor: Option<Anchor>, strength: f64, wins: f64, denominator: f64) -> f64 {
+    match anchor {
+        Some(Anchor {
+            strength: known,
+            mode: AnchorMode::Fixed,
+            ..
+        }) => known.exp(),
+        Some(Anchor {
+            strength: known,
+            mode: AnchorMode::Prior { weight },
+            ..
+        }) => (wins + weight / 2.0) / (denominator + weight / (strength + known.exp())),
+        None if wins > 0.0 && denominator > 0.0 => wins / denominator,
+        None => strength,
+    }
+}
+
+fn uncertainty_from_count(total: u32) -> f64 {
+    if total == 0 {
+        return 1.0;
+    }
+    1.0 / (1.0 + f64::from(total).sqrt())
+}
+
+/// Bradley-Terry extended with a display-position advantage.
+///
+/// # Model
+///
+/// When item i is displayed before item j, the earlier slot gets a
+/// multiplicative advantage γ > 0:
+///
+/// ```text
+/// P(i beats j | i shown first) = γθᵢ / (γθᵢ + θⱼ)
+/// ```
+///
+/// γ = 1 means no bias; γ > 1 means people favor the earlier image. Pairs
+/// with unknown display order fall back to plain Bradley-Terry.
+///
+/// # MM Algorithm
+///
+/// This is the home-field advantage model from Hunter (2004), section 3.2,
+/// with "shown first" playing the role of "home". Strengths and γ are
+/// updated alternately:
+///
+/// ```text
+/// θᵢ_new = wins_i / Σⱼ (fᵢⱼ γ / (γθᵢ + θⱼ) + fⱼᵢ / (γθⱼ + θᵢ) + uᵢⱼ / (θᵢ + θⱼ))
+/// γ_new  = first_wins / Σᵢⱼ (fᵢⱼ θᵢ / (γθᵢ + θⱼ))
+/// ```
+///
+/// where:
+/// - `fᵢⱼ` = comparisons between i and j with i shown first
+/// - `uᵢⱼ` = comparisons between i and j with unknown order
+/// - `first_wins` = outcomes won by the earlier-displayed item
+///
+/// Strengths are normalized to sum to N after each iteration, as in
+/// [`BradleyTerry`], so ratings from both models share a scale. With
+/// [`Anchor`]s the anchors set the scale instead.
+pub struct PositionBiasedBradleyTerry {
+    num_items: u32,
+    wins: Vec<u32>,
+    shown_first: Vec<Vec<u32>>,
+    unordered: Vec<Vec<u32>>,
+    first_wins: u32,
+    ordered_total: u32,
+    anchors: Vec<Anchor>,
+}
+
+/// Measured display-position bias, for reporting to the campaign owner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PositionBias {
+    /// Fitted γ: odds multiplier for the earlier-displayed photo. 1.0 means no bias.
+    pub first_position_advantage: f64,
+    /// Share of ordered outcomes won by the earlier-displayed photo, before correction.
+    pub raw_first_win_rate: f64,
+    /// Pairwise outcomes with a known display order.
+    pub ordered_pairs: u32,
+}
+
+impl PositionBias {
+    /// P(earlier photo wins) between two photos of equal strength.
+    #[must_use]
+    pub fn equal_strength_first_win_probability(&self) -> f64 {
+        self.first_position_advantage / (1.0 + self.first_position_advantage)
+    }
+}
+
+impl PositionBiasedBradleyTerry {
+    /// Creates a new model. Returns `None` if `num_items` exceeds `u32::MAX`.
+    #[must_use]
+    pub fn new(num_items: usize) -> Option<Self> {
+        let num_items_u32 = u32::try_from(num_items).ok()?;
+        Some(Self {
+            num_items: num_items_u32,
+            wins: vec![0; num_items],
+            shown_first: vec![vec![0; num_items]; num_items],
+            unordered: vec![vec![0; num_items]; num_items],
+            first_wins: 0,
+            ordered_total: 0,
+            anchors: Vec::new(),
+        })
+    }
+
+    /// Pins or priors the given photos to known strengths; see [`Anchor`].
+    #[must_use]
+    pub fn with_anchors(mut self, anchors: Vec<Anchor>) -> Self {
+        self.anchors = anchors;
+        self
+    }
+
+    pub fn record_comparison(&mut self, winner: u32, loser: u32, position: PairPosition) {
+        let w = winner as usize;
+        let l = loser as usize;
+        let n = self.num_items as usize;
+        if w >= n || l >= n {
+            return;
+        }
+        self.wins[w] += 1;
+        match position {
+            PairPosition::WinnerFirst => {
+                self.shown_first[w][l] += 1;
+                self.first_wins += 1;
+                self.ordered_total += 1;
+            }
+            PairPosition::LoserFirst => {
+                self.shown_first[l][w] += 1;
+                self.ordered_total += 1;
+            }
+            PairPosition::Unknown => {
+                self.unordered[w][l] += 1;
+                self.unordered[l][w] += 1;
+            }
+        }
+    }
+
+    pub fn record_comparisons(&mut self, results: &[(u32, u32, PairPosition)]) {
+        for &(winner, loser, position) in results {
+            self.record_comparison(winner, loser, position);
+        }
+    }
+
+    /// Fits strengths and γ jointly. Ratings are sorted strongest first.
+    #[must_use]
+    pub fn compute_ratings(&self, iterations: u32) -> (Vec<PhotoRating>, PositionBias) {
+        let n = self.num_items as usize;
+        let anchors = anchor_slots(n, &self.anchors);
+        let mut strengths = initial_strengths(&anchors);
+        let mut gamma = 1.0;
+
+        for _ in 0..iterations {
+            let mut new_strengths = vec![0.0; n];
+
+            for i in 0..n {
+                let mut denominator = 0.0;
+                for j in 0..n {
+                    if i == j {
+                        continue;
+                    }
+                    let first_ij = f64::from(self.shown_first[i][j]);
+                    let first_ji = f64::from(self.shown_first[j][i]);
+                    let unordered = f64::from(self.unordered[i][j]);
+                    if first_ij > 0.0 {
+                        denominator += first_ij * gamma / (gamma * strengths[i] + strengths[j]);
+                    }
+                    if first_ji > 0.0 {
+                        denominator += first_ji / (gamma * strengths[j] + strengths[i]);
+                    }
+                    if unordered > 0.0 {
+                        denominator += unordered / (strengths[i] + strengths[j]);
+                    }
+                }
+
+                new_strengths[i] = anchored_update(
+                    anchors[i],
+                    strengths[i],
+                    f64::from(self.wins[i]),
+                    denominator,
+                );
+            }
+
+            let sum: f64 = new_strengths.iter().sum();
+            if self.anchors.is_empty() && sum > 0.0 {
+                let scale = f64::from(self.num_items) / sum;
+                for s in &mut new_strengths {
+                    *s *= scale;
+                }
+            }
+            strengths = new_strengths;
+
+            if self.first_wins > 0 {
+                let mut denominator = 0.0;
+                for i in 0..n {
+                    for j in 0..n {
+                        let first_ij = f64::from(self.shown_first[i][j]);
+                        if first_ij > 0.0 {
+                            denominator +=
+                                first_ij * strengths[i] / (gamma * strengths[i] + strengths[j]);
+                        }
+                    }
+                }
+                if denominator > 0.0 {
+                    gamma = f64::from(self.first_wins) / denominator;
+                }
+            }
+        }
+
+        let mut ratings: Vec<PhotoRating> = strengths
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, strength)| {
+                Some(PhotoRating {
+                    photo_idx: u32::try_from(idx).ok()?,
+                    strength: strength.ln(),
+                    uncertainty: uncertainty_from_count(self.comparison_count(idx)),
+                })
+            })
+            .collect();
+        ratings.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+
+        let raw_first_win_rate = if self.ordered_total == 0 {
+            0.5
+        } else {
+            f64::from(self.first_wins) / f64::from(self.ordered_total)
+        };
+
+        let bias = PositionBias {
+            first_position_advantage: gamma,
+            raw_first_win_rate,
+            ordered_pairs: self.ordered_total,
+        };
+
+        (ratings, bias)
+    }
+
+    fn comparison_count(&self, item_idx: usize) -> u32 {
+        let n = self.num_items as usize;
+        (0..n)
+            .map(|j| {
+                self.shown_first[item_idx][j]
+                    + self.shown_first[j][item_idx]
+                    + self.unordered[item_idx][j]
+            })
+            .sum()
+    }
+}
+
 /// Computes P(i beats j) given log-strength parameters.
 ///
 /// Uses the Bradley-Terry formula: `1 / (1 + exp(-(sᵢ - sⱼ)))`
@@ -176,6 +466,32 @@ pub fn win_probability(strength_i: f64, strength_j: f64) -> f64 {
     1.0 / (1.0 + (-(strength_i - strength_j)).exp())
 }
 
+/// Standard error of each log-strength, from the diagonal of the
+/// Bradley-Terry Fisher information: `1 / √Σⱼ nᵢⱼ pᵢⱼ (1 − pᵢⱼ)` over the
+/// `(winner, loser)` pairs.
+///
+/// Covariance between photos, position bias and anchors are left out, so this
+/// understates the error somewhat. `None` for a photo the pairs carry no
+/// information about, such as one never compared or one that always wins.
+#[must_use]
+pub fn standard_errors(strengths: &[f64], pairs: &[(u32, u32)]) -> Vec<Option<f64>> {
+    let mut information = vec![0.0; strengths.len()];
+    for &(winner, loser) in pairs {
+        let (w, l) = (winner as usize, loser as usize);
+        let (Some(&sw), Some(&sl)) = (strengths.get(w), strengths.get(l)) else {
+            continue;
+        };
+        let p = win_probability(sw, sl);
+        let pair_information = p * (1.0 - p);
+        information[w] += pair_information;
+        information[l] += pair_information;
+    }
+    information
+        .into_iter()
+        .map(|i| (i > 0.0).then(|| i.sqrt().recip()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,6 +509,55 @@ mod tests {
         assert_eq!(ratings[2].photo_idx, 2);
     }
 
+    #[test]
+    fn fixed_anchor_sets_scale() {
+        let mut bt = BradleyTerry::new(3).unwrap().with_anchors(vec![Anchor {
+            photo_idx: 0,
+            strength: 2.0,
+            mode: AnchorMode::Fixed,
+        }]);
+        // Photo 1 splits evenly with the anchor, photo 2 loses to it.
+        bt.record_comparisons(&[(0, 1), (1, 0), (0, 2)]);
+
+        let ratings = bt.compute_ratings(200);
+        let strength = |idx| {
+            ratings
+                .iter()
+                .find(|r| r.photo_idx == idx)
+                .unwrap()
+                .strength
+        };
+        assert!((strength(0) - 2.0).abs() < 1e-12);
+        assert!((strength(1) - 2.0).abs() < 1e-6);
+        assert!(strength(2) < 2.0);
+    }
+
+    #[test]
+    fn prior_anchor_pulls_toward_known_strength() {
+        let prior = |weight| Anchor {
+            photo_idx: 0,
+            strength: 1.0,
+            mode: AnchorMode::Prior { weight },
+        };
+        let fit = |anchor: Anchor| {
+            let mut bt = PositionBiasedBradleyTerry::new(2)
+                .unwrap()
+                .with_anchors(vec![anchor]);
+            bt.record_comparisons(&[
+                (0, 1, PairPosition::Unknown),
+                (1, 0, PairPosition::Unknown),
+                (1, 0, PairPosition::Unknown),
+            ]);
+            let (ratings, _) = bt.compute_ratings(500);
+            ratings.iter().find(|r| r.photo_idx == 0).unwrap().strength
+        };
+
+        let weak = fit(prior(0.1));
+        let strong = fit(prior(100.0));
+        assert!((strong - 1.0).abs() < 0.05);
+        assert!((weak - 1.0).abs() > (strong - 1.0).abs());
+    }
+
     #[test]
     fn equal_strengths_give_even_odds() {
         let prob = win_probability(0.0, 0.0);
@@ -205,6 +570,75 @@ mod tests {
         assert!(win_probability(0.0, 1.0) < 0.5);
     }
 
+    #[test]
+    fn standard_errors_shrink_with_pairs() {
+        // Four even pairs: information 4 × ¼ = 1, so a standard error of 1
+        let even = [(0, 1), (1, 0), (0, 1), (1, 0)];
+        let errors = standard_errors(&[0.0, 0.0, 0.0], &even);
+        assert_eq!(errors[..2], [Some(1.0), Some(1.0)]);
+        assert_eq!(errors[2], None);
+
+        let more: Vec<_> = even.iter().copied().cycle().take(16).collect();
+        assert_eq!(
+            standard_errors(&[0.0, 0.0], &more),
+            vec![Some(0.5), Some(0.5)]
+        );
+
+        // A lopsided pair says less than an even one
+        let lopsided = standard_errors(&[2.0, 0.0], &even);
+        assert!(lopsided[0].unwrap() > 1.0);
+    }
+
+    #[test]
+    fn position_bias_detected_between_equal_items() {
+        let mut model = PositionBiasedBradleyTerry::new(2).unwrap();
+        // Each photo wins 3 of 4 times when shown first.
+        for _ in 0..3 {
+            model.record_comparison(0, 1, PairPosition::WinnerFirst);
+            model.record_comparison(1, 0, PairPosition::WinnerFirst);
+        }
+        model.record_comparison(1, 0, PairPosition::LoserFirst);
+        model.record_comparison(0, 1, PairPosition::LoserFirst);
+
+        let (ratings, bias) = model.compute_ratings(100);
+        assert!((bias.first_position_advantage - 3.0).abs() < 0.01);
+        assert!((bias.raw_first_win_rate - 0.75).abs() < f64::EPSILON);
+        assert!((ratings[0].strength - ratings[1].strength).abs() < 1e-9);
+    }
+
+    #[test]
+    fn position_bias_corrects_lopsided_display() {
+        let mut plain = BradleyTerry::new(2).unwrap();
+        let mut corrected = PositionBiasedBradleyTerry::new(2).unwrap();
+        // Photo 0 was always shown first; its wins are partly position advantage.
+        // Photo 1 wins half the time despite being shown second.
+        let outcomes = [(0, 1), (0, 1), (0, 1), (1, 0), (1, 0)];
+        for &(winner, loser) in &outcomes {
+            plain.record_comparison(winner, loser);
+            let position = if winner == 0 {
+                PairPosition::WinnerFirst
+            } else {
+                PairPosition::LoserFirst
+            };
+            corrected.record_comparison(winner, loser, position);
+        }
+        // Unordered evidence that the two are evenly matched pins down γ.
+        corrected.record_comparison(0, 1, PairPosition::Unknown);
+        corrected.record_comparison(1, 0, PairPosition::Unknown);
+        plain.record_comparison(0, 1);
+        plain.record_comparison(1, 0);
+
+        let plain_gap = {
+            let ratings = plain.compute_ratings(100);
+            ratings[0].strength - ratings[1].strength
+        };
+        let (ratings, bias) = corrected.compute_ratings(100);
+        let corrected_gap = (ratings[0].strength - ratings[1].strength).abs();
+
+        assert!(bias.first_position_advantage > 1.0);
+        assert!(corrected_gap < plain_gap);
+    }
+
     #[test]
     fn uncertainty_decreases_with_comparisons() {
         let mut bt = BradleyTerry::new(3).unwrap();
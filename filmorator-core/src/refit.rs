@@ -0,0 +1,312 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::attention::{results_for_fit, AttentionPolicy};
+use crate::models::{CampaignRating, ComparisonResult, Matchup, PhotoRating, Session};
+use crate::ranking::{Anchor, PositionBias, PositionBiasedBradleyTerry};
+
+/// Version of the rating model. Bump when a change makes stored fits incomparable
+/// with new ones, so they can be told apart and refitted.
+pub const RATING_MODEL_VERSION: u32 = 1;
+
+/// Quiet period after the last submission before a refit starts.
+pub const DEFAULT_REFIT_QUIET: Duration = Duration::from_millis(500);
+
+/// Longest a refit waits under a steady stream of submissions.
+pub const DEFAULT_REFIT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Bookkeeping stored alongside a set of fitted ratings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RatingFit {
+    /// Increases by one with every stored fit of the same ratings.
+    pub version: u64,
+    pub model_version: u32,
+    /// Comparisons the fit was computed from.
+    pub comparisons: u32,
+    pub iterations: u32,
+    /// Fitted display-position advantage; see [`crate::ranking::PositionBias`].
+    pub first_position_advantage: f64,
+    pub fitted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    first: Instant,
+    last: Instant,
+}
+
+/// Debounces refit requests per key (a session or campaign).
+///
+/// A key is due once no request has arrived for `quiet`, or `max_delay` after
+/// its first pending request, whichever comes first. A burst of submissions
+/// therefore costs one refit, and a steady stream still gets fresh ratings.
+#[derive(Debug, Clone)]
+pub struct RefitSchedule<K> {
+    quiet: Duration,
+    max_delay: Duration,
+    pending: HashMap<K, Pending>,
+}
+
+impl<K: Clone + Eq + Hash> RefitSchedule<K> {
+    #[must_use]
+    pub fn new(quiet: Duration, max_delay: Duration) -> Self {
+        Self {
+            quiet,
+            max_delay,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Notes that `key`'s ratings are stale as of `now`.
+    pub fn request(&mut self, key: K, now: Instant) {
+        self.pending
+            .entry(key)
+            .and_modify(|p| p.last = now)
+            .or_insert(Pending {
+                first: now,
+                last: now,
+            });
+    }
+
+    /// When the earliest pending key falls due; `None` if nothing is pending.
+    #[must_use]
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|p| self.due(p)).min()
+    }
+
+    /// Removes and returns the keys due at `now`.
+    pub fn take_due(&mut self, now: Instant) -> Vec<K> {
+        let due: Vec<K> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| self.due(p) <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &due {
+            self.pending.remove(key);
+        }
+        due
+    }
+
+    /// Removes and returns every pending key, due or not.
+    pub fn drain(&mut self) -> Vec<K> {
+        self.pending.drain().map(|(key, _)| key).collect()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn due(&self, pending: &Pending) -> Instant {
+        (pending.last + self.quiet).min(pending.first + self.max_delay)
+    }
+}
+
+impl<K: Clone + Eq + Hash> Default for RefitSchedule<K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_REFIT_QUIET, DEFAULT_REFIT_MAX_DELAY)
+    }
+}
+
+/// Fits a session's ratings from its active comparisons, correcting for
+/// display-position bias and leaving quality checks among `served` out as the
+/// default [`AttentionPolicy`] says.
+///
+/// The returned fit's `version` is 0; storing it assigns the real one. `None`
+/// if there are too many photos to fit.
+#[must_use]
+pub fn fit_ratings(
+    num_photos: u32,
+    served: &[Matchup],
+    comparisons: &[ComparisonResult],
+    anchors: Vec<Anchor>,
+    iterations: u32,
+) -> Option<(Vec<PhotoRating>, RatingFit)> {
+    fit_with_bias(num_photos, served, comparisons, anchors, iterations)
+        .map(|(ratings, fit, _)| (ratings, fit))
+}
+
+fn fit_with_bias(
+    num_photos: u32,
+    served: &[Matchup],
+    comparisons: &[ComparisonResult],
+    anchors: Vec<Anchor>,
+    iterations: u32,
+) -> Option<(Vec<PhotoRating>, RatingFit, PositionBias)> {
+    let mut bt = PositionBiasedBradleyTerry::new(num_photos as usize)?.with_anchors(anchors);
+
+    let fitted = results_for_fit(served, comparisons, &AttentionPolicy::default());
+    let pairs: Vec<_> = fitted
+        .iter()
+        .copied()
+        .flat_map(ComparisonResult::to_positioned_pairwise)
+        .collect();
+    bt.record_comparisons(&pairs);
+    let (ratings, bias) = bt.compute_ratings(iterations);
+
+    let fit = RatingFit {
+        version: 0,
+        model_version: RATING_MODEL_VERSION,
+        comparisons: u32::try_from(fitted.len()).unwrap_or(u32::MAX),
+        iterations,
+        first_position_advantage: bias.first_position_advantage,
+        fitted_at: Utc::now(),
+    };
+    Some((ratings, fit, bias))
+}
+
+/// Fits a campaign's ratings from the active comparisons of its sessions that
+/// aren't excluded, as [`fit_ratings`] fits a session's, along with the
+/// display-position bias measured in the fit. A rating's `comparison_count` is
+/// the fitted answers its photo was in.
+///
+/// `None` if there are too many photos to fit.
+#[must_use]
+pub fn fit_campaign_ratings(
+    num_photos: u32,
+    sessions: &[Session],
+    served: &[Matchup],
+    comparisons: &[ComparisonResult],
+    anchors: Vec<Anchor>,
+    iterations: u32,
+) -> Option<(Vec<CampaignRating>, PositionBias)> {
+    let excluded: HashSet<_> = sessions
+        .iter()
+        .filter(|s| s.excluded)
+        .map(|s| s.id)
+        .collect();
+    let included: Vec<ComparisonResult> = comparisons
+        .iter()
+        .filter(|c| c.is_active() && !excluded.contains(&c.session_id))
+        .cloned()
+        .collect();
+    let (ratings, fit, bias) = fit_with_bias(num_photos, served, &included, anchors, iterations)?;
+
+    let mut counts = vec![0u32; num_photos as usize];
+    for comparison in results_for_fit(served, &included, &AttentionPolicy::default()) {
+        for idx in comparison.ranked_photo_indices.indices() {
+            if let Some(count) = counts.get_mut(idx as usize) {
+                *count += 1;
+            }
+        }
+    }
+
+    let ratings = ratings
+        .into_iter()
+        .map(|r| CampaignRating {
+            photo_idx: r.photo_idx,
+            strength: r.strength,
+            uncertainty: r.uncertainty,
+            comparison_count: counts.get(r.photo_idx as usize).copied().unwrap_or(0),
+            updated_at: fit.fitted_at,
+        })
+        .collect();
+    Some((ratings, bias))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::triple;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn burst_collapses_into_one_refit() {
+        let start = Instant::now();
+        let mut schedule = RefitSchedule::new(100 * MS, 1000 * MS);
+        schedule.request(1, start);
+        schedule.request(1, start + 50 * MS);
+        schedule.request(1, start + 80 * MS);
+
+        assert_eq!(schedule.next_due(), Some(start + 180 * MS));
+        assert!(schedule.take_due(start + 150 * MS).is_empty());
+        assert_eq!(schedule.take_due(start + 180 * MS), vec![1]);
+        assert!(schedule.is_empty());
+    }
+
+    #[test]
+    fn steady_stream_is_capped_by_max_delay() {
+        let start = Instant::now();
+        let mut schedule = RefitSchedule::new(100 * MS, 300 * MS);
+        for step in 0..10 {
+            schedule.request(7, start + step * 50 * MS);
+        }
+
+        assert_eq!(schedule.next_due(), Some(start + 300 * MS));
+        assert_eq!(schedule.take_due(start + 300 * MS), vec![7]);
+    }
+
+    #[test]
+    fn keys_fall_due_independently() {
+        let start = Instant::now();
+        let mut schedule = RefitSchedule::new(100 * MS, 1000 * MS);
+        schedule.request('a', start);
+        schedule.request('b', start + 60 * MS);
+
+        assert_eq!(schedule.take_due(start + 120 * MS), vec!['a']);
+        assert_eq!(schedule.next_due(), Some(start + 160 * MS));
+        assert_eq!(schedule.drain(), vec!['b']);
+        assert_eq!(schedule.next_due(), None);
+    }
+
+    #[test]
+    fn fit_leaves_out_quality_checks() {
+        use crate::models::MatchupKind;
+
+        let session = Session::default();
+        let regular = Matchup::new(&session, triple([0, 1, 2]), false);
+        let check = Matchup::check(&session, triple([0, 1, 2]), MatchupKind::Calibration);
+        let comparisons = [
+            ComparisonResult::new(regular.id, session.id, triple([0, 1, 2])),
+            ComparisonResult::new(check.id, session.id, triple([0, 1, 2])),
+        ];
+
+        let (ratings, fit) =
+            fit_ratings(3, &[regular, check], &comparisons, Vec::new(), 20).unwrap();
+        assert_eq!(ratings.len(), 3);
+        assert_eq!(fit.comparisons, 1);
+        assert_eq!(fit.version, 0);
+    }
+
+    #[test]
+    fn campaign_fit_leaves_out_excluded_sessions() {
+        let kept = Session::default();
+        let excluded = Session {
+            excluded: true,
+            ..Session::default()
+        };
+        let first = Matchup::new(&kept, triple([0, 1, 2]), false);
+        let second = Matchup::new(&excluded, triple([1, 2, 3]), false);
+        let comparisons = [
+            ComparisonResult::new(first.id, kept.id, triple([0, 1, 2])),
+            ComparisonResult::new(second.id, excluded.id, triple([3, 2, 1])),
+        ];
+
+        let (ratings, bias) = fit_campaign_ratings(
+            4,
+            &[kept, excluded],
+            &[first, second],
+            &comparisons,
+            Vec::new(),
+            20,
+        )
+        .unwrap();
+        let count = |idx| {
+            ratings
+                .iter()
+                .find(|r| r.photo_idx == idx)
+                .unwrap()
+                .comparison_count
+        };
+        assert_eq!(ratings.len(), 4);
+        assert_eq!((count(0), count(1), count(3)), (1, 1, 0));
+        assert_eq!(ratings[0].photo_idx, 0);
+        // Neither answer reported a display order
+        assert_eq!(bias.ordered_pairs, 0);
+    }
+}
@@ -0,0 +1,1229 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::future::{ready, Future, Ready};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use uuid::Uuid;
+
+use crate::events::{Event, LoggedEvent};
+use crate::groups::PhotoGroups;
+use crate::identity::{content_hash, PhotoSync};
+use crate::matchup::SwissTournament;
+use crate::models::{
+    Campaign, CampaignRating, ComparisonResult, Matchup, Photo, PhotoRating, Session,
+    DEFAULT_CAMPAIGN_ID,
+};
+use crate::pool::MatchupPool;
+use crate::ranking::{Anchor, PositionBias};
+use crate::refit::RatingFit;
+
+/// Storage behind the web handlers, the matchup scheduler and the rating worker.
+///
+/// Methods return `Send` futures so implementations can be awaited from any
+/// async runtime. Reads of comparisons see active (not superseded) ones only.
+///
+/// Every change to comparisons, session exclusions and campaign status is
+/// appended to the event log together with the change itself; see [`Event`].
+pub trait Repository: Send + Sync {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Starts a session in `campaign_id`, or marks an existing one active. An
+    /// existing session stays in its own campaign, whatever `campaign_id` says.
+    fn touch_session(
+        &self,
+        session_id: Uuid,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Session, Self::Error>> + Send;
+
+    /// Stores a session from another server as given, except that it starts
+    /// included; see [`Self::set_session_excluded`]. Returns `false`, changing
+    /// nothing, if a session with its id exists.
+    fn import_session(
+        &self,
+        session: &Session,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Leaves a session out of (or back in) its campaign's ranking. Returns
+    /// `false` for an unknown session.
+    fn set_session_excluded(
+        &self,
+        session_id: Uuid,
+        excluded: bool,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// The campaign a session belongs to; `None` for an unknown session.
+    fn session_campaign(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Campaign>, Self::Error>> + Send;
+
+    fn campaign(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Option<Campaign>, Self::Error>> + Send;
+
+    /// Every session of a campaign, oldest first.
+    fn campaign_sessions(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Session>, Self::Error>> + Send;
+
+    /// Stores a new campaign or the changed status and timestamps of an existing one.
+    /// A stored `threshold_reached_at` is kept; see [`Self::set_threshold_reached`].
+    fn save_campaign(
+        &self,
+        campaign: &Campaign,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Records when the campaign first met its statistical threshold, leaving
+    /// its status alone. Returns `false` if it is unknown, closed, or already
+    /// has a threshold time.
+    fn set_threshold_reached(
+        &self,
+        campaign_id: &str,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Replaces a campaign's owner secret hash, which [`Self::save_campaign`]
+    /// leaves alone. Returns `false` if there is no such campaign.
+    fn set_owner_secret_hash(
+        &self,
+        campaign_id: &str,
+        owner_secret_hash: &str,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    fn count_photos(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<u32, Self::Error>> + Send;
+
+    /// A campaign's photos in index order.
+    fn photos(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Photo>, Self::Error>> + Send;
+
+    /// Stores a sync's added, renamed and rehashed photos atomically, added
+    /// ones under their `campaign_id`. Positions of existing photos are never written.
+    fn apply_photo_sync(
+        &self,
+        sync: &PhotoSync,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    fn photo_filename(
+        &self,
+        campaign_id: &str,
+        position: u32,
+    ) -> impl Future<Output = Result<Option<String>, Self::Error>> + Send;
+
+    /// Group labels of a campaign's photos, indexed by position.
+    fn photo_groups(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<PhotoGroups, Self::Error>> + Send;
+
+    fn anchors(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Anchor>, Self::Error>> + Send;
+
+    /// Designates the campaign's photo at `anchor.photo_idx` as an anchor.
+    /// Returns `false` if there is no such photo.
+    fn upsert_anchor(
+        &self,
+        campaign_id: &str,
+        anchor: &Anchor,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Returns `false` if the photo was not an anchor.
+    fn delete_anchor(
+        &self,
+        campaign_id: &str,
+        photo_idx: u32,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    fn create_matchup(
+        &self,
+        matchup: &Matchup,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    fn matchup(
+        &self,
+        matchup_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, Self::Error>> + Send;
+
+    /// Records that the matchup was sent to its session at `at`. Returns `false`
+    /// if there is no such matchup.
+    fn mark_matchup_served(
+        &self,
+        matchup_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Every matchup served to a session, oldest first.
+    fn session_matchups(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<Matchup>, Self::Error>> + Send;
+
+    /// Every matchup served in a campaign, oldest first.
+    fn campaign_matchups(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Matchup>, Self::Error>> + Send;
+
+    /// The oldest unanswered seed matchup.
+    fn pending_seed_matchup(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, Self::Error>> + Send;
+
+    /// The oldest unanswered quality-check matchup.
+    fn pending_check_matchup(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, Self::Error>> + Send;
+
+    /// A matchup whose answer was retracted and not yet given again.
+    fn requeued_matchup(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, Self::Error>> + Send;
+
+    fn has_seed_matchups(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// The pool the session's seed matchups were generated as.
+    fn seed_pool(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<MatchupPool>, Self::Error>> + Send;
+
+    /// Stores the session's seed pool, replacing any earlier one.
+    fn save_seed_pool(
+        &self,
+        session_id: Uuid,
+        pool: &MatchupPool,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// The Swiss tournament a campaign's matchups are paired by, if started.
+    fn swiss_tournament(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Option<SwissTournament>, Self::Error>> + Send;
+
+    /// Stores a campaign's Swiss tournament, replacing any earlier one.
+    /// Sessions served at the same time may race; the last save wins.
+    fn save_swiss_tournament(
+        &self,
+        campaign_id: &str,
+        tournament: &SwissTournament,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Stores a comparison unless its matchup is already answered, its
+    /// idempotency key already used, or its session unknown. Returns whether it
+    /// was stored.
+    fn save_comparison(
+        &self,
+        result: &ComparisonResult,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Marks an active comparison superseded at `at` with no replacement,
+    /// re-opening its matchup. Returns `false` if it was already superseded.
+    fn retract_comparison(
+        &self,
+        comparison_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Atomically supersedes an active comparison with `revision`. Returns
+    /// `false`, changing nothing, if the old comparison was already superseded.
+    fn revise_comparison(
+        &self,
+        comparison_id: Uuid,
+        revision: &ComparisonResult,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// The stored answer a submission collides with: the active one for its
+    /// matchup, or the one the session stored under the same idempotency key.
+    fn find_prior_submission(
+        &self,
+        session_id: Uuid,
+        matchup_id: Uuid,
+        idempotency_key: Option<Uuid>,
+    ) -> impl Future<Output = Result<Option<ComparisonResult>, Self::Error>> + Send;
+
+    /// Active comparisons of a session, oldest first.
+    fn session_comparisons(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<ComparisonResult>, Self::Error>> + Send;
+
+    /// Active comparisons of every session in a campaign, oldest first.
+    fn campaign_comparisons(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<ComparisonResult>, Self::Error>> + Send;
+
+    /// Every comparison of a session, superseded ones included, oldest first.
+    fn comparison_history(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<ComparisonResult>, Self::Error>> + Send;
+
+    /// A session's ratings, strongest first.
+    fn session_ratings(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<PhotoRating>, Self::Error>> + Send;
+
+    /// Replaces a session's ratings and fit metadata atomically. `fit.version`
+    /// is ignored; returns the stored version, one past the previous fit's.
+    fn save_rating_fit(
+        &self,
+        session_id: Uuid,
+        ratings: &[PhotoRating],
+        fit: &RatingFit,
+    ) -> impl Future<Output = Result<u64, Self::Error>> + Send;
+
+    /// Metadata of the fit behind a session's current ratings; `None` before the first fit.
+    fn rating_fit(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<RatingFit>, Self::Error>> + Send;
+
+    /// A campaign's ratings over all its included sessions, strongest first.
+    fn campaign_ratings(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<CampaignRating>, Self::Error>> + Send;
+
+    /// The display-position bias measured by the campaign's latest fit.
+    fn campaign_position_bias(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Option<PositionBias>, Self::Error>> + Send;
+
+    /// Replaces a campaign's ratings, and the position bias measured with
+    /// them, atomically.
+    fn save_campaign_ratings(
+        &self,
+        campaign_id: &str,
+        ratings: &[CampaignRating],
+        position_bias: &PositionBias,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// The event log after `after_seq` (all of it for 0), in append order.
+    fn events(
+        &self,
+        after_seq: u64,
+    ) -> impl Future<Output = Result<Vec<LoggedEvent>, Self::Error>> + Send;
+}
+
+/// A [`Repository`] held in memory, for tests and simulations.
+///
+/// Enforces the same uniqueness and reference rules as the database schema.
+#[derive(Debug)]
+pub struct MemoryRepository {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    campaigns: Vec<Campaign>,
+    sessions: Vec<Session>,
+    photos: Vec<Photo>,
+    /// Anchors by the id of their photo.
+    anchors: Vec<(Uuid, Anchor)>,
+    matchups: Vec<Matchup>,
+    comparisons: Vec<ComparisonResult>,
+    ratings: HashMap<Uuid, Vec<PhotoRating>>,
+    fits: HashMap<Uuid, RatingFit>,
+    campaign_ratings: HashMap<String, Vec<CampaignRating>>,
+    position_biases: HashMap<String, PositionBias>,
+    seed_pools: HashMap<Uuid, MatchupPool>,
+    swiss_tournaments: HashMap<String, SwissTournament>,
+    events: Vec<LoggedEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MemoryError {
+    #[error("unknown session {0}")]
+    UnknownSession(Uuid),
+    #[error("matchup {0} already exists")]
+    DuplicateMatchup(Uuid),
+    #[error("unknown campaign {0}")]
+    UnknownCampaign(String),
+}
+
+impl MemoryRepository {
+    /// An empty repository holding only the default campaign, as after migrations.
+    #[must_use]
+    pub fn new() -> Self {
+        // Nobody knows the default campaign's owner secret
+        let placeholder = content_hash(Uuid::new_v4().as_bytes());
+        let default = Campaign::new(
+            DEFAULT_CAMPAIGN_ID.to_string(),
+            "Default campaign".to_string(),
+            placeholder,
+        );
+        Self {
+            state: Mutex::new(MemoryState {
+                campaigns: vec![default],
+                ..MemoryState::default()
+            }),
+        }
+    }
+
+    /// Adds photos, each to its own campaign.
+    #[must_use]
+    pub fn with_photos(self, photos: Vec<Photo>) -> Self {
+        self.lock().photos.extend(photos);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        // Every mutation completes before it can panic; the state stays consistent
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn with_state<T>(
+        &self,
+        f: impl FnOnce(&mut MemoryState) -> Result<T, MemoryError>,
+    ) -> Ready<Result<T, MemoryError>> {
+        ready(f(&mut self.lock()))
+    }
+}
+
+impl Default for MemoryRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryState {
+    fn is_answered(&self, matchup_id: Uuid) -> bool {
+        self.comparisons
+            .iter()
+            .any(|c| c.matchup_id == matchup_id && c.is_active())
+    }
+
+    fn was_retracted(&self, matchup_id: Uuid) -> bool {
+        self.comparisons
+            .iter()
+            .any(|c| c.matchup_id == matchup_id && !c.is_active())
+    }
+
+    /// A campaign's photos in index order.
+    fn campaign_photos(&self, campaign_id: &str) -> Vec<&Photo> {
+        let mut photos: Vec<&Photo> = self
+            .photos
+            .iter()
+            .filter(|p| p.campaign_id == campaign_id)
+            .collect();
+        photos.sort_by_key(|p| p.position);
+        photos
+    }
+
+    fn photo_at(&self, campaign_id: &str, position: u32) -> Option<&Photo> {
+        self.photos
+            .iter()
+            .find(|p| p.campaign_id == campaign_id && p.position == position)
+    }
+
+    fn first_matchup(&self, session_id: Uuid, f: impl Fn(&Matchup) -> bool) -> Option<Matchup> {
+        self.matchups
+            .iter()
+            .find(|m| m.session_id == session_id && f(m))
+            .cloned()
+    }
+
+    fn log(&mut self, at: DateTime<Utc>, event: Event) {
+        let seq = self.events.last().map_or(1, |e| e.seq + 1);
+        self.events.push(LoggedEvent { seq, at, event });
+    }
+
+    fn insert_comparison(&mut self, result: &ComparisonResult) -> bool {
+        let known_session = self.sessions.iter().any(|s| s.id == result.session_id);
+        let key_used = result.idempotency_key.is_some_and(|key| {
+            self.comparisons
+                .iter()
+                .any(|c| c.session_id == result.session_id && c.idempotency_key == Some(key))
+        });
+        let duplicate = self.comparisons.iter().any(|c| c.id == result.id);
+        if !known_session || key_used || duplicate || self.is_answered(result.matchup_id) {
+            return false;
+        }
+        self.comparisons.push(result.clone());
+        true
+    }
+
+    fn active_comparisons(&self, f: impl Fn(&ComparisonResult) -> bool) -> Vec<ComparisonResult> {
+        let mut active: Vec<ComparisonResult> = self
+            .comparisons
+            .iter()
+            .filter(|c| c.is_active() && f(c))
+            .cloned()
+            .collect();
+        active.sort_by_key(|c| c.created_at);
+        active
+    }
+}
+
+impl Repository for MemoryRepository {
+    type Error = MemoryError;
+
+    fn touch_session(
+        &self,
+        session_id: Uuid,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Session, MemoryError>> + Send {
+        self.with_state(|state| {
+            let now = Utc::now();
+            if let Some(session) = state.sessions.iter_mut().find(|s| s.id == session_id) {
+                session.last_active_at = now;
+                return Ok(session.clone());
+            }
+            if !state.campaigns.iter().any(|c| c.id == campaign_id) {
+                return Err(MemoryError::UnknownCampaign(campaign_id.to_string()));
+            }
+            let session = Session {
+                id: session_id,
+                ..Session::new(campaign_id.to_string())
+            };
+            state.sessions.push(session.clone());
+            Ok(session)
+        })
+    }
+
+    fn import_session(
+        &self,
+        session: &Session,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            if state.sessions.iter().any(|s| s.id == session.id) {
+                return Ok(false);
+            }
+            state.sessions.push(Session {
+                excluded: false,
+                ..session.clone()
+            });
+            Ok(true)
+        })
+    }
+
+    fn set_session_excluded(
+        &self,
+        session_id: Uuid,
+        excluded: bool,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            let Some(session) = state.sessions.iter_mut().find(|s| s.id == session_id) else {
+                return Ok(false);
+            };
+            session.excluded = excluded;
+            state.log(
+                Utc::now(),
+                Event::SessionExcluded {
+                    session_id,
+                    excluded,
+                },
+            );
+            Ok(true)
+        })
+    }
+
+    fn session_campaign(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Campaign>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .sessions
+                .iter()
+                .find(|s| s.id == session_id)
+                .and_then(|s| state.campaigns.iter().find(|c| c.id == s.campaign_id))
+                .cloned())
+        })
+    }
+
+    fn campaign(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Option<Campaign>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .campaigns
+                .iter()
+                .find(|c| c.id == campaign_id)
+                .cloned())
+        })
+    }
+
+    fn campaign_sessions(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Session>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let mut sessions: Vec<Session> = state
+                .sessions
+                .iter()
+                .filter(|s| s.campaign_id == campaign_id)
+                .cloned()
+                .collect();
+            sessions.sort_by_key(|s| s.created_at);
+            Ok(sessions)
+        })
+    }
+
+    fn save_campaign(
+        &self,
+        campaign: &Campaign,
+    ) -> impl Future<Output = Result<(), MemoryError>> + Send {
+        self.with_state(|state| {
+            let before = state
+                .campaigns
+                .iter()
+                .find(|c| c.id == campaign.id)
+                .map(|c| c.status);
+            if let Some((at, event)) = Event::campaign_transition(before, campaign) {
+                state.log(at, event);
+            }
+            match state.campaigns.iter_mut().find(|c| c.id == campaign.id) {
+                Some(stored) => {
+                    stored.name.clone_from(&campaign.name);
+                    stored.status = campaign.status;
+                    stored.threshold_reached_at = stored
+                        .threshold_reached_at
+                        .or(campaign.threshold_reached_at);
+                    stored.closed_at = campaign.closed_at;
+                    stored.reopened_at = campaign.reopened_at;
+                }
+                None => state.campaigns.push(campaign.clone()),
+            }
+            Ok(())
+        })
+    }
+
+    fn set_threshold_reached(
+        &self,
+        campaign_id: &str,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .campaigns
+                .iter_mut()
+                .find(|c| c.id == campaign_id)
+                .is_some_and(|c| c.mark_threshold_reached(at).is_ok()))
+        })
+    }
+
+    fn set_owner_secret_hash(
+        &self,
+        campaign_id: &str,
+        owner_secret_hash: &str,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(
+                match state.campaigns.iter_mut().find(|c| c.id == campaign_id) {
+                    Some(campaign) => {
+                        campaign.owner_secret_hash = owner_secret_hash.to_string();
+                        true
+                    }
+                    None => false,
+                },
+            )
+        })
+    }
+
+    fn count_photos(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<u32, MemoryError>> + Send {
+        self.with_state(|state| {
+            let count = state.campaign_photos(campaign_id).len();
+            Ok(u32::try_from(count).unwrap_or(u32::MAX))
+        })
+    }
+
+    fn photos(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Photo>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .campaign_photos(campaign_id)
+                .into_iter()
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn apply_photo_sync(
+        &self,
+        sync: &PhotoSync,
+    ) -> impl Future<Output = Result<(), MemoryError>> + Send {
+        self.with_state(|state| {
+            for photo in &sync.photos {
+                if sync.added.contains(&photo.position) {
+                    state.photos.push(photo.clone());
+                } else if let Some(stored) = state.photos.iter_mut().find(|p| p.id == photo.id) {
+                    stored.filename.clone_from(&photo.filename);
+                    stored.file_hash.clone_from(&photo.file_hash);
+                    stored.group.clone_from(&photo.group);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn photo_filename(
+        &self,
+        campaign_id: &str,
+        position: u32,
+    ) -> impl Future<Output = Result<Option<String>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .photo_at(campaign_id, position)
+                .map(|p| p.filename.clone()))
+        })
+    }
+
+    fn photo_groups(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<PhotoGroups, MemoryError>> + Send {
+        self.with_state(|state| {
+            let labels: Vec<Option<&str>> = state
+                .campaign_photos(campaign_id)
+                .iter()
+                .map(|p| p.group.as_deref())
+                .collect();
+            Ok(PhotoGroups::from_labels(&labels))
+        })
+    }
+
+    fn anchors(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Anchor>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let photos = state.campaign_photos(campaign_id);
+            let mut anchors: Vec<Anchor> = state
+                .anchors
+                .iter()
+                .filter(|(photo_id, _)| photos.iter().any(|p| p.id == *photo_id))
+                .map(|(_, anchor)| *anchor)
+                .collect();
+            anchors.sort_by_key(|a| a.photo_idx);
+            Ok(anchors)
+        })
+    }
+
+    fn upsert_anchor(
+        &self,
+        campaign_id: &str,
+        anchor: &Anchor,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            let Some(photo_id) = state.photo_at(campaign_id, anchor.photo_idx).map(|p| p.id) else {
+                return Ok(false);
+            };
+            state.anchors.retain(|(id, _)| *id != photo_id);
+            state.anchors.push((photo_id, *anchor));
+            Ok(true)
+        })
+    }
+
+    fn delete_anchor(
+        &self,
+        campaign_id: &str,
+        photo_idx: u32,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            let Some(photo_id) = state.photo_at(campaign_id, photo_idx).map(|p| p.id) else {
+                return Ok(false);
+            };
+            let before = state.anchors.len();
+            state.anchors.retain(|(id, _)| *id != photo_id);
+            Ok(state.anchors.len() < before)
+        })
+    }
+
+    fn create_matchup(
+        &self,
+        matchup: &Matchup,
+    ) -> impl Future<Output = Result<(), MemoryError>> + Send {
+        self.with_state(|state| {
+            if !state.sessions.iter().any(|s| s.id == matchup.session_id) {
+                return Err(MemoryError::UnknownSession(matchup.session_id));
+            }
+            if state.matchups.iter().any(|m| m.id == matchup.id) {
+                return Err(MemoryError::DuplicateMatchup(matchup.id));
+            }
+            state.matchups.push(matchup.clone());
+            Ok(())
+        })
+    }
+
+    fn matchup(
+        &self,
+        matchup_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, MemoryError>> + Send {
+        self.with_state(|state| Ok(state.matchups.iter().find(|m| m.id == matchup_id).cloned()))
+    }
+
+    fn mark_matchup_served(
+        &self,
+        matchup_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            let Some(matchup) = state.matchups.iter_mut().find(|m| m.id == matchup_id) else {
+                return Ok(false);
+            };
+            matchup.served_at = Some(at);
+            Ok(true)
+        })
+    }
+
+    fn session_matchups(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<Matchup>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .matchups
+                .iter()
+                .filter(|m| m.session_id == session_id)
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn campaign_matchups(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<Matchup>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .matchups
+                .iter()
+                .filter(|m| m.campaign_id == campaign_id)
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn pending_seed_matchup(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state.first_matchup(session_id, |m| m.is_seed && !state.is_answered(m.id)))
+        })
+    }
+
+    fn pending_check_matchup(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state.first_matchup(session_id, |m| {
+                m.kind.is_check() && !state.is_answered(m.id)
+            }))
+        })
+    }
+
+    fn requeued_matchup(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Matchup>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state.first_matchup(session_id, |m| {
+                state.was_retracted(m.id) && !state.is_answered(m.id)
+            }))
+        })
+    }
+
+    fn has_seed_matchups(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .matchups
+                .iter()
+                .any(|m| m.session_id == session_id && m.is_seed))
+        })
+    }
+
+    fn seed_pool(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<MatchupPool>, MemoryError>> + Send {
+        self.with_state(|state| Ok(state.seed_pools.get(&session_id).cloned()))
+    }
+
+    fn save_seed_pool(
+        &self,
+        session_id: Uuid,
+        pool: &MatchupPool,
+    ) -> impl Future<Output = Result<(), MemoryError>> + Send {
+        self.with_state(|state| {
+            if !state.sessions.iter().any(|s| s.id == session_id) {
+                return Err(MemoryError::UnknownSession(session_id));
+            }
+            state.seed_pools.insert(session_id, pool.clone());
+            Ok(())
+        })
+    }
+
+    fn swiss_tournament(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Option<SwissTournament>, MemoryError>> + Send {
+        self.with_state(|state| Ok(state.swiss_tournaments.get(campaign_id).cloned()))
+    }
+
+    fn save_swiss_tournament(
+        &self,
+        campaign_id: &str,
+        tournament: &SwissTournament,
+    ) -> impl Future<Output = Result<(), MemoryError>> + Send {
+        self.with_state(|state| {
+            if !state.campaigns.iter().any(|c| c.id == campaign_id) {
+                return Err(MemoryError::UnknownCampaign(campaign_id.to_string()));
+            }
+            state
+                .swiss_tournaments
+                .insert(campaign_id.to_string(), tournament.clone());
+            Ok(())
+        })
+    }
+
+    fn save_comparison(
+        &self,
+        result: &ComparisonResult,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            if !state.insert_comparison(result) {
+                return Ok(false);
+            }
+            state.log(
+                result.created_at,
+                Event::ComparisonSubmitted {
+                    comparison: result.clone(),
+                },
+            );
+            Ok(true)
+        })
+    }
+
+    fn retract_comparison(
+        &self,
+        comparison_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            match state
+                .comparisons
+                .iter_mut()
+                .find(|c| c.id == comparison_id && c.is_active())
+            {
+                Some(comparison) => {
+                    comparison.supersede(at, None);
+                    state.log(at, Event::ComparisonRetracted { comparison_id });
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+    }
+
+    fn revise_comparison(
+        &self,
+        comparison_id: Uuid,
+        revision: &ComparisonResult,
+    ) -> impl Future<Output = Result<bool, MemoryError>> + Send {
+        self.with_state(|state| {
+            let Some(i) = state
+                .comparisons
+                .iter()
+                .position(|c| c.id == comparison_id && c.is_active())
+            else {
+                return Ok(false);
+            };
+            let original = state.comparisons[i].clone();
+            state.comparisons[i].supersede(revision.created_at, Some(revision.id));
+            if !state.insert_comparison(revision) {
+                state.comparisons[i] = original;
+                return Ok(false);
+            }
+            state.log(
+                revision.created_at,
+                Event::ComparisonRevised {
+                    comparison_id,
+                    revision: revision.clone(),
+                },
+            );
+            Ok(true)
+        })
+    }
+
+    fn find_prior_submission(
+        &self,
+        session_id: Uuid,
+        matchup_id: Uuid,
+        idempotency_key: Option<Uuid>,
+    ) -> impl Future<Output = Result<Option<ComparisonResult>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let for_matchup = state
+                .comparisons
+                .iter()
+                .find(|c| c.matchup_id == matchup_id && c.is_active());
+            let for_key = || {
+                state.comparisons.iter().find(|c| {
+                    c.session_id == session_id
+                        && idempotency_key.is_some()
+                        && c.idempotency_key == idempotency_key
+                })
+            };
+            Ok(for_matchup.or_else(for_key).cloned())
+        })
+    }
+
+    fn session_comparisons(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<ComparisonResult>, MemoryError>> + Send {
+        self.with_state(|state| Ok(state.active_comparisons(|c| c.session_id == session_id)))
+    }
+
+    fn campaign_comparisons(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<ComparisonResult>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let sessions: Vec<Uuid> = state
+                .sessions
+                .iter()
+                .filter(|s| s.campaign_id == campaign_id)
+                .map(|s| s.id)
+                .collect();
+            Ok(state.active_comparisons(|c| sessions.contains(&c.session_id)))
+        })
+    }
+
+    fn comparison_history(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<ComparisonResult>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let mut history: Vec<ComparisonResult> = state
+                .comparisons
+                .iter()
+                .filter(|c| c.session_id == session_id)
+                .cloned()
+                .collect();
+            history.sort_by_key(|c| c.created_at);
+            Ok(history)
+        })
+    }
+
+    fn session_ratings(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<PhotoRating>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let mut ratings = state.ratings.get(&session_id).cloned().unwrap_or_default();
+            ratings.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+            Ok(ratings)
+        })
+    }
+
+    fn save_rating_fit(
+        &self,
+        session_id: Uuid,
+        ratings: &[PhotoRating],
+        fit: &RatingFit,
+    ) -> impl Future<Output = Result<u64, MemoryError>> + Send {
+        self.with_state(|state| {
+            let version = state.fits.get(&session_id).map_or(1, |f| f.version + 1);
+            state.ratings.insert(session_id, ratings.to_vec());
+            state.fits.insert(session_id, RatingFit { version, ..*fit });
+            Ok(version)
+        })
+    }
+
+    fn rating_fit(
+        &self,
+        session_id: Uuid,
+    ) -> impl Future<Output = Result<Option<RatingFit>, MemoryError>> + Send {
+        self.with_state(|state| Ok(state.fits.get(&session_id).copied()))
+    }
+
+    fn campaign_ratings(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Vec<CampaignRating>, MemoryError>> + Send {
+        self.with_state(|state| {
+            let mut ratings = state
+                .campaign_ratings
+                .get(campaign_id)
+                .cloned()
+                .unwrap_or_default();
+            ratings.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+            Ok(ratings)
+        })
+    }
+
+    fn campaign_position_bias(
+        &self,
+        campaign_id: &str,
+    ) -> impl Future<Output = Result<Option<PositionBias>, MemoryError>> + Send {
+        self.with_state(|state| Ok(state.position_biases.get(campaign_id).copied()))
+    }
+
+    fn save_campaign_ratings(
+        &self,
+        campaign_id: &str,
+        ratings: &[CampaignRating],
+        position_bias: &PositionBias,
+    ) -> impl Future<Output = Result<(), MemoryError>> + Send {
+        self.with_state(|state| {
+            if !state.campaigns.iter().any(|c| c.id == campaign_id) {
+                return Err(MemoryError::UnknownCampaign(campaign_id.to_string()));
+            }
+            state
+                .campaign_ratings
+                .insert(campaign_id.to_string(), ratings.to_vec());
+            state
+                .position_biases
+                .insert(campaign_id.to_string(), *position_bias);
+            Ok(())
+        })
+    }
+
+    fn events(
+        &self,
+        after_seq: u64,
+    ) -> impl Future<Output = Result<Vec<LoggedEvent>, MemoryError>> + Send {
+        self.with_state(|state| {
+            Ok(state
+                .events
+                .iter()
+                .filter(|e| e.seq > after_seq)
+                .cloned()
+                .collect())
+        })
+    }
+}
+
+/// Runs a future that never waits, such as any [`MemoryRepository`] call.
+///
+/// # Panics
+///
+/// If the future is not ready on its first poll.
+#[cfg(test)]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future was not ready"),
+    }
+}
+
+/// Photos synced into `campaign_id`, each file given as its name and contents.
+#[cfg(test)]
+pub(crate) fn synced_photos<C: AsRef<[u8]>>(
+    campaign_id: &str,
+    files: impl IntoIterator<Item = (String, C)>,
+) -> Vec<Photo> {
+    use crate::identity::{sync_photos, ListedPhoto};
+
+    let listed: Vec<ListedPhoto> = files
+        .into_iter()
+        .map(|(filename, contents)| ListedPhoto {
+            filename,
+            file_hash: content_hash(contents.as_ref()),
+        })
+        .collect();
+    sync_photos(campaign_id, &[], &listed)
+        .expect("test photos sync")
+        .photos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract;
+    use crate::types::triple;
+
+    #[test]
+    fn comparisons_are_unique_per_matchup_and_key() {
+        let repo = MemoryRepository::new();
+        block_on(contract::comparisons_are_unique_per_matchup_and_key(&repo));
+        assert!(matches!(
+            block_on(repo.create_matchup(&Matchup::new(
+                &Session::default(),
+                triple([0, 1, 2]),
+                false
+            ))),
+            Err(MemoryError::UnknownSession(_))
+        ));
+    }
+
+    #[test]
+    fn retracted_matchups_are_requeued_until_answered_again() {
+        block_on(
+            contract::retracted_matchups_are_requeued_until_answered_again(&MemoryRepository::new()),
+        );
+    }
+
+    #[test]
+    fn failed_revision_changes_nothing() {
+        block_on(contract::failed_revision_changes_nothing(
+            &MemoryRepository::new(),
+        ));
+    }
+
+    #[test]
+    fn event_log_replays_to_the_stored_comparisons() {
+        block_on(contract::event_log_replays_to_the_stored_comparisons(
+            &MemoryRepository::new(),
+        ));
+    }
+
+    #[test]
+    fn photos_and_anchors_are_per_campaign() {
+        block_on(contract::photos_and_anchors_are_per_campaign(
+            &MemoryRepository::new(),
+        ));
+    }
+
+    #[test]
+    fn campaign_status_changes_are_logged() {
+        block_on(contract::campaign_status_changes_are_logged(
+            &MemoryRepository::new(),
+        ));
+    }
+
+    #[test]
+    fn threshold_is_recorded_once_and_never_while_closed() {
+        block_on(contract::threshold_is_recorded_once_and_never_while_closed(
+            &MemoryRepository::new(),
+        ));
+    }
+
+    #[test]
+    fn sessions_keep_the_campaign_they_started_in() {
+        block_on(contract::sessions_keep_the_campaign_they_started_in(
+            &MemoryRepository::new(),
+        ));
+    }
+}
@@ -0,0 +1,674 @@
+use crate::attention::{next_check_matchup, AttentionPolicy};
+use crate::exposure::{ExposurePolicy, ExposureTracker};
+use crate::groups::{GroupMode, PhotoGroups};
+use crate::matchup::{
+    anchors_for_group_mode, extract_compared_pairs, generate_balanced_seed_matchups,
+    generate_grouped_seed_matchups, inject_anchor, select_balanced_dynamic_matchup,
+    select_grouped_dynamic_matchup, SwissTournament,
+};
+use crate::models::{ComparisonResult, Matchup, PhotoRating, Session};
+use crate::pool::{MatchupPool, PoolAlgorithm, PoolError};
+use crate::repository::Repository;
+use crate::types::{RankingError, Triple};
+use std::collections::HashSet;
+use std::num::NonZeroU32;
+
+pub const MATCHUP_SIZE: u32 = 3;
+/// Every this many matchups, one photo is swapped for an anchor photo.
+pub const ANCHOR_EVERY: usize = 4;
+
+/// How the scheduler builds matchups.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchupPolicy {
+    /// Restricts seeds and dynamic matchups by photo group; `None` ignores groups.
+    pub grouping: Option<GroupMode>,
+    /// Caps how unevenly photos are shown.
+    pub exposure: ExposurePolicy,
+    pub mode: MatchupMode,
+    /// When repeat and calibration checks are interleaved.
+    pub attention: AttentionPolicy,
+}
+
+/// How matchups after the quality checks are chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchupMode {
+    /// Seeds, then matchups chosen from the session's ratings.
+    #[default]
+    Adaptive,
+    /// Rounds of matchups between photos of similar campaign standing, shared
+    /// by every session of the campaign. Ignores seeds, groups and anchors.
+    Swiss {
+        /// Answers that complete a round; `None` means one matchup per
+        /// [`MATCHUP_SIZE`] photos.
+        matchups_per_round: Option<NonZeroU32>,
+    },
+}
+
+/// What a session should be shown next.
+#[derive(Debug, Clone)]
+pub enum Scheduled {
+    Matchup(Matchup),
+    /// Every pair the session could be asked about has been compared.
+    Exhausted,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError<E> {
+    #[error("need at least {MATCHUP_SIZE} photos")]
+    NotEnoughPhotos,
+    #[error("invalid seed pool: {0}")]
+    Pool(#[from] PoolError),
+    #[error("invalid matchup: {0}")]
+    Matchup(#[from] RankingError),
+    #[error(transparent)]
+    Repository(E),
+}
+
+/// Picks the session's next matchup, storing it if it is new.
+///
+/// In order: an unanswered quality check, a matchup whose answer was undone, a
+/// newly due quality check, the next seed (generating the seed pool on first
+/// use), and finally a dynamic matchup chosen from the session's ratings.
+/// Photos come from the session's campaign, arranged according to `policy`.
+/// In [`MatchupMode::Swiss`], the campaign's tournament replaces the seeds and
+/// dynamic matchups.
+///
+/// # Errors
+///
+/// [`ScheduleError::NotEnoughPhotos`] for fewer than [`MATCHUP_SIZE`] photos,
+/// or any storage error.
+pub async fn next_matchup<R: Repository>(
+    repo: &R,
+    session: &Session,
+    policy: &MatchupPolicy,
+) -> Result<Scheduled, ScheduleError<R::Error>> {
+    let session_id = session.id;
+    let campaign_id = session.campaign_id.as_str();
+    let num_photos = repo
+        .count_photos(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    if num_photos < MATCHUP_SIZE {
+        return Err(ScheduleError::NotEnoughPhotos);
+    }
+
+    // Unanswered quality checks come first so they can't be skipped by reloading
+    if let Some(pending) = repo
+        .pending_check_matchup(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?
+    {
+        return Ok(Scheduled::Matchup(pending));
+    }
+
+    // An undone answer is asked again before anything new
+    if let Some(requeued) = repo
+        .requeued_matchup(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?
+    {
+        return Ok(Scheduled::Matchup(requeued));
+    }
+
+    let comparisons = repo
+        .session_comparisons(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let served = repo
+        .session_matchups(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let ratings = repo
+        .session_ratings(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+
+    // Interleave a quality check when one falls due
+    if let Some(check) = next_check_matchup(
+        session,
+        &served,
+        &comparisons,
+        &ratings,
+        &policy.attention,
+        MATCHUP_SIZE as usize,
+    ) {
+        repo.create_matchup(&check)
+            .await
+            .map_err(ScheduleError::Repository)?;
+        return Ok(Scheduled::Matchup(check));
+    }
+
+    match policy.mode {
+        MatchupMode::Adaptive => {
+            adaptive_matchup(
+                repo,
+                session,
+                num_photos,
+                &served,
+                &comparisons,
+                &ratings,
+                policy,
+            )
+            .await
+        }
+        MatchupMode::Swiss { matchups_per_round } => {
+            swiss_matchup(repo, session, num_photos, matchups_per_round).await
+        }
+    }
+}
+
+/// The session's next seed, generating its seed pool on first use, or else a
+/// dynamic matchup with an anchor swapped in on schedule.
+async fn adaptive_matchup<R: Repository>(
+    repo: &R,
+    session: &Session,
+    num_photos: u32,
+    served: &[Matchup],
+    comparisons: &[ComparisonResult],
+    ratings: &[PhotoRating],
+    policy: &MatchupPolicy,
+) -> Result<Scheduled, ScheduleError<R::Error>> {
+    let session_id = session.id;
+    let campaign_id = session.campaign_id.as_str();
+
+    if let Some(pending) = repo
+        .pending_seed_matchup(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?
+    {
+        return Ok(Scheduled::Matchup(pending));
+    }
+
+    // Exposure is balanced across the campaign, not just this session
+    let campaign_served = repo
+        .campaign_matchups(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let campaign_comparisons = repo
+        .campaign_comparisons(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let exposure =
+        ExposureTracker::from_history(num_photos, &campaign_served, &campaign_comparisons);
+    let groups = photo_groups(repo, campaign_id, policy).await?;
+    let grouping = policy.grouping.zip(groups.as_ref());
+
+    let has_seeds = repo
+        .has_seed_matchups(session_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    if !has_seeds {
+        create_seed_matchups(
+            repo,
+            session,
+            num_photos,
+            served.len(),
+            &exposure,
+            policy,
+            grouping,
+        )
+        .await?;
+
+        if let Some(first) = repo
+            .pending_seed_matchup(session_id)
+            .await
+            .map_err(ScheduleError::Repository)?
+        {
+            return Ok(Scheduled::Matchup(first));
+        }
+    }
+
+    // Seeds exhausted: generate dynamic matchup
+    let Some(mut photo_indices) = dynamic_photos(
+        num_photos,
+        comparisons,
+        ratings,
+        &exposure,
+        policy,
+        grouping,
+    ) else {
+        return Ok(Scheduled::Exhausted);
+    };
+
+    if served.len().is_multiple_of(ANCHOR_EVERY) {
+        let anchors = anchor_photos(repo, campaign_id).await?;
+        let anchors = usable_anchors(&photo_indices, &anchors, grouping);
+        inject_anchor(&mut photo_indices, &anchors, &exposure);
+    }
+
+    let matchup = Matchup::new(session, Triple::try_from(photo_indices.as_slice())?, false);
+    repo.create_matchup(&matchup)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    Ok(Scheduled::Matchup(matchup))
+}
+
+/// The next pairing of the campaign's Swiss tournament, starting the tournament
+/// on first use and advancing it by the answers given since it was last saved.
+async fn swiss_matchup<R: Repository>(
+    repo: &R,
+    session: &Session,
+    num_photos: u32,
+    matchups_per_round: Option<NonZeroU32>,
+) -> Result<Scheduled, ScheduleError<R::Error>> {
+    let campaign_id = session.campaign_id.as_str();
+    let served = repo
+        .campaign_matchups(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let comparisons = repo
+        .campaign_comparisons(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+
+    let mut standings: Vec<PhotoRating> = (0..num_photos).map(PhotoRating::new).collect();
+    for rating in repo
+        .campaign_ratings(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?
+    {
+        if let Some(standing) = standings.get_mut(rating.photo_idx as usize) {
+            standing.strength = rating.strength;
+            standing.uncertainty = rating.uncertainty;
+        }
+    }
+
+    let stored = repo
+        .swiss_tournament(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let per_round = matchups_per_round.map_or((num_photos / MATCHUP_SIZE).max(1), NonZeroU32::get);
+    let Some(mut tournament) =
+        stored.or_else(|| SwissTournament::new(MATCHUP_SIZE as usize, per_round))
+    else {
+        return Ok(Scheduled::Exhausted);
+    };
+
+    // Only the tournament's own matchups count towards its rounds
+    let paired: HashSet<_> = served
+        .iter()
+        .filter(|m| !m.is_seed && !m.kind.is_check())
+        .map(|m| m.id)
+        .collect();
+    let answered = comparisons
+        .iter()
+        .filter(|c| paired.contains(&c.matchup_id))
+        .count();
+    tournament.record_answered_until(u32::try_from(answered).unwrap_or(u32::MAX));
+
+    let pairs: Vec<(u32, u32)> = comparisons
+        .iter()
+        .flat_map(ComparisonResult::to_pairwise)
+        .collect();
+    let compared = extract_compared_pairs(&pairs);
+    let Some(photo_indices) = tournament.next_matchup(&standings, &compared, &mut rand::rng())
+    else {
+        return Ok(Scheduled::Exhausted);
+    };
+
+    repo.save_swiss_tournament(campaign_id, &tournament)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    let matchup = Matchup::new(session, Triple::try_from(photo_indices.as_slice())?, false);
+    repo.create_matchup(&matchup)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    Ok(Scheduled::Matchup(matchup))
+}
+
+/// Photos for a matchup chosen from the session's ratings, avoiding compared
+/// pairs; `None` once every pair has been compared. Until the session has been
+/// rated, every photo counts as equally strong.
+fn dynamic_photos(
+    num_photos: u32,
+    comparisons: &[ComparisonResult],
+    ratings: &[PhotoRating],
+    exposure: &ExposureTracker,
+    policy: &MatchupPolicy,
+    grouping: Option<(GroupMode, &PhotoGroups)>,
+) -> Option<Vec<u32>> {
+    let uniform: Vec<PhotoRating>;
+    let ratings = if ratings.is_empty() {
+        uniform = (0..num_photos).map(PhotoRating::new).collect();
+        &uniform
+    } else {
+        ratings
+    };
+
+    let pairs: Vec<(u32, u32)> = comparisons
+        .iter()
+        .flat_map(ComparisonResult::to_pairwise)
+        .collect();
+    let compared = extract_compared_pairs(&pairs);
+
+    match grouping {
+        Some((mode, groups)) => select_grouped_dynamic_matchup(
+            ratings,
+            &compared,
+            groups,
+            mode,
+            exposure,
+            &policy.exposure,
+            MATCHUP_SIZE as usize,
+        ),
+        None => select_balanced_dynamic_matchup(
+            ratings,
+            &compared,
+            exposure,
+            &policy.exposure,
+            MATCHUP_SIZE as usize,
+        ),
+    }
+}
+
+/// Generates the session's seed pool with anchors swapped in, and stores it
+/// along with its matchups.
+async fn create_seed_matchups<R: Repository>(
+    repo: &R,
+    session: &Session,
+    num_photos: u32,
+    served: usize,
+    exposure: &ExposureTracker,
+    policy: &MatchupPolicy,
+    grouping: Option<(GroupMode, &PhotoGroups)>,
+) -> Result<(), ScheduleError<R::Error>> {
+    let (mut seeds, algorithm) = match grouping {
+        Some((mode, groups)) => (
+            generate_grouped_seed_matchups(
+                groups,
+                mode,
+                exposure,
+                &policy.exposure,
+                MATCHUP_SIZE as usize,
+            ),
+            PoolAlgorithm::GroupedSeed { mode },
+        ),
+        None => (
+            generate_balanced_seed_matchups(exposure, &policy.exposure, MATCHUP_SIZE as usize),
+            PoolAlgorithm::BalancedSeed,
+        ),
+    };
+
+    // Anchors go in first, so the pool's coverage describes what is served
+    let anchors = anchor_photos(repo, &session.campaign_id).await?;
+    let mut projected = exposure.clone();
+    for (i, indices) in seeds.iter_mut().enumerate() {
+        if (served + i).is_multiple_of(ANCHOR_EVERY) {
+            let anchors = usable_anchors(indices, &anchors, grouping);
+            inject_anchor(indices, &anchors, &projected);
+        }
+        projected.record_shown(indices);
+    }
+
+    let pool = MatchupPool::new(num_photos, MATCHUP_SIZE as usize, seeds, algorithm, None)?;
+    repo.save_seed_pool(session.id, &pool)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    for indices in pool.matchups() {
+        let matchup = Matchup::new(session, Triple::try_from(indices.as_slice())?, true);
+        repo.create_matchup(&matchup)
+            .await
+            .map_err(ScheduleError::Repository)?;
+    }
+    Ok(())
+}
+
+/// Anchors that keep `matchup` true to the grouping mode.
+fn usable_anchors(
+    matchup: &[u32],
+    anchors: &[u32],
+    grouping: Option<(GroupMode, &PhotoGroups)>,
+) -> Vec<u32> {
+    match grouping {
+        Some((mode, groups)) => anchors_for_group_mode(matchup, anchors, groups, mode),
+        None => anchors.to_vec(),
+    }
+}
+
+/// The campaign's photo groups, if `policy` arranges matchups by group.
+async fn photo_groups<R: Repository>(
+    repo: &R,
+    campaign_id: &str,
+    policy: &MatchupPolicy,
+) -> Result<Option<PhotoGroups>, ScheduleError<R::Error>> {
+    if policy.grouping.is_none() {
+        return Ok(None);
+    }
+    repo.photo_groups(campaign_id)
+        .await
+        .map(Some)
+        .map_err(ScheduleError::Repository)
+}
+
+async fn anchor_photos<R: Repository>(
+    repo: &R,
+    campaign_id: &str,
+) -> Result<Vec<u32>, ScheduleError<R::Error>> {
+    let anchors = repo
+        .anchors(campaign_id)
+        .await
+        .map_err(ScheduleError::Repository)?;
+    Ok(anchors.iter().map(|a| a.photo_idx).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DEFAULT_CAMPAIGN_ID;
+    use crate::ranking::{Anchor, AnchorMode};
+    use crate::repository::{block_on, synced_photos, MemoryRepository};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn repository(num_photos: u32) -> MemoryRepository {
+        repository_of((0..num_photos).map(|i| format!("{i:02}.jpg")))
+    }
+
+    fn repository_of(filenames: impl Iterator<Item = String>) -> MemoryRepository {
+        let files = filenames.zip(0u32..).map(|(f, i)| (f, i.to_le_bytes()));
+        MemoryRepository::new().with_photos(synced_photos(DEFAULT_CAMPAIGN_ID, files))
+    }
+
+    fn next(repo: &MemoryRepository, session: &Session) -> Matchup {
+        match block_on(next_matchup(repo, session, &MatchupPolicy::default())).unwrap() {
+            Scheduled::Matchup(matchup) => matchup,
+            Scheduled::Exhausted => panic!("no matchup scheduled"),
+        }
+    }
+
+    fn answer(repo: &MemoryRepository, matchup: &Matchup) -> ComparisonResult {
+        let result = ComparisonResult::new(matchup.id, matchup.session_id, matchup.photo_indices);
+        assert!(block_on(repo.save_comparison(&result)).unwrap());
+        result
+    }
+
+    #[test]
+    fn serves_seeds_until_answered() {
+        let repo = repository(9);
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+
+        let first = next(&repo, &session);
+        assert!(first.is_seed);
+        // Reloading serves the same matchup rather than skipping it
+        assert_eq!(next(&repo, &session).id, first.id);
+
+        answer(&repo, &first);
+        let second = next(&repo, &session);
+        assert_ne!(second.id, first.id);
+        assert!(second.is_seed);
+    }
+
+    #[test]
+    fn stores_the_seed_pool_as_served() {
+        let repo = repository(9);
+        let anchor = Anchor {
+            photo_idx: 8,
+            strength: 0.0,
+            mode: AnchorMode::Fixed,
+        };
+        assert!(block_on(repo.upsert_anchor(DEFAULT_CAMPAIGN_ID, &anchor)).unwrap());
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+
+        next(&repo, &session);
+        let pool = block_on(repo.seed_pool(session.id)).unwrap().unwrap();
+        let seeds: Vec<Vec<u32>> = block_on(repo.session_matchups(session.id))
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.is_seed)
+            .map(|m| m.photo_indices.indices().to_vec())
+            .collect();
+        assert_eq!(pool.matchups(), seeds.as_slice());
+        // The first seed falls on the anchor schedule
+        assert!(seeds[0].contains(&8));
+    }
+
+    #[test]
+    fn undone_answers_are_asked_again_first() {
+        let repo = repository(9);
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+
+        let first = next(&repo, &session);
+        let result = answer(&repo, &first);
+        let second = next(&repo, &session);
+        answer(&repo, &second);
+
+        assert!(block_on(repo.retract_comparison(result.id, Utc::now())).unwrap());
+        assert_eq!(next(&repo, &session).id, first.id);
+    }
+
+    #[test]
+    fn within_groups_take_anchors_only_from_their_own_group() {
+        let repo = repository_of((0..12).map(|i| format!("{}/{i:02}.jpg", i / 6)));
+        let anchor = Anchor {
+            photo_idx: 11,
+            strength: 0.0,
+            mode: AnchorMode::Fixed,
+        };
+        assert!(block_on(repo.upsert_anchor(DEFAULT_CAMPAIGN_ID, &anchor)).unwrap());
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+        let policy = MatchupPolicy {
+            grouping: Some(GroupMode::Within),
+            ..MatchupPolicy::default()
+        };
+
+        for _ in 0..12 {
+            let Scheduled::Matchup(matchup) =
+                block_on(next_matchup(&repo, &session, &policy)).unwrap()
+            else {
+                panic!("no matchup scheduled");
+            };
+            let [first, rest @ ..] = matchup.photo_indices.indices();
+            assert!(rest.iter().all(|&p| p / 6 == first / 6));
+            answer(&repo, &matchup);
+        }
+    }
+
+    #[test]
+    fn unrated_sessions_get_fresh_pairs_after_their_seeds() {
+        let repo = repository(30);
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+
+        // No refit runs here, so the session stays unrated throughout
+        let mut compared = HashSet::new();
+        let mut dynamic = 0;
+        while dynamic < 3 {
+            let matchup = next(&repo, &session);
+            let pairs = answer(&repo, &matchup).to_pairwise();
+            let fresh = pairs
+                .into_iter()
+                .filter(|&(a, b)| compared.insert((a.min(b), a.max(b))))
+                .count();
+            if !matchup.is_seed && !matchup.kind.is_check() {
+                dynamic += 1;
+                assert!(fresh > 0, "dynamic matchup repeats only compared pairs");
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_balance_exposure_across_the_campaign() {
+        let repo = repository(4);
+        for _ in 0..8 {
+            let session =
+                block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+            answer(&repo, &next(&repo, &session));
+        }
+
+        let served = block_on(repo.campaign_matchups(DEFAULT_CAMPAIGN_ID)).unwrap();
+        let answered = block_on(repo.campaign_comparisons(DEFAULT_CAMPAIGN_ID)).unwrap();
+        let exposure = ExposureTracker::from_history(4, &served, &answered);
+        assert!(exposure.counts().iter().all(|c| c.shown == 6));
+    }
+
+    #[test]
+    fn checks_follow_the_attention_policy() {
+        let repo = repository(9);
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+        let policy = MatchupPolicy {
+            attention: AttentionPolicy {
+                repeat_every: 2,
+                calibration_every: 0,
+                ..AttentionPolicy::default()
+            },
+            ..MatchupPolicy::default()
+        };
+
+        let mut kinds = Vec::new();
+        for _ in 0..6 {
+            let Scheduled::Matchup(matchup) =
+                block_on(next_matchup(&repo, &session, &policy)).unwrap()
+            else {
+                panic!("no matchup scheduled");
+            };
+            answer(&repo, &matchup);
+            kinds.push(matchup.kind.is_check());
+        }
+        assert_eq!(kinds, [false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn swiss_rounds_are_shared_by_the_campaign() {
+        let repo = repository(9);
+        let policy = MatchupPolicy {
+            mode: MatchupMode::Swiss {
+                matchups_per_round: NonZeroU32::new(2),
+            },
+            ..MatchupPolicy::default()
+        };
+        let sessions: Vec<Session> = (0..2)
+            .map(|_| block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap())
+            .collect();
+        let round = || {
+            block_on(repo.swiss_tournament(DEFAULT_CAMPAIGN_ID))
+                .unwrap()
+                .unwrap()
+                .round()
+        };
+
+        let mut compared = HashSet::new();
+        for (i, session) in sessions.iter().cycle().take(4).enumerate() {
+            let Scheduled::Matchup(matchup) =
+                block_on(next_matchup(&repo, session, &policy)).unwrap()
+            else {
+                panic!("no matchup scheduled");
+            };
+            assert!(!matchup.is_seed);
+            // One answer from each session completes the first round
+            assert_eq!(round(), u32::from(i >= 2));
+            for (a, b) in answer(&repo, &matchup).to_pairwise() {
+                assert!(compared.insert((a.min(b), a.max(b))), "paired twice");
+            }
+        }
+        assert!(block_on(repo.seed_pool(sessions[0].id)).unwrap().is_none());
+    }
+
+    #[test]
+    fn refuses_too_few_photos() {
+        let repo = repository(2);
+        let session = block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap();
+        assert!(matches!(
+            block_on(next_matchup(&repo, &session, &MatchupPolicy::default())),
+            Err(ScheduleError::NotEnoughPhotos)
+        ));
+    }
+}
@@ -0,0 +1,494 @@
+use rand::rngs::StdRng;
+use rand::seq::{index, IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+use crate::exposure::{ExposurePolicy, ExposureTracker};
+use crate::matchup::{
+    generate_balanced_seed_matchups_with_rng, generate_seed_matchups_with_rng, normalize_pair,
+    select_balanced_dynamic_matchup, select_dynamic_matchup, SwissTournament,
+};
+use crate::models::{pairwise, positioned_pairwise, PairPosition, PhotoRating};
+use crate::ranking::{BradleyTerry, PositionBiasedBradleyTerry};
+
+/// How the simulated campaign picks matchups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Uniformly random distinct photos every time.
+    Random,
+    /// Seed rounds, then highest-uncertainty dynamic matchups.
+    SeedThenUncertainty,
+    /// Seed rounds and dynamic matchups under the default [`ExposurePolicy`].
+    Balanced,
+    /// [`SwissTournament`] rounds over the latest ratings.
+    Swiss,
+}
+
+/// Which model turns simulated answers into ratings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingModel {
+    BradleyTerry,
+    PositionBiased,
+}
+
+/// A simulated participant.
+///
+/// Each judgment adds independent Gaussian noise to the true log-strengths
+/// (a Thurstonian observer) and ranks by the noisy values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Participant {
+    /// Standard deviation of the per-judgment noise.
+    pub noise: f64,
+    /// Utility bonus for the leftmost photo, falling linearly to 0 at the rightmost.
+    pub position_bias: f64,
+    /// Probability of clicking photos in display order without looking.
+    pub lazy_rate: f64,
+}
+
+impl Participant {
+    #[must_use]
+    pub const fn thurstonian(noise: f64) -> Self {
+        Self {
+            noise,
+            position_bias: 0.0,
+            lazy_rate: 0.0,
+        }
+    }
+
+    /// Always ranks photos in the order they were displayed.
+    #[must_use]
+    pub const fn lazy_clicker() -> Self {
+        Self {
+            noise: 0.0,
+            position_bias: 0.0,
+            lazy_rate: 1.0,
+        }
+    }
+
+    /// Ranks `displayed` best first, given true log-strengths indexed by photo.
+    pub fn rank<R: Rng + ?Sized>(&self, truth: &[f64], displayed: &[u32], rng: &mut R) -> Vec<u32> {
+        if rng.random_bool(self.lazy_rate.clamp(0.0, 1.0)) {
+            return displayed.to_vec();
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let last_slot = displayed.len().saturating_sub(1).max(1) as f64;
+        let mut scored: Vec<(u32, f64)> = displayed
+            .iter()
+            .enumerate()
+            .map(|(slot, &idx)| {
+                #[allow(clippy::cast_precision_loss)]
+                let bonus = self.position_bias * (1.0 - slot as f64 / last_slot);
+                let utility = truth[idx as usize] + self.noise * standard_normal(rng) + bonus;
+                (idx, utility)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().map(|(idx, _)| idx).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    pub num_photos: u32,
+    pub matchup_size: usize,
+    /// Comparisons (answered matchups) before the run stops.
+    pub budget: u32,
+    pub strategy: Strategy,
+    pub model: RatingModel,
+    /// Each comparison is answered by a participant drawn uniformly from this pool.
+    pub participants: Vec<Participant>,
+    /// Standard deviation of the true log-strengths.
+    pub strength_spread: f64,
+    /// Refit ratings and record a checkpoint after this many comparisons.
+    pub refit_every: u32,
+    pub rating_iterations: u32,
+    pub top_k: usize,
+    /// Kendall tau that counts as ranked well enough for [`SimulationReport::comparisons_to_threshold`].
+    pub tau_threshold: f64,
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            num_photos: 30,
+            matchup_size: 3,
+            budget: 200,
+            strategy: Strategy::SeedThenUncertainty,
+            model: RatingModel::BradleyTerry,
+            participants: vec![Participant::thurstonian(1.0)],
+            strength_spread: 1.0,
+            refit_every: 5,
+            rating_iterations: 50,
+            top_k: 5,
+            tau_threshold: 0.8,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub comparisons: u32,
+    pub kendall_tau: f64,
+    pub top_k_recall: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    /// Comparisons actually answered; below the budget if the strategy ran dry.
+    pub comparisons: u32,
+    pub kendall_tau: f64,
+    pub top_k_recall: f64,
+    /// First checkpoint at which Kendall tau reached the threshold.
+    pub comparisons_to_threshold: Option<u32>,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+/// Runs one simulated campaign. Identical configs produce identical reports.
+///
+/// Returns `None` if there are fewer photos than `matchup_size`, no
+/// participants, or `refit_every` is zero.
+#[must_use]
+pub fn run(config: &SimulationConfig) -> Option<SimulationReport> {
+    let n = config.num_photos as usize;
+    if config.matchup_size == 0
+        || n < config.matchup_size
+        || config.participants.is_empty()
+        || config.refit_every == 0
+    {
+        return None;
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let truth: Vec<f64> = (0..n)
+        .map(|_| config.strength_spread * standard_normal(&mut rng))
+        .collect();
+
+    let mut scheduler = Scheduler::new(config, &mut rng);
+    let mut ratings: Vec<PhotoRating> = (0..config.num_photos).map(PhotoRating::new).collect();
+    let mut outcomes: Vec<(u32, u32, PairPosition)> = Vec::new();
+    let mut checkpoints = Vec::new();
+    let mut answered = 0;
+
+    while answered < config.budget {
+        let Some(matchup) = scheduler.next(&ratings, &mut rng) else {
+            break;
+        };
+        let mut displayed = matchup;
+        displayed.shuffle(&mut rng);
+
+        let participant = config.participants.choose(&mut rng)?;
+        let ranking = participant.rank(&truth, &displayed, &mut rng);
+        scheduler.record(&ranking);
+        outcomes.extend(positioned_pairwise(&ranking, Some(&displayed)));
+        answered += 1;
+
+        if answered % config.refit_every == 0 {
+            ratings = fit(config, &outcomes)?;
+            checkpoints.push(checkpoint(config, &truth, &ratings, answered));
+        }
+    }
+
+    if checkpoints.last().is_none_or(|c| c.comparisons != answered) {
+        ratings = fit(config, &outcomes)?;
+        checkpoints.push(checkpoint(config, &truth, &ratings, answered));
+    }
+    let last = checkpoints.last().copied()?;
+    let comparisons_to_threshold = checkpoints
+        .iter()
+        .find(|c| c.kendall_tau >= config.tau_threshold)
+        .map(|c| c.comparisons);
+
+    Some(SimulationReport {
+        comparisons: answered,
+        kendall_tau: last.kendall_tau,
+        top_k_recall: last.top_k_recall,
+        comparisons_to_threshold,
+        checkpoints,
+    })
+}
+
+/// Matchup selection state for one simulated campaign.
+struct Scheduler {
+    strategy: Strategy,
+    num_photos: u32,
+    matchup_size: usize,
+    seeds: VecDeque<Vec<u32>>,
+    compared: HashSet<(u32, u32)>,
+    exposure: ExposureTracker,
+    policy: ExposurePolicy,
+    swiss: Option<SwissTournament>,
+}
+
+impl Scheduler {
+    fn new<R: Rng + ?Sized>(config: &SimulationConfig, rng: &mut R) -> Self {
+        let exposure = ExposureTracker::new(config.num_photos);
+        let policy = ExposurePolicy::default();
+        // One Swiss round is as many matchups as fit the photos once.
+        let swiss = match config.strategy {
+            Strategy::Swiss => u32::try_from(config.matchup_size).ok().and_then(|size| {
+                SwissTournament::new(config.matchup_size, config.num_photos / size)
+            }),
+            _ => None,
+        };
+        let seeds = match config.strategy {
+            Strategy::Random | Strategy::Swiss => Vec::new(),
+            Strategy::SeedThenUncertainty => {
+                generate_seed_matchups_with_rng(config.num_photos, config.matchup_size, rng)
+            }
+            Strategy::Balanced => generate_balanced_seed_matchups_with_rng(
+                &exposure,
+                &policy,
+                config.matchup_size,
+                rng,
+            ),
+        };
+        Self {
+            strategy: config.strategy,
+            num_photos: config.num_photos,
+            matchup_size: config.matchup_size,
+            seeds: seeds.into(),
+            compared: HashSet::new(),
+            exposure,
+            policy,
+            swiss,
+        }
+    }
+
+    fn next<R: Rng + ?Sized>(&mut self, ratings: &[PhotoRating], rng: &mut R) -> Option<Vec<u32>> {
+        if let Some(seed) = self.seeds.pop_front() {
+            return Some(seed);
+        }
+        match self.strategy {
+            Strategy::Random => {
+                let picked = index::sample(rng, self.num_photos as usize, self.matchup_size);
+                picked
+                    .into_iter()
+                    .map(|idx| u32::try_from(idx).ok())
+                    .collect()
+            }
+            Strategy::SeedThenUncertainty => {
+                select_dynamic_matchup(ratings, &self.compared, self.matchup_size)
+            }
+            Strategy::Balanced => select_balanced_dynamic_matchup(
+                ratings,
+                &self.compared,
+                &self.exposure,
+                &self.policy,
+                self.matchup_size,
+            ),
+            Strategy::Swiss => self
+                .swiss
+                .as_mut()?
+                .next_matchup(ratings, &self.compared, rng),
+        }
+    }
+
+    /// Notes an answer, best first.
+    fn record(&mut self, ranking: &[u32]) {
+        if let Some(swiss) = &mut self.swiss {
+            swiss.record_answered();
+        }
+        self.exposure.record_shown(ranking);
+        self.exposure.record_answered(ranking);
+        self.compared.extend(
+            pairwise(ranking)
+                .into_iter()
+                .map(|(a, b)| normalize_pair(a, b)),
+        );
+    }
+}
+
+fn checkpoint(
+    config: &SimulationConfig,
+    truth: &[f64],
+    ratings: &[PhotoRating],
+    comparisons: u32,
+) -> Checkpoint {
+    Checkpoint {
+        comparisons,
+        kendall_tau: kendall_tau(truth, ratings),
+        top_k_recall: top_k_recall(truth, ratings, config.top_k),
+    }
+}
+
+fn fit(
+    config: &SimulationConfig,
+    outcomes: &[(u32, u32, PairPosition)],
+) -> Option<Vec<PhotoRating>> {
+    let n = config.num_photos as usize;
+    match config.model {
+        RatingModel::BradleyTerry => {
+            let mut bt = BradleyTerry::new(n)?;
+            for &(winner, loser, _) in outcomes {
+                bt.record_comparison(winner, loser);
+            }
+            Some(bt.compute_ratings(config.rating_iterations))
+        }
+        RatingModel::PositionBiased => {
+            let mut bt = PositionBiasedBradleyTerry::new(n)?;
+            bt.record_comparisons(outcomes);
+            Some(bt.compute_ratings(config.rating_iterations).0)
+        }
+    }
+}
+
+/// Kendall's tau-a between true strengths (indexed by photo) and estimated ratings.
+///
+/// 1.0 is perfect agreement, -1.0 a fully reversed ranking. Ties count as neither.
+#[must_use]
+pub fn kendall_tau(truth: &[f64], ratings: &[PhotoRating]) -> f64 {
+    let mut estimate = vec![0.0; truth.len()];
+    for rating in ratings {
+        if let Some(slot) = estimate.get_mut(rating.photo_idx as usize) {
+            *slot = rating.strength;
+        }
+    }
+
+    let mut score = 0_i64;
+    let mut pairs = 0_i64;
+    for i in 0..truth.len() {
+        for j in i + 1..truth.len() {
+            let agreement = (truth[i] - truth[j]) * (estimate[i] - estimate[j]);
+            if agreement > 0.0 {
+                score += 1;
+            } else if agreement < 0.0 {
+                score -= 1;
+            }
+            pairs += 1;
+        }
+    }
+
+    if pairs == 0 {
+        return 1.0;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let tau = score as f64 / pairs as f64;
+    tau
+}
+
+/// Share of the true top `k` photos that appear in the estimated top `k`.
+#[must_use]
+pub fn top_k_recall(truth: &[f64], ratings: &[PhotoRating], k: usize) -> f64 {
+    let k = k.min(truth.len());
+    if k == 0 {
+        return 1.0;
+    }
+
+    let mut true_order: Vec<usize> = (0..truth.len()).collect();
+    true_order.sort_by(|&a, &b| truth[b].total_cmp(&truth[a]));
+    let true_top: HashSet<usize> = true_order.into_iter().take(k).collect();
+
+    let mut estimated: Vec<&PhotoRating> = ratings.iter().collect();
+    estimated.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+    let hits = estimated
+        .iter()
+        .take(k)
+        .filter(|r| true_top.contains(&(r.photo_idx as usize)))
+        .count();
+
+    #[allow(clippy::cast_precision_loss)]
+    let recall = hits as f64 / k as f64;
+    recall
+}
+
+/// Box-Muller draw from N(0, 1).
+fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratings_from(strengths: &[f64]) -> Vec<PhotoRating> {
+        strengths
+            .iter()
+            .enumerate()
+            .map(|(idx, &strength)| PhotoRating {
+                photo_idx: u32::try_from(idx).unwrap(),
+                strength,
+                uncertainty: 0.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kendall_tau_bounds() {
+        let truth = [3.0, 2.0, 1.0, 0.0];
+        assert!((kendall_tau(&truth, &ratings_from(&truth)) - 1.0).abs() < f64::EPSILON);
+        let reversed = ratings_from(&[0.0, 1.0, 2.0, 3.0]);
+        assert!((kendall_tau(&truth, &reversed) + 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn top_k_recall_counts_overlap() {
+        let truth = [3.0, 2.0, 1.0, 0.0];
+        let estimate = ratings_from(&[3.0, 0.0, 2.0, 1.0]);
+        assert!((top_k_recall(&truth, &estimate, 2) - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn same_seed_reproduces_report() {
+        let config = SimulationConfig {
+            num_photos: 12,
+            budget: 40,
+            participants: vec![
+                Participant::thurstonian(0.5),
+                Participant::lazy_clicker(),
+                Participant {
+                    noise: 0.5,
+                    position_bias: 0.3,
+                    lazy_rate: 0.0,
+                },
+            ],
+            ..SimulationConfig::default()
+        };
+        let first = run(&config).unwrap();
+        let second = run(&config).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.comparisons, 40);
+        assert_eq!(first.checkpoints.len(), 8);
+    }
+
+    #[test]
+    fn careful_participants_recover_ranking() {
+        for strategy in [
+            Strategy::Random,
+            Strategy::SeedThenUncertainty,
+            Strategy::Balanced,
+        ] {
+            let config = SimulationConfig {
+                num_photos: 12,
+                budget: 80,
+                strategy,
+                participants: vec![Participant::thurstonian(0.2)],
+                strength_spread: 2.0,
+                seed: 7,
+                ..SimulationConfig::default()
+            };
+            let report = run(&config).unwrap();
+            assert!(
+                report.kendall_tau > 0.6,
+                "{strategy:?} reached only {}",
+                report.kendall_tau
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let config = SimulationConfig {
+            participants: vec![],
+            ..SimulationConfig::default()
+        };
+        assert!(run(&config).is_none());
+    }
+}
@@ -0,0 +1,457 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::{CampaignError, ComparisonResult, Matchup, Resubmission};
+use crate::provenance::Provenance;
+use crate::repository::Repository;
+use crate::types::{PhotoIdx, Ranking, RankingError, Triple};
+use crate::undo::{latest_undoable, UndoError, DEFAULT_UNDO_WINDOW};
+
+/// A participant's answer to a matchup, as sent by the client.
+#[derive(Debug, Clone, Default)]
+pub struct Submission {
+    /// Best first. Must list exactly the matchup's photos.
+    pub ranked: Vec<PhotoIdx>,
+    /// Left to right as shown, if reported. Must list exactly the matchup's photos.
+    pub displayed_order: Option<Vec<PhotoIdx>>,
+    pub provenance: Provenance,
+    /// Makes retries safe: a repeat with the same key gets the stored answer back.
+    pub idempotency_key: Option<Uuid>,
+}
+
+/// A stored answer and the matchup it answers.
+#[derive(Debug, Clone)]
+pub struct Answered {
+    pub comparison: ComparisonResult,
+    pub matchup: Matchup,
+}
+
+/// What became of a [`submit`].
+#[derive(Debug, Clone)]
+pub enum Submitted {
+    Created(Answered),
+    /// A retry of an answer already stored; nothing changed.
+    Replayed(ComparisonResult),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubmissionError<E> {
+    #[error(transparent)]
+    Campaign(#[from] CampaignError),
+    #[error("matchup not found")]
+    MatchupNotFound,
+    #[error("matchup belongs to a different session")]
+    ForeignMatchup,
+    #[error("invalid ranking: {0}")]
+    InvalidRanking(RankingError),
+    #[error("invalid displayed order: {0}")]
+    InvalidDisplayedOrder(RankingError),
+    #[error("matchup already answered with a different ranking")]
+    Conflict,
+    #[error(transparent)]
+    Undo(#[from] UndoError),
+    #[error("comparison already undone")]
+    AlreadyUndone,
+    /// The repository changed under us in a way its contract rules out.
+    #[error("inconsistent repository: {0}")]
+    Inconsistent(&'static str),
+    #[error(transparent)]
+    Repository(E),
+}
+
+/// Stores a session's answer to one of its matchups.
+///
+/// A retry of a stored answer, by matchup or by idempotency key, is
+/// [`Submitted::Replayed`] rather than stored twice.
+///
+/// # Errors
+///
+/// [`SubmissionError::Campaign`] once the session's campaign is closed,
+/// [`SubmissionError::Conflict`] if the matchup or key already has a different
+/// answer, the matchup and ranking errors if the submission does not fit the
+/// matchup, and [`SubmissionError::Repository`] if storage fails.
+pub async fn submit<R: Repository>(
+    repo: &R,
+    session_id: Uuid,
+    matchup_id: Uuid,
+    submission: Submission,
+) -> Result<Submitted, SubmissionError<R::Error>> {
+    ensure_accepting(repo, session_id).await?;
+
+    let matchup = match repo
+        .matchup(matchup_id)
+        .await
+        .map_err(SubmissionError::Repository)?
+    {
+        Some(m) if m.session_id == session_id => m,
+        Some(_) => return Err(SubmissionError::ForeignMatchup),
+        None => return Err(SubmissionError::MatchupNotFound),
+    };
+
+    let (ranking, displayed) = check(&matchup, &submission)?;
+    let mut result = ComparisonResult::from_ranking(matchup_id, session_id, &ranking);
+    if let Some(displayed) = displayed {
+        result = result.with_displayed_order(displayed);
+    }
+    if let Some(key) = submission.idempotency_key {
+        result = result.with_idempotency_key(key);
+    }
+    result = result
+        .with_provenance(submission.provenance)
+        .with_latency_since(matchup.served_at);
+
+    if repo
+        .save_comparison(&result)
+        .await
+        .map_err(SubmissionError::Repository)?
+    {
+        return Ok(Submitted::Created(Answered {
+            comparison: result,
+            matchup,
+        }));
+    }
+
+    let prior = repo
+        .find_prior_submission(session_id, matchup_id, submission.idempotency_key)
+        .await
+        .map_err(SubmissionError::Repository)?
+        .ok_or(SubmissionError::Inconsistent(
+            "conflicting comparison vanished",
+        ))?;
+    match prior.resubmission(matchup_id, result.ranked_photo_indices) {
+        Resubmission::Replay => Ok(Submitted::Replayed(prior)),
+        Resubmission::Conflict => Err(SubmissionError::Conflict),
+    }
+}
+
+/// Retracts the session's latest comparison if it is within the undo window
+/// at `now`, returning its matchup to be served again.
+///
+/// # Errors
+///
+/// [`SubmissionError::Campaign`] once the session's campaign is closed,
+/// [`SubmissionError::Undo`] if there is nothing undoable, and
+/// [`SubmissionError::AlreadyUndone`] if a concurrent undo got there first.
+pub async fn undo<R: Repository>(
+    repo: &R,
+    session_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Matchup, SubmissionError<R::Error>> {
+    ensure_accepting(repo, session_id).await?;
+    let latest = latest_comparison(repo, session_id, now).await?;
+
+    if !repo
+        .retract_comparison(latest.id, now)
+        .await
+        .map_err(SubmissionError::Repository)?
+    {
+        return Err(SubmissionError::AlreadyUndone);
+    }
+    repo.matchup(latest.matchup_id)
+        .await
+        .map_err(SubmissionError::Repository)?
+        .ok_or(SubmissionError::Inconsistent("undone matchup vanished"))
+}
+
+/// Replaces the session's latest comparison with a new answer to the same
+/// matchup, if it is within the undo window at `now`.
+///
+/// The idempotency key of `submission` is ignored; the revision is a new answer.
+///
+/// # Errors
+///
+/// As [`undo`], plus the ranking errors if the submission does not fit the matchup.
+pub async fn revise<R: Repository>(
+    repo: &R,
+    session_id: Uuid,
+    submission: Submission,
+    now: DateTime<Utc>,
+) -> Result<Answered, SubmissionError<R::Error>> {
+    ensure_accepting(repo, session_id).await?;
+    let latest = latest_comparison(repo, session_id, now).await?;
+
+    let matchup = repo
+        .matchup(latest.matchup_id)
+        .await
+        .map_err(SubmissionError::Repository)?
+        .ok_or(SubmissionError::Inconsistent("revised matchup vanished"))?;
+    let (ranking, displayed) = check(&matchup, &submission)?;
+
+    let mut revision = latest
+        .revision(ranking.ranked())
+        .with_provenance(submission.provenance)
+        .with_latency_since(matchup.served_at);
+    if let Some(displayed) = displayed {
+        revision = revision.with_displayed_order(displayed);
+    }
+
+    if !repo
+        .revise_comparison(latest.id, &revision)
+        .await
+        .map_err(SubmissionError::Repository)?
+    {
+        return Err(SubmissionError::AlreadyUndone);
+    }
+    Ok(Answered {
+        comparison: revision,
+        matchup,
+    })
+}
+
+/// Refuses answers once the session's campaign is closed.
+async fn ensure_accepting<R: Repository>(
+    repo: &R,
+    session_id: Uuid,
+) -> Result<(), SubmissionError<R::Error>> {
+    if let Some(campaign) = repo
+        .session_campaign(session_id)
+        .await
+        .map_err(SubmissionError::Repository)?
+    {
+        campaign.ensure_accepting()?;
+    }
+    Ok(())
+}
+
+async fn latest_comparison<R: Repository>(
+    repo: &R,
+    session_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<ComparisonResult, SubmissionError<R::Error>> {
+    let comparisons = repo
+        .session_comparisons(session_id)
+        .await
+        .map_err(SubmissionError::Repository)?;
+    Ok(latest_undoable(&comparisons, now, DEFAULT_UNDO_WINDOW)?.clone())
+}
+
+/// The submission's ranking and reported display order, checked against the matchup.
+fn check<E>(
+    matchup: &Matchup,
+    submission: &Submission,
+) -> Result<(Ranking, Option<Triple>), SubmissionError<E>> {
+    let ranking = Ranking::new(matchup.photo_indices, &submission.ranked)
+        .map_err(SubmissionError::InvalidRanking)?;
+    let displayed = submission
+        .displayed_order
+        .as_deref()
+        .map(|order| matchup.photo_indices.permutation(order))
+        .transpose()
+        .map_err(SubmissionError::InvalidDisplayedOrder)?;
+    Ok((ranking, displayed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Session, DEFAULT_CAMPAIGN_ID};
+    use crate::repository::{block_on, MemoryRepository};
+    use crate::types::triple;
+
+    fn session(repo: &MemoryRepository) -> Session {
+        block_on(repo.touch_session(Uuid::new_v4(), DEFAULT_CAMPAIGN_ID)).unwrap()
+    }
+
+    fn matchup(repo: &MemoryRepository, session: &Session) -> Matchup {
+        let matchup = Matchup::new(session, triple([0, 1, 2]), false);
+        block_on(repo.create_matchup(&matchup)).unwrap();
+        matchup
+    }
+
+    fn photos(order: [u32; 3]) -> Vec<PhotoIdx> {
+        order.into_iter().map(PhotoIdx::from).collect()
+    }
+
+    fn ranked(order: [u32; 3]) -> Submission {
+        Submission {
+            ranked: photos(order),
+            ..Submission::default()
+        }
+    }
+
+    fn created(submitted: Submitted) -> Answered {
+        match submitted {
+            Submitted::Created(answered) => answered,
+            Submitted::Replayed(_) => panic!("answer was replayed"),
+        }
+    }
+
+    #[test]
+    fn stores_an_answer_in_the_matchups_photos() {
+        let repo = MemoryRepository::new();
+        let session = session(&repo);
+        let matchup = matchup(&repo, &session);
+        let submission = Submission {
+            displayed_order: Some(photos([1, 2, 0])),
+            ..ranked([2, 0, 1])
+        };
+
+        let answered =
+            created(block_on(submit(&repo, session.id, matchup.id, submission)).unwrap());
+        assert_eq!(answered.matchup.id, matchup.id);
+        assert_eq!(answered.comparison.ranked_photo_indices, triple([2, 0, 1]));
+        assert_eq!(answered.comparison.displayed_order, Some(triple([1, 2, 0])));
+        assert_eq!(
+            block_on(repo.session_comparisons(session.id)).unwrap(),
+            vec![answered.comparison]
+        );
+    }
+
+    #[test]
+    fn refuses_answers_that_do_not_fit_the_matchup() {
+        let repo = MemoryRepository::new();
+        let session = session(&repo);
+        let matchup = matchup(&repo, &session);
+
+        assert!(matches!(
+            block_on(submit(&repo, session.id, matchup.id, ranked([2, 0, 3]))),
+            Err(SubmissionError::InvalidRanking(RankingError::NotInMatchup(
+                _
+            )))
+        ));
+        let submission = Submission {
+            displayed_order: Some(photos([1, 1, 0])),
+            ..ranked([2, 0, 1])
+        };
+        assert!(matches!(
+            block_on(submit(&repo, session.id, matchup.id, submission)),
+            Err(SubmissionError::InvalidDisplayedOrder(_))
+        ));
+        assert!(matches!(
+            block_on(submit(&repo, session.id, Uuid::new_v4(), ranked([2, 0, 1]))),
+            Err(SubmissionError::MatchupNotFound)
+        ));
+        let other = self::session(&repo);
+        assert!(matches!(
+            block_on(submit(&repo, other.id, matchup.id, ranked([2, 0, 1]))),
+            Err(SubmissionError::ForeignMatchup)
+        ));
+        assert!(block_on(repo.session_comparisons(session.id))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn replays_retries_and_refuses_changed_answers() {
+        let repo = MemoryRepository::new();
+        let session = session(&repo);
+        let matchup = matchup(&repo, &session);
+        let key = Uuid::new_v4();
+        let keyed = |ranking| Submission {
+            idempotency_key: Some(key),
+            ..ranked(ranking)
+        };
+
+        let first =
+            created(block_on(submit(&repo, session.id, matchup.id, keyed([2, 0, 1]))).unwrap());
+        match block_on(submit(&repo, session.id, matchup.id, keyed([2, 0, 1]))).unwrap() {
+            Submitted::Replayed(prior) => assert_eq!(prior.id, first.comparison.id),
+            Submitted::Created(_) => panic!("retry stored twice"),
+        }
+        assert!(matches!(
+            block_on(submit(&repo, session.id, matchup.id, keyed([0, 1, 2]))),
+            Err(SubmissionError::Conflict)
+        ));
+        assert!(matches!(
+            block_on(submit(&repo, session.id, matchup.id, ranked([0, 1, 2]))),
+            Err(SubmissionError::Conflict)
+        ));
+
+        // The key cannot be reused for another matchup either
+        let second = self::matchup(&repo, &session);
+        assert!(matches!(
+            block_on(submit(&repo, session.id, second.id, keyed([2, 0, 1]))),
+            Err(SubmissionError::Conflict)
+        ));
+        assert_eq!(
+            block_on(repo.session_comparisons(session.id))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn undo_retracts_the_latest_answer_and_returns_its_matchup() {
+        let repo = MemoryRepository::new();
+        let session = session(&repo);
+        let matchup = matchup(&repo, &session);
+        block_on(submit(&repo, session.id, matchup.id, ranked([2, 0, 1]))).unwrap();
+
+        let undone = block_on(undo(&repo, session.id, Utc::now())).unwrap();
+        assert_eq!(undone.id, matchup.id);
+        assert!(block_on(repo.session_comparisons(session.id))
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            block_on(undo(&repo, session.id, Utc::now())),
+            Err(SubmissionError::Undo(UndoError::NothingToUndo))
+        ));
+
+        // The matchup can be answered again once undone
+        created(block_on(submit(&repo, session.id, matchup.id, ranked([0, 1, 2]))).unwrap());
+        assert!(matches!(
+            block_on(undo(
+                &repo,
+                session.id,
+                Utc::now() + DEFAULT_UNDO_WINDOW * 2
+            )),
+            Err(SubmissionError::Undo(UndoError::WindowExpired))
+        ));
+    }
+
+    #[test]
+    fn revise_replaces_the_latest_answer() {
+        let repo = MemoryRepository::new();
+        let session = session(&repo);
+        let matchup = matchup(&repo, &session);
+        let first =
+            created(block_on(submit(&repo, session.id, matchup.id, ranked([2, 0, 1]))).unwrap());
+
+        assert!(matches!(
+            block_on(revise(&repo, session.id, ranked([2, 0, 5]), Utc::now())),
+            Err(SubmissionError::InvalidRanking(_))
+        ));
+        let revised = block_on(revise(&repo, session.id, ranked([0, 1, 2]), Utc::now())).unwrap();
+        assert_eq!(revised.matchup.id, matchup.id);
+        assert_ne!(revised.comparison.id, first.comparison.id);
+        assert_eq!(
+            block_on(repo.session_comparisons(session.id)).unwrap(),
+            vec![revised.comparison]
+        );
+    }
+
+    #[test]
+    fn closed_campaigns_take_no_answers() {
+        let repo = MemoryRepository::new();
+        let session = session(&repo);
+        let matchup = matchup(&repo, &session);
+        block_on(submit(&repo, session.id, matchup.id, ranked([2, 0, 1]))).unwrap();
+
+        let mut campaign = block_on(repo.campaign(DEFAULT_CAMPAIGN_ID))
+            .unwrap()
+            .unwrap();
+        campaign.close(Utc::now()).unwrap();
+        block_on(repo.save_campaign(&campaign)).unwrap();
+
+        let second = self::matchup(&repo, &session);
+        assert!(matches!(
+            block_on(submit(&repo, session.id, second.id, ranked([2, 0, 1]))),
+            Err(SubmissionError::Campaign(CampaignError::Closed))
+        ));
+        assert!(matches!(
+            block_on(undo(&repo, session.id, Utc::now())),
+            Err(SubmissionError::Campaign(CampaignError::Closed))
+        ));
+        assert!(matches!(
+            block_on(revise(&repo, session.id, ranked([0, 1, 2]), Utc::now())),
+            Err(SubmissionError::Campaign(CampaignError::Closed))
+        ));
+        assert_eq!(
+            block_on(repo.session_comparisons(session.id))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
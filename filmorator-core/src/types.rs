@@ -0,0 +1,266 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Position of a photo in its campaign; the index comparisons refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PhotoIdx(u32);
+
+impl PhotoIdx {
+    #[must_use]
+    pub const fn new(idx: u32) -> Self {
+        Self(idx)
+    }
+
+    #[must_use]
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for PhotoIdx {
+    fn from(idx: u32) -> Self {
+        Self(idx)
+    }
+}
+
+impl From<PhotoIdx> for u32 {
+    fn from(idx: PhotoIdx) -> Self {
+        idx.0
+    }
+}
+
+impl fmt::Display for PhotoIdx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RankingError {
+    #[error("expected {expected} photos, got {found}")]
+    WrongLength { expected: usize, found: usize },
+    #[error("photo {0} appears more than once")]
+    DuplicatePhoto(PhotoIdx),
+    #[error("photo {0} is not part of the matchup")]
+    NotInMatchup(PhotoIdx),
+}
+
+/// Three distinct photos shown together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<PhotoIdx>", into = "[PhotoIdx; 3]")]
+pub struct Triple([PhotoIdx; 3]);
+
+impl Triple {
+    /// # Errors
+    ///
+    /// [`RankingError::DuplicatePhoto`] unless all three photos differ.
+    pub fn new(photos: [PhotoIdx; 3]) -> Result<Self, RankingError> {
+        let [a, b, c] = photos;
+        if a == b || a == c {
+            return Err(RankingError::DuplicatePhoto(a));
+        }
+        if b == c {
+            return Err(RankingError::DuplicatePhoto(b));
+        }
+        Ok(Self(photos))
+    }
+
+    #[must_use]
+    pub const fn photos(&self) -> [PhotoIdx; 3] {
+        self.0
+    }
+
+    /// The photos as bare indices, in order.
+    #[must_use]
+    pub const fn indices(&self) -> [u32; 3] {
+        let [a, b, c] = self.0;
+        [a.get(), b.get(), c.get()]
+    }
+
+    /// The same photos in a random order.
+    #[must_use]
+    pub fn shuffled<R: Rng + ?Sized>(mut self, rng: &mut R) -> Self {
+        self.0.shuffle(rng);
+        self
+    }
+
+    #[must_use]
+    pub fn contains(&self, photo: PhotoIdx) -> bool {
+        self.0.contains(&photo)
+    }
+
+    /// Checks that `order` lists exactly this triple's photos, each once.
+    ///
+    /// # Errors
+    ///
+    /// A [`RankingError`] naming the first photo out of place.
+    pub fn permutation(&self, order: &[PhotoIdx]) -> Result<Self, RankingError> {
+        let order: [PhotoIdx; 3] = order.try_into().map_err(|_| RankingError::WrongLength {
+            expected: 3,
+            found: order.len(),
+        })?;
+        for (i, &photo) in order.iter().enumerate() {
+            if !self.contains(photo) {
+                return Err(RankingError::NotInMatchup(photo));
+            }
+            if order[..i].contains(&photo) {
+                return Err(RankingError::DuplicatePhoto(photo));
+            }
+        }
+        Ok(Self(order))
+    }
+}
+
+impl TryFrom<&[u32]> for Triple {
+    type Error = RankingError;
+
+    fn try_from(photos: &[u32]) -> Result<Self, Self::Error> {
+        match *photos {
+            [a, b, c] => Self::new([a.into(), b.into(), c.into()]),
+            _ => Err(RankingError::WrongLength {
+                expected: 3,
+                found: photos.len(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Vec<PhotoIdx>> for Triple {
+    type Error = RankingError;
+
+    fn try_from(photos: Vec<PhotoIdx>) -> Result<Self, Self::Error> {
+        let found = photos.len();
+        let photos: [PhotoIdx; 3] = photos
+            .try_into()
+            .map_err(|_| RankingError::WrongLength { expected: 3, found })?;
+        Self::new(photos)
+    }
+}
+
+impl From<Triple> for [PhotoIdx; 3] {
+    fn from(triple: Triple) -> Self {
+        triple.0
+    }
+}
+
+/// A participant's ordering of a triple, best first.
+///
+/// Only constructible as a permutation of its matchup, including when deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawRanking")]
+pub struct Ranking {
+    matchup: Triple,
+    ranked: Triple,
+}
+
+#[derive(Deserialize)]
+struct RawRanking {
+    matchup: Triple,
+    ranked: Vec<PhotoIdx>,
+}
+
+impl TryFrom<RawRanking> for Ranking {
+    type Error = RankingError;
+
+    fn try_from(raw: RawRanking) -> Result<Self, Self::Error> {
+        Self::new(raw.matchup, &raw.ranked)
+    }
+}
+
+impl Ranking {
+    /// # Errors
+    ///
+    /// A [`RankingError`] unless `ranked` lists each of `matchup`'s photos once.
+    pub fn new(matchup: Triple, ranked: &[PhotoIdx]) -> Result<Self, RankingError> {
+        let ranked = matchup.permutation(ranked)?;
+        Ok(Self { matchup, ranked })
+    }
+
+    #[must_use]
+    pub const fn matchup(&self) -> Triple {
+        self.matchup
+    }
+
+    /// Photos best first, as stored in [`crate::models::ComparisonResult`].
+    #[must_use]
+    pub const fn ranked(&self) -> Triple {
+        self.ranked
+    }
+}
+
+/// A [`Triple`] of bare indices, for tests.
+///
+/// # Panics
+///
+/// If the photos are not distinct.
+#[cfg(any(test, feature = "contract"))]
+pub(crate) fn triple(photos: [u32; 3]) -> Triple {
+    Triple::try_from(photos.as_slice()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(photos: &[u32]) -> Vec<PhotoIdx> {
+        photos.iter().copied().map(PhotoIdx::from).collect()
+    }
+
+    #[test]
+    fn triple_requires_three_distinct_photos() {
+        assert_eq!(
+            Triple::try_from([1, 2, 1].as_slice()),
+            Err(RankingError::DuplicatePhoto(PhotoIdx::new(1)))
+        );
+        assert_eq!(
+            Triple::try_from([1, 2].as_slice()),
+            Err(RankingError::WrongLength {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert!(serde_json::from_str::<Triple>("[4, 4, 5]").is_err());
+        assert_eq!(
+            serde_json::from_str::<Triple>("[4, 6, 5]").unwrap(),
+            triple([4, 6, 5])
+        );
+    }
+
+    #[test]
+    fn ranking_must_permute_its_matchup() {
+        let matchup = triple([1, 2, 3]);
+
+        // Would pass a set-equality check
+        assert_eq!(
+            Ranking::new(matchup, &ranked(&[1, 1, 2, 3])),
+            Err(RankingError::WrongLength {
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            Ranking::new(matchup, &ranked(&[3, 3, 1])),
+            Err(RankingError::DuplicatePhoto(PhotoIdx::new(3)))
+        );
+        assert_eq!(
+            Ranking::new(matchup, &ranked(&[3, 4, 1])),
+            Err(RankingError::NotInMatchup(PhotoIdx::new(4)))
+        );
+        let ranking = Ranking::new(matchup, &ranked(&[3, 1, 2])).unwrap();
+        assert_eq!(ranking.ranked(), triple([3, 1, 2]));
+    }
+
+    #[test]
+    fn ranking_serde_revalidates() {
+        let ranking = Ranking::new(triple([7, 8, 9]), &ranked(&[9, 7, 8])).unwrap();
+        let json = serde_json::to_string(&ranking).unwrap();
+        assert_eq!(json, r#"{"matchup":[7,8,9],"ranked":[9,7,8]}"#);
+        assert_eq!(serde_json::from_str::<Ranking>(&json).unwrap(), ranking);
+
+        let forged = r#"{"matchup":[7,8,9],"ranked":[9,9,8]}"#;
+        assert!(serde_json::from_str::<Ranking>(forged).is_err());
+    }
+}
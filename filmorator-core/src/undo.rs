@@ -0,0 +1,88 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::models::ComparisonResult;
+
+/// How long after submitting a comparison it can still be retracted or revised.
+pub const DEFAULT_UNDO_WINDOW: TimeDelta = TimeDelta::minutes(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum UndoError {
+    #[error("no comparison to undo")]
+    NothingToUndo,
+    #[error("the last comparison is older than the undo window")]
+    WindowExpired,
+}
+
+/// The session's most recent active comparison, if it can still be undone at `now`.
+///
+/// Only the latest answer is undoable, so undo never reaches past an answer
+/// the participant has not reconsidered.
+///
+/// # Errors
+///
+/// [`UndoError::NothingToUndo`] if every comparison is already superseded, and
+/// [`UndoError::WindowExpired`] if the latest one is older than `window`.
+pub fn latest_undoable(
+    results: &[ComparisonResult],
+    now: DateTime<Utc>,
+    window: TimeDelta,
+) -> Result<&ComparisonResult, UndoError> {
+    let latest = results
+        .iter()
+        .filter(|r| r.is_active())
+        .max_by_key(|r| r.created_at)
+        .ok_or(UndoError::NothingToUndo)?;
+
+    if now - latest.created_at > window {
+        return Err(UndoError::WindowExpired);
+    }
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::triple;
+    use uuid::Uuid;
+
+    fn answered_at(at: DateTime<Utc>) -> ComparisonResult {
+        ComparisonResult {
+            created_at: at,
+            ..ComparisonResult::new(Uuid::new_v4(), Uuid::new_v4(), triple([0, 1, 2]))
+        }
+    }
+
+    #[test]
+    fn picks_latest_active_comparison() {
+        let now = Utc::now();
+        let older = answered_at(now - TimeDelta::seconds(30));
+        let mut newer = answered_at(now - TimeDelta::seconds(10));
+        let results = vec![older.clone(), newer.clone()];
+        assert_eq!(
+            latest_undoable(&results, now, DEFAULT_UNDO_WINDOW).map(|r| r.id),
+            Ok(newer.id)
+        );
+
+        newer.supersede(now, None);
+        let results = vec![older.clone(), newer];
+        assert_eq!(
+            latest_undoable(&results, now, DEFAULT_UNDO_WINDOW).map(|r| r.id),
+            Ok(older.id)
+        );
+    }
+
+    #[test]
+    fn rejects_expired_or_missing() {
+        let now = Utc::now();
+        let stale = answered_at(now - TimeDelta::minutes(10));
+
+        assert_eq!(
+            latest_undoable(&[stale], now, DEFAULT_UNDO_WINDOW).map(|r| r.id),
+            Err(UndoError::WindowExpired)
+        );
+        assert_eq!(
+            latest_undoable(&[], now, DEFAULT_UNDO_WINDOW).map(|r| r.id),
+            Err(UndoError::NothingToUndo)
+        );
+    }
+}
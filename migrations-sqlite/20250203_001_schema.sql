@@ -0,0 +1,119 @@
+-- Schema for single-user local mode, equivalent to migrations/ up to 20250201_013.
+-- UUIDs are 16-byte blobs, timestamps RFC 3339 text, and photo index arrays JSON text.
+-- Schema changes must be made here as well as in migrations/.
+
+CREATE TABLE campaigns (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    owner_secret_hash TEXT NOT NULL UNIQUE,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'closed', 'reopened')),
+    threshold_reached_at TEXT,
+    closed_at TEXT,
+    reopened_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Nobody knows the default campaign's owner secret; a random value is a placeholder
+INSERT INTO campaigns (id, name, owner_secret_hash)
+VALUES ('default', 'Default campaign', lower(hex(randomblob(32))));
+
+-- A photo's position is its index in every stored comparison; it must never be reused
+CREATE TABLE photos (
+    id BLOB PRIMARY KEY,
+    campaign_id TEXT NOT NULL DEFAULT 'default' REFERENCES campaigns(id),
+    filename TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    group_label TEXT,
+    UNIQUE (campaign_id, file_hash),
+    UNIQUE (campaign_id, position)
+);
+
+CREATE TABLE sessions (
+    id BLOB PRIMARY KEY,
+    campaign_id TEXT NOT NULL DEFAULT 'default' REFERENCES campaigns(id),
+    excluded INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    last_active_at TEXT NOT NULL
+);
+
+CREATE TABLE matchups (
+    id BLOB PRIMARY KEY,
+    session_id BLOB NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    photo_indices TEXT NOT NULL CHECK (json_valid(photo_indices)),
+    is_seed INTEGER NOT NULL DEFAULT 0,
+    kind TEXT NOT NULL DEFAULT 'regular' CHECK (kind IN ('regular', 'repeat', 'calibration')),
+    repeat_of BLOB REFERENCES matchups(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    CHECK ((kind = 'repeat') = (repeat_of IS NOT NULL))
+);
+
+CREATE TABLE comparison_results (
+    id BLOB PRIMARY KEY,
+    matchup_id BLOB NOT NULL REFERENCES matchups(id) ON DELETE CASCADE,
+    session_id BLOB NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    campaign_id TEXT NOT NULL DEFAULT 'default' REFERENCES campaigns(id),
+    ranked_photo_indices TEXT NOT NULL CHECK (json_valid(ranked_photo_indices)),
+    displayed_order TEXT CHECK (displayed_order IS NULL OR json_valid(displayed_order)),
+    idempotency_key BLOB,
+    created_at TEXT NOT NULL,
+    superseded_at TEXT,
+    superseded_by BLOB REFERENCES comparison_results(id)
+        ON DELETE SET NULL DEFERRABLE INITIALLY DEFERRED,
+    decision_ms INTEGER CHECK (decision_ms >= 0),
+    viewport_width INTEGER CHECK (viewport_width >= 0),
+    viewport_height INTEGER CHECK (viewport_height >= 0),
+    device_class TEXT CHECK (device_class IN ('phone', 'tablet', 'desktop')),
+    previews_loaded INTEGER,
+    zoomed INTEGER
+);
+
+CREATE UNIQUE INDEX idx_comparison_results_active_matchup
+    ON comparison_results(matchup_id)
+    WHERE superseded_at IS NULL;
+
+CREATE UNIQUE INDEX idx_comparison_results_idempotency
+    ON comparison_results(session_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
+
+CREATE TABLE photo_ratings (
+    session_id BLOB NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    photo_idx INTEGER NOT NULL,
+    strength REAL NOT NULL DEFAULT 0.0,
+    uncertainty REAL NOT NULL DEFAULT 1.0,
+    PRIMARY KEY (session_id, photo_idx)
+);
+
+CREATE TABLE rating_fits (
+    session_id BLOB PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    model_version INTEGER NOT NULL,
+    comparisons INTEGER NOT NULL,
+    iterations INTEGER NOT NULL,
+    first_position_advantage REAL NOT NULL,
+    fitted_at TEXT NOT NULL
+);
+
+CREATE TABLE anchor_photos (
+    photo_id BLOB PRIMARY KEY REFERENCES photos(id) ON DELETE CASCADE,
+    strength REAL NOT NULL,
+    prior_weight REAL CHECK (prior_weight IS NULL OR prior_weight > 0),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE campaign_ratings (
+    campaign_id TEXT NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+    photo_idx INTEGER NOT NULL,
+    strength REAL NOT NULL,
+    uncertainty REAL NOT NULL,
+    comparison_count INTEGER NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (campaign_id, photo_idx)
+);
+
+CREATE INDEX idx_photos_campaign ON photos(campaign_id);
+CREATE INDEX idx_sessions_campaign ON sessions(campaign_id);
+CREATE INDEX idx_matchups_session ON matchups(session_id);
+CREATE INDEX idx_comparison_results_session ON comparison_results(session_id);
+CREATE INDEX idx_comparison_results_campaign ON comparison_results(campaign_id);
+CREATE INDEX idx_photo_ratings_session ON photo_ratings(session_id);
@@ -0,0 +1,80 @@
+-- Equivalent to migrations/20250203_014_event_log.sql.
+CREATE TABLE events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    occurred_at TEXT NOT NULL,
+    payload TEXT NOT NULL CHECK (json_valid(payload) AND json_extract(payload, '$.type') IS NOT NULL)
+);
+
+CREATE TRIGGER events_no_update BEFORE UPDATE ON events
+BEGIN
+    SELECT RAISE(ABORT, 'events are append-only');
+END;
+
+CREATE TRIGGER events_no_delete BEFORE DELETE ON events
+BEGIN
+    SELECT RAISE(ABORT, 'events are append-only');
+END;
+
+-- Backfill from the current rows, in time order. Only the latest close and
+-- reopen of each campaign are known, and exclusions are dated now.
+CREATE TEMP VIEW comparison_event_json AS
+SELECT id, json_object(
+    'id', lower(hex(id)),
+    'matchup_id', lower(hex(matchup_id)),
+    'session_id', lower(hex(session_id)),
+    'ranked_photo_indices', json(ranked_photo_indices),
+    'displayed_order', json(displayed_order),
+    'idempotency_key', CASE WHEN idempotency_key IS NOT NULL THEN lower(hex(idempotency_key)) END,
+    'provenance', json_object(
+        'decision_ms', decision_ms,
+        'viewport', CASE WHEN viewport_width IS NOT NULL AND viewport_height IS NOT NULL
+            THEN json_object('width', viewport_width, 'height', viewport_height) END,
+        'device_class', device_class,
+        'previews_loaded', json(CASE previews_loaded WHEN 0 THEN 'false' WHEN 1 THEN 'true' END),
+        'zoomed', json(CASE zoomed WHEN 0 THEN 'false' WHEN 1 THEN 'true' END)
+    ),
+    'created_at', created_at,
+    'superseded_at', NULL,
+    'superseded_by', NULL
+) AS comparison
+FROM comparison_results;
+
+INSERT INTO events (occurred_at, payload)
+SELECT occurred_at, payload FROM (
+    -- Revisions are logged by the answer they replaced, not on their own
+    SELECT c.created_at AS occurred_at, 0 AS tiebreak,
+           json_object('type', 'comparison_submitted', 'comparison', json(j.comparison)) AS payload
+    FROM comparison_results c
+    JOIN comparison_event_json j ON j.id = c.id
+    WHERE NOT EXISTS (SELECT 1 FROM comparison_results p WHERE p.superseded_by = c.id)
+    UNION ALL
+    SELECT superseded_at, 1,
+           json_object('type', 'comparison_retracted', 'comparison_id', lower(hex(id)))
+    FROM comparison_results
+    WHERE superseded_at IS NOT NULL AND superseded_by IS NULL
+    UNION ALL
+    SELECT c.superseded_at, 1,
+           json_object('type', 'comparison_revised', 'comparison_id', lower(hex(c.id)),
+                       'revision', json(j.comparison))
+    FROM comparison_results c
+    JOIN comparison_event_json j ON j.id = c.superseded_by
+    UNION ALL
+    SELECT closed_at, 2, json_object('type', 'campaign_closed', 'campaign_id', id)
+    FROM campaigns
+    WHERE status <> 'active' AND closed_at IS NOT NULL
+    UNION ALL
+    SELECT reopened_at, 3, json_object('type', 'campaign_reopened', 'campaign_id', id)
+    FROM campaigns
+    WHERE status = 'reopened' AND reopened_at IS NOT NULL
+    UNION ALL
+    SELECT strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 4,
+           json_object('type', 'session_excluded', 'session_id', lower(hex(id)),
+                       'excluded', json('true'))
+    FROM sessions
+    WHERE excluded
+)
+ORDER BY occurred_at, tiebreak;
+
+DROP VIEW comparison_event_json;
+
+CREATE INDEX idx_events_occurred_at ON events(occurred_at);
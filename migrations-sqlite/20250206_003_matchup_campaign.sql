@@ -0,0 +1,7 @@
+-- Equivalent to migrations/20250205_015_matchup_campaign.sql. SQLite can't add a
+-- referencing column with a non-null default, so the campaign is only checked by the app.
+ALTER TABLE matchups ADD COLUMN campaign_id TEXT NOT NULL DEFAULT 'default';
+
+UPDATE matchups SET campaign_id = (SELECT s.campaign_id FROM sessions s WHERE s.id = matchups.session_id);
+
+CREATE INDEX idx_matchups_campaign ON matchups(campaign_id);
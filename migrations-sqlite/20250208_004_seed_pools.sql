@@ -0,0 +1,5 @@
+-- Equivalent to migrations/20250207_016_seed_pools.sql.
+CREATE TABLE seed_pools (
+    session_id BLOB PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    pool TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Equivalent to migrations/20250209_017_serve_latency.sql.
+ALTER TABLE matchups ADD COLUMN served_at TEXT;
+
+ALTER TABLE comparison_results ADD COLUMN latency_ms INTEGER CHECK (latency_ms >= 0);
@@ -0,0 +1,5 @@
+-- Equivalent to migrations/20250211_018_swiss_tournaments.sql.
+CREATE TABLE swiss_tournaments (
+    campaign_id TEXT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    state TEXT NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Equivalent to migrations/20250213_019_campaign_position_bias.sql.
+CREATE TABLE campaign_position_bias (
+    campaign_id TEXT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    bias TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Left-to-right order photos were shown in; NULL for comparisons recorded before it was tracked
+ALTER TABLE comparison_results ADD COLUMN displayed_order INT[];
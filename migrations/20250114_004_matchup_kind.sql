@@ -0,0 +1,7 @@
+-- Quality-check matchups: repeats of answered triples and calibration triples
+ALTER TABLE matchups
+    ADD COLUMN kind TEXT NOT NULL DEFAULT 'regular'
+        CHECK (kind IN ('regular', 'repeat', 'calibration')),
+    ADD COLUMN repeat_of UUID REFERENCES matchups(id) ON DELETE CASCADE,
+    ADD CONSTRAINT matchups_repeat_of_matches_kind
+        CHECK ((kind = 'repeat') = (repeat_of IS NOT NULL));
@@ -0,0 +1,2 @@
+-- Roll or series label, taken from the photo's directory in the bucket; NULL when ungrouped
+ALTER TABLE photos ADD COLUMN group_label TEXT;
@@ -0,0 +1,8 @@
+-- Photos with known strengths from an earlier campaign, used to keep scores on one scale
+CREATE TABLE anchor_photos (
+    photo_id UUID PRIMARY KEY REFERENCES photos(id) ON DELETE CASCADE,
+    strength DOUBLE PRECISION NOT NULL,
+    -- NULL holds the strength fixed; otherwise the weight of the prior, in comparisons
+    prior_weight DOUBLE PRECISION CHECK (prior_weight IS NULL OR prior_weight > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
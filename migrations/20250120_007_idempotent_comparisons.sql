@@ -0,0 +1,13 @@
+-- One answer per matchup. Duplicates from double submits are removed, keeping the earliest
+DELETE FROM comparison_results later
+USING comparison_results earlier
+WHERE later.matchup_id = earlier.matchup_id
+  AND (later.created_at, later.id) > (earlier.created_at, earlier.id);
+
+ALTER TABLE comparison_results
+    ADD CONSTRAINT comparison_results_matchup_unique UNIQUE (matchup_id),
+    ADD COLUMN idempotency_key UUID;
+
+CREATE UNIQUE INDEX idx_comparison_results_idempotency
+    ON comparison_results(session_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Retracted and revised answers are kept, marked superseded; only active answers count
+ALTER TABLE comparison_results
+    ADD COLUMN superseded_at TIMESTAMPTZ,
+    ADD COLUMN superseded_by UUID REFERENCES comparison_results(id)
+        ON DELETE SET NULL DEFERRABLE INITIALLY DEFERRED,
+    DROP CONSTRAINT comparison_results_matchup_unique;
+
+CREATE UNIQUE INDEX idx_comparison_results_active_matchup
+    ON comparison_results(matchup_id)
+    WHERE superseded_at IS NULL;
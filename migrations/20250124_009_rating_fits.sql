@@ -0,0 +1,10 @@
+-- One row per session describing the fit currently stored in photo_ratings
+CREATE TABLE rating_fits (
+    session_id UUID PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    version BIGINT NOT NULL,
+    model_version INT NOT NULL,
+    comparisons INT NOT NULL,
+    iterations INT NOT NULL,
+    first_position_advantage FLOAT8 NOT NULL,
+    fitted_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,43 @@
+-- Campaigns own photos, sessions and comparisons; ratings aggregate per campaign
+CREATE TABLE campaigns (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    owner_secret_hash TEXT NOT NULL UNIQUE,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'closed')),
+    threshold_reached_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Existing data becomes the default campaign. Nobody knows its owner secret;
+-- the hash of a random value is a placeholder until one is issued.
+INSERT INTO campaigns (id, name, owner_secret_hash)
+VALUES ('default', 'Default campaign',
+        encode(sha256(convert_to(gen_random_uuid()::text, 'UTF8')), 'hex'));
+
+-- Rows written without a campaign, by code that predates campaigns, land in the default one
+ALTER TABLE photos
+    ADD COLUMN campaign_id TEXT NOT NULL DEFAULT 'default' REFERENCES campaigns(id),
+    DROP CONSTRAINT photos_file_hash_key,
+    ADD CONSTRAINT photos_campaign_file_hash_key UNIQUE (campaign_id, file_hash);
+
+ALTER TABLE sessions
+    ADD COLUMN campaign_id TEXT NOT NULL DEFAULT 'default' REFERENCES campaigns(id),
+    ADD COLUMN excluded BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE comparison_results
+    ADD COLUMN campaign_id TEXT NOT NULL DEFAULT 'default' REFERENCES campaigns(id);
+
+-- Global ratings across a campaign's non-excluded sessions (computed cache)
+CREATE TABLE campaign_ratings (
+    campaign_id TEXT NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+    photo_idx INT NOT NULL,
+    strength FLOAT8 NOT NULL,
+    uncertainty FLOAT8 NOT NULL,
+    comparison_count INT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (campaign_id, photo_idx)
+);
+
+CREATE INDEX idx_photos_campaign ON photos(campaign_id);
+CREATE INDEX idx_sessions_campaign ON sessions(campaign_id);
+CREATE INDEX idx_comparison_results_campaign ON comparison_results(campaign_id);
@@ -0,0 +1,6 @@
+-- Closed campaigns can be reopened; transitions are timestamped
+ALTER TABLE campaigns
+    DROP CONSTRAINT campaigns_status_check,
+    ADD CONSTRAINT campaigns_status_check CHECK (status IN ('active', 'closed', 'reopened')),
+    ADD COLUMN closed_at TIMESTAMPTZ,
+    ADD COLUMN reopened_at TIMESTAMPTZ;
@@ -0,0 +1,30 @@
+-- A photo's position is its index in every stored comparison; it must never be reused.
+-- file_hash holds a SHA-256 of the content from the next sync on (filenames before that).
+
+-- Syncs before this renumbered photos by listing order and never removed deleted files, so a
+-- deleted file's row may share its position with the file that took it over. Nothing stored
+-- tells the two apart, so rather than guess, stop and name them: the owner deletes the rows
+-- (and any anchors) of files no longer in the bucket, then restarts.
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(
+               format('campaign %s, position %s: %s', campaign_id, position, filenames),
+               E'\n' ORDER BY campaign_id, position)
+    INTO duplicates
+    FROM (
+        SELECT campaign_id, position, string_agg(filename, ', ' ORDER BY filename) AS filenames
+        FROM photos
+        GROUP BY campaign_id, position
+        HAVING count(*) > 1
+    ) shared;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION E'photos share positions; delete the rows of files no longer in the bucket, then restart:\n%',
+            duplicates;
+    END IF;
+END $$;
+
+ALTER TABLE photos
+    ADD CONSTRAINT photos_campaign_position_key UNIQUE (campaign_id, position);
@@ -0,0 +1,8 @@
+-- Client-reported context of each answer; NULL where the client reported nothing
+ALTER TABLE comparison_results
+    ADD COLUMN decision_ms INT CHECK (decision_ms >= 0),
+    ADD COLUMN viewport_width INT CHECK (viewport_width >= 0),
+    ADD COLUMN viewport_height INT CHECK (viewport_height >= 0),
+    ADD COLUMN device_class TEXT CHECK (device_class IN ('phone', 'tablet', 'desktop')),
+    ADD COLUMN previews_loaded BOOLEAN,
+    ADD COLUMN zoomed BOOLEAN;
@@ -0,0 +1,81 @@
+-- Append-only history of comparisons, session exclusions and campaign status.
+-- Payloads are serde JSON of filmorator_core::events::Event. No foreign keys,
+-- so the history survives rows being deleted by cascade.
+CREATE TABLE events (
+    seq BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    payload JSONB NOT NULL CHECK (payload ? 'type')
+);
+
+CREATE FUNCTION events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'events are append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER events_append_only
+    BEFORE UPDATE OR DELETE ON events
+    FOR EACH ROW EXECUTE FUNCTION events_append_only();
+
+-- Backfill from the current rows, in time order. Only the latest close and
+-- reopen of each campaign are known, and exclusions are dated now.
+CREATE FUNCTION comparison_event_json(c comparison_results) RETURNS jsonb AS $$
+    SELECT jsonb_build_object(
+        'id', c.id,
+        'matchup_id', c.matchup_id,
+        'session_id', c.session_id,
+        'ranked_photo_indices', to_jsonb(c.ranked_photo_indices),
+        'displayed_order', to_jsonb(c.displayed_order),
+        'idempotency_key', c.idempotency_key,
+        'provenance', jsonb_build_object(
+            'decision_ms', c.decision_ms,
+            'viewport', CASE WHEN c.viewport_width IS NOT NULL AND c.viewport_height IS NOT NULL
+                THEN jsonb_build_object('width', c.viewport_width, 'height', c.viewport_height) END,
+            'device_class', c.device_class,
+            'previews_loaded', c.previews_loaded,
+            'zoomed', c.zoomed
+        ),
+        'created_at', c.created_at,
+        'superseded_at', NULL,
+        'superseded_by', NULL
+    )
+$$ LANGUAGE sql;
+
+INSERT INTO events (occurred_at, payload)
+SELECT occurred_at, payload FROM (
+    -- Revisions are logged by the answer they replaced, not on their own
+    SELECT c.created_at AS occurred_at, 0 AS tiebreak,
+           jsonb_build_object('type', 'comparison_submitted',
+                              'comparison', comparison_event_json(c)) AS payload
+    FROM comparison_results c
+    WHERE NOT EXISTS (SELECT 1 FROM comparison_results p WHERE p.superseded_by = c.id)
+    UNION ALL
+    SELECT c.superseded_at, 1,
+           jsonb_build_object('type', 'comparison_retracted', 'comparison_id', c.id)
+    FROM comparison_results c
+    WHERE c.superseded_at IS NOT NULL AND c.superseded_by IS NULL
+    UNION ALL
+    SELECT c.superseded_at, 1,
+           jsonb_build_object('type', 'comparison_revised', 'comparison_id', c.id,
+                              'revision', comparison_event_json(r))
+    FROM comparison_results c
+    JOIN comparison_results r ON r.id = c.superseded_by
+    UNION ALL
+    SELECT closed_at, 2, jsonb_build_object('type', 'campaign_closed', 'campaign_id', id)
+    FROM campaigns
+    WHERE status <> 'active' AND closed_at IS NOT NULL
+    UNION ALL
+    SELECT reopened_at, 3, jsonb_build_object('type', 'campaign_reopened', 'campaign_id', id)
+    FROM campaigns
+    WHERE status = 'reopened' AND reopened_at IS NOT NULL
+    UNION ALL
+    SELECT now(), 4,
+           jsonb_build_object('type', 'session_excluded', 'session_id', id, 'excluded', true)
+    FROM sessions
+    WHERE excluded
+) backfill
+ORDER BY occurred_at, tiebreak;
+
+DROP FUNCTION comparison_event_json(comparison_results);
+
+CREATE INDEX idx_events_occurred_at ON events(occurred_at);
@@ -0,0 +1,8 @@
+-- A matchup's photo indices are positions in its campaign; record which one.
+ALTER TABLE matchups ADD COLUMN campaign_id TEXT REFERENCES campaigns(id) ON DELETE CASCADE;
+
+UPDATE matchups m SET campaign_id = s.campaign_id FROM sessions s WHERE s.id = m.session_id;
+
+ALTER TABLE matchups ALTER COLUMN campaign_id SET NOT NULL;
+
+CREATE INDEX idx_matchups_campaign ON matchups(campaign_id);
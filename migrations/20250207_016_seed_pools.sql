@@ -0,0 +1,5 @@
+-- The pool each session's seed matchups were generated as, with its generation metadata
+CREATE TABLE seed_pools (
+    session_id UUID PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    pool JSONB NOT NULL
+);
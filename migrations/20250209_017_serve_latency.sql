@@ -0,0 +1,5 @@
+-- When each matchup was last sent to its session, and the server-measured time
+-- from then to each answer; decision_ms stays as the client's own report
+ALTER TABLE matchups ADD COLUMN served_at TIMESTAMPTZ;
+
+ALTER TABLE comparison_results ADD COLUMN latency_ms INT CHECK (latency_ms >= 0);
@@ -0,0 +1,5 @@
+-- The Swiss tournament each campaign's matchups are paired by, when the server runs in Swiss mode
+CREATE TABLE swiss_tournaments (
+    campaign_id TEXT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    state JSONB NOT NULL
+);
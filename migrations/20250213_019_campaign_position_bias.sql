@@ -0,0 +1,5 @@
+-- Display-position bias measured by each campaign's latest rating fit, replaced with its ratings
+CREATE TABLE campaign_position_bias (
+    campaign_id TEXT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    bias JSONB NOT NULL
+);